@@ -0,0 +1,111 @@
+-- Restore M3U Tables Migration
+-- Migration 006 dropped the M3U tables assuming Xtream-only mode, but M3U
+-- playlists are still parsed and stored through them (items repository COPY,
+-- groups, series and episodes). On a fresh database every M3U import fails
+-- without them, and later migrations (search, facets, titles) extend them.
+-- Definitions match 001/003; IF NOT EXISTS keeps this a no-op where 006 never ran.
+
+-- ============================================================================
+-- 1. M3U TABLES
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS playlist_groups (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    playlist_id     UUID NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    group_hash      VARCHAR(64) NOT NULL,
+    name            VARCHAR(512) NOT NULL,
+    media_kind      VARCHAR(16) NOT NULL,
+    item_count      INTEGER NOT NULL DEFAULT 0,
+    logo            TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(playlist_id, group_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_groups_playlist ON playlist_groups(playlist_id);
+CREATE INDEX IF NOT EXISTS idx_groups_kind ON playlist_groups(playlist_id, media_kind);
+
+CREATE TABLE IF NOT EXISTS playlist_items (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    playlist_id     UUID NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    item_hash       VARCHAR(64) NOT NULL,
+    name            VARCHAR(1024) NOT NULL,
+    url             TEXT NOT NULL,
+    logo            TEXT,
+    group_name      VARCHAR(512) NOT NULL,
+    media_kind      VARCHAR(16) NOT NULL,
+    parsed_title    VARCHAR(1024),
+    parsed_year     SMALLINT,
+    parsed_quality  VARCHAR(16),
+    series_id       VARCHAR(64),
+    season_number   SMALLINT,
+    episode_number  SMALLINT,
+    sort_order      INTEGER NOT NULL DEFAULT 0,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(playlist_id, item_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_items_playlist ON playlist_items(playlist_id);
+CREATE INDEX IF NOT EXISTS idx_items_group ON playlist_items(playlist_id, group_name);
+CREATE INDEX IF NOT EXISTS idx_items_kind ON playlist_items(playlist_id, media_kind);
+CREATE INDEX IF NOT EXISTS idx_items_series ON playlist_items(playlist_id, series_id) WHERE series_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_items_order ON playlist_items(playlist_id, sort_order);
+CREATE INDEX IF NOT EXISTS idx_items_filter ON playlist_items(playlist_id, media_kind, group_name, sort_order);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_items_unique_url ON playlist_items(playlist_id, url);
+
+CREATE TABLE IF NOT EXISTS series (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    playlist_id     UUID NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    series_hash     VARCHAR(64) NOT NULL,
+    name            VARCHAR(1024) NOT NULL,
+    logo            TEXT,
+    group_name      VARCHAR(512) NOT NULL,
+    total_episodes  INTEGER NOT NULL DEFAULT 0,
+    total_seasons   INTEGER NOT NULL DEFAULT 0,
+    first_season    SMALLINT,
+    last_season     SMALLINT,
+    year            SMALLINT,
+    quality         VARCHAR(16),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(playlist_id, series_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_series_playlist ON series(playlist_id);
+CREATE INDEX IF NOT EXISTS idx_series_group ON series(playlist_id, group_name);
+
+CREATE TABLE IF NOT EXISTS series_episodes (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    series_id       UUID NOT NULL REFERENCES series(id) ON DELETE CASCADE,
+    item_id         UUID REFERENCES playlist_items(id) ON DELETE CASCADE,
+    item_hash       VARCHAR(64) NOT NULL,
+    season          SMALLINT NOT NULL,
+    episode         SMALLINT NOT NULL,
+    name            VARCHAR(1024) NOT NULL,
+    url             TEXT NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(series_id, item_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_episodes_series ON series_episodes(series_id);
+CREATE INDEX IF NOT EXISTS idx_episodes_season ON series_episodes(series_id, season);
+
+DROP TRIGGER IF EXISTS update_items_updated_at ON playlist_items;
+CREATE TRIGGER update_items_updated_at
+    BEFORE UPDATE ON playlist_items
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+DROP TRIGGER IF EXISTS update_groups_updated_at ON playlist_groups;
+CREATE TRIGGER update_groups_updated_at
+    BEFORE UPDATE ON playlist_groups
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+DROP TRIGGER IF EXISTS update_series_updated_at ON series;
+CREATE TRIGGER update_series_updated_at
+    BEFORE UPDATE ON series
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
@@ -0,0 +1,43 @@
+-- Accent-insensitive Search Migration
+-- Implements: unaccent + tsvector ranking with trigram fallback on name and parsed_title
+
+-- ============================================================================
+-- 1. UNACCENT: Immutable wrapper usable in generated columns and indexes
+-- ============================================================================
+
+CREATE EXTENSION IF NOT EXISTS "unaccent";
+CREATE EXTENSION IF NOT EXISTS "pg_trgm";
+
+-- unaccent() is STABLE (depends on search_path); pin the dictionary explicitly
+CREATE OR REPLACE FUNCTION f_unaccent(text)
+RETURNS text AS $$
+    SELECT public.unaccent('public.unaccent'::regdictionary, $1)
+$$ LANGUAGE sql IMMUTABLE PARALLEL SAFE STRICT;
+
+-- ============================================================================
+-- 2. SEARCH VECTOR: Rebuild accent-insensitive (name > parsed_title > group)
+-- ============================================================================
+
+ALTER TABLE playlist_items DROP COLUMN IF EXISTS search_vector;
+
+ALTER TABLE playlist_items
+ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', f_unaccent(coalesce(name, ''))), 'A') ||
+    setweight(to_tsvector('simple', f_unaccent(coalesce(parsed_title, ''))), 'B') ||
+    setweight(to_tsvector('simple', f_unaccent(coalesce(group_name, ''))), 'C')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_items_search ON playlist_items USING gin(search_vector);
+
+-- ============================================================================
+-- 3. TRIGRAM FALLBACK: Typo-tolerant matching on normalized name/title
+-- ============================================================================
+
+DROP INDEX IF EXISTS idx_items_trgm;
+CREATE INDEX IF NOT EXISTS idx_items_name_trgm
+    ON playlist_items USING gin(f_unaccent(lower(name)) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_items_title_trgm
+    ON playlist_items USING gin(f_unaccent(lower(parsed_title)) gin_trgm_ops)
+    WHERE parsed_title IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_items_year ON playlist_items(playlist_id, parsed_year)
+    WHERE parsed_year IS NOT NULL;
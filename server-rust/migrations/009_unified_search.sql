@@ -0,0 +1,20 @@
+-- Unified Search Migration
+-- Implements: accent-insensitive search over series and group names
+
+-- ============================================================================
+-- 1. SERIES: Full-text and trigram indexes on normalized name
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_series_search
+    ON series USING gin(to_tsvector('simple', f_unaccent(name)));
+CREATE INDEX IF NOT EXISTS idx_series_name_trgm
+    ON series USING gin(f_unaccent(lower(name)) gin_trgm_ops);
+
+-- ============================================================================
+-- 2. GROUPS: Full-text and trigram indexes on normalized name
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_groups_search
+    ON playlist_groups USING gin(to_tsvector('simple', f_unaccent(name)));
+CREATE INDEX IF NOT EXISTS idx_groups_name_trgm
+    ON playlist_groups USING gin(f_unaccent(lower(name)) gin_trgm_ops);
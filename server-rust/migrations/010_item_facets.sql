@@ -0,0 +1,28 @@
+-- Item Facets Migration
+-- Implements: persist the full parsed title (language, dubbed/subbed/multi-audio)
+-- for faceted filtering and facet counts on the items endpoint
+
+-- ============================================================================
+-- 1. PARSED TITLE FLAGS
+-- ============================================================================
+
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS parsed_language VARCHAR(16),
+ADD COLUMN IF NOT EXISTS is_dubbed BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN IF NOT EXISTS is_subbed BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN IF NOT EXISTS is_multi_audio BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Quality values like "1080P" / "4K" can exceed the original 16 chars with suffixes
+ALTER TABLE playlist_items ALTER COLUMN parsed_quality TYPE VARCHAR(50);
+
+-- ============================================================================
+-- 2. FACET INDEXES
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_items_quality ON playlist_items(playlist_id, parsed_quality)
+    WHERE parsed_quality IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_items_language ON playlist_items(playlist_id, parsed_language)
+    WHERE parsed_language IS NOT NULL;
+-- "Dublado" is the most common filter for Brazilian users
+CREATE INDEX IF NOT EXISTS idx_items_dubbed ON playlist_items(playlist_id, media_kind, sort_order)
+    WHERE is_dubbed;
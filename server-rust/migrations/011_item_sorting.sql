@@ -0,0 +1,49 @@
+-- Item Sorting Migration
+-- Implements: keyset pagination and sort modes (name, year, recent, quality)
+
+-- ============================================================================
+-- 1. SORT KEYS (generated, so the COPY writer doesn't need to know about them)
+-- ============================================================================
+
+-- Accent/emoji-insensitive name: unaccented, lowercased, non-alphanumerics
+-- collapsed to spaces. Names with no letters/digits sort last ('~' > 'z' in "C").
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS sort_name TEXT COLLATE "C" GENERATED ALWAYS AS (
+    COALESCE(
+        NULLIF(trim(regexp_replace(f_unaccent(lower(coalesce(parsed_title, name))), '[^a-z0-9]+', ' ', 'g')), ''),
+        '~'
+    )
+) STORED;
+
+-- Higher is better; unknown quality labels rank above missing ones
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS quality_rank SMALLINT GENERATED ALWAYS AS (
+    CASE
+        WHEN upper(parsed_quality) IN ('8K', '4320P') THEN 6
+        WHEN upper(parsed_quality) IN ('4K', 'UHD', '2160P') THEN 5
+        WHEN upper(parsed_quality) IN ('FHD', '1080P') THEN 4
+        WHEN upper(parsed_quality) IN ('HD', '720P') THEN 3
+        WHEN upper(parsed_quality) IN ('SD', '480P', '360P') THEN 2
+        WHEN parsed_quality IS NOT NULL THEN 1
+        ELSE 0
+    END
+) STORED;
+
+-- ============================================================================
+-- 2. KEYSET INDEXES (sort key + sort_order + id tiebreaker)
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_items_keyset_default
+    ON playlist_items(playlist_id, sort_order, id);
+CREATE INDEX IF NOT EXISTS idx_items_keyset_group
+    ON playlist_items(playlist_id, group_name, sort_order, id);
+CREATE INDEX IF NOT EXISTS idx_items_keyset_name
+    ON playlist_items(playlist_id, sort_name, sort_order, id);
+CREATE INDEX IF NOT EXISTS idx_items_keyset_group_name
+    ON playlist_items(playlist_id, group_name, sort_name, sort_order, id);
+CREATE INDEX IF NOT EXISTS idx_items_keyset_year
+    ON playlist_items(playlist_id, (COALESCE(parsed_year, 0)) DESC, sort_order, id);
+CREATE INDEX IF NOT EXISTS idx_items_keyset_recent
+    ON playlist_items(playlist_id, created_at DESC, sort_order DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_items_keyset_quality
+    ON playlist_items(playlist_id, quality_rank DESC, sort_order, id);
@@ -0,0 +1,22 @@
+-- Media Metadata Migration
+-- Implements: cache of provider (TMDB) metadata for M3U movies and series
+
+-- ============================================================================
+-- 1. METADATA CACHE: One row per provider lookup (hits and misses)
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS media_metadata (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    provider        VARCHAR(32) NOT NULL,
+    -- movie | series
+    kind            VARCHAR(16) NOT NULL,
+    -- Normalized title + year, e.g. "flow|2024" (not tied to a playlist)
+    lookup_key      VARCHAR(1100) NOT NULL,
+    -- NULL when the provider had no match (negative cache)
+    external_id     VARCHAR(64),
+    data            JSONB,
+    fetched_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(provider, kind, lookup_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_metadata_fetched ON media_metadata(fetched_at);
@@ -0,0 +1,49 @@
+-- Movie Titles Migration
+-- Implements: title-level clustering of movie versions ("Filme (2023) 4K",
+-- "Filme (2023) FHD DUB", "Filme (2023) LEG") and per-device variant preferences
+
+-- ============================================================================
+-- 1. TITLE KEY: normalized parsed title + year (generated, like sort_name)
+-- ============================================================================
+
+-- Same normalization as sort_name (generated columns can't reference each other).
+-- Names without letters/digits never cluster: they get a key of their own.
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS title_key VARCHAR(32) GENERATED ALWAYS AS (
+    CASE
+        WHEN trim(regexp_replace(f_unaccent(lower(coalesce(parsed_title, name))), '[^a-z0-9]+', ' ', 'g')) = ''
+            THEN md5(id::text)
+        ELSE md5(
+            trim(regexp_replace(f_unaccent(lower(coalesce(parsed_title, name))), '[^a-z0-9]+', ' ', 'g'))
+            || '|' || COALESCE(parsed_year::text, '')
+        )
+    END
+) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_items_title_key
+    ON playlist_items(playlist_id, title_key)
+    WHERE media_kind = 'movie';
+CREATE INDEX IF NOT EXISTS idx_items_movie_order
+    ON playlist_items(playlist_id, sort_order)
+    WHERE media_kind = 'movie';
+
+-- ============================================================================
+-- 2. DEVICE PREFERENCES: which variant a device plays by default
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS device_preferences (
+    device_id       VARCHAR(64) PRIMARY KEY,
+    -- Highest quality the device should pick by default (e.g. 'FHD' on older TVs)
+    max_quality     VARCHAR(16),
+    -- 'dubbed' | 'subbed' | 'any'
+    audio           VARCHAR(16) NOT NULL DEFAULT 'any',
+    language        VARCHAR(16),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+DROP TRIGGER IF EXISTS update_device_preferences_updated_at ON device_preferences;
+CREATE TRIGGER update_device_preferences_updated_at
+    BEFORE UPDATE ON device_preferences
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
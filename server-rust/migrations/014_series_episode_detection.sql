@@ -0,0 +1,9 @@
+-- Series Episode Detection Migration
+-- Implements: multi-episode files and dated (daily show) episodes
+
+ALTER TABLE series_episodes
+ADD COLUMN IF NOT EXISTS episode_end SMALLINT,
+ADD COLUMN IF NOT EXISTS air_date DATE;
+
+-- Shows are now merged across groups; keep episode listing by season cheap
+CREATE INDEX IF NOT EXISTS idx_episodes_order ON series_episodes(series_id, season, episode);
@@ -0,0 +1,38 @@
+-- Parental Controls Migration
+-- Implements: per-device PIN-protected restrictions (adult content, media kinds,
+-- groups, keywords) enforced on listings and search
+
+-- ============================================================================
+-- 1. ADULT FLAG (generated; mirrors ContentClassifier's ADULT_CONTENT pattern)
+-- ============================================================================
+
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS is_adult BOOLEAN GENERATED ALWAYS AS (
+    group_name ~* '(xxx|onlyfans|adulto|\+18)' OR name ~* '(xxx|onlyfans|\+18)'
+) STORED;
+
+ALTER TABLE playlist_groups
+ADD COLUMN IF NOT EXISTS is_adult BOOLEAN GENERATED ALWAYS AS (
+    name ~* '(xxx|onlyfans|adulto|\+18)'
+) STORED;
+
+-- ============================================================================
+-- 2. PARENTAL CONTROLS: one row per device, PIN stored as an argon2 hash
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS parental_controls (
+    device_id        VARCHAR(64) PRIMARY KEY,
+    pin_hash         TEXT NOT NULL,
+    hide_adult       BOOLEAN NOT NULL DEFAULT TRUE,
+    blocked_kinds    TEXT[] NOT NULL DEFAULT '{}',
+    blocked_groups   TEXT[] NOT NULL DEFAULT '{}',
+    blocked_keywords TEXT[] NOT NULL DEFAULT '{}',
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+DROP TRIGGER IF EXISTS update_parental_controls_updated_at ON parental_controls;
+CREATE TRIGGER update_parental_controls_updated_at
+    BEFORE UPDATE ON parental_controls
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,5 @@
+-- Continue Watching Migration
+-- Implements: "Up next" row (watch history resolved to series episodes)
+
+-- History entries are matched to episodes by item_hash
+CREATE INDEX IF NOT EXISTS idx_episodes_item_hash ON series_episodes(item_hash);
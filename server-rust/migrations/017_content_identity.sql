@@ -0,0 +1,27 @@
+-- Content Identity Migration
+-- Implements: stable content identities (show/season/episode, tvg-id, title + year)
+-- that survive reordering, refreshes and URL token rotation
+
+-- ============================================================================
+-- 1. ITEMS: identity computed by the parser (NULL for rows parsed before this)
+-- ============================================================================
+
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS content_id VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS idx_items_content_id ON playlist_items(playlist_id, content_id);
+
+-- ============================================================================
+-- 2. WATCH HISTORY: keyed by content identity (item_hash for legacy entries)
+-- ============================================================================
+
+ALTER TABLE watch_history
+ADD COLUMN IF NOT EXISTS content_id VARCHAR(64);
+
+ALTER TABLE watch_history
+ADD COLUMN IF NOT EXISTS history_key VARCHAR(64) GENERATED ALWAYS AS (
+    COALESCE(content_id, item_hash)
+) STORED;
+
+ALTER TABLE watch_history DROP CONSTRAINT IF EXISTS watch_history_device_id_item_hash_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_watch_history_key ON watch_history(device_id, history_key);
@@ -0,0 +1,45 @@
+-- Favorites & Lists Migration
+-- Implements: per-device favorites and user-named lists of channels, movies
+-- and series, keyed by stable identities so they survive playlist refreshes
+
+-- ============================================================================
+-- 1. LISTS: one built-in favorites list per device plus user-named lists
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS user_lists (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    device_id       VARCHAR(64) NOT NULL,
+    name            VARCHAR(128) NOT NULL,
+    is_favorites    BOOLEAN NOT NULL DEFAULT FALSE,
+    position        INTEGER NOT NULL DEFAULT 0,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_lists_favorites ON user_lists(device_id) WHERE is_favorites;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_lists_name ON user_lists(device_id, lower(name));
+
+DROP TRIGGER IF EXISTS update_user_lists_updated_at ON user_lists;
+CREATE TRIGGER update_user_lists_updated_at
+    BEFORE UPDATE ON user_lists
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- ============================================================================
+-- 2. ENTRIES: content_id (items), series id (series) or Xtream stream id
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS user_list_items (
+    list_id           UUID NOT NULL REFERENCES user_lists(id) ON DELETE CASCADE,
+    entry_key         VARCHAR(64) NOT NULL,
+    media_kind        VARCHAR(16) NOT NULL,
+    name              VARCHAR(1024) NOT NULL,
+    logo              TEXT,
+    xtream_stream_id  BIGINT,
+    xtream_extension  VARCHAR(16),
+    position          INTEGER NOT NULL,
+    added_at          TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (list_id, entry_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_list_items_order ON user_list_items(list_id, position);
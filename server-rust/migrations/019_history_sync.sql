@@ -0,0 +1,95 @@
+-- Watch History Sync Migration
+-- Implements: delta sync with server-assigned revisions, last-writer-wins by
+-- watched_at, tombstones for deletions and account-level shared history
+
+-- ============================================================================
+-- 1. ACCOUNTS: devices linked to an account share one history
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS history_accounts (
+    device_id       VARCHAR(64) PRIMARY KEY,
+    account_id      VARCHAR(64) NOT NULL,
+    linked_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_history_accounts_account ON history_accounts(account_id);
+
+-- History owner of a device: its account if linked, otherwise the device itself
+CREATE OR REPLACE FUNCTION watch_history_owner(p_device_id VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT COALESCE(
+        (SELECT 'acct:' || account_id FROM history_accounts WHERE device_id = p_device_id),
+        p_device_id
+    );
+$$ LANGUAGE sql STABLE;
+
+-- ============================================================================
+-- 2. WATCH HISTORY: owner, revision and tombstones
+-- ============================================================================
+
+ALTER TABLE watch_history
+ADD COLUMN IF NOT EXISTS owner_id VARCHAR(80);
+
+UPDATE watch_history SET owner_id = device_id WHERE owner_id IS NULL;
+
+ALTER TABLE watch_history ALTER COLUMN owner_id SET NOT NULL;
+
+-- device_id now records the device that made the last change
+DROP INDEX IF EXISTS idx_watch_history_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_watch_history_owner_key ON watch_history(owner_id, history_key);
+CREATE INDEX IF NOT EXISTS idx_watch_history_owner_recent ON watch_history(owner_id, watched_at DESC);
+
+CREATE SEQUENCE IF NOT EXISTS watch_history_revision_seq;
+
+ALTER TABLE watch_history
+ADD COLUMN IF NOT EXISTS revision BIGINT NOT NULL DEFAULT nextval('watch_history_revision_seq');
+
+CREATE INDEX IF NOT EXISTS idx_watch_history_revision ON watch_history(owner_id, revision);
+
+-- Set when the entry is deleted; the row stays so other devices see the deletion
+ALTER TABLE watch_history
+ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- Every change gets a new revision
+CREATE OR REPLACE FUNCTION bump_watch_history_revision()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.revision := nextval('watch_history_revision_seq');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS bump_watch_history_revision ON watch_history;
+CREATE TRIGGER bump_watch_history_revision
+    BEFORE INSERT OR UPDATE ON watch_history
+    FOR EACH ROW
+    EXECUTE FUNCTION bump_watch_history_revision();
+
+-- ============================================================================
+-- 3. CLEANUP FUNCTION UPDATE: keep only recent entries per owner
+-- ============================================================================
+
+CREATE OR REPLACE FUNCTION cleanup_watch_history(keep_count INTEGER DEFAULT 100)
+RETURNS INTEGER AS $$
+DECLARE
+    deleted_count INTEGER := 0;
+    temp_count INTEGER;
+    owner_rec RECORD;
+BEGIN
+    -- For each owner, keep only the most recent N entries (tombstones included)
+    FOR owner_rec IN SELECT DISTINCT owner_id FROM watch_history LOOP
+        WITH ranked AS (
+            SELECT id, ROW_NUMBER() OVER (ORDER BY watched_at DESC) as rn
+            FROM watch_history
+            WHERE owner_id = owner_rec.owner_id
+        )
+        DELETE FROM watch_history
+        WHERE id IN (SELECT id FROM ranked WHERE rn > keep_count);
+
+        GET DIAGNOSTICS temp_count = ROW_COUNT;
+        deleted_count := deleted_count + temp_count;
+    END LOOP;
+
+    RETURN deleted_count;
+END;
+$$ LANGUAGE plpgsql;
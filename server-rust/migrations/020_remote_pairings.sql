@@ -0,0 +1,15 @@
+-- Remote Control Pairing Migration
+-- Implements: persistent phone-to-TV pairings created from the QR session,
+-- used by the phone to browse the TV's catalog and send commands
+
+CREATE TABLE IF NOT EXISTS remote_pairings (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    device_id       VARCHAR(64) NOT NULL,
+    -- SHA-1 of the bearer token held by the phone (the token itself isn't stored)
+    token_hash      VARCHAR(40) NOT NULL UNIQUE,
+    name            VARCHAR(128) NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_remote_pairings_device ON remote_pairings(device_id);
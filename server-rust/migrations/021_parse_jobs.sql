@@ -0,0 +1,40 @@
+-- Parse Job Queue Migration
+-- Implements: persistent queue for background playlist parses with a bounded
+-- worker pool, priorities, cancellation and recovery of interrupted jobs
+
+CREATE TABLE IF NOT EXISTS parse_jobs (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    hash            VARCHAR(64) NOT NULL,
+    -- Playlist URL (may contain credentials, never returned by the API)
+    url             TEXT NOT NULL,
+    device_id       VARCHAR(64),
+    -- Higher runs first (interactive imports before background refreshes)
+    priority        SMALLINT NOT NULL DEFAULT 0,
+    -- queued | running | complete | failed | cancelled
+    status          VARCHAR(16) NOT NULL DEFAULT 'queued',
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    -- Instance running the job; its heartbeat going stale means it died
+    worker_id       VARCHAR(64),
+    heartbeat_at    TIMESTAMPTZ,
+    error           TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at      TIMESTAMPTZ,
+    finished_at     TIMESTAMPTZ,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- At most one active job per playlist
+CREATE UNIQUE INDEX IF NOT EXISTS idx_parse_jobs_active_hash ON parse_jobs(hash)
+WHERE status IN ('queued', 'running');
+
+-- Index for workers claiming the next job
+CREATE INDEX IF NOT EXISTS idx_parse_jobs_queue ON parse_jobs(priority DESC, created_at)
+WHERE status = 'queued';
+
+-- Index for finding stale running jobs
+CREATE INDEX IF NOT EXISTS idx_parse_jobs_running ON parse_jobs(heartbeat_at)
+WHERE status = 'running';
+
+-- Index for cleanup of finished jobs
+CREATE INDEX IF NOT EXISTS idx_parse_jobs_finished ON parse_jobs(finished_at)
+WHERE finished_at IS NOT NULL;
@@ -0,0 +1,21 @@
+-- Device Playback Hints Migration
+-- Implements: per-device defaults for HLS variant filtering in the proxy
+-- (older TVs that stutter on 4K/HEVC variants), overridable per request
+
+CREATE TABLE IF NOT EXISTS device_playback_hints (
+    device_id       VARCHAR(64) PRIMARY KEY,
+    -- Highest variant height in pixels the device decodes smoothly
+    max_height      INTEGER,
+    -- Highest variant bandwidth in bits per second
+    max_bandwidth   BIGINT,
+    -- Allowed codec prefixes (e.g. {avc1,mp4a}); empty means any
+    codecs          TEXT[] NOT NULL DEFAULT '{}',
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+DROP TRIGGER IF EXISTS update_device_playback_hints_updated_at ON device_playback_hints;
+CREATE TRIGGER update_device_playback_hints_updated_at
+    BEFORE UPDATE ON device_playback_hints
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,35 @@
+-- DVR Recordings Migration
+-- Implements: server-side recording of live channels, listed per device
+
+-- ============================================================================
+-- 1. RECORDINGS: Scheduled and captured live recordings
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS recordings (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    device_id       VARCHAR(64) NOT NULL,
+    -- Source playlist (kept nullable: playlists expire, recordings don't)
+    playlist_id     UUID REFERENCES playlists(id) ON DELETE SET NULL,
+    stream_id       BIGINT,
+    channel_name    VARCHAR(1024) NOT NULL,
+    title           VARCHAR(1024),
+    -- Upstream URL (may contain credentials, never returned by the API)
+    stream_url      TEXT NOT NULL,
+    start_at        TIMESTAMPTZ NOT NULL,
+    end_at          TIMESTAMPTZ NOT NULL,
+    -- scheduled | recording | completed | failed
+    status          VARCHAR(16) NOT NULL DEFAULT 'scheduled',
+    file_path       TEXT,
+    size_bytes      BIGINT NOT NULL DEFAULT 0,
+    error           TEXT,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (end_at > start_at)
+);
+
+-- Index for device listings
+CREATE INDEX IF NOT EXISTS idx_recordings_device ON recordings(device_id, start_at DESC);
+
+-- Index for the scheduler (pending/active recordings)
+CREATE INDEX IF NOT EXISTS idx_recordings_pending ON recordings(start_at)
+WHERE status IN ('scheduled', 'recording');
@@ -0,0 +1,9 @@
+-- Recording Quota Reservations Migration
+-- Implements: per-device quota held by active captures, reserved atomically in
+-- blocks so concurrent recordings (on any instance) can't each fill the quota
+
+ALTER TABLE recordings ADD COLUMN IF NOT EXISTS reserved_bytes BIGINT NOT NULL DEFAULT 0;
+
+-- Index for the quota check (per-device usage under an advisory lock)
+CREATE INDEX IF NOT EXISTS idx_recordings_device_usage ON recordings(device_id)
+WHERE status IN ('scheduled', 'recording');
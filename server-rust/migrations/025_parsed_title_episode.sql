@@ -0,0 +1,9 @@
+-- Parsed Title Season/Episode Migration
+-- Implements: persist the season/episode parsed from an item's name, so the
+-- parsedTitle returned from the database matches the one built while parsing.
+-- season_number/episode_number only hold the series classification (NULL for
+-- movies and live channels whose names carry such markers).
+
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS parsed_season SMALLINT,
+ADD COLUMN IF NOT EXISTS parsed_episode SMALLINT;
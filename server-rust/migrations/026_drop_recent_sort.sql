@@ -0,0 +1,6 @@
+-- Drop Recent Sort Migration
+-- Implements: removal of the "recent" item sort. Items of a playlist are all
+-- written by the same COPY, so created_at is identical within a playlist and
+-- never said which items were added recently; its keyset index goes with it.
+
+DROP INDEX IF EXISTS idx_items_keyset_recent;
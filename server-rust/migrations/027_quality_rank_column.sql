@@ -0,0 +1,9 @@
+-- Quality Rank Column Migration
+-- Implements: a single definition of the quality ranking. The rank is computed
+-- by services::titles::quality_rank and written with each item, instead of
+-- repeating the mapping in a generated column. Existing values stay valid.
+
+ALTER TABLE playlist_items ALTER COLUMN quality_rank DROP EXPRESSION IF EXISTS;
+ALTER TABLE playlist_items ALTER COLUMN quality_rank SET DEFAULT 0;
+UPDATE playlist_items SET quality_rank = 0 WHERE quality_rank IS NULL;
+ALTER TABLE playlist_items ALTER COLUMN quality_rank SET NOT NULL;
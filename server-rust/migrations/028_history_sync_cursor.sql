@@ -0,0 +1,80 @@
+-- History Sync Cursor Migration
+-- Implements: revisions ordered by transaction so a delta cursor never skips
+-- a change that commits after a later one, and tombstone retention
+
+-- ============================================================================
+-- 1. REVISIONS: the writing transaction's id
+-- ============================================================================
+
+-- Sequence values are handed out before commit, so revision 11 can become
+-- visible before revision 10 and a client that already read 11 never sees 10.
+-- A transaction id is different: every transaction below the snapshot xmin
+-- has finished, so nothing can still appear below that high-water mark.
+-- Revisions are offset past the old sequence values so existing client
+-- cursors stay valid.
+CREATE TABLE IF NOT EXISTS watch_history_revision_base (
+    id              BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    base            BIGINT NOT NULL
+);
+
+INSERT INTO watch_history_revision_base (base)
+SELECT last_value FROM watch_history_revision_seq
+ON CONFLICT (id) DO NOTHING;
+
+CREATE OR REPLACE FUNCTION watch_history_revision()
+RETURNS BIGINT AS $$
+    SELECT pg_current_xact_id()::text::bigint + base FROM watch_history_revision_base;
+$$ LANGUAGE sql VOLATILE;
+
+-- Highest revision no running transaction can still write
+CREATE OR REPLACE FUNCTION watch_history_high_water()
+RETURNS BIGINT AS $$
+    SELECT pg_snapshot_xmin(pg_current_snapshot())::text::bigint + base - 1
+    FROM watch_history_revision_base;
+$$ LANGUAGE sql VOLATILE;
+
+CREATE OR REPLACE FUNCTION bump_watch_history_revision()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.revision := watch_history_revision();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE watch_history ALTER COLUMN revision SET DEFAULT 0;
+DROP SEQUENCE IF EXISTS watch_history_revision_seq;
+
+-- ============================================================================
+-- 2. CLEANUP FUNCTION UPDATE: keep tombstones for a retention window
+-- ============================================================================
+
+DROP FUNCTION IF EXISTS cleanup_watch_history(INTEGER);
+
+CREATE OR REPLACE FUNCTION cleanup_watch_history(keep_count INTEGER, tombstone_days INTEGER)
+RETURNS INTEGER AS $$
+DECLARE
+    deleted_count INTEGER := 0;
+    temp_count INTEGER;
+BEGIN
+    -- Entries beyond the most recent N per owner become tombstones, so other
+    -- devices drop them too
+    WITH ranked AS (
+        SELECT id, ROW_NUMBER() OVER (PARTITION BY owner_id ORDER BY watched_at DESC) AS rn
+        FROM watch_history
+        WHERE deleted_at IS NULL
+    )
+    UPDATE watch_history SET deleted_at = NOW(), position_ms = 0
+    WHERE id IN (SELECT id FROM ranked WHERE rn > keep_count);
+
+    GET DIAGNOSTICS deleted_count = ROW_COUNT;
+
+    -- Tombstones are only removed once every device should have synced them
+    DELETE FROM watch_history
+    WHERE deleted_at IS NOT NULL AND deleted_at < NOW() - make_interval(days => tombstone_days);
+
+    GET DIAGNOSTICS temp_count = ROW_COUNT;
+    deleted_count := deleted_count + temp_count;
+
+    RETURN deleted_count;
+END;
+$$ LANGUAGE plpgsql;
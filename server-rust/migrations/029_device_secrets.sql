@@ -0,0 +1,10 @@
+-- Device Secrets Migration
+-- Implements: a secret issued to each TV on first use, required for its
+-- event stream and for managing its paired remotes
+
+CREATE TABLE IF NOT EXISTS device_secrets (
+    device_id       VARCHAR(64) PRIMARY KEY,
+    -- SHA-1 of the secret held by the TV (the secret itself isn't stored)
+    secret_hash     VARCHAR(40) NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,55 @@
+-- History Owner Revisions Migration
+-- Implements: per-owner revision counters for delta sync, replacing
+-- transaction-id revisions whose high-water mark any long transaction in the
+-- cluster (such as a playlist import's COPY) held back for every owner
+
+-- ============================================================================
+-- 1. COUNTERS: one per history owner, bumped under its row lock
+-- ============================================================================
+
+-- A writer keeps its owner's counter row locked until it commits, so the next
+-- writer of the same owner waits and gets a higher revision. Each owner's
+-- revisions therefore become visible in order, and the committed counter is a
+-- high-water mark no running transaction can still write below.
+CREATE TABLE IF NOT EXISTS watch_history_revisions (
+    owner_id        VARCHAR(80) PRIMARY KEY,
+    revision        BIGINT NOT NULL
+);
+
+-- Counters start above every revision (and client cursor) handed out so far
+CREATE TABLE IF NOT EXISTS watch_history_revision_floor (
+    id              BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    revision        BIGINT NOT NULL
+);
+
+INSERT INTO watch_history_revision_floor (revision)
+SELECT GREATEST(watch_history_revision(), COALESCE(MAX(revision), 0)) FROM watch_history
+ON CONFLICT (id) DO NOTHING;
+
+CREATE OR REPLACE FUNCTION bump_watch_history_revision()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO watch_history_revisions (owner_id, revision)
+    SELECT NEW.owner_id, revision + 1 FROM watch_history_revision_floor
+    ON CONFLICT (owner_id) DO UPDATE SET revision = watch_history_revisions.revision + 1
+    RETURNING revision INTO NEW.revision;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Highest revision of an owner whose writer has committed
+CREATE OR REPLACE FUNCTION watch_history_high_water(p_owner_id VARCHAR)
+RETURNS BIGINT AS $$
+    SELECT COALESCE(
+        (SELECT revision FROM watch_history_revisions WHERE owner_id = p_owner_id),
+        (SELECT revision FROM watch_history_revision_floor)
+    );
+$$ LANGUAGE sql STABLE;
+
+-- ============================================================================
+-- 2. DROP TRANSACTION-ID REVISIONS
+-- ============================================================================
+
+DROP FUNCTION IF EXISTS watch_history_high_water();
+DROP FUNCTION IF EXISTS watch_history_revision();
+DROP TABLE IF EXISTS watch_history_revision_base;
@@ -0,0 +1,44 @@
+-- Item First Seen Migration
+-- Implements: the "recent" item sort, keyed by when each content identity
+-- first appeared in a playlist. created_at can't be used: every item of an
+-- import is written by the same COPY, and a refresh writes them all again.
+
+-- ============================================================================
+-- 1. FIRST SEEN: per playlist URL, kept across imports
+-- ============================================================================
+
+-- Keyed by the playlist hash (not id) so it survives the playlist record
+-- being deleted on expiry and created again by the next import
+CREATE TABLE IF NOT EXISTS playlist_content_seen (
+    playlist_hash   VARCHAR(64) NOT NULL,
+    content_id      VARCHAR(64) NOT NULL,
+    first_seen_at   TIMESTAMPTZ NOT NULL,
+    -- Last import that still had the content (old rows are cleaned up)
+    last_seen_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (playlist_hash, content_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_content_seen_last ON playlist_content_seen(last_seen_at);
+
+ALTER TABLE playlist_items
+ADD COLUMN IF NOT EXISTS first_seen_at TIMESTAMPTZ;
+
+UPDATE playlist_items SET first_seen_at = created_at WHERE first_seen_at IS NULL;
+
+ALTER TABLE playlist_items ALTER COLUMN first_seen_at SET DEFAULT NOW();
+ALTER TABLE playlist_items ALTER COLUMN first_seen_at SET NOT NULL;
+
+INSERT INTO playlist_content_seen (playlist_hash, content_id, first_seen_at)
+SELECT p.hash, i.content_id, MIN(i.first_seen_at)
+FROM playlist_items i
+JOIN playlists p ON p.id = i.playlist_id
+WHERE i.content_id IS NOT NULL
+GROUP BY p.hash, i.content_id
+ON CONFLICT (playlist_hash, content_id) DO NOTHING;
+
+-- ============================================================================
+-- 2. KEYSET INDEX (newest first, ties in playlist order)
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_items_keyset_recent
+    ON playlist_items(playlist_id, first_seen_at DESC, sort_order, id);
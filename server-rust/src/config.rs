@@ -24,14 +24,65 @@ pub struct Config {
 
     // HLS Proxy
     pub hls_proxy_timeout_ms: u64,
+    pub upstream_breaker_failures: u32,
+    pub upstream_breaker_cooldown_ms: u64,
+    pub upstream_max_hosts: usize,
+    pub probe_cache_ttl_seconds: u64,
 
     // Cache
     pub parse_cache_dir: String,
     pub parse_cache_max_entries: Option<usize>,
     pub parse_cache_max_mb: Option<u64>,
+    pub suggest_max_playlists: usize,
+
+    // Logo/image proxy
+    pub logo_cache_dir: String,
+    pub logo_cache_max_entries: Option<usize>,
+    pub logo_cache_max_mb: u64,
+    pub image_max_dimension: u32,
+    pub image_max_source_mb: usize,
+
+    // Metadata enrichment (TMDB)
+    pub tmdb_api_key: Option<String>,
+    pub tmdb_base_url: String,
+    pub tmdb_image_base_url: String,
+    pub metadata_language: String,
+    pub metadata_cache_days: i64,
+    pub metadata_miss_cache_hours: i64,
 
     // Session
     pub session_ttl_seconds: u64,
+    pub session_create_limit: u64,
+    pub session_send_limit: u64,
+    pub session_poll_limit: u64,
+    pub session_max_failed_lookups: u64,
+    pub session_lockout_minutes: u64,
+    /// Take client IPs from X-Forwarded-For (TRUST_PROXY_HEADERS=true). Only
+    /// enable behind a reverse proxy that appends the client address to it:
+    /// without one, clients pick their own IP and dodge per-IP limits.
+    pub trust_proxy_headers: bool,
+
+    // Parse queue
+    pub parse_workers: usize,
+    pub parse_job_stale_seconds: u64,
+    pub parse_job_max_attempts: i32,
+
+    // Push events
+    pub events_keepalive_seconds: u64,
+    pub account_warning_days: i64,
+
+    // Continue watching
+    pub watch_finished_percent: u8,
+
+    // Parental controls
+    pub parental_unlock_minutes: u64,
+    pub parental_max_attempts: u64,
+    pub parental_lockout_minutes: u64,
+
+    // Recordings (DVR)
+    pub recordings_dir: String,
+    pub recording_quota_mb: u64,
+    pub recording_max_minutes: u64,
 
     // Misc
     pub user_agent: String,
@@ -94,6 +145,26 @@ impl Config {
                 .parse()
                 .unwrap_or(45_000), // 45 seconds (live streams need more time)
 
+            upstream_breaker_failures: env::var("UPSTREAM_BREAKER_FAILURES")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+
+            upstream_breaker_cooldown_ms: env::var("UPSTREAM_BREAKER_COOLDOWN_MS")
+                .unwrap_or_else(|_| "30000".to_string())
+                .parse()
+                .unwrap_or(30_000), // 30 seconds before a probe request
+
+            upstream_max_hosts: env::var("UPSTREAM_MAX_HOSTS")
+                .unwrap_or_else(|_| "256".to_string())
+                .parse()
+                .unwrap_or(256),
+
+            probe_cache_ttl_seconds: env::var("PROBE_CACHE_TTL_SECONDS")
+                .unwrap_or_else(|_| "21600".to_string())
+                .parse()
+                .unwrap_or(21_600), // 6 hours
+
             // Cache
             parse_cache_dir: env::var("PARSE_CACHE_DIR")
                 .unwrap_or_else(|_| ".parse-cache".to_string()),
@@ -103,12 +174,132 @@ impl Config {
             parse_cache_max_mb: env::var("PARSE_CACHE_MAX_MB")
                 .ok()
                 .and_then(|v| v.parse().ok()),
+            suggest_max_playlists: env::var("SUGGEST_MAX_PLAYLISTS")
+                .unwrap_or_else(|_| "16".to_string())
+                .parse()
+                .unwrap_or(16),
+
+            // Logo/image proxy
+            logo_cache_dir: env::var("LOGO_CACHE_DIR")
+                .unwrap_or_else(|_| ".logo-cache".to_string()),
+            logo_cache_max_entries: env::var("LOGO_CACHE_MAX_ENTRIES")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            logo_cache_max_mb: env::var("LOGO_CACHE_MAX_MB")
+                .unwrap_or_else(|_| "256".to_string())
+                .parse()
+                .unwrap_or(256),
+            image_max_dimension: env::var("IMAGE_MAX_DIMENSION")
+                .unwrap_or_else(|_| "1024".to_string())
+                .parse()
+                .unwrap_or(1024),
+            image_max_source_mb: env::var("IMAGE_MAX_SOURCE_MB")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+
+            // Metadata enrichment (TMDB)
+            tmdb_api_key: env::var("TMDB_API_KEY").ok().filter(|v| !v.is_empty()),
+            tmdb_base_url: env::var("TMDB_BASE_URL")
+                .unwrap_or_else(|_| "https://api.themoviedb.org/3".to_string()),
+            tmdb_image_base_url: env::var("TMDB_IMAGE_BASE_URL")
+                .unwrap_or_else(|_| "https://image.tmdb.org/t/p/w500".to_string()),
+            metadata_language: env::var("METADATA_LANGUAGE")
+                .unwrap_or_else(|_| "pt-BR".to_string()),
+            metadata_cache_days: env::var("METADATA_CACHE_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            metadata_miss_cache_hours: env::var("METADATA_MISS_CACHE_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .unwrap_or(24),
 
             // Session
             session_ttl_seconds: env::var("SESSION_TTL_SECONDS")
                 .unwrap_or_else(|_| "900".to_string())
                 .parse()
                 .unwrap_or(900), // 15 minutes
+            session_create_limit: env::var("SESSION_CREATE_LIMIT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10), // per IP per minute
+            session_send_limit: env::var("SESSION_SEND_LIMIT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10), // per IP per minute
+            session_poll_limit: env::var("SESSION_POLL_LIMIT")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60), // per IP per minute
+            session_max_failed_lookups: env::var("SESSION_MAX_FAILED_LOOKUPS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            session_lockout_minutes: env::var("SESSION_LOCKOUT_MINUTES")
+                .unwrap_or_else(|_| "15".to_string())
+                .parse()
+                .unwrap_or(15),
+            trust_proxy_headers: env::var("TRUST_PROXY_HEADERS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+
+            // Parse queue
+            parse_workers: env::var("PARSE_WORKERS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .unwrap_or(2),
+            parse_job_stale_seconds: env::var("PARSE_JOB_STALE_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            parse_job_max_attempts: env::var("PARSE_JOB_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+
+            // Push events
+            events_keepalive_seconds: env::var("EVENTS_KEEPALIVE_SECONDS")
+                .unwrap_or_else(|_| "15".to_string())
+                .parse()
+                .unwrap_or(15),
+            account_warning_days: env::var("ACCOUNT_WARNING_DAYS")
+                .unwrap_or_else(|_| "7".to_string())
+                .parse()
+                .unwrap_or(7),
+
+            // Continue watching
+            watch_finished_percent: env::var("WATCH_FINISHED_PERCENT")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .unwrap_or(90),
+
+            // Parental controls
+            parental_unlock_minutes: env::var("PARENTAL_UNLOCK_MINUTES")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            parental_max_attempts: env::var("PARENTAL_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            parental_lockout_minutes: env::var("PARENTAL_LOCKOUT_MINUTES")
+                .unwrap_or_else(|_| "15".to_string())
+                .parse()
+                .unwrap_or(15),
+
+            // Recordings (DVR)
+            recordings_dir: env::var("RECORDINGS_DIR")
+                .unwrap_or_else(|_| ".recordings".to_string()),
+            recording_quota_mb: env::var("RECORDING_QUOTA_MB")
+                .unwrap_or_else(|_| "4096".to_string())
+                .parse()
+                .unwrap_or(4096), // 4 GB per device
+            recording_max_minutes: env::var("RECORDING_MAX_MINUTES")
+                .unwrap_or_else(|_| "360".to_string())
+                .parse()
+                .unwrap_or(360), // 6 hours
 
             // Misc - Use VLC user agent to avoid IPTV server blocks
             user_agent: env::var("USER_AGENT")
@@ -3,13 +3,14 @@
 //! These types map directly to database rows and can be converted
 //! to the API response types in models/playlist.rs
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use sqlx::FromRow;
 use uuid::Uuid;
 
 use crate::models::playlist::{
     MediaKind, ParsedTitle, PlaylistGroup, PlaylistItem, PlaylistStats, SeriesEpisode, SeriesInfo,
 };
+use crate::services::titles::quality_rank;
 
 // ============================================================================
 // Database Row Types
@@ -136,6 +137,16 @@ pub struct ItemRow {
     pub season_number: Option<i16>,
     pub episode_number: Option<i16>,
     pub sort_order: i32,
+    pub parsed_language: Option<String>,
+    pub is_dubbed: bool,
+    pub is_subbed: bool,
+    pub is_multi_audio: bool,
+    /// NULL for rows written before content identities existed
+    pub content_id: Option<String>,
+    /// Season/episode as parsed from the name, kept apart from the series
+    /// classification above (NULL for rows written before they were stored)
+    pub parsed_season: Option<i16>,
+    pub parsed_episode: Option<i16>,
 }
 
 impl From<ItemRow> for PlaylistItem {
@@ -143,13 +154,17 @@ impl From<ItemRow> for PlaylistItem {
         let parsed_title = row.parsed_title.map(|title| ParsedTitle {
             title,
             year: row.parsed_year.map(|y| y as u16),
-            season: row.season_number.map(|s| s as u8),
-            episode: row.episode_number.map(|e| e as u16),
+            season: row.parsed_season.or(row.season_number).map(|s| s as u8),
+            episode: row.parsed_episode.or(row.episode_number).map(|e| e as u16),
             quality: row.parsed_quality.clone(),
-            ..Default::default()
+            language: row.parsed_language.clone(),
+            is_multi_audio: row.is_multi_audio,
+            is_dubbed: row.is_dubbed,
+            is_subbed: row.is_subbed,
         });
 
         PlaylistItem {
+            content_id: row.content_id.unwrap_or_else(|| row.item_hash.clone()),
             id: row.item_hash,
             name: row.name,
             url: row.url,
@@ -209,6 +224,8 @@ pub struct EpisodeRow {
     pub item_hash: String,
     pub season: i16,
     pub episode: i16,
+    pub episode_end: Option<i16>,
+    pub air_date: Option<NaiveDate>,
     pub name: String,
     pub url: String,
 }
@@ -219,6 +236,8 @@ impl From<EpisodeRow> for SeriesEpisode {
             item_id: row.item_hash,
             season: row.season as u8,
             episode: row.episode as u16,
+            episode_end: row.episode_end.map(|e| e as u16),
+            air_date: row.air_date,
             name: row.name,
             url: row.url,
         }
@@ -256,6 +275,7 @@ pub struct NewGroup {
 pub struct NewItem {
     pub playlist_id: Uuid,
     pub item_hash: String,
+    pub content_id: String,
     pub name: String,
     pub url: String,
     pub logo: Option<String>,
@@ -268,6 +288,16 @@ pub struct NewItem {
     pub season_number: Option<i16>,
     pub episode_number: Option<i16>,
     pub sort_order: i32,
+    pub parsed_language: Option<String>,
+    pub is_dubbed: bool,
+    pub is_subbed: bool,
+    pub is_multi_audio: bool,
+    pub parsed_season: Option<i16>,
+    pub parsed_episode: Option<i16>,
+    pub quality_rank: i16,
+    /// When the content first appeared in the playlist (the writer carries
+    /// it over from earlier imports)
+    pub first_seen_at: DateTime<Utc>,
 }
 
 impl NewItem {
@@ -276,6 +306,7 @@ impl NewItem {
         NewItem {
             playlist_id,
             item_hash: truncate_str(&item.id, 255),
+            content_id: truncate_str(&item.content_id, 64),
             name: sanitize_name(&item.name, 1024),
             url: truncate_str(&item.url, 2048),
             logo: item.logo.as_ref().map(|s| truncate_str(s, 2048)),
@@ -288,6 +319,14 @@ impl NewItem {
             season_number: item.season_number.map(|s| s as i16),
             episode_number: item.episode_number.map(|e| e as i16),
             sort_order,
+            parsed_language: item.parsed_title.as_ref().and_then(|p| p.language.as_ref().map(|l| truncate_str(l, 16))),
+            is_dubbed: item.parsed_title.as_ref().map(|p| p.is_dubbed).unwrap_or(false),
+            is_subbed: item.parsed_title.as_ref().map(|p| p.is_subbed).unwrap_or(false),
+            is_multi_audio: item.parsed_title.as_ref().map(|p| p.is_multi_audio).unwrap_or(false),
+            parsed_season: item.parsed_title.as_ref().and_then(|p| p.season.map(|s| s as i16)),
+            parsed_episode: item.parsed_title.as_ref().and_then(|p| p.episode.map(|e| e as i16)),
+            quality_rank: quality_rank(item.parsed_title.as_ref().and_then(|p| p.quality.as_deref())),
+            first_seen_at: Utc::now(),
         }
     }
 }
@@ -353,6 +392,8 @@ pub struct NewEpisode {
     pub item_hash: String,
     pub season: i16,
     pub episode: i16,
+    pub episode_end: Option<i16>,
+    pub air_date: Option<NaiveDate>,
     pub name: String,
     pub url: String,
 }
@@ -374,11 +415,14 @@ fn parse_media_kind(s: &str) -> MediaKind {
 /// Format item for COPY protocol (tab-separated values)
 pub fn format_copy_line(item: &NewItem) -> String {
     // UUID, playlist_id, item_hash, name, url, logo, group_name, media_kind,
-    // parsed_title, parsed_year, parsed_quality, series_id, season_number, episode_number, sort_order
+    // parsed_title, parsed_year, parsed_quality, series_id, season_number, episode_number, sort_order,
+    // parsed_language, is_dubbed, is_subbed, is_multi_audio, content_id, parsed_season, parsed_episode,
+    // quality_rank, first_seen_at
     let escape = |s: &str| s.replace('\t', " ").replace('\n', " ").replace('\r', "");
+    let flag = |b: bool| if b { "t" } else { "f" };
 
     format!(
-        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
         Uuid::new_v4(),
         item.playlist_id,
         escape(&item.item_hash),
@@ -394,5 +438,87 @@ pub fn format_copy_line(item: &NewItem) -> String {
         item.season_number.map(|s| s.to_string()).unwrap_or_else(|| "\\N".to_string()),
         item.episode_number.map(|e| e.to_string()).unwrap_or_else(|| "\\N".to_string()),
         item.sort_order,
+        item.parsed_language.as_ref().map(|s| escape(s)).unwrap_or_else(|| "\\N".to_string()),
+        flag(item.is_dubbed),
+        flag(item.is_subbed),
+        flag(item.is_multi_audio),
+        escape(&item.content_id),
+        item.parsed_season.map(|s| s.to_string()).unwrap_or_else(|| "\\N".to_string()),
+        item.parsed_episode.map(|e| e.to_string()).unwrap_or_else(|| "\\N".to_string()),
+        item.quality_rank,
+        item.first_seen_at.to_rfc3339(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_line_includes_parsed_title_flags() {
+        let item = PlaylistItem {
+            id: "abc".to_string(),
+            content_id: "c_abc".to_string(),
+            name: "Flow (2024) Dublado 4K".to_string(),
+            url: "http://example.com/flow.mp4".to_string(),
+            logo: None,
+            group: "Filmes".to_string(),
+            media_kind: MediaKind::Movie,
+            parsed_title: Some(ParsedTitle {
+                title: "Flow".to_string(),
+                year: Some(2024),
+                quality: Some("4K".to_string()),
+                language: Some("PT".to_string()),
+                is_dubbed: true,
+                ..Default::default()
+            }),
+            epg_id: None,
+            series_id: None,
+            season_number: None,
+            episode_number: None,
+        };
+
+        let mut new_item = NewItem::from_item(&item, Uuid::nil(), 7);
+        new_item.first_seen_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let line = format_copy_line(&new_item);
+        let fields: Vec<&str> = line.trim_end_matches('\n').split('\t').collect();
+
+        // Must match the COPY column list in repository::items
+        assert_eq!(fields.len(), 24);
+        assert_eq!(&fields[14..23], &["7", "PT", "t", "f", "f", "c_abc", "\\N", "\\N", "5"]);
+        assert_eq!(fields[23], "2023-11-14T22:13:20+00:00");
+    }
+
+    #[test]
+    fn test_item_row_keeps_parsed_season_of_non_series() {
+        let row = ItemRow {
+            id: Uuid::nil(),
+            playlist_id: Uuid::nil(),
+            item_hash: "abc".to_string(),
+            name: "Show S02E05 Trailer".to_string(),
+            url: "http://example.com/t.mp4".to_string(),
+            logo: None,
+            group_name: "Trailers".to_string(),
+            media_kind: "movie".to_string(),
+            parsed_title: Some("Show Trailer".to_string()),
+            parsed_year: None,
+            parsed_quality: None,
+            series_id: None,
+            season_number: None,
+            episode_number: None,
+            sort_order: 0,
+            parsed_language: None,
+            is_dubbed: false,
+            is_subbed: false,
+            is_multi_audio: false,
+            content_id: None,
+            parsed_season: Some(2),
+            parsed_episode: Some(5),
+        };
+
+        let item = PlaylistItem::from(row);
+        let parsed = item.parsed_title.unwrap();
+        assert_eq!((parsed.season, parsed.episode), (Some(2), Some(5)));
+        assert_eq!(item.season_number, None);
+    }
+}
@@ -0,0 +1,35 @@
+//! Device secrets repository
+//!
+//! A TV registers once and gets a secret it sends with requests that only
+//! the TV itself may make. Only the secret's hash is stored.
+
+use sqlx::PgPool;
+
+/// Store a device's secret; false if the device already has one
+pub async fn register_secret(
+    pool: &PgPool,
+    device_id: &str,
+    secret_hash: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO device_secrets (device_id, secret_hash)
+        VALUES ($1, $2)
+        ON CONFLICT (device_id) DO NOTHING
+        "#,
+    )
+    .bind(device_id)
+    .bind(secret_hash)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Hash of a device's secret (None if it never registered)
+pub async fn secret_hash(pool: &PgPool, device_id: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT secret_hash FROM device_secrets WHERE device_id = $1")
+        .bind(device_id)
+        .fetch_optional(pool)
+        .await
+}
@@ -3,8 +3,10 @@
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use super::items::{bind_restrictions, restriction_clause};
 use crate::db::models::{GroupRow, NewGroup};
 use crate::models::playlist::PlaylistGroup;
+use crate::services::parental::ContentRestrictions;
 
 /// Insert or update a group
 pub async fn upsert_group(
@@ -95,6 +97,58 @@ pub async fn get_by_kind(
     Ok(rows)
 }
 
+/// Group search hit with relevance score and total match count
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct GroupHitRow {
+    #[sqlx(flatten)]
+    pub group: GroupRow,
+    pub rank: f32,
+    pub total: i64,
+}
+
+/// Accent-insensitive search over group names
+pub async fn search_groups(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    tsquery: &str,
+    normalized: &str,
+    media_kind: Option<&str>,
+    restrictions: Option<&ContentRestrictions>,
+    limit: i64,
+) -> Result<Vec<GroupHitRow>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        WITH q AS (
+            SELECT to_tsquery('simple', f_unaccent($2)) AS ts, $3::text AS norm
+        )
+        SELECT id, playlist_id, group_hash, name, media_kind, item_count, logo,
+               (ts_rank(to_tsvector('simple', f_unaccent(name)), q.ts) * 2
+                    + similarity(f_unaccent(lower(name)), q.norm))::REAL AS rank,
+               COUNT(*) OVER() AS total
+        FROM playlist_groups, q
+        WHERE playlist_id = $1
+          AND ($4::text IS NULL OR media_kind = $4)
+          AND (to_tsvector('simple', f_unaccent(name)) @@ q.ts
+               OR f_unaccent(lower(name)) % q.norm
+               OR f_unaccent(lower(name)) LIKE '%' || q.norm || '%')
+          {}
+        ORDER BY rank DESC, item_count DESC
+        LIMIT $5
+        "#,
+        restriction_clause(6, "is_adult", "media_kind", "name", "name")
+    );
+
+    let query = sqlx::query_as::<_, GroupHitRow>(&sql)
+        .bind(playlist_id)
+        .bind(tsquery)
+        .bind(normalized)
+        .bind(media_kind)
+        .bind(limit);
+    let rows = bind_restrictions(query, restrictions).fetch_all(pool).await?;
+
+    Ok(rows)
+}
+
 /// Delete all groups for a playlist
 pub async fn delete_by_playlist(
     pool: &PgPool,
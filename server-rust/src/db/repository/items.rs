@@ -1,25 +1,46 @@
 //! Playlist items repository with streaming writes
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
 use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::db::models::{format_copy_line, ItemRow, NewItem};
 use crate::models::playlist::PlaylistItem;
+use crate::services::parental::ContentRestrictions;
 
 /// Streaming database writer for bulk item inserts
 /// Uses PostgreSQL COPY protocol for 50x faster inserts
+///
+/// Items keep the time their content first appeared in the playlist (from
+/// earlier imports of the same URL); new content gets the import's start.
 pub struct StreamingDbWriter<'a> {
     tx: Transaction<'a, Postgres>,
     playlist_id: Uuid,
     batch: Vec<NewItem>,
     batch_size: usize,
     items_written: usize,
+    first_seen: HashMap<String, DateTime<Utc>>,
+    started_at: DateTime<Utc>,
 }
 
 impl<'a> StreamingDbWriter<'a> {
     /// Create a new streaming writer
     pub async fn new(pool: &PgPool, playlist_id: Uuid) -> Result<StreamingDbWriter<'static>, sqlx::Error> {
-        let tx = pool.begin().await?;
+        let mut tx = pool.begin().await?;
+
+        let first_seen: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            SELECT s.content_id, s.first_seen_at
+            FROM playlist_content_seen s
+            JOIN playlists p ON p.hash = s.playlist_hash
+            WHERE p.id = $1
+            "#,
+        )
+        .bind(playlist_id)
+        .fetch_all(&mut *tx)
+        .await?;
 
         Ok(StreamingDbWriter {
             tx,
@@ -27,12 +48,15 @@ impl<'a> StreamingDbWriter<'a> {
             batch: Vec::with_capacity(500),
             batch_size: 500,
             items_written: 0,
+            first_seen: first_seen.into_iter().collect(),
+            started_at: Utc::now(),
         })
     }
 
     /// Write a single item (batched)
     pub async fn write_item(&mut self, item: &PlaylistItem) -> Result<(), sqlx::Error> {
-        let new_item = NewItem::from_item(item, self.playlist_id, self.items_written as i32);
+        let mut new_item = NewItem::from_item(item, self.playlist_id, self.items_written as i32);
+        new_item.first_seen_at = self.first_seen.get(&new_item.content_id).copied().unwrap_or(self.started_at);
         self.batch.push(new_item);
         self.items_written += 1;
 
@@ -53,7 +77,9 @@ impl<'a> StreamingDbWriter<'a> {
         let copy_query = r#"
             COPY playlist_items (id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
                                  parsed_title, parsed_year, parsed_quality, series_id,
-                                 season_number, episode_number, sort_order)
+                                 season_number, episode_number, sort_order,
+                                 parsed_language, is_dubbed, is_subbed, is_multi_audio, content_id,
+                                 parsed_season, parsed_episode, quality_rank, first_seen_at)
             FROM STDIN WITH (FORMAT text, NULL '\N')
         "#;
 
@@ -75,6 +101,22 @@ impl<'a> StreamingDbWriter<'a> {
         // Flush any remaining items
         self.flush_batch().await?;
 
+        // Remember first-seen times for the next import
+        sqlx::query(
+            r#"
+            INSERT INTO playlist_content_seen (playlist_hash, content_id, first_seen_at, last_seen_at)
+            SELECT p.hash, i.content_id, MIN(i.first_seen_at), NOW()
+            FROM playlist_items i
+            JOIN playlists p ON p.id = i.playlist_id
+            WHERE i.playlist_id = $1 AND i.content_id IS NOT NULL
+            GROUP BY p.hash, i.content_id
+            ON CONFLICT (playlist_hash, content_id) DO UPDATE SET last_seen_at = EXCLUDED.last_seen_at
+            "#,
+        )
+        .bind(self.playlist_id)
+        .execute(&mut *self.tx)
+        .await?;
+
         // Commit the transaction
         self.tx.commit().await?;
 
@@ -87,6 +129,20 @@ impl<'a> StreamingDbWriter<'a> {
     }
 }
 
+/// Forget first-seen times of content no import has had for `max_age_days`
+///
+/// Content that comes back after that counts as recently added again.
+pub async fn delete_unseen_content(pool: &PgPool, max_age_days: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM playlist_content_seen WHERE last_seen_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(max_age_days as i32)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
 /// Delete all items for a playlist
 pub async fn delete_by_playlist(
     pool: &PgPool,
@@ -100,168 +156,487 @@ pub async fn delete_by_playlist(
     Ok(result.rows_affected())
 }
 
-/// Get items with pagination and optional filters
+/// Columns selected into `ItemRow`
+pub(super) const ITEM_COLUMNS: &str = r#"
+    id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
+    parsed_title, parsed_year, parsed_quality, series_id,
+    season_number, episode_number, sort_order,
+    parsed_language, is_dubbed, is_subbed, is_multi_audio, content_id,
+    parsed_season, parsed_episode
+"#;
+
+/// WHERE clause shared by get_items/count_items ($1 = playlist_id; facet_counts
+/// binds the same parameters)
+const ITEM_FILTERS: &str = r#"
+    playlist_id = $1
+    AND ($2::text IS NULL OR group_name = $2)
+    AND ($3::text IS NULL OR media_kind = $3)
+    AND ($4::text IS NULL OR upper(parsed_quality) = upper($4))
+    AND ($5::text IS NULL OR upper(parsed_language) = upper($5))
+    AND ($6::boolean IS NULL OR is_dubbed = $6)
+    AND ($7::boolean IS NULL OR is_subbed = $7)
+    AND ($8::boolean IS NULL OR is_multi_audio = $8)
+    AND ($9::smallint IS NULL OR parsed_year >= $9)
+    AND ($10::smallint IS NULL OR parsed_year <= $10)
+"#;
+
+/// Optional filters for item listing (group/kind plus parsed-title facets)
+#[derive(Debug, Default, Clone)]
+pub struct ItemFilters<'a> {
+    pub group: Option<&'a str>,
+    pub media_kind: Option<&'a str>,
+    pub quality: Option<&'a str>,
+    pub language: Option<&'a str>,
+    pub dubbed: Option<bool>,
+    pub subbed: Option<bool>,
+    pub multi_audio: Option<bool>,
+    pub year_from: Option<i16>,
+    pub year_to: Option<i16>,
+    /// Parental restrictions of the requesting device
+    pub restrictions: Option<&'a ContentRestrictions>,
+}
+
+/// Parental restriction clause with its 4 parameters starting at `$first`,
+/// over the row's adult flag, media kind, group and searchable text
+pub(super) fn restriction_clause(first: usize, adult: &str, kind: &str, group: &str, text: &str) -> String {
+    format!(
+        r#"
+        AND NOT (${a}::boolean AND {adult})
+        AND NOT ({kind} = ANY(${b}::text[]))
+        AND NOT (lower({group}) = ANY(${c}::text[]))
+        AND NOT (f_unaccent(lower({text})) LIKE ANY(${d}::text[]))
+        "#,
+        a = first,
+        b = first + 1,
+        c = first + 2,
+        d = first + 3,
+        adult = adult,
+        kind = kind,
+        group = group,
+        text = text
+    )
+}
+
+/// Bind the parameters of `restriction_clause` (no-ops without restrictions)
+pub(super) fn bind_restrictions<'q, O>(
+    query: sqlx::query::QueryAs<'q, Postgres, O, sqlx::postgres::PgArguments>,
+    restrictions: Option<&'q ContentRestrictions>,
+) -> sqlx::query::QueryAs<'q, Postgres, O, sqlx::postgres::PgArguments> {
+    match restrictions {
+        Some(r) => query
+            .bind(r.hide_adult)
+            .bind(&r.blocked_kinds)
+            .bind(&r.blocked_groups)
+            .bind(r.keyword_patterns()),
+        None => query
+            .bind(false)
+            .bind(Vec::<String>::new())
+            .bind(Vec::<String>::new())
+            .bind(Vec::<String>::new()),
+    }
+}
+
+impl<'a> ItemFilters<'a> {
+    /// Parental restriction clause with its 4 parameters starting at `$first`
+    fn restriction_sql(first: usize) -> String {
+        restriction_clause(first, "is_adult", "media_kind", "group_name", "name || ' ' || group_name")
+    }
+
+    /// Bind the parameters of `restriction_sql` (no-ops without restrictions)
+    fn bind_restrictions<'q, O>(
+        &self,
+        query: sqlx::query::QueryAs<'q, Postgres, O, sqlx::postgres::PgArguments>,
+    ) -> sqlx::query::QueryAs<'q, Postgres, O, sqlx::postgres::PgArguments>
+    where
+        'a: 'q,
+    {
+        bind_restrictions(query, self.restrictions)
+    }
+
+    /// Bind filters as $2..$10 (matches ITEM_FILTERS)
+    fn bind<'q, O>(
+        &self,
+        query: sqlx::query::QueryAs<'q, Postgres, O, sqlx::postgres::PgArguments>,
+    ) -> sqlx::query::QueryAs<'q, Postgres, O, sqlx::postgres::PgArguments>
+    where
+        'a: 'q,
+    {
+        query
+            .bind(self.group)
+            .bind(self.media_kind)
+            .bind(self.quality)
+            .bind(self.language)
+            .bind(self.dubbed)
+            .bind(self.subbed)
+            .bind(self.multi_audio)
+            .bind(self.year_from)
+            .bind(self.year_to)
+    }
+}
+
+/// Sort modes for item listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ItemSort {
+    /// Playlist order (sort_order)
+    #[default]
+    Default,
+    /// Accent/emoji-insensitive title
+    Name,
+    /// Newest year first
+    Year,
+    /// Recently added first (by when the content first appeared in the playlist)
+    Recent,
+    /// Best quality first
+    Quality,
+}
+
+impl ItemSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "default" | "" => Some(ItemSort::Default),
+            "name" => Some(ItemSort::Name),
+            "year" => Some(ItemSort::Year),
+            "recent" => Some(ItemSort::Recent),
+            "quality" => Some(ItemSort::Quality),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemSort::Default => "default",
+            ItemSort::Name => "name",
+            ItemSort::Year => "year",
+            ItemSort::Recent => "recent",
+            ItemSort::Quality => "quality",
+        }
+    }
+
+    /// Sort key expression and the type its cursor value is cast back to
+    fn key(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            ItemSort::Default => None,
+            ItemSort::Name => Some(("sort_name", "text COLLATE \"C\"")),
+            ItemSort::Year => Some(("COALESCE(parsed_year, 0)", "smallint")),
+            ItemSort::Recent => Some(("first_seen_at", "timestamptz")),
+            ItemSort::Quality => Some(("quality_rank", "smallint")),
+        }
+    }
+
+    fn key_descending(&self) -> bool {
+        matches!(self, ItemSort::Year | ItemSort::Recent | ItemSort::Quality)
+    }
+
+    /// Ties are always broken by playlist order (sort_order, id ascending)
+    fn order_by(&self) -> String {
+        match self.key() {
+            Some((key, _)) => format!(
+                "{} {}, sort_order ASC, id ASC",
+                key,
+                if self.key_descending() { "DESC" } else { "ASC" }
+            ),
+            None => "sort_order ASC, id ASC".to_string(),
+        }
+    }
+
+    /// Rows strictly after the cursor ($13 = key, $14 = sort_order, $15 = id)
+    fn after_cursor(&self) -> String {
+        match self.key() {
+            None => "(sort_order, id) > ($14, $15)".to_string(),
+            Some((key, cast)) if !self.key_descending() => {
+                format!("({}, sort_order, id) > ($13::{}, $14, $15)", key, cast)
+            }
+            Some((key, cast)) => format!(
+                "({key} < $13::{cast} OR ({key} = $13::{cast} AND (sort_order, id) > ($14, $15)))",
+                key = key,
+                cast = cast
+            ),
+        }
+    }
+}
+
+/// Keyset position: sort key, sort_order and id of the last row of a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemCursor {
+    pub sort: String,
+    pub key: Option<String>,
+    pub sort_order: i32,
+    pub id: Uuid,
+}
+
+impl ItemCursor {
+    /// Opaque URL-safe token ("sort|sort_order|id|key", key last since it may contain '|')
+    pub fn encode(&self) -> String {
+        let raw = match &self.key {
+            Some(key) => format!("{}|{}|{}|{}", self.sort, self.sort_order, self.id, key),
+            None => format!("{}|{}|{}", self.sort, self.sort_order, self.id),
+        };
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    /// Decode a token; None if malformed or created for another sort mode
+    pub fn decode(token: &str, sort: ItemSort) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(token).ok()?).ok()?;
+        let mut parts = raw.splitn(4, '|');
+        let cursor = ItemCursor {
+            sort: parts.next()?.to_string(),
+            sort_order: parts.next()?.parse().ok()?,
+            id: parts.next()?.parse().ok()?,
+            key: parts.next().map(str::to_string),
+        };
+        (cursor.sort == sort.as_str()).then_some(cursor)
+    }
+}
+
+/// Item row plus its sort key (as text) for building the next cursor
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct SortedItemRow {
+    #[sqlx(flatten)]
+    item: ItemRow,
+    sort_key: Option<String>,
+}
+
+/// Get a page of items with optional filters
+///
+/// With a cursor the page starts right after it (keyset pagination, offset is
+/// ignored); otherwise `offset` is applied. Returns the rows and the cursor of
+/// the next page, if there is one.
 pub async fn get_items(
     pool: &PgPool,
     playlist_id: Uuid,
-    group: Option<&str>,
-    media_kind: Option<&str>,
+    filters: &ItemFilters<'_>,
+    sort: ItemSort,
+    cursor: Option<&ItemCursor>,
     limit: i64,
     offset: i64,
-) -> Result<Vec<ItemRow>, sqlx::Error> {
-    let rows = match (group, media_kind) {
-        (Some(g), Some(k)) => {
-            sqlx::query_as::<_, ItemRow>(
-                r#"
-                SELECT id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
-                       parsed_title, parsed_year, parsed_quality, series_id,
-                       season_number, episode_number, sort_order
-                FROM playlist_items
-                WHERE playlist_id = $1 AND group_name = $2 AND media_kind = $3
-                ORDER BY sort_order
-                LIMIT $4 OFFSET $5
-                "#,
-            )
-            .bind(playlist_id)
-            .bind(g)
-            .bind(k)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(pool)
-            .await?
-        }
-        (Some(g), None) => {
-            sqlx::query_as::<_, ItemRow>(
-                r#"
-                SELECT id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
-                       parsed_title, parsed_year, parsed_quality, series_id,
-                       season_number, episode_number, sort_order
-                FROM playlist_items
-                WHERE playlist_id = $1 AND group_name = $2
-                ORDER BY sort_order
-                LIMIT $3 OFFSET $4
-                "#,
-            )
-            .bind(playlist_id)
-            .bind(g)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(pool)
-            .await?
-        }
-        (None, Some(k)) => {
-            sqlx::query_as::<_, ItemRow>(
-                r#"
-                SELECT id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
-                       parsed_title, parsed_year, parsed_quality, series_id,
-                       season_number, episode_number, sort_order
-                FROM playlist_items
-                WHERE playlist_id = $1 AND media_kind = $2
-                ORDER BY sort_order
-                LIMIT $3 OFFSET $4
-                "#,
-            )
-            .bind(playlist_id)
-            .bind(k)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(pool)
-            .await?
-        }
-        (None, None) => {
-            sqlx::query_as::<_, ItemRow>(
-                r#"
-                SELECT id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
-                       parsed_title, parsed_year, parsed_quality, series_id,
-                       season_number, episode_number, sort_order
-                FROM playlist_items
-                WHERE playlist_id = $1
-                ORDER BY sort_order
-                LIMIT $2 OFFSET $3
-                "#,
-            )
-            .bind(playlist_id)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(pool)
-            .await?
-        }
+) -> Result<(Vec<ItemRow>, Option<ItemCursor>), sqlx::Error> {
+    let sort_key = sort
+        .key()
+        .map(|(key, _)| format!("{}::text", key))
+        .unwrap_or_else(|| "NULL::text".to_string());
+    let keyset = match cursor {
+        Some(_) => format!("AND {}", sort.after_cursor()),
+        None => String::new(),
     };
+    // Restriction parameters follow the cursor's ($13..$15) when there is one
+    let restrictions = ItemFilters::restriction_sql(if cursor.is_some() { 16 } else { 13 });
+    let sql = format!(
+        "SELECT {}, {} AS sort_key FROM playlist_items WHERE {} {} {} ORDER BY {} LIMIT $11 OFFSET $12",
+        ITEM_COLUMNS,
+        sort_key,
+        ITEM_FILTERS,
+        restrictions,
+        keyset,
+        sort.order_by()
+    );
 
-    Ok(rows)
+    // Fetch one extra row to know whether there is a next page
+    let query = sqlx::query_as::<_, SortedItemRow>(&sql).bind(playlist_id);
+    let mut query = filters
+        .bind(query)
+        .bind(limit + 1)
+        .bind(if cursor.is_some() { 0 } else { offset });
+    if let Some(cursor) = cursor {
+        query = query.bind(&cursor.key).bind(cursor.sort_order).bind(cursor.id);
+    }
+    let mut rows = filters.bind_restrictions(query).fetch_all(pool).await?;
+
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit.max(0) as usize);
+        rows.last().map(|last| ItemCursor {
+            sort: sort.as_str().to_string(),
+            key: last.sort_key.clone(),
+            sort_order: last.item.sort_order,
+            id: last.item.id,
+        })
+    } else {
+        None
+    };
+
+    Ok((rows.into_iter().map(|r| r.item).collect(), next_cursor))
 }
 
 /// Count items with optional filters
 pub async fn count_items(
     pool: &PgPool,
     playlist_id: Uuid,
-    group: Option<&str>,
-    media_kind: Option<&str>,
+    filters: &ItemFilters<'_>,
 ) -> Result<i64, sqlx::Error> {
-    let count: (i64,) = match (group, media_kind) {
-        (Some(g), Some(k)) => {
-            sqlx::query_as(
-                "SELECT COUNT(*) FROM playlist_items WHERE playlist_id = $1 AND group_name = $2 AND media_kind = $3",
-            )
-            .bind(playlist_id)
-            .bind(g)
-            .bind(k)
-            .fetch_one(pool)
-            .await?
-        }
-        (Some(g), None) => {
-            sqlx::query_as(
-                "SELECT COUNT(*) FROM playlist_items WHERE playlist_id = $1 AND group_name = $2",
-            )
-            .bind(playlist_id)
-            .bind(g)
-            .fetch_one(pool)
-            .await?
-        }
-        (None, Some(k)) => {
-            sqlx::query_as(
-                "SELECT COUNT(*) FROM playlist_items WHERE playlist_id = $1 AND media_kind = $2",
-            )
-            .bind(playlist_id)
-            .bind(k)
-            .fetch_one(pool)
-            .await?
-        }
-        (None, None) => {
-            sqlx::query_as(
-                "SELECT COUNT(*) FROM playlist_items WHERE playlist_id = $1",
-            )
-            .bind(playlist_id)
-            .fetch_one(pool)
-            .await?
-        }
-    };
+    let sql = format!(
+        "SELECT COUNT(*) FROM playlist_items WHERE {} {}",
+        ITEM_FILTERS,
+        ItemFilters::restriction_sql(11)
+    );
+
+    let query = sqlx::query_as::<_, (i64,)>(&sql).bind(playlist_id);
+    let count = filters.bind_restrictions(filters.bind(query)).fetch_one(pool).await?;
 
     Ok(count.0)
 }
 
-/// Search items using fuzzy matching (pg_trgm)
+/// Facet counts (facet, value, count) for items matching the filters
+///
+/// Facets: quality, language and year (one row per value), plus dubbed,
+/// subbed and multi_audio (single row, value NULL). Each facet is counted
+/// with every filter except its own, so picking a quality still shows how
+/// many items the other qualities have.
+pub async fn facet_counts(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    filters: &ItemFilters<'_>,
+) -> Result<Vec<(String, Option<String>, i64)>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        WITH f AS (
+            SELECT parsed_quality, parsed_language, parsed_year, is_dubbed, is_subbed, is_multi_audio,
+                   ($4::text IS NULL OR upper(parsed_quality) = upper($4)) AS m_quality,
+                   ($5::text IS NULL OR upper(parsed_language) = upper($5)) AS m_language,
+                   ($6::boolean IS NULL OR is_dubbed = $6) AS m_dubbed,
+                   ($7::boolean IS NULL OR is_subbed = $7) AS m_subbed,
+                   ($8::boolean IS NULL OR is_multi_audio = $8) AS m_multi_audio,
+                   (($9::smallint IS NULL OR parsed_year >= $9)
+                    AND ($10::smallint IS NULL OR parsed_year <= $10)) AS m_year
+            FROM playlist_items
+            WHERE playlist_id = $1
+              AND ($2::text IS NULL OR group_name = $2)
+              AND ($3::text IS NULL OR media_kind = $3)
+              {}
+        )
+        SELECT 'quality', upper(parsed_quality), COUNT(*) FROM f
+            WHERE parsed_quality IS NOT NULL
+              AND m_language AND m_dubbed AND m_subbed AND m_multi_audio AND m_year
+            GROUP BY 2
+        UNION ALL
+        SELECT 'language', upper(parsed_language), COUNT(*) FROM f
+            WHERE parsed_language IS NOT NULL
+              AND m_quality AND m_dubbed AND m_subbed AND m_multi_audio AND m_year
+            GROUP BY 2
+        UNION ALL
+        SELECT 'year', parsed_year::text, COUNT(*) FROM f
+            WHERE parsed_year IS NOT NULL
+              AND m_quality AND m_language AND m_dubbed AND m_subbed AND m_multi_audio
+            GROUP BY 2
+        UNION ALL
+        SELECT 'dubbed', NULL, COUNT(*) FROM f
+            WHERE is_dubbed AND m_quality AND m_language AND m_subbed AND m_multi_audio AND m_year
+        UNION ALL
+        SELECT 'subbed', NULL, COUNT(*) FROM f
+            WHERE is_subbed AND m_quality AND m_language AND m_dubbed AND m_multi_audio AND m_year
+        UNION ALL
+        SELECT 'multi_audio', NULL, COUNT(*) FROM f
+            WHERE is_multi_audio AND m_quality AND m_language AND m_dubbed AND m_subbed AND m_year
+        "#,
+        ItemFilters::restriction_sql(11)
+    );
+
+    let query = sqlx::query_as::<_, (String, Option<String>, i64)>(&sql).bind(playlist_id);
+    let rows = filters.bind_restrictions(filters.bind(query)).fetch_all(pool).await?;
+
+    Ok(rows)
+}
+
+/// Optional filters applied to a search
+#[derive(Debug, Default, Clone)]
+pub struct SearchFilters<'a> {
+    pub media_kind: Option<&'a str>,
+    pub group: Option<&'a str>,
+    pub year: Option<i16>,
+    /// Parental restrictions of the requesting device
+    pub restrictions: Option<&'a ContentRestrictions>,
+}
+
+/// Search hit with its combined relevance score
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SearchHitRow {
+    #[sqlx(flatten)]
+    pub item: ItemRow,
+    pub rank: f32,
+    /// Total matches before LIMIT (same on every row)
+    pub total: i64,
+}
+
+/// Accent-insensitive search over name and parsed_title
+///
+/// `tsquery` is a prefix query built by `services::search::prefix_tsquery` and
+/// `normalized` the lowercased/unaccented text used for the trigram fallback.
+/// Rank = full-text rank (weighted name > title > group) + best trigram similarity.
 pub async fn search_items(
     pool: &PgPool,
     playlist_id: Uuid,
-    query: &str,
+    tsquery: &str,
+    normalized: &str,
+    filters: &SearchFilters<'_>,
     limit: i64,
-) -> Result<Vec<ItemRow>, sqlx::Error> {
-    let rows = sqlx::query_as::<_, ItemRow>(
+) -> Result<Vec<SearchHitRow>, sqlx::Error> {
+    let sql = format!(
         r#"
+        WITH q AS (
+            SELECT to_tsquery('simple', f_unaccent($2)) AS ts, $3::text AS norm
+        )
         SELECT id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
                parsed_title, parsed_year, parsed_quality, series_id,
-               season_number, episode_number, sort_order
+               season_number, episode_number, sort_order,
+               parsed_language, is_dubbed, is_subbed, is_multi_audio, content_id,
+               parsed_season, parsed_episode,
+               (ts_rank(search_vector, q.ts) * 2 + GREATEST(
+                    similarity(f_unaccent(lower(name)), q.norm),
+                    similarity(f_unaccent(lower(coalesce(parsed_title, ''))), q.norm)
+               ))::REAL AS rank,
+               COUNT(*) OVER() AS total
+        FROM playlist_items, q
+        WHERE playlist_id = $1
+          AND ($4::text IS NULL OR media_kind = $4)
+          AND ($5::text IS NULL OR group_name = $5)
+          AND ($6::smallint IS NULL OR parsed_year = $6)
+          AND (search_vector @@ q.ts
+               OR f_unaccent(lower(name)) % q.norm
+               OR f_unaccent(lower(parsed_title)) % q.norm
+               OR f_unaccent(lower(name)) LIKE '%' || q.norm || '%')
+          {}
+        ORDER BY rank DESC, sort_order
+        LIMIT $7
+        "#,
+        ItemFilters::restriction_sql(8)
+    );
+
+    let query = sqlx::query_as::<_, SearchHitRow>(&sql)
+        .bind(playlist_id)
+        .bind(tsquery)
+        .bind(normalized)
+        .bind(filters.media_kind)
+        .bind(filters.group)
+        .bind(filters.year)
+        .bind(limit);
+    let rows = bind_restrictions(query, filters.restrictions).fetch_all(pool).await?;
+
+    Ok(rows)
+}
+
+/// Distinct suggestion terms for a playlist as (text, kind, weight)
+///
+/// Item titles (episodes excluded, they are covered by their series), series
+/// names weighted by episode count, and group names weighted by item count.
+pub async fn suggestion_terms(
+    pool: &PgPool,
+    playlist_id: Uuid,
+) -> Result<Vec<(String, String, i64)>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String, i64)>(
+        r#"
+        SELECT coalesce(parsed_title, name), media_kind, COUNT(*)::BIGINT
         FROM playlist_items
+        WHERE playlist_id = $1 AND media_kind <> 'series'
+        GROUP BY 1, 2
+        UNION ALL
+        SELECT name, 'series', total_episodes::BIGINT
+        FROM series
+        WHERE playlist_id = $1
+        UNION ALL
+        SELECT name, 'group', item_count::BIGINT
+        FROM playlist_groups
         WHERE playlist_id = $1
-          AND (name % $2 OR name ILIKE '%' || $2 || '%')
-        ORDER BY similarity(name, $2) DESC
-        LIMIT $3
         "#,
     )
     .bind(playlist_id)
-    .bind(query)
-    .bind(limit)
     .fetch_all(pool)
     .await?;
 
@@ -278,7 +653,9 @@ pub async fn get_by_hash(
         r#"
         SELECT id, playlist_id, item_hash, name, url, logo, group_name, media_kind,
                parsed_title, parsed_year, parsed_quality, series_id,
-               season_number, episode_number, sort_order
+               season_number, episode_number, sort_order,
+               parsed_language, is_dubbed, is_subbed, is_multi_audio, content_id,
+               parsed_season, parsed_episode
         FROM playlist_items
         WHERE playlist_id = $1 AND item_hash = $2
         "#,
@@ -291,10 +668,179 @@ pub async fn get_by_hash(
     Ok(row)
 }
 
+/// Get several items of a playlist by hash
+pub async fn get_by_hashes(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    item_hashes: &[String],
+) -> Result<Vec<ItemRow>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        SELECT {}
+        FROM playlist_items
+        WHERE playlist_id = $1 AND item_hash = ANY($2)
+        "#,
+        ITEM_COLUMNS
+    );
+
+    sqlx::query_as::<_, ItemRow>(&sql)
+        .bind(playlist_id)
+        .bind(item_hashes)
+        .fetch_all(pool)
+        .await
+}
+
+/// Current item for each content identity (first in playlist order)
+pub async fn get_by_content_ids(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    content_ids: &[String],
+) -> Result<Vec<ItemRow>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        SELECT DISTINCT ON (content_id) {}
+        FROM playlist_items
+        WHERE playlist_id = $1 AND content_id = ANY($2)
+        ORDER BY content_id, sort_order
+        "#,
+        ITEM_COLUMNS
+    );
+
+    sqlx::query_as::<_, ItemRow>(&sql)
+        .bind(playlist_id)
+        .bind(content_ids)
+        .fetch_all(pool)
+        .await
+}
+
 /// Count all items for a playlist
 pub async fn count_by_playlist(
     pool: &PgPool,
     playlist_id: Uuid,
 ) -> Result<i64, sqlx::Error> {
-    count_items(pool, playlist_id, None, None).await
+    count_items(pool, playlist_id, &ItemFilters::default()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_roundtrip_and_sort_mismatch() {
+        let cursor = ItemCursor {
+            sort: "name".to_string(),
+            key: Some("acao total".to_string()),
+            sort_order: 42,
+            id: Uuid::nil(),
+        };
+        let token = cursor.encode();
+        assert_eq!(ItemCursor::decode(&token, ItemSort::Name), Some(cursor));
+        assert_eq!(ItemCursor::decode(&token, ItemSort::Year), None);
+        assert_eq!(ItemCursor::decode("not a cursor", ItemSort::Name), None);
+    }
+
+    #[test]
+    fn test_keyset_conditions() {
+        assert_eq!(ItemSort::Default.after_cursor(), "(sort_order, id) > ($14, $15)");
+        assert_eq!(
+            ItemSort::Name.after_cursor(),
+            "(sort_name, sort_order, id) > ($13::text COLLATE \"C\", $14, $15)"
+        );
+        assert_eq!(ItemSort::parse("recent"), Some(ItemSort::Recent));
+        assert_eq!(
+            ItemSort::Recent.order_by(),
+            "first_seen_at DESC, sort_order ASC, id ASC"
+        );
+        assert!(ItemSort::Recent
+            .after_cursor()
+            .starts_with("(first_seen_at < $13::timestamptz OR (first_seen_at = $13::timestamptz AND"));
+        // Mixed directions can't use a row comparison
+        assert!(ItemSort::Year.after_cursor().starts_with("(COALESCE(parsed_year, 0) < $13::smallint OR"));
+        assert_eq!(ItemSort::Quality.order_by(), "quality_rank DESC, sort_order ASC, id ASC");
+    }
+
+    #[test]
+    fn test_restriction_clause_placeholders() {
+        let sql = restriction_clause(6, "is_adult", "media_kind", "name", "name");
+        assert!(sql.contains("NOT ($6::boolean AND is_adult)"));
+        assert!(sql.contains("NOT (media_kind = ANY($7::text[]))"));
+        assert!(sql.contains("NOT (lower(name) = ANY($8::text[]))"));
+        assert!(sql.contains("LIKE ANY($9::text[])"));
+    }
+
+    /// Needs a database: runs against TEST_DATABASE_URL, skipped when unset
+    #[tokio::test]
+    async fn test_recent_sort_keeps_first_seen_across_imports() {
+        use crate::models::playlist::MediaKind;
+
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            return;
+        };
+        let pool = PgPool::connect(&url).await.unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let hash = format!("test-{}", &Uuid::new_v4().simple().to_string()[..30]);
+        let item = |id: &str| PlaylistItem {
+            id: id.to_string(),
+            content_id: format!("c_{}", id),
+            name: id.to_string(),
+            url: format!("http://example.com/{}.ts", id),
+            logo: None,
+            group: "Canais".to_string(),
+            media_kind: MediaKind::Live,
+            parsed_title: None,
+            epg_id: None,
+            series_id: None,
+            season_number: None,
+            episode_number: None,
+        };
+        // The playlist record is deleted on expiry and created again by the next import
+        let import = |ids: &'static [&'static str]| {
+            let pool = pool.clone();
+            let hash = hash.clone();
+            async move {
+                sqlx::query("DELETE FROM playlists WHERE hash = $1").bind(&hash).execute(&pool).await.unwrap();
+                let (playlist_id,): (Uuid,) =
+                    sqlx::query_as("INSERT INTO playlists (hash, url) VALUES ($1, 'http://example.com') RETURNING id")
+                        .bind(&hash)
+                        .fetch_one(&pool)
+                        .await
+                        .unwrap();
+                let mut writer = StreamingDbWriter::new(&pool, playlist_id).await.unwrap();
+                for id in ids {
+                    writer.write_item(&item(id)).await.unwrap();
+                }
+                writer.finish().await.unwrap();
+                playlist_id
+            }
+        };
+
+        import(&["a", "b"]).await;
+        let playlist_id = import(&["b", "c", "a"]).await;
+
+        let (rows, _) = get_items(&pool, playlist_id, &ItemFilters::default(), ItemSort::Recent, None, 10, 0)
+            .await
+            .unwrap();
+        let order: Vec<&str> = rows.iter().map(|row| row.item_hash.as_str()).collect();
+        // New content first, then the rest in playlist order
+        assert_eq!(order, ["c", "b", "a"]);
+
+        // Keyset pages follow the same order
+        let (first, cursor) = get_items(&pool, playlist_id, &ItemFilters::default(), ItemSort::Recent, None, 1, 0)
+            .await
+            .unwrap();
+        let (second, _) =
+            get_items(&pool, playlist_id, &ItemFilters::default(), ItemSort::Recent, cursor.as_ref(), 1, 0)
+                .await
+                .unwrap();
+        assert_eq!(first[0].item_hash, "c");
+        assert_eq!(second[0].item_hash, "b");
+
+        sqlx::query("DELETE FROM playlists WHERE hash = $1").bind(&hash).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM playlist_content_seen WHERE playlist_hash = $1")
+            .bind(&hash)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
 }
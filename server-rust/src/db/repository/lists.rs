@@ -0,0 +1,337 @@
+//! Favorites and user lists repository
+//!
+//! Lists belong to a device_id. Each device has one built-in favorites list
+//! (created on first use) plus any number of user-named lists. Entries are
+//! keyed by a stable identity: content_id for items, series id for series,
+//! or an Xtream stream key.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// List row with its entry count
+#[derive(Debug, Clone, FromRow)]
+pub struct ListRow {
+    pub id: Uuid,
+    pub name: String,
+    pub is_favorites: bool,
+    pub position: i32,
+    pub item_count: i64,
+}
+
+/// Database row for a list entry
+#[derive(Debug, Clone, FromRow)]
+pub struct ListEntryRow {
+    pub entry_key: String,
+    pub media_kind: String,
+    pub name: String,
+    pub logo: Option<String>,
+    pub xtream_stream_id: Option<i64>,
+    pub xtream_extension: Option<String>,
+    pub position: i32,
+    pub added_at: DateTime<Utc>,
+}
+
+/// New list entry
+#[derive(Debug, Clone)]
+pub struct NewListEntry {
+    pub entry_key: String,
+    pub media_kind: String,
+    pub name: String,
+    pub logo: Option<String>,
+    pub xtream_stream_id: Option<i64>,
+    pub xtream_extension: Option<String>,
+}
+
+const LIST_COLUMNS: &str = r#"
+    l.id, l.name, l.is_favorites, l.position,
+    (SELECT COUNT(*) FROM user_list_items e WHERE e.list_id = l.id) AS item_count
+"#;
+
+/// All lists of a device (favorites first, then by position)
+pub async fn get_lists(pool: &PgPool, device_id: &str) -> Result<Vec<ListRow>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {} FROM user_lists l WHERE l.device_id = $1 ORDER BY l.is_favorites DESC, l.position, l.created_at",
+        LIST_COLUMNS
+    );
+
+    sqlx::query_as::<_, ListRow>(&sql)
+        .bind(device_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Get one list of a device
+pub async fn get_list(
+    pool: &PgPool,
+    device_id: &str,
+    list_id: Uuid,
+) -> Result<Option<ListRow>, sqlx::Error> {
+    let sql = format!(
+        "SELECT {} FROM user_lists l WHERE l.device_id = $1 AND l.id = $2",
+        LIST_COLUMNS
+    );
+
+    sqlx::query_as::<_, ListRow>(&sql)
+        .bind(device_id)
+        .bind(list_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The device's favorites list, created on first use
+pub async fn favorites(pool: &PgPool, device_id: &str) -> Result<ListRow, sqlx::Error> {
+    let (list_id,): (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO user_lists (device_id, name, is_favorites)
+        VALUES ($1, 'Favoritos', TRUE)
+        ON CONFLICT (device_id) WHERE is_favorites DO UPDATE SET device_id = EXCLUDED.device_id
+        RETURNING id
+        "#,
+    )
+    .bind(device_id)
+    .fetch_one(pool)
+    .await?;
+
+    get_list(pool, device_id, list_id)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)
+}
+
+/// Number of lists of a device (favorites included)
+pub async fn count_lists(pool: &PgPool, device_id: &str) -> Result<i64, sqlx::Error> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_lists WHERE device_id = $1")
+        .bind(device_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Create a user-named list (appended after existing ones)
+///
+/// Returns None if the device already has a list with that name or already
+/// has `max_lists` lists.
+pub async fn create_list(
+    pool: &PgPool,
+    device_id: &str,
+    name: &str,
+    max_lists: i64,
+) -> Result<Option<ListRow>, sqlx::Error> {
+    let created: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        INSERT INTO user_lists (device_id, name, position)
+        SELECT $1, $2, COALESCE(MAX(position) + 1, 0) FROM user_lists WHERE device_id = $1
+        HAVING COUNT(*) < $3
+        ON CONFLICT (device_id, lower(name)) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(device_id)
+    .bind(name)
+    .bind(max_lists)
+    .fetch_optional(pool)
+    .await?;
+
+    match created {
+        Some((list_id,)) => get_list(pool, device_id, list_id).await,
+        None => Ok(None),
+    }
+}
+
+/// Rename a list; false if not found or the name is taken
+pub async fn rename_list(
+    pool: &PgPool,
+    device_id: &str,
+    list_id: Uuid,
+    name: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE user_lists SET name = $3
+        WHERE device_id = $1 AND id = $2
+          AND NOT EXISTS (
+              SELECT 1 FROM user_lists
+              WHERE device_id = $1 AND id <> $2 AND lower(name) = lower($3)
+          )
+        "#,
+    )
+    .bind(device_id)
+    .bind(list_id)
+    .bind(name)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete a user-named list (the favorites list can't be deleted)
+pub async fn delete_list(pool: &PgPool, device_id: &str, list_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM user_lists WHERE device_id = $1 AND id = $2 AND NOT is_favorites",
+    )
+    .bind(device_id)
+    .bind(list_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Entries of a list in order
+pub async fn get_entries(pool: &PgPool, list_id: Uuid) -> Result<Vec<ListEntryRow>, sqlx::Error> {
+    sqlx::query_as::<_, ListEntryRow>(
+        r#"
+        SELECT entry_key, media_kind, name, logo, xtream_stream_id, xtream_extension,
+               position, added_at
+        FROM user_list_items
+        WHERE list_id = $1
+        ORDER BY position, added_at
+        "#,
+    )
+    .bind(list_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Append an entry to a list; false if it's already there or the list
+/// already has `max_entries` entries
+pub async fn add_entry(
+    pool: &PgPool,
+    list_id: Uuid,
+    entry: &NewListEntry,
+    max_entries: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_list_items (list_id, entry_key, media_kind, name, logo,
+                                     xtream_stream_id, xtream_extension, position)
+        SELECT $1, $2, $3, $4, $5, $6, $7, COALESCE(MAX(position) + 1, 0)
+        FROM user_list_items WHERE list_id = $1
+        HAVING COUNT(*) < $8
+        ON CONFLICT (list_id, entry_key) DO NOTHING
+        "#,
+    )
+    .bind(list_id)
+    .bind(&entry.entry_key)
+    .bind(&entry.media_kind)
+    .bind(&entry.name)
+    .bind(&entry.logo)
+    .bind(entry.xtream_stream_id)
+    .bind(&entry.xtream_extension)
+    .bind(max_entries)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Remove an entry from a list
+pub async fn remove_entry(pool: &PgPool, list_id: Uuid, entry_key: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM user_list_items WHERE list_id = $1 AND entry_key = $2")
+        .bind(list_id)
+        .bind(entry_key)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// New order for `current`: `wanted` first (unknown and repeated ones are
+/// skipped), the rest after them in their current order
+pub fn ordered<K: Eq + Hash + Clone>(current: &[K], wanted: &[K]) -> Vec<K> {
+    let known: HashSet<&K> = current.iter().collect();
+    let mut placed = HashSet::new();
+
+    let mut order: Vec<K> = wanted
+        .iter()
+        .filter(|key| known.contains(key) && placed.insert(*key))
+        .cloned()
+        .collect();
+    order.extend(current.iter().filter(|key| !placed.contains(key)).cloned());
+    order
+}
+
+/// Reorder a list's entries (see [`ordered`])
+pub async fn reorder(pool: &PgPool, list_id: Uuid, keys: &[String]) -> Result<(), sqlx::Error> {
+    let current: Vec<String> = sqlx::query_scalar(
+        "SELECT entry_key FROM user_list_items WHERE list_id = $1 ORDER BY position, added_at",
+    )
+    .bind(list_id)
+    .fetch_all(pool)
+    .await?;
+    let order = ordered(&current, keys);
+    let positions: Vec<i32> = (0..order.len() as i32).collect();
+
+    sqlx::query(
+        r#"
+        UPDATE user_list_items e
+        SET position = o.position
+        FROM unnest($2::text[], $3::int4[]) AS o(entry_key, position)
+        WHERE e.list_id = $1 AND e.entry_key = o.entry_key
+        "#,
+    )
+    .bind(list_id)
+    .bind(&order)
+    .bind(&positions)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Reorder a device's named lists (see [`ordered`]); favorites stays first
+pub async fn reorder_lists(pool: &PgPool, device_id: &str, list_ids: &[Uuid]) -> Result<(), sqlx::Error> {
+    let current: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM user_lists WHERE device_id = $1 AND NOT is_favorites ORDER BY position, created_at",
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await?;
+    let order = ordered(&current, list_ids);
+    let positions: Vec<i32> = (0..order.len() as i32).collect();
+
+    sqlx::query(
+        r#"
+        UPDATE user_lists l
+        SET position = o.position
+        FROM unnest($2::uuid[], $3::int4[]) AS o(id, position)
+        WHERE l.device_id = $1 AND l.id = o.id
+        "#,
+    )
+    .bind(device_id)
+    .bind(&order)
+    .bind(&positions)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn test_ordered_moves_wanted_first() {
+        let current = keys(&["a", "b", "c", "d"]);
+        assert_eq!(ordered(&current, &keys(&["c", "a"])), keys(&["c", "a", "b", "d"]));
+        assert_eq!(ordered(&current, &[]), current);
+    }
+
+    #[test]
+    fn test_ordered_skips_unknown_and_repeated() {
+        let current = keys(&["a", "b", "c"]);
+        assert_eq!(
+            ordered(&current, &keys(&["x", "b", "b", "a"])),
+            keys(&["b", "a", "c"])
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Media metadata cache repository
+//!
+//! Provider lookups are keyed by normalized title + year, so the same movie in
+//! many playlists is fetched once. Misses are stored too (data NULL).
+
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::{FromRow, PgPool};
+
+/// Cached lookup result
+#[derive(Debug, Clone, FromRow)]
+pub struct MetadataCacheRow {
+    pub data: Option<Json<serde_json::Value>>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Find a cached lookup
+pub async fn find(
+    pool: &PgPool,
+    provider: &str,
+    kind: &str,
+    lookup_key: &str,
+) -> Result<Option<MetadataCacheRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, MetadataCacheRow>(
+        r#"
+        SELECT data, fetched_at
+        FROM media_metadata
+        WHERE provider = $1 AND kind = $2 AND lookup_key = $3
+        "#,
+    )
+    .bind(provider)
+    .bind(kind)
+    .bind(lookup_key)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// Store a lookup result (None = provider had no match)
+pub async fn upsert(
+    pool: &PgPool,
+    provider: &str,
+    kind: &str,
+    lookup_key: &str,
+    external_id: Option<&str>,
+    data: Option<&serde_json::Value>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO media_metadata (provider, kind, lookup_key, external_id, data, fetched_at)
+        VALUES ($1, $2, $3, $4, $5, NOW())
+        ON CONFLICT (provider, kind, lookup_key) DO UPDATE SET
+            external_id = EXCLUDED.external_id,
+            data = EXCLUDED.data,
+            fetched_at = NOW()
+        "#,
+    )
+    .bind(provider)
+    .bind(kind)
+    .bind(lookup_key)
+    .bind(external_id)
+    .bind(data.map(Json))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
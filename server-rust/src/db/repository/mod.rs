@@ -3,10 +3,19 @@
 //! Repository pattern for database access, separating data access logic
 //! from business logic.
 
+pub mod devices;
 pub mod groups;
 pub mod items;
+pub mod lists;
+pub mod metadata;
+pub mod parental;
+pub mod parse_jobs;
 pub mod playlists;
+pub mod preferences;
+pub mod recordings;
+pub mod remotes;
 pub mod series;
+pub mod titles;
 pub mod watch_history;
 
 // Re-export commonly used items
@@ -0,0 +1,72 @@
+//! Parental controls repository
+//!
+//! One row per device_id. The PIN is only ever stored as an argon2 hash.
+
+use sqlx::{FromRow, PgPool};
+
+/// Database row for parental controls
+#[derive(Debug, Clone, FromRow)]
+pub struct ParentalControlsRow {
+    pub device_id: String,
+    pub pin_hash: String,
+    pub hide_adult: bool,
+    pub blocked_kinds: Vec<String>,
+    pub blocked_groups: Vec<String>,
+    pub blocked_keywords: Vec<String>,
+}
+
+/// Get parental controls for a device
+pub async fn get(
+    pool: &PgPool,
+    device_id: &str,
+) -> Result<Option<ParentalControlsRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, ParentalControlsRow>(
+        r#"
+        SELECT device_id, pin_hash, hide_adult, blocked_kinds, blocked_groups, blocked_keywords
+        FROM parental_controls
+        WHERE device_id = $1
+        "#,
+    )
+    .bind(device_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// Insert or replace parental controls for a device
+pub async fn upsert(pool: &PgPool, row: &ParentalControlsRow) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO parental_controls (device_id, pin_hash, hide_adult,
+                                       blocked_kinds, blocked_groups, blocked_keywords)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (device_id) DO UPDATE SET
+            pin_hash = EXCLUDED.pin_hash,
+            hide_adult = EXCLUDED.hide_adult,
+            blocked_kinds = EXCLUDED.blocked_kinds,
+            blocked_groups = EXCLUDED.blocked_groups,
+            blocked_keywords = EXCLUDED.blocked_keywords
+        "#,
+    )
+    .bind(&row.device_id)
+    .bind(&row.pin_hash)
+    .bind(row.hide_adult)
+    .bind(&row.blocked_kinds)
+    .bind(&row.blocked_groups)
+    .bind(&row.blocked_keywords)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove parental controls for a device (back to defaults)
+pub async fn delete(pool: &PgPool, device_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM parental_controls WHERE device_id = $1")
+        .bind(device_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
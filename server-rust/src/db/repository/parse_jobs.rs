@@ -0,0 +1,267 @@
+//! Parse jobs repository for database operations
+//!
+//! Background playlist parses are queued here and claimed by workers on any
+//! instance. Running jobs are heartbeated; a job whose heartbeat goes stale
+//! (its process died) is queued again.
+
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_COMPLETE: &str = "complete";
+pub const STATUS_FAILED: &str = "failed";
+
+/// Database row for a parse job
+#[derive(Debug, Clone, FromRow)]
+pub struct ParseJobRow {
+    pub id: Uuid,
+    pub hash: String,
+    pub url: String,
+    pub device_id: Option<String>,
+    pub priority: i16,
+    pub status: String,
+    pub attempts: i32,
+    pub worker_id: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+const JOB_COLUMNS: &str = "id, hash, url, device_id, priority, status, attempts, worker_id, error, \
+     created_at, started_at, finished_at";
+
+/// Result of queueing a parse
+#[derive(Debug, Clone, FromRow)]
+pub struct Enqueued {
+    #[sqlx(flatten)]
+    pub job: ParseJobRow,
+    /// A new job was queued (false: joined the active job)
+    pub inserted: bool,
+    /// Priority of the joined job before this request
+    pub previous_priority: Option<i16>,
+}
+
+/// Queue a parse, or return the active job for the same playlist
+///
+/// A queued job is raised to the higher of both priorities.
+pub async fn enqueue(
+    pool: &PgPool,
+    hash: &str,
+    url: &str,
+    device_id: Option<&str>,
+    priority: i16,
+) -> Result<Enqueued, sqlx::Error> {
+    let query = format!(
+        r#"
+        WITH previous AS (
+            SELECT priority FROM parse_jobs WHERE hash = $1 AND status IN ('queued', 'running')
+        )
+        INSERT INTO parse_jobs (hash, url, device_id, priority)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (hash) WHERE status IN ('queued', 'running') DO UPDATE SET
+            priority = GREATEST(parse_jobs.priority, EXCLUDED.priority),
+            updated_at = NOW()
+        RETURNING {}, (xmax = 0) AS inserted, (SELECT priority FROM previous) AS previous_priority
+        "#,
+        JOB_COLUMNS
+    );
+
+    sqlx::query_as::<_, Enqueued>(&query)
+        .bind(hash)
+        .bind(url)
+        .bind(device_id)
+        .bind(priority)
+        .fetch_one(pool)
+        .await
+}
+
+/// Queued or running job for a playlist
+pub async fn find_active(pool: &PgPool, hash: &str) -> Result<Option<ParseJobRow>, sqlx::Error> {
+    let query = format!(
+        "SELECT {} FROM parse_jobs WHERE hash = $1 AND status IN ('queued', 'running')",
+        JOB_COLUMNS
+    );
+
+    sqlx::query_as::<_, ParseJobRow>(&query)
+        .bind(hash)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Claim the next queued job for a worker (highest priority, oldest first)
+pub async fn claim_next(pool: &PgPool, worker_id: &str) -> Result<Option<ParseJobRow>, sqlx::Error> {
+    let query = format!(
+        r#"
+        UPDATE parse_jobs SET
+            status = 'running',
+            worker_id = $1,
+            attempts = attempts + 1,
+            started_at = NOW(),
+            heartbeat_at = NOW(),
+            updated_at = NOW()
+        WHERE id = (
+            SELECT id FROM parse_jobs
+            WHERE status = 'queued'
+            ORDER BY priority DESC, created_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING {}
+        "#,
+        JOB_COLUMNS
+    );
+
+    sqlx::query_as::<_, ParseJobRow>(&query)
+        .bind(worker_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Record that a worker is still running a job
+///
+/// Returns false if the job is no longer running on this worker (cancelled
+/// or requeued), in which case the worker should stop.
+pub async fn heartbeat(pool: &PgPool, id: Uuid, worker_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE parse_jobs SET heartbeat_at = NOW()
+        WHERE id = $1 AND status = 'running' AND worker_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(worker_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Set the final status of a job still running on this worker
+pub async fn finish(
+    pool: &PgPool,
+    id: Uuid,
+    worker_id: &str,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE parse_jobs SET status = $3, error = $4, finished_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND status = 'running' AND worker_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(worker_id)
+    .bind(status)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Cancel a job that is still queued or running (None if it already finished)
+///
+/// `worker_id` is kept, so a returned job with a worker was running.
+pub async fn cancel(pool: &PgPool, id: Uuid) -> Result<Option<ParseJobRow>, sqlx::Error> {
+    let query = format!(
+        r#"
+        UPDATE parse_jobs SET
+            status = 'cancelled',
+            error = 'Cancelado',
+            finished_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1 AND status IN ('queued', 'running')
+        RETURNING {}
+        "#,
+        JOB_COLUMNS
+    );
+
+    sqlx::query_as::<_, ParseJobRow>(&query)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Running jobs whose worker stopped heartbeating
+pub async fn find_stale(pool: &PgPool, stale_seconds: u64) -> Result<Vec<ParseJobRow>, sqlx::Error> {
+    let query = format!(
+        r#"
+        SELECT {} FROM parse_jobs
+        WHERE status = 'running'
+          AND heartbeat_at < NOW() - make_interval(secs => $1)
+        "#,
+        JOB_COLUMNS
+    );
+
+    sqlx::query_as::<_, ParseJobRow>(&query)
+        .bind(stale_seconds as f64)
+        .fetch_all(pool)
+        .await
+}
+
+/// Take a stale job away from its worker, queueing it again or failing it
+///
+/// Returns false if the job heartbeated or finished in the meantime.
+pub async fn release_stale(
+    pool: &PgPool,
+    id: Uuid,
+    stale_seconds: u64,
+    status: &str,
+    error: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE parse_jobs SET
+            status = $3,
+            error = COALESCE($4, error),
+            finished_at = CASE WHEN $3 = 'queued' THEN NULL ELSE NOW() END,
+            worker_id = NULL,
+            updated_at = NOW()
+        WHERE id = $1
+          AND status = 'running'
+          AND heartbeat_at < NOW() - make_interval(secs => $2)
+        "#,
+    )
+    .bind(id)
+    .bind(stale_seconds as f64)
+    .bind(status)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Jobs queued ahead of a job (0 when it runs next or is running)
+pub async fn queue_position(pool: &PgPool, job: &ParseJobRow) -> Result<i64, sqlx::Error> {
+    if job.status != STATUS_QUEUED {
+        return Ok(0);
+    }
+
+    sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM parse_jobs
+        WHERE status = 'queued'
+          AND (priority > $1 OR (priority = $1 AND created_at < $2))
+        "#,
+    )
+    .bind(job.priority)
+    .bind(job.created_at)
+    .fetch_one(pool)
+    .await
+}
+
+/// Delete finished jobs older than the given number of days
+pub async fn delete_finished(pool: &PgPool, max_age_days: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM parse_jobs WHERE finished_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(max_age_days as i32)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
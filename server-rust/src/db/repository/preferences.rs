@@ -0,0 +1,117 @@
+//! Device preferences repository
+//!
+//! Per-device playback preferences and HLS variant hints, keyed by device_id
+//! like watch history.
+
+use sqlx::{FromRow, PgPool};
+
+/// Database row for device preferences
+#[derive(Debug, Clone, FromRow)]
+pub struct DevicePreferencesRow {
+    pub device_id: String,
+    pub max_quality: Option<String>,
+    pub audio: String,
+    pub language: Option<String>,
+}
+
+/// Get preferences for a device
+pub async fn get(
+    pool: &PgPool,
+    device_id: &str,
+) -> Result<Option<DevicePreferencesRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, DevicePreferencesRow>(
+        r#"
+        SELECT device_id, max_quality, audio, language
+        FROM device_preferences
+        WHERE device_id = $1
+        "#,
+    )
+    .bind(device_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// Insert or replace preferences for a device
+pub async fn upsert(
+    pool: &PgPool,
+    device_id: &str,
+    max_quality: Option<&str>,
+    audio: &str,
+    language: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO device_preferences (device_id, max_quality, audio, language)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (device_id) DO UPDATE SET
+            max_quality = EXCLUDED.max_quality,
+            audio = EXCLUDED.audio,
+            language = EXCLUDED.language
+        "#,
+    )
+    .bind(device_id)
+    .bind(max_quality)
+    .bind(audio)
+    .bind(language)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Database row for device playback hints (HLS variant filtering defaults)
+#[derive(Debug, Clone, FromRow)]
+pub struct PlaybackHintsRow {
+    pub max_height: Option<i32>,
+    pub max_bandwidth: Option<i64>,
+    pub codecs: Vec<String>,
+}
+
+/// Get playback hints for a device
+pub async fn get_playback_hints(
+    pool: &PgPool,
+    device_id: &str,
+) -> Result<Option<PlaybackHintsRow>, sqlx::Error> {
+    let row = sqlx::query_as::<_, PlaybackHintsRow>(
+        r#"
+        SELECT max_height, max_bandwidth, codecs
+        FROM device_playback_hints
+        WHERE device_id = $1
+        "#,
+    )
+    .bind(device_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// Insert or replace playback hints for a device
+pub async fn upsert_playback_hints(
+    pool: &PgPool,
+    device_id: &str,
+    max_height: Option<i32>,
+    max_bandwidth: Option<i64>,
+    codecs: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO device_playback_hints (device_id, max_height, max_bandwidth, codecs)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (device_id) DO UPDATE SET
+            max_height = EXCLUDED.max_height,
+            max_bandwidth = EXCLUDED.max_bandwidth,
+            codecs = EXCLUDED.codecs
+        "#,
+    )
+    .bind(device_id)
+    .bind(max_height)
+    .bind(max_bandwidth)
+    .bind(codecs)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
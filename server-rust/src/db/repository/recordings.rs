@@ -0,0 +1,297 @@
+//! Recordings repository for database operations
+//!
+//! Stores DVR recordings per device. Due rows are claimed atomically by one
+//! recorder instance; `recording` rows whose heartbeat went stale (the instance
+//! died) are claimed again and resumed.
+
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+pub const STATUS_SCHEDULED: &str = "scheduled";
+pub const STATUS_RECORDING: &str = "recording";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_FAILED: &str = "failed";
+
+/// Database row for a recording
+#[derive(Debug, Clone, FromRow)]
+pub struct RecordingRow {
+    pub id: Uuid,
+    pub device_id: String,
+    pub playlist_id: Option<Uuid>,
+    pub stream_id: Option<i64>,
+    pub channel_name: String,
+    pub title: Option<String>,
+    pub stream_url: String,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+    pub status: String,
+    pub file_path: Option<String>,
+    pub size_bytes: i64,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Data for scheduling a new recording
+#[derive(Debug, Clone)]
+pub struct NewRecording {
+    pub device_id: String,
+    pub playlist_id: Option<Uuid>,
+    pub stream_id: Option<i64>,
+    pub channel_name: String,
+    pub title: Option<String>,
+    pub stream_url: String,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+}
+
+const RECORDING_COLUMNS: &str = "id, device_id, playlist_id, stream_id, channel_name, title, stream_url, \
+     start_at, end_at, status, file_path, size_bytes, error, created_at";
+
+/// Insert a scheduled recording
+pub async fn insert(pool: &PgPool, rec: &NewRecording) -> Result<RecordingRow, sqlx::Error> {
+    let query = format!(
+        r#"
+        INSERT INTO recordings (device_id, playlist_id, stream_id, channel_name, title, stream_url, start_at, end_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING {}
+        "#,
+        RECORDING_COLUMNS
+    );
+
+    sqlx::query_as::<_, RecordingRow>(&query)
+        .bind(&rec.device_id)
+        .bind(rec.playlist_id)
+        .bind(rec.stream_id)
+        .bind(&rec.channel_name)
+        .bind(&rec.title)
+        .bind(&rec.stream_url)
+        .bind(rec.start_at)
+        .bind(rec.end_at)
+        .fetch_one(pool)
+        .await
+}
+
+/// List recordings for a device (most recent first)
+pub async fn list_by_device(pool: &PgPool, device_id: &str) -> Result<Vec<RecordingRow>, sqlx::Error> {
+    let query = format!(
+        "SELECT {} FROM recordings WHERE device_id = $1 ORDER BY start_at DESC",
+        RECORDING_COLUMNS
+    );
+
+    sqlx::query_as::<_, RecordingRow>(&query)
+        .bind(device_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Find a recording owned by a device
+pub async fn find_for_device(
+    pool: &PgPool,
+    device_id: &str,
+    id: Uuid,
+) -> Result<Option<RecordingRow>, sqlx::Error> {
+    let query = format!(
+        "SELECT {} FROM recordings WHERE id = $1 AND device_id = $2",
+        RECORDING_COLUMNS
+    );
+
+    sqlx::query_as::<_, RecordingRow>(&query)
+        .bind(id)
+        .bind(device_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Claim recordings that should be capturing right now: new ones, and ones whose
+/// capturing instance stopped heartbeating. Claimed rows are moved to `recording`
+/// in the same statement, so each row is started by exactly one instance.
+pub async fn claim_due(pool: &PgPool, stale_seconds: i64) -> Result<Vec<RecordingRow>, sqlx::Error> {
+    let query = format!(
+        r#"
+        UPDATE recordings SET status = 'recording', updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM recordings
+            WHERE start_at <= NOW()
+              AND end_at > NOW()
+              AND (status = 'scheduled'
+                   OR (status = 'recording' AND updated_at < NOW() - make_interval(secs => $1)))
+            ORDER BY start_at
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING {}
+        "#,
+        RECORDING_COLUMNS
+    );
+
+    sqlx::query_as::<_, RecordingRow>(&query)
+        .bind(stale_seconds as f64)
+        .fetch_all(pool)
+        .await
+}
+
+/// Heartbeat of an active capture. Returns the current end time, which moves
+/// when the recording is stopped from another instance.
+pub async fn touch(pool: &PgPool, id: Uuid) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row: Option<(DateTime<Utc>,)> = sqlx::query_as(
+        "UPDATE recordings SET updated_at = NOW() WHERE id = $1 AND status = 'recording' RETURNING end_at",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| r.0))
+}
+
+/// Close recordings whose window passed without an active capture
+/// (server was down during the whole window, or crashed near the end)
+pub async fn finalize_stale(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE recordings SET
+            status = CASE WHEN size_bytes > 0 THEN 'completed' ELSE 'failed' END,
+            error = CASE WHEN size_bytes > 0 THEN error ELSE COALESCE(error, 'Gravação perdida') END,
+            reserved_bytes = 0,
+            updated_at = NOW()
+        WHERE status IN ('scheduled', 'recording')
+          AND end_at <= NOW() - INTERVAL '1 minute'
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Mark a recording as actively capturing
+pub async fn mark_recording(pool: &PgPool, id: Uuid, file_path: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE recordings SET status = 'recording', file_path = $2, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(id)
+    .bind(file_path)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Update captured size while recording
+pub async fn update_size(pool: &PgPool, id: Uuid, size_bytes: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE recordings SET size_bytes = $2, updated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .bind(size_bytes)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Set final status of a recording
+pub async fn finish(
+    pool: &PgPool,
+    id: Uuid,
+    status: &str,
+    size_bytes: i64,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE recordings SET status = $2, size_bytes = $3, error = $4, reserved_bytes = 0, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(size_bytes)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// End a recording early by moving its end time to now
+pub async fn stop_now(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE recordings SET
+            end_at = GREATEST(NOW(), start_at + INTERVAL '1 second'),
+            status = CASE WHEN status = 'scheduled' THEN 'failed' ELSE status END,
+            error = CASE WHEN status = 'scheduled' THEN 'Cancelada' ELSE error END,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Total bytes stored or held by active captures for a device
+pub async fn used_bytes(pool: &PgPool, device_id: &str) -> Result<i64, sqlx::Error> {
+    let row: (Option<i64>,) = sqlx::query_as(
+        "SELECT SUM(GREATEST(size_bytes, reserved_bytes))::BIGINT FROM recordings WHERE device_id = $1",
+    )
+    .bind(device_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.0.unwrap_or(0))
+}
+
+/// Grow the quota held by a capture from `held` by up to `wanted` bytes.
+/// Usage of the device is checked and updated under a per-device advisory lock,
+/// so concurrent captures never hold more than the quota in total.
+/// Returns the new amount held (unchanged when the quota is exhausted).
+pub async fn reserve_bytes(
+    pool: &PgPool,
+    id: Uuid,
+    device_id: &str,
+    held: i64,
+    wanted: i64,
+    quota: i64,
+) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(device_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let others: (Option<i64>,) = sqlx::query_as(
+        r#"
+        SELECT SUM(GREATEST(size_bytes, reserved_bytes))::BIGINT FROM recordings
+        WHERE device_id = $1 AND id <> $2
+        "#,
+    )
+    .bind(device_id)
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let available = quota - others.0.unwrap_or(0) - held;
+    let granted = wanted.min(available).max(0);
+    let reserved = held + granted;
+
+    sqlx::query("UPDATE recordings SET reserved_bytes = $2, updated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .bind(reserved)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(reserved)
+}
+
+/// Delete a recording row
+pub async fn delete(pool: &PgPool, id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM recordings WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
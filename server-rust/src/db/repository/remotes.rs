@@ -0,0 +1,94 @@
+//! Remote control pairings repository
+//!
+//! A pairing links a phone (holding a bearer token) to a TV's device_id.
+//! Only the token's hash is stored.
+
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// Database row for a pairing
+#[derive(Debug, Clone, FromRow)]
+pub struct RemoteRow {
+    pub id: Uuid,
+    pub device_id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// Pair a phone with a device
+pub async fn create(
+    pool: &PgPool,
+    device_id: &str,
+    token_hash: &str,
+    name: &str,
+) -> Result<RemoteRow, sqlx::Error> {
+    sqlx::query_as::<_, RemoteRow>(
+        r#"
+        INSERT INTO remote_pairings (device_id, token_hash, name)
+        VALUES ($1, $2, $3)
+        RETURNING id, device_id, name, created_at, last_used_at
+        "#,
+    )
+    .bind(device_id)
+    .bind(token_hash)
+    .bind(name)
+    .fetch_one(pool)
+    .await
+}
+
+/// Find the pairing for a token hash, marking it as used
+pub async fn touch_by_token_hash(
+    pool: &PgPool,
+    token_hash: &str,
+) -> Result<Option<RemoteRow>, sqlx::Error> {
+    sqlx::query_as::<_, RemoteRow>(
+        r#"
+        UPDATE remote_pairings SET last_used_at = NOW()
+        WHERE token_hash = $1
+        RETURNING id, device_id, name, created_at, last_used_at
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Pairings of a device (most recently used first)
+pub async fn list_by_device(pool: &PgPool, device_id: &str) -> Result<Vec<RemoteRow>, sqlx::Error> {
+    sqlx::query_as::<_, RemoteRow>(
+        r#"
+        SELECT id, device_id, name, created_at, last_used_at
+        FROM remote_pairings
+        WHERE device_id = $1
+        ORDER BY last_used_at DESC
+        "#,
+    )
+    .bind(device_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Remove a pairing of a device
+pub async fn delete(pool: &PgPool, device_id: &str, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM remote_pairings WHERE device_id = $1 AND id = $2")
+        .bind(device_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Remove pairings unused for `max_idle_days`
+pub async fn delete_idle(pool: &PgPool, max_idle_days: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM remote_pairings WHERE last_used_at < NOW() - make_interval(days => $1::int)",
+    )
+    .bind(max_idle_days as i32)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
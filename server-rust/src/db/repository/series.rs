@@ -3,6 +3,7 @@
 use sqlx::{PgPool, Row};
 use uuid::Uuid;
 
+use super::items::{bind_restrictions, restriction_clause, SearchFilters};
 use crate::db::models::{EpisodeRow, NewEpisode, NewSeries, SeriesRow};
 use crate::models::playlist::{SeasonData, SeriesEpisode, SeriesInfo};
 
@@ -137,6 +138,65 @@ pub async fn get_by_group(
     Ok(rows)
 }
 
+/// Series search hit with relevance score and total match count
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SeriesHitRow {
+    #[sqlx(flatten)]
+    pub series: SeriesRow,
+    pub rank: f32,
+    pub total: i64,
+}
+
+/// Adult flag of a series (series have no generated `is_adult` column; same
+/// patterns as `ContentClassifier::is_adult`)
+const SERIES_IS_ADULT: &str = r"(group_name ~* '(xxx|onlyfans|adulto|\+18)' OR name ~* '(xxx|onlyfans|\+18)')";
+
+/// Accent-insensitive search over series names (same ranking as item search;
+/// the media_kind filter is left to the caller)
+pub async fn search_series(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    tsquery: &str,
+    normalized: &str,
+    filters: &SearchFilters<'_>,
+    limit: i64,
+) -> Result<Vec<SeriesHitRow>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        WITH q AS (
+            SELECT to_tsquery('simple', f_unaccent($2)) AS ts, $3::text AS norm
+        )
+        SELECT id, playlist_id, series_hash, name, logo, group_name,
+               total_episodes, total_seasons, first_season, last_season, year, quality,
+               (ts_rank(to_tsvector('simple', f_unaccent(name)), q.ts) * 2
+                    + similarity(f_unaccent(lower(name)), q.norm))::REAL AS rank,
+               COUNT(*) OVER() AS total
+        FROM series, q
+        WHERE playlist_id = $1
+          AND ($4::text IS NULL OR group_name = $4)
+          AND ($5::smallint IS NULL OR year = $5)
+          AND (to_tsvector('simple', f_unaccent(name)) @@ q.ts
+               OR f_unaccent(lower(name)) % q.norm
+               OR f_unaccent(lower(name)) LIKE '%' || q.norm || '%')
+          {}
+        ORDER BY rank DESC, name
+        LIMIT $6
+        "#,
+        restriction_clause(7, SERIES_IS_ADULT, "'series'", "group_name", "name || ' ' || group_name")
+    );
+
+    let query = sqlx::query_as::<_, SeriesHitRow>(&sql)
+        .bind(playlist_id)
+        .bind(tsquery)
+        .bind(normalized)
+        .bind(filters.group)
+        .bind(filters.year)
+        .bind(limit);
+    let rows = bind_restrictions(query, filters.restrictions).fetch_all(pool).await?;
+
+    Ok(rows)
+}
+
 /// Get a single series by hash
 pub async fn get_by_hash(
     pool: &PgPool,
@@ -159,6 +219,28 @@ pub async fn get_by_hash(
     Ok(row)
 }
 
+/// Get several series by hash
+pub async fn get_by_hashes(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    series_hashes: &[String],
+) -> Result<Vec<SeriesRow>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, SeriesRow>(
+        r#"
+        SELECT id, playlist_id, series_hash, name, logo, group_name,
+               total_episodes, total_seasons, first_season, last_season, year, quality
+        FROM series
+        WHERE playlist_id = $1 AND series_hash = ANY($2)
+        "#,
+    )
+    .bind(playlist_id)
+    .bind(series_hashes)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
 /// Get series by database ID
 pub async fn get_by_id(pool: &PgPool, series_id: Uuid) -> Result<Option<SeriesRow>, sqlx::Error> {
     let row = sqlx::query_as::<_, SeriesRow>(
@@ -176,6 +258,21 @@ pub async fn get_by_id(pool: &PgPool, series_id: Uuid) -> Result<Option<SeriesRo
     Ok(row)
 }
 
+/// Get several series by ID
+pub async fn get_by_ids(pool: &PgPool, series_ids: &[Uuid]) -> Result<Vec<SeriesRow>, sqlx::Error> {
+    sqlx::query_as::<_, SeriesRow>(
+        r#"
+        SELECT id, playlist_id, series_hash, name, logo, group_name,
+               total_episodes, total_seasons, first_season, last_season, year, quality
+        FROM series
+        WHERE id = ANY($1)
+        "#,
+    )
+    .bind(series_ids)
+    .fetch_all(pool)
+    .await
+}
+
 /// Delete all series for a playlist
 pub async fn delete_by_playlist(pool: &PgPool, playlist_id: Uuid) -> Result<u64, sqlx::Error> {
     let result = sqlx::query("DELETE FROM series WHERE playlist_id = $1")
@@ -194,11 +291,14 @@ pub async fn delete_by_playlist(pool: &PgPool, playlist_id: Uuid) -> Result<u64,
 pub async fn insert_episode(pool: &PgPool, episode: &NewEpisode) -> Result<Uuid, sqlx::Error> {
     let row = sqlx::query(
         r#"
-        INSERT INTO series_episodes (series_id, item_id, item_hash, season, episode, name, url)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        INSERT INTO series_episodes (series_id, item_id, item_hash, season, episode,
+                                     episode_end, air_date, name, url)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         ON CONFLICT (series_id, item_hash) DO UPDATE SET
             season = EXCLUDED.season,
             episode = EXCLUDED.episode,
+            episode_end = EXCLUDED.episode_end,
+            air_date = EXCLUDED.air_date,
             name = EXCLUDED.name,
             url = EXCLUDED.url
         RETURNING id
@@ -209,6 +309,8 @@ pub async fn insert_episode(pool: &PgPool, episode: &NewEpisode) -> Result<Uuid,
     .bind(&episode.item_hash)
     .bind(episode.season)
     .bind(episode.episode)
+    .bind(episode.episode_end)
+    .bind(episode.air_date)
     .bind(&episode.name)
     .bind(&episode.url)
     .fetch_one(pool)
@@ -229,7 +331,7 @@ pub async fn insert_many_episodes(
 
     // Use COPY protocol for bulk insert (much faster than individual INSERTs)
     let copy_query = r#"
-        COPY series_episodes (id, series_id, item_id, item_hash, season, episode, name, url)
+        COPY series_episodes (id, series_id, item_id, item_hash, season, episode, episode_end, air_date, name, url)
         FROM STDIN WITH (FORMAT text, NULL '\N')
     "#;
 
@@ -241,13 +343,15 @@ pub async fn insert_many_episodes(
 
     for episode in episodes {
         let line = format!(
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
             Uuid::new_v4(),
             episode.series_id,
             episode.item_id.map(|id| id.to_string()).unwrap_or_else(|| "\\N".to_string()),
             escape(&truncate(&episode.item_hash, 255)),
             episode.season,
             episode.episode,
+            episode.episode_end.map(|e| e.to_string()).unwrap_or_else(|| "\\N".to_string()),
+            episode.air_date.map(|d| d.to_string()).unwrap_or_else(|| "\\N".to_string()),
             escape(&truncate(&episode.name, 1024)),
             escape(&truncate(&episode.url, 2048)),
         );
@@ -264,7 +368,7 @@ pub async fn insert_many_episodes(
 pub async fn get_episodes(pool: &PgPool, series_id: Uuid) -> Result<Vec<EpisodeRow>, sqlx::Error> {
     let rows = sqlx::query_as::<_, EpisodeRow>(
         r#"
-        SELECT id, series_id, item_id, item_hash, season, episode, name, url
+        SELECT id, series_id, item_id, item_hash, season, episode, episode_end, air_date, name, url
         FROM series_episodes
         WHERE series_id = $1
         ORDER BY season, episode
@@ -285,7 +389,7 @@ pub async fn get_episodes_by_season(
 ) -> Result<Vec<EpisodeRow>, sqlx::Error> {
     let rows = sqlx::query_as::<_, EpisodeRow>(
         r#"
-        SELECT id, series_id, item_id, item_hash, season, episode, name, url
+        SELECT id, series_id, item_id, item_hash, season, episode, episode_end, air_date, name, url
         FROM series_episodes
         WHERE series_id = $1 AND season = $2
         ORDER BY episode
@@ -299,6 +403,41 @@ pub async fn get_episodes_by_season(
     Ok(rows)
 }
 
+/// Next episode for several series at once
+///
+/// Each position is `(series_id, season, episode)`, where the episode is the
+/// last one covered by the current file (`episode_end` for multi-episode
+/// files) so its siblings aren't returned. Series already at their last
+/// episode have no row in the result.
+pub async fn next_episodes(
+    pool: &PgPool,
+    positions: &[(Uuid, i16, i16)],
+) -> Result<Vec<EpisodeRow>, sqlx::Error> {
+    let series_ids: Vec<Uuid> = positions.iter().map(|p| p.0).collect();
+    let seasons: Vec<i16> = positions.iter().map(|p| p.1).collect();
+    let episodes: Vec<i16> = positions.iter().map(|p| p.2).collect();
+
+    sqlx::query_as::<_, EpisodeRow>(
+        r#"
+        SELECT nx.id, nx.series_id, nx.item_id, nx.item_hash, nx.season, nx.episode,
+               nx.episode_end, nx.air_date, nx.name, nx.url
+        FROM UNNEST($1::uuid[], $2::int2[], $3::int2[]) AS cur(series_id, season, episode)
+        CROSS JOIN LATERAL (
+            SELECT se.*
+            FROM series_episodes se
+            WHERE se.series_id = cur.series_id AND (se.season, se.episode) > (cur.season, cur.episode)
+            ORDER BY se.season, se.episode
+            LIMIT 1
+        ) nx
+        "#,
+    )
+    .bind(&series_ids)
+    .bind(&seasons)
+    .bind(&episodes)
+    .fetch_all(pool)
+    .await
+}
+
 /// Get series with episodes grouped by season
 pub async fn get_series_with_episodes(
     pool: &PgPool,
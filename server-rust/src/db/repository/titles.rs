@@ -0,0 +1,111 @@
+//! Movie titles repository
+//!
+//! A title clusters the movie items sharing a `title_key` (normalized parsed
+//! title + year, see migration 013), so "Filme (2023) 4K" and
+//! "Filme (2023) LEG" from different groups become variants of one title.
+
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use super::items::ITEM_COLUMNS;
+use crate::db::models::ItemRow;
+
+/// Movie item with its cluster key and quality rank
+#[derive(Debug, Clone, FromRow)]
+pub struct TitleVariantRow {
+    #[sqlx(flatten)]
+    pub item: ItemRow,
+    pub title_key: String,
+    pub quality_rank: i16,
+}
+
+/// Variants of a page of titles, in playlist order of each title's first
+/// appearance. A group filter selects titles with at least one variant in
+/// that group, but every variant is returned.
+pub async fn get_title_variants(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    group: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TitleVariantRow>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        WITH page AS (
+            SELECT title_key, MIN(sort_order) AS first_order
+            FROM playlist_items
+            WHERE playlist_id = $1 AND media_kind = 'movie'
+            GROUP BY title_key
+            HAVING $2::text IS NULL OR bool_or(group_name = $2)
+            ORDER BY first_order
+            LIMIT $3 OFFSET $4
+        )
+        SELECT {}, i.title_key, i.quality_rank
+        FROM playlist_items i
+        JOIN page p ON p.title_key = i.title_key
+        WHERE i.playlist_id = $1 AND i.media_kind = 'movie'
+        ORDER BY p.first_order, i.sort_order
+        "#,
+        ITEM_COLUMNS
+    );
+
+    let rows = sqlx::query_as::<_, TitleVariantRow>(&sql)
+        .bind(playlist_id)
+        .bind(group)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
+
+/// Count titles (same group semantics as `get_title_variants`)
+pub async fn count_titles(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    group: Option<&str>,
+) -> Result<i64, sqlx::Error> {
+    let count: (i64,) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*) FROM (
+            SELECT 1
+            FROM playlist_items
+            WHERE playlist_id = $1 AND media_kind = 'movie'
+            GROUP BY title_key
+            HAVING $2::text IS NULL OR bool_or(group_name = $2)
+        ) t
+        "#,
+    )
+    .bind(playlist_id)
+    .bind(group)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count.0)
+}
+
+/// All variants of a single title
+pub async fn get_by_key(
+    pool: &PgPool,
+    playlist_id: Uuid,
+    title_key: &str,
+) -> Result<Vec<TitleVariantRow>, sqlx::Error> {
+    let sql = format!(
+        r#"
+        SELECT {}, title_key, quality_rank
+        FROM playlist_items
+        WHERE playlist_id = $1 AND media_kind = 'movie' AND title_key = $2
+        ORDER BY sort_order
+        "#,
+        ITEM_COLUMNS
+    );
+
+    let rows = sqlx::query_as::<_, TitleVariantRow>(&sql)
+        .bind(playlist_id)
+        .bind(title_key)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
+}
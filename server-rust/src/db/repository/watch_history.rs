@@ -2,6 +2,14 @@
 //!
 //! Manages persistent watch history tied to device_id (not playlist).
 //! This allows "Continue Watching" to persist across playlist changes.
+//! Entries are keyed by the item's content identity, so they also survive
+//! playlist refreshes that change item hashes.
+//!
+//! History belongs to an owner: the device's account when it's linked to one
+//! (so a phone and a TV share history), otherwise the device itself. Every
+//! change gets a server-assigned revision (from a per-owner counter) for
+//! delta sync, and deletions leave tombstones that are kept long enough for
+//! every device to sync them.
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -13,6 +21,9 @@ use uuid::Uuid;
 #[serde(rename_all = "camelCase")]
 pub struct WatchHistoryItem {
     pub item_hash: String,
+    /// Resolved from item_hash (device's playlist) when not sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
     pub media_kind: String,
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -21,6 +32,21 @@ pub struct WatchHistoryItem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration_ms: Option<i64>,
     pub watched_at: i64, // Timestamp in milliseconds
+    /// Server-assigned revision (ignored when sent by clients)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i64>,
+}
+
+/// Deleted watch history entry, sent to clients so they drop it too
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedHistoryItem {
+    pub item_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
+    pub deleted_at: i64, // Timestamp in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<i64>,
 }
 
 /// Database row for watch history
@@ -29,65 +55,159 @@ pub struct WatchHistoryRow {
     pub id: Uuid,
     pub device_id: String,
     pub item_hash: String,
+    pub content_id: Option<String>,
     pub media_kind: String,
     pub name: Option<String>,
     pub logo: Option<String>,
     pub position_ms: i64,
     pub duration_ms: Option<i64>,
     pub watched_at: DateTime<Utc>,
+    pub revision: i64,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
+const HISTORY_COLUMNS: &str = "id, device_id, item_hash, content_id, media_kind, name, logo, \
+    position_ms, duration_ms, watched_at, revision, deleted_at";
+
 impl From<WatchHistoryRow> for WatchHistoryItem {
     fn from(row: WatchHistoryRow) -> Self {
         Self {
             item_hash: row.item_hash,
+            content_id: row.content_id,
             media_kind: row.media_kind,
             name: row.name.unwrap_or_default(),
             logo: row.logo,
             position_ms: row.position_ms,
             duration_ms: row.duration_ms,
             watched_at: row.watched_at.timestamp_millis(),
+            revision: Some(row.revision),
         }
     }
 }
 
+impl From<WatchHistoryRow> for DeletedHistoryItem {
+    fn from(row: WatchHistoryRow) -> Self {
+        Self {
+            item_hash: row.item_hash,
+            content_id: row.content_id,
+            deleted_at: row.deleted_at.unwrap_or(row.watched_at).timestamp_millis(),
+            revision: Some(row.revision),
+        }
+    }
+}
+
+/// Content identity of an item in the device's playlist
+pub async fn resolve_content_id(
+    pool: &PgPool,
+    device_id: &str,
+    item_hash: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT pi.content_id
+        FROM playlist_items pi
+        JOIN playlists p ON p.id = pi.playlist_id
+        WHERE p.device_id = $1 AND pi.item_hash = $2 AND pi.content_id IS NOT NULL
+        LIMIT 1
+        "#,
+    )
+    .bind(device_id)
+    .bind(item_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| r.0))
+}
+
 /// Upsert (insert or update) a single watch history item
+///
+/// Keyed by content identity when known, so a refreshed playlist (new item
+/// hashes) updates the existing entry instead of adding one. Last writer
+/// wins by `watched_at`: an older update (or one older than a deletion) is
+/// ignored. Returns the entry's new revision, or None if ignored.
 pub async fn upsert_item(
     pool: &PgPool,
     device_id: &str,
     item: &WatchHistoryItem,
-) -> Result<(), sqlx::Error> {
+) -> Result<Option<i64>, sqlx::Error> {
     let watched_at = DateTime::from_timestamp_millis(item.watched_at)
         .unwrap_or_else(Utc::now);
 
-    sqlx::query(
+    let content_id = match &item.content_id {
+        Some(content_id) => Some(content_id.clone()),
+        None => resolve_content_id(pool, device_id, &item.item_hash).await?,
+    };
+
+    // Move the legacy entry (keyed by item_hash) to the identity once known,
+    // unless there already is an entry for it
+    if let Some(content_id) = &content_id {
+        sqlx::query(
+            r#"
+            DELETE FROM watch_history
+            WHERE owner_id = watch_history_owner($1) AND item_hash = $2 AND content_id IS NULL
+              AND EXISTS (
+                  SELECT 1 FROM watch_history
+                  WHERE owner_id = watch_history_owner($1) AND content_id = $3
+              )
+            "#,
+        )
+        .bind(device_id)
+        .bind(&item.item_hash)
+        .bind(content_id)
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE watch_history SET content_id = $3
+            WHERE owner_id = watch_history_owner($1) AND item_hash = $2 AND content_id IS NULL
+            "#,
+        )
+        .bind(device_id)
+        .bind(&item.item_hash)
+        .bind(content_id)
+        .execute(pool)
+        .await?;
+    }
+
+    let revision: Option<(i64,)> = sqlx::query_as(
         r#"
-        INSERT INTO watch_history (device_id, item_hash, media_kind, name, logo, position_ms, duration_ms, watched_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        ON CONFLICT (device_id, item_hash) DO UPDATE SET
+        INSERT INTO watch_history (owner_id, device_id, item_hash, content_id, media_kind, name,
+                                   logo, position_ms, duration_ms, watched_at)
+        VALUES (watch_history_owner($1), $1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (owner_id, history_key) DO UPDATE SET
+            device_id = EXCLUDED.device_id,
+            item_hash = EXCLUDED.item_hash,
             media_kind = EXCLUDED.media_kind,
             name = EXCLUDED.name,
             logo = EXCLUDED.logo,
             position_ms = EXCLUDED.position_ms,
             duration_ms = EXCLUDED.duration_ms,
-            watched_at = EXCLUDED.watched_at
+            watched_at = EXCLUDED.watched_at,
+            deleted_at = NULL
+        WHERE EXCLUDED.watched_at > watch_history.watched_at
+        RETURNING revision
         "#,
     )
     .bind(device_id)
     .bind(&item.item_hash)
+    .bind(&content_id)
     .bind(&item.media_kind)
     .bind(&item.name)
     .bind(&item.logo)
     .bind(item.position_ms)
     .bind(item.duration_ms)
     .bind(watched_at)
-    .execute(pool)
+    .fetch_optional(pool)
     .await?;
 
-    Ok(())
+    Ok(revision.map(|r| r.0))
 }
 
 /// Sync multiple watch history items at once
+///
+/// Returns how many items were applied (the rest were older than the
+/// server's entries).
 pub async fn sync_items(
     pool: &PgPool,
     device_id: &str,
@@ -96,29 +216,181 @@ pub async fn sync_items(
     let mut count = 0;
 
     for item in items {
-        upsert_item(pool, device_id, item).await?;
-        count += 1;
+        if upsert_item(pool, device_id, item).await?.is_some() {
+            count += 1;
+        }
     }
 
     Ok(count)
 }
 
-/// Get recent watch history for a device (sorted by most recent first)
+/// Get recent watch history for a device's owner (sorted by most recent first)
 pub async fn get_recent(
     pool: &PgPool,
     device_id: &str,
     limit: i64,
 ) -> Result<Vec<WatchHistoryRow>, sqlx::Error> {
-    let rows = sqlx::query_as::<_, WatchHistoryRow>(
+    let sql = format!(
         r#"
-        SELECT id, device_id, item_hash, media_kind, name, logo, position_ms, duration_ms, watched_at
+        SELECT {}
         FROM watch_history
-        WHERE device_id = $1
+        WHERE owner_id = watch_history_owner($1) AND deleted_at IS NULL
         ORDER BY watched_at DESC
         LIMIT $2
         "#,
+        HISTORY_COLUMNS
+    );
+
+    sqlx::query_as::<_, WatchHistoryRow>(&sql)
+        .bind(device_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+/// A page of changes for delta sync
+#[derive(Debug, Clone)]
+pub struct ChangesPage {
+    /// Entries changed after `since`, tombstones included (oldest change first)
+    pub rows: Vec<WatchHistoryRow>,
+    /// Cursor for the next request: every change up to it has been returned
+    pub revision: i64,
+    pub has_more: bool,
+}
+
+/// Highest revision no running transaction can still write for a device's owner
+///
+/// Writers of an owner bump its counter under a row lock held until they
+/// commit, so everything at or below the committed counter is visible and a
+/// cursor never needs to go back past it. Other owners' transactions (and
+/// unrelated long ones) don't hold it back.
+pub async fn high_water(pool: &PgPool, device_id: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT watch_history_high_water(watch_history_owner($1))")
+        .bind(device_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Cursor after a page of changes
+///
+/// A full page ends at its last revision (the page holds every row of that
+/// revision); otherwise the client has caught up to the high-water mark.
+pub fn page_cursor(last_revision: Option<i64>, len: usize, limit: i64, since: i64, high_water: i64) -> (i64, bool) {
+    match last_revision {
+        Some(last) if len as i64 >= limit => (last, true),
+        _ => (high_water.max(since), false),
+    }
+}
+
+/// Entries changed after revision `since`, up to the high-water mark
+///
+/// The high-water mark is read first: rows at or below it have committed, so
+/// they're all visible to the query that follows. Pages end on a revision
+/// boundary, so a page may exceed `limit` if a revision has several rows.
+pub async fn get_changes(
+    pool: &PgPool,
+    device_id: &str,
+    since: i64,
+    limit: i64,
+) -> Result<ChangesPage, sqlx::Error> {
+    let high_water = high_water(pool, device_id).await?;
+
+    let sql = format!(
+        r#"
+        WITH changed AS (
+            SELECT {}
+            FROM watch_history
+            WHERE owner_id = watch_history_owner($1) AND revision > $2 AND revision <= $3
+        ),
+        boundary AS (
+            SELECT revision FROM changed ORDER BY revision OFFSET $4 - 1 LIMIT 1
+        )
+        SELECT * FROM changed
+        WHERE revision <= COALESCE((SELECT revision FROM boundary), $3)
+        ORDER BY revision, id
+        "#,
+        HISTORY_COLUMNS
+    );
+
+    let rows = sqlx::query_as::<_, WatchHistoryRow>(&sql)
+        .bind(device_id)
+        .bind(since)
+        .bind(high_water)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    let (revision, has_more) =
+        page_cursor(rows.last().map(|row| row.revision), rows.len(), limit, since, high_water);
+    Ok(ChangesPage {
+        rows,
+        revision,
+        has_more,
+    })
+}
+
+/// Watch progress with the item's position in a series (for episodes)
+#[derive(Debug, Clone, FromRow)]
+pub struct ProgressRow {
+    pub item_hash: String,
+    pub media_kind: String,
+    pub position_ms: i64,
+    pub duration_ms: Option<i64>,
+    pub watched_at: DateTime<Utc>,
+    pub series_id: Option<Uuid>,
+    pub season: Option<i16>,
+    pub episode: Option<i16>,
+    pub episode_end: Option<i16>,
+}
+
+/// Recent watch progress for a device, matched against a playlist's episodes
+///
+/// `item_hash` is the entry's current item in the playlist (found by content
+/// identity, so entries survive refreshes that change item hashes). Only the
+/// most recent entry of each series (or item) is returned, so `limit` counts
+/// collapsed entries rather than raw history rows. Live channels are skipped.
+pub async fn get_progress(
+    pool: &PgPool,
+    device_id: &str,
+    playlist_id: Uuid,
+    limit: i64,
+) -> Result<Vec<ProgressRow>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ProgressRow>(
+        r#"
+        WITH progress AS (
+            SELECT COALESCE(cur.item_hash, wh.item_hash) AS item_hash, wh.media_kind,
+                   wh.position_ms, wh.duration_ms, wh.watched_at,
+                   ep.series_id, ep.season, ep.episode, ep.episode_end
+            FROM watch_history wh
+            LEFT JOIN LATERAL (
+                SELECT pi.item_hash
+                FROM playlist_items pi
+                WHERE pi.playlist_id = $2 AND pi.content_id = wh.content_id
+                ORDER BY pi.sort_order
+                LIMIT 1
+            ) cur ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT se.series_id, se.season, se.episode, se.episode_end
+                FROM series_episodes se
+                JOIN series s ON s.id = se.series_id
+                WHERE se.item_hash = COALESCE(cur.item_hash, wh.item_hash) AND s.playlist_id = $2
+                LIMIT 1
+            ) ep ON TRUE
+            WHERE wh.owner_id = watch_history_owner($1) AND wh.deleted_at IS NULL
+              AND wh.media_kind <> 'live'
+        ),
+        latest AS (
+            SELECT DISTINCT ON (COALESCE(series_id::text, item_hash)) *
+            FROM progress
+            ORDER BY COALESCE(series_id::text, item_hash), watched_at DESC
+        )
+        SELECT * FROM latest
+        ORDER BY watched_at DESC
+        LIMIT $3
+        "#,
     )
     .bind(device_id)
+    .bind(playlist_id)
     .bind(limit)
     .fetch_all(pool)
     .await?;
@@ -132,72 +404,249 @@ pub async fn get_by_hash(
     device_id: &str,
     item_hash: &str,
 ) -> Result<Option<WatchHistoryRow>, sqlx::Error> {
-    let row = sqlx::query_as::<_, WatchHistoryRow>(
+    let sql = format!(
         r#"
-        SELECT id, device_id, item_hash, media_kind, name, logo, position_ms, duration_ms, watched_at
+        SELECT {}
         FROM watch_history
-        WHERE device_id = $1 AND item_hash = $2
+        WHERE owner_id = watch_history_owner($1) AND item_hash = $2 AND deleted_at IS NULL
         "#,
-    )
-    .bind(device_id)
-    .bind(item_hash)
-    .fetch_optional(pool)
-    .await?;
+        HISTORY_COLUMNS
+    );
 
-    Ok(row)
+    sqlx::query_as::<_, WatchHistoryRow>(&sql)
+        .bind(device_id)
+        .bind(item_hash)
+        .fetch_optional(pool)
+        .await
 }
 
-/// Delete watch history for a device
+/// Clear a device owner's watch history
+///
+/// Entries become tombstones so other devices drop them on their next sync.
 pub async fn delete_by_device(
     pool: &PgPool,
     device_id: &str,
 ) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM watch_history WHERE device_id = $1")
-        .bind(device_id)
-        .execute(pool)
-        .await?;
+    let result = sqlx::query(
+        r#"
+        UPDATE watch_history
+        SET deleted_at = NOW(), watched_at = GREATEST(watched_at, NOW()), position_ms = 0, device_id = $1
+        WHERE owner_id = watch_history_owner($1) AND deleted_at IS NULL
+        "#,
+    )
+    .bind(device_id)
+    .execute(pool)
+    .await?;
 
     Ok(result.rows_affected())
 }
 
-/// Delete a specific watch history item
+/// Delete a specific watch history item (by item hash or content identity)
+///
+/// Leaves a tombstone dated `deleted_at`; entries watched after that are kept.
 pub async fn delete_item(
     pool: &PgPool,
     device_id: &str,
     item_hash: &str,
+    deleted_at: DateTime<Utc>,
 ) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM watch_history WHERE device_id = $1 AND item_hash = $2")
-        .bind(device_id)
-        .bind(item_hash)
-        .execute(pool)
-        .await?;
+    let result = sqlx::query(
+        r#"
+        UPDATE watch_history
+        SET deleted_at = $3, watched_at = $3, position_ms = 0, device_id = $1
+        WHERE owner_id = watch_history_owner($1) AND (item_hash = $2 OR content_id = $2)
+          AND deleted_at IS NULL AND watched_at < $3
+        "#,
+    )
+    .bind(device_id)
+    .bind(item_hash)
+    .bind(deleted_at)
+    .execute(pool)
+    .await?;
 
     Ok(result.rows_affected())
 }
 
-/// Count watch history items for a device
+/// Count live watch history items for a device's owner
 pub async fn count_by_device(
     pool: &PgPool,
     device_id: &str,
 ) -> Result<i64, sqlx::Error> {
-    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM watch_history WHERE device_id = $1")
-        .bind(device_id)
-        .fetch_one(pool)
-        .await?;
+    let row: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM watch_history WHERE owner_id = watch_history_owner($1) AND deleted_at IS NULL",
+    )
+    .bind(device_id)
+    .fetch_one(pool)
+    .await?;
 
     Ok(row.0)
 }
 
-/// Cleanup old watch history entries, keeping only the most recent N entries per device
-pub async fn cleanup_old_entries(
+/// Account a device's history is shared with
+pub async fn get_account(pool: &PgPool, device_id: &str) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT account_id FROM history_accounts WHERE device_id = $1")
+            .bind(device_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(row.map(|r| r.0))
+}
+
+/// Link a device to an account so it shares the account's history
+///
+/// A device that had its own history merges it into the account (last
+/// writer wins per entry). Switching from another account doesn't carry that
+/// account's history over. Returns how many entries were merged.
+pub async fn link_account(
     pool: &PgPool,
-    keep_count: i64,
-) -> Result<i64, sqlx::Error> {
-    // Use the database function for cleanup
-    let row: (i32,) = sqlx::query_as("SELECT cleanup_watch_history($1)")
-        .bind(keep_count as i32)
-        .fetch_one(pool)
+    device_id: &str,
+    account_id: &str,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let previous: Option<(String,)> =
+        sqlx::query_as("SELECT account_id FROM history_accounts WHERE device_id = $1 FOR UPDATE")
+            .bind(device_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO history_accounts (device_id, account_id) VALUES ($1, $2)
+        ON CONFLICT (device_id) DO UPDATE SET account_id = EXCLUDED.account_id, linked_at = NOW()
+        "#,
+    )
+    .bind(device_id)
+    .bind(account_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut merged = 0;
+    if previous.is_none() {
+        merged = sqlx::query(
+            r#"
+            INSERT INTO watch_history (owner_id, device_id, item_hash, content_id, media_kind, name,
+                                       logo, position_ms, duration_ms, watched_at, deleted_at)
+            SELECT 'acct:' || $2, device_id, item_hash, content_id, media_kind, name,
+                   logo, position_ms, duration_ms, watched_at, deleted_at
+            FROM watch_history
+            WHERE owner_id = $1
+            ON CONFLICT (owner_id, history_key) DO UPDATE SET
+                device_id = EXCLUDED.device_id,
+                item_hash = EXCLUDED.item_hash,
+                media_kind = EXCLUDED.media_kind,
+                name = EXCLUDED.name,
+                logo = EXCLUDED.logo,
+                position_ms = EXCLUDED.position_ms,
+                duration_ms = EXCLUDED.duration_ms,
+                watched_at = EXCLUDED.watched_at,
+                deleted_at = EXCLUDED.deleted_at
+            WHERE EXCLUDED.watched_at > watch_history.watched_at
+            "#,
+        )
+        .bind(device_id)
+        .bind(account_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        sqlx::query("DELETE FROM watch_history WHERE owner_id = $1")
+            .bind(device_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(merged)
+}
+
+/// Unlink a device from its account; it starts over with its own history
+pub async fn unlink_account(pool: &PgPool, device_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM history_accounts WHERE device_id = $1")
+        .bind(device_id)
+        .execute(pool)
         .await?;
 
-    Ok(row.0 as i64)
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_cursor() {
+        // Caught up: jump to the high-water mark
+        assert_eq!(page_cursor(Some(120), 3, 50, 100, 200), (200, false));
+        assert_eq!(page_cursor(None, 0, 50, 100, 200), (200, false));
+        // Full page: resume after its last revision
+        assert_eq!(page_cursor(Some(120), 50, 50, 100, 200), (120, true));
+        assert_eq!(page_cursor(Some(120), 60, 50, 100, 200), (120, true));
+        // Never move a cursor backwards
+        assert_eq!(page_cursor(None, 0, 50, 300, 200), (300, false));
+    }
+
+    fn entry(item_hash: &str, watched_at: i64) -> WatchHistoryItem {
+        WatchHistoryItem {
+            item_hash: item_hash.to_string(),
+            content_id: Some(format!("content-{}", item_hash)),
+            media_kind: "movie".to_string(),
+            name: item_hash.to_string(),
+            logo: None,
+            position_ms: 1000,
+            duration_ms: None,
+            watched_at,
+            revision: None,
+        }
+    }
+
+    /// Needs a database: runs against TEST_DATABASE_URL, skipped when unset
+    #[tokio::test]
+    async fn test_unrelated_transaction_does_not_hold_cursor() {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            return;
+        };
+        let pool = PgPool::connect(&url).await.unwrap();
+        crate::db::run_migrations(&pool).await.unwrap();
+
+        let device = format!("test-{}", Uuid::new_v4().simple());
+        let other = format!("test-{}", Uuid::new_v4().simple());
+        let now = Utc::now().timestamp_millis();
+
+        // A long transaction of another owner stays open throughout
+        let mut open = pool.begin().await.unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO watch_history (owner_id, device_id, item_hash, media_kind, name, position_ms, watched_at)
+            VALUES ($1, $1, 'pending', 'movie', 'Pending', 0, NOW())
+            "#,
+        )
+        .bind(&other)
+        .execute(&mut *open)
+        .await
+        .unwrap();
+
+        let first = upsert_item(&pool, &device, &entry("a", now)).await.unwrap().unwrap();
+        let page = get_changes(&pool, &device, 0, 50).await.unwrap();
+        assert_eq!(page.rows.len(), 1);
+        assert_eq!(page.revision, first);
+        assert!(!page.has_more);
+
+        let second = upsert_item(&pool, &device, &entry("b", now)).await.unwrap().unwrap();
+        assert!(second > first);
+        let next = get_changes(&pool, &device, page.revision, 50).await.unwrap();
+        assert_eq!(next.rows.len(), 1);
+        assert_eq!(next.rows[0].item_hash, "b");
+        assert_eq!(next.revision, second);
+
+        open.rollback().await.unwrap();
+        for table in ["watch_history", "watch_history_revisions"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE owner_id = $1", table))
+                .bind(&device)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+    }
 }
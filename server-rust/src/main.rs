@@ -5,7 +5,7 @@ mod routes;
 mod services;
 
 use axum::{
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use std::net::SocketAddr;
@@ -24,8 +24,17 @@ use crate::services::{
     cache::CacheService,
     cleanup::{start_cleanup_task, CleanupConfig},
     db_cache::DbCacheService,
+    events::EventHub,
+    logo_cache::LogoCacheService,
     m3u_parser::M3UParser,
+    metadata::{MetadataProvider, MetadataService, TmdbConfig, TmdbProvider},
+    parental::ParentalService,
+    parse_queue::{start_parse_workers, ParseQueue, ParseQueueConfig},
+    recorder::{start_recorder_task, Recorder},
     redis::RedisService,
+    session_guard::SessionGuard,
+    suggest::SuggestService,
+    upstream::{BreakerConfig, UpstreamPool},
 };
 use sqlx::PgPool;
 
@@ -36,7 +45,15 @@ pub struct AppState {
     pub redis: RedisService,
     pub cache: CacheService,
     pub db_cache: DbCacheService,
-    pub parser: M3UParser,
+    pub logo_cache: LogoCacheService,
+    pub parse_queue: ParseQueue,
+    pub suggest: SuggestService,
+    pub metadata: MetadataService,
+    pub parental: ParentalService,
+    pub session_guard: SessionGuard,
+    pub events: EventHub,
+    pub upstream: UpstreamPool,
+    pub recorder: Recorder,
     pub start_time: Instant,
 }
 
@@ -45,6 +62,13 @@ async fn main() -> anyhow::Result<()> {
     // Load environment variables
     dotenvy::dotenv().ok();
 
+    // Offline mode: `ativeplay-server parse <playlist.m3u>` prints items as NDJSON
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("parse") {
+        let path = args.get(2).ok_or_else(|| anyhow::anyhow!("usage: ativeplay-server parse <playlist.m3u>"))?;
+        return services::m3u::print_ndjson(path).await;
+    }
+
     // Initialize tracing/logging
     tracing_subscriber::registry()
         .with(
@@ -82,10 +106,58 @@ async fn main() -> anyhow::Result<()> {
     .await?;
     tracing::info!("Disk cache initialized: {}", config.parse_cache_dir);
 
+    // Disk cache for resized logos
+    let logo_cache = LogoCacheService::new(
+        &config.logo_cache_dir,
+        config.logo_cache_max_entries,
+        Some(config.logo_cache_max_mb * 1024 * 1024),
+    )
+    .await?;
+    tracing::info!("Logo cache initialized: {}", config.logo_cache_dir);
+
     // PostgreSQL-based cache (primary storage)
     let db_cache = DbCacheService::new(pool.clone());
     tracing::info!("Database cache initialized");
 
+    // In-memory search-as-you-type indexes (built lazily / after parsing)
+    let suggest = SuggestService::new(db_cache.clone(), config.suggest_max_playlists);
+
+    // Metadata enrichment (disabled without TMDB_API_KEY)
+    let metadata_provider = config.tmdb_api_key.as_ref().map(|api_key| {
+        Arc::new(TmdbProvider::new(TmdbConfig {
+            api_key: api_key.clone(),
+            base_url: config.tmdb_base_url.clone(),
+            image_base_url: config.tmdb_image_base_url.clone(),
+            language: config.metadata_language.clone(),
+        })) as Arc<dyn MetadataProvider>
+    });
+    let metadata = MetadataService::new(
+        pool.clone(),
+        metadata_provider,
+        chrono::Duration::days(config.metadata_cache_days),
+        chrono::Duration::hours(config.metadata_miss_cache_hours),
+    );
+    tracing::info!("Metadata enrichment: {}", if metadata.is_enabled() { "TMDB" } else { "disabled" });
+
+    // Parental controls (PIN unlock windows and attempt limits live in Redis)
+    let parental = ParentalService::new(
+        pool.clone(),
+        redis.clone(),
+        config.parental_unlock_minutes * 60,
+        config.parental_max_attempts,
+        config.parental_lockout_minutes * 60,
+    );
+
+    // Pairing session limits (counters live in Redis, shared by instances)
+    let session_guard = SessionGuard::new(
+        redis.clone(),
+        config.session_create_limit,
+        config.session_send_limit,
+        config.session_poll_limit,
+        config.session_max_failed_lookups,
+        config.session_lockout_minutes * 60,
+    );
+
     // Initialize M3U parser with PostgreSQL storage
     let parser = M3UParser::new(
         cache.clone(),
@@ -98,11 +170,52 @@ async fn main() -> anyhow::Result<()> {
     );
     tracing::info!("M3U parser initialized with PostgreSQL storage");
 
+    // Shared upstream clients for the HLS proxy (per-host keep-alive + circuit breakers)
+    let upstream = UpstreamPool::new(
+        &config.user_agent,
+        BreakerConfig {
+            failure_threshold: config.upstream_breaker_failures,
+            cooldown: std::time::Duration::from_millis(config.upstream_breaker_cooldown_ms),
+        },
+        config.upstream_max_hosts,
+    );
+
     // Start cleanup task (runs in background)
     let cleanup_pool = pool.clone();
     tokio::spawn(start_cleanup_task(cleanup_pool, CleanupConfig::default()));
     tracing::info!("Cleanup task started (hourly)");
 
+    // DVR recorder (resumes interrupted recordings on startup)
+    let recorder = Recorder::new(
+        pool.clone(),
+        &config.recordings_dir,
+        config.recording_quota_mb * 1024 * 1024,
+        &config.user_agent,
+    )
+    .await?;
+    tokio::spawn(start_recorder_task(recorder.clone()));
+    tracing::info!("Recorder started: {}", config.recordings_dir);
+
+    // Push events (one Redis subscription per instance, fanned out to SSE streams)
+    let events = EventHub::new(redis.clone());
+    tokio::spawn(events.clone().run());
+
+    // Background parse queue (bounded workers, resumes interrupted jobs)
+    let parse_queue = ParseQueue::new(
+        pool.clone(),
+        redis.clone(),
+        db_cache.clone(),
+        Arc::new(parser),
+        events.clone(),
+        suggest.clone(),
+        ParseQueueConfig {
+            workers: config.parse_workers,
+            stale_seconds: config.parse_job_stale_seconds,
+            max_attempts: config.parse_job_max_attempts,
+        },
+    );
+    tokio::spawn(start_parse_workers(parse_queue.clone()));
+
     // Build application state
     let state = Arc::new(AppState {
         config,
@@ -110,7 +223,15 @@ async fn main() -> anyhow::Result<()> {
         redis,
         cache,
         db_cache,
-        parser,
+        logo_cache,
+        parse_queue,
+        suggest,
+        metadata,
+        parental,
+        session_guard,
+        events,
+        upstream,
+        recorder,
         start_time: Instant::now(),
     });
 
@@ -124,9 +245,30 @@ async fn main() -> anyhow::Result<()> {
         .route("/live", get(routes::health::live))
         // Session endpoints (QR code)
         .route("/session/create", post(routes::session::create_session))
+        .route("/session/code", post(routes::session::resolve_code))
         .route("/session/:id/poll", get(routes::session::poll_session))
         .route("/session/:id/send", post(routes::session::send_url))
+        .route("/s", get(routes::session::code_page))
         .route("/s/:id", get(routes::session::mobile_page))
+        // Phone-as-remote (paired through the QR session)
+        .route("/session/:id/pair", post(routes::remote::pair_session))
+        .route("/r", get(routes::remote::remote_page))
+        .route(
+            "/api/remote",
+            get(routes::remote::get_remote).delete(routes::remote::unpair),
+        )
+        .route("/api/remote/command", post(routes::remote::send_command))
+        .route("/api/devices/:device_id/secret", post(routes::devices::register))
+        .route(
+            "/api/devices/:device_id/remotes",
+            get(routes::remote::list_remotes),
+        )
+        .route(
+            "/api/devices/:device_id/remotes/:remote_id",
+            delete(routes::remote::remove_remote),
+        )
+        // Push events (replaces session and parse status polling)
+        .route("/api/events/:device_id", get(routes::events::device_events))
         // Playlist endpoints
         .route("/api/playlist/parse", post(routes::playlist::parse_playlist))
         .route(
@@ -153,14 +295,38 @@ async fn main() -> anyhow::Result<()> {
             "/api/playlist/:hash/series/:series_id/episodes",
             get(routes::playlist::get_series_episodes),
         )
+        .route(
+            "/api/playlist/:hash/titles",
+            get(routes::titles::get_titles),
+        )
+        .route(
+            "/api/playlist/:hash/titles/:title_id",
+            get(routes::titles::get_title),
+        )
+        .route(
+            "/api/playlist/:hash/items/:item_id/metadata",
+            get(routes::metadata::get_item_metadata),
+        )
+        .route(
+            "/api/playlist/:hash/series/:series_id/metadata",
+            get(routes::metadata::get_series_metadata),
+        )
         .route(
             "/api/playlist/:hash/search",
             get(routes::playlist::search_items),
         )
+        .route(
+            "/api/playlist/:hash/suggest",
+            get(routes::playlist::suggest),
+        )
         .route(
             "/api/playlist/:hash/status",
             get(routes::playlist::get_parse_status),
         )
+        .route(
+            "/api/playlist/:hash/cancel",
+            post(routes::playlist::cancel_parse),
+        )
         // Admin endpoints (protected by ADMIN_KEY)
         .route(
             "/api/admin/playlist/:hash",
@@ -171,6 +337,10 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/admin/expired", delete(routes::admin::delete_expired))
         // HLS Proxy
         .route("/api/proxy/hls", get(routes::proxy::hls_proxy))
+        // Logo/image proxy (resize + placeholders)
+        .route("/api/image", get(routes::image::image_proxy))
+        // Stream probe (codecs/resolution detection)
+        .route("/api/probe", get(routes::probe::probe_stream))
         // Xtream Codes Proxy routes (for Xtream playlists)
         .route(
             "/api/xtream/:playlist_id/info",
@@ -208,6 +378,50 @@ async fn main() -> anyhow::Result<()> {
             "/api/xtream/:playlist_id/epg-url",
             get(routes::xtream::get_epg_url),
         )
+        // Parental controls
+        .route(
+            "/api/parental/:device_id",
+            get(routes::parental::get_settings)
+                .put(routes::parental::put_settings)
+                .delete(routes::parental::delete_settings),
+        )
+        .route("/api/parental/:device_id/unlock", post(routes::parental::unlock))
+        .route("/api/parental/:device_id/lock", post(routes::parental::lock))
+        // Favorites and lists
+        .route(
+            "/api/devices/:device_id/lists",
+            get(routes::lists::get_lists)
+                .post(routes::lists::create_list)
+                .put(routes::lists::reorder_lists),
+        )
+        .route(
+            "/api/devices/:device_id/lists/:list_id",
+            get(routes::lists::get_list)
+                .put(routes::lists::rename_list)
+                .delete(routes::lists::delete_list),
+        )
+        .route(
+            "/api/devices/:device_id/lists/:list_id/items",
+            post(routes::lists::add_entry),
+        )
+        .route(
+            "/api/devices/:device_id/lists/:list_id/items/:entry_id",
+            delete(routes::lists::remove_entry),
+        )
+        .route(
+            "/api/devices/:device_id/lists/:list_id/order",
+            put(routes::lists::reorder),
+        )
+        // Device preferences
+        .route(
+            "/api/devices/:device_id/preferences",
+            get(routes::preferences::get_preferences).put(routes::preferences::put_preferences),
+        )
+        .route(
+            "/api/devices/:device_id/playback-hints",
+            get(routes::preferences::get_playback_hints)
+                .put(routes::preferences::put_playback_hints),
+        )
         // Watch History endpoints
         .route(
             "/api/watch-history/sync",
@@ -218,10 +432,38 @@ async fn main() -> anyhow::Result<()> {
             get(routes::watch_history::get_watch_history)
                 .delete(routes::watch_history::clear_watch_history),
         )
+        .route(
+            "/api/watch-history/:device_id/continue",
+            get(routes::watch_history::continue_watching),
+        )
+        .route(
+            "/api/watch-history/:device_id/account",
+            get(routes::watch_history::get_account)
+                .put(routes::watch_history::link_account)
+                .delete(routes::watch_history::unlink_account),
+        )
         .route(
             "/api/watch-history/:device_id/:item_hash",
             delete(routes::watch_history::delete_history_item),
         )
+        // Recording (DVR) endpoints
+        .route("/api/recordings", post(routes::recordings::create_recording))
+        .route(
+            "/api/recordings/:device_id",
+            get(routes::recordings::list_recordings),
+        )
+        .route(
+            "/api/recordings/:device_id/:id",
+            delete(routes::recordings::delete_recording),
+        )
+        .route(
+            "/api/recordings/:device_id/:id/stop",
+            post(routes::recordings::stop_recording),
+        )
+        .route(
+            "/api/recordings/:device_id/:id/stream",
+            get(routes::recordings::stream_recording),
+        )
         // Middleware
         .layer(TraceLayer::new_for_http())
         .layer(CompressionLayer::new())
@@ -238,7 +480,8 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Listening on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    // Peer addresses are needed for per-IP session limits
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
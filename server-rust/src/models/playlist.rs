@@ -1,5 +1,8 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use crate::services::search::Highlight;
+
 /// Media type classification
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -58,6 +61,12 @@ pub struct ExtractedSeriesInfo {
     pub series_name: String,
     pub season: u8,
     pub episode: u16,
+    /// Last episode of a multi-episode file (S01E01-E02)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episode_end: Option<u16>,
+    /// Air date of a daily show episode (season = 2-digit year, episode = day of year)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub air_date: Option<NaiveDate>,
     pub is_series: bool,
 }
 
@@ -66,6 +75,9 @@ pub struct ExtractedSeriesInfo {
 #[serde(rename_all = "camelCase")]
 pub struct PlaylistItem {
     pub id: String,
+    /// Stable identity across refreshes (key for watch history and favorites)
+    #[serde(default)]
+    pub content_id: String,
     pub name: String,
     pub url: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -105,6 +117,12 @@ pub struct SeriesEpisode {
     pub item_id: String,
     pub season: u8,
     pub episode: u16,
+    /// Last episode of a multi-episode file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episode_end: Option<u16>,
+    /// Air date of a daily show episode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub air_date: Option<NaiveDate>,
     pub name: String,
     #[serde(default)]
     pub url: String,
@@ -178,6 +196,9 @@ pub struct ParseRequest {
     /// Device ID for single-playlist-per-device enforcement
     #[serde(default)]
     pub device_id: Option<String>,
+    /// "interactive" (default) or "background" for refreshes nobody waits on
+    #[serde(default)]
+    pub priority: Option<String>,
     #[serde(default)]
     pub options: ParseOptions,
 }
@@ -218,6 +239,34 @@ pub struct ItemsResponse {
     pub limit: usize,
     pub offset: usize,
     pub has_more: bool,
+    /// Pass as `cursor` to fetch the next page (keyset pagination)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<ItemFacets>,
+}
+
+/// Count of items for one facet value
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FacetValue {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Facet counts over the filtered items
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemFacets {
+    /// Most common first
+    pub quality: Vec<FacetValue>,
+    /// Most common first
+    pub language: Vec<FacetValue>,
+    /// Newest first
+    pub year: Vec<FacetValue>,
+    pub dubbed: usize,
+    pub subbed: usize,
+    pub multi_audio: usize,
 }
 
 /// Groups response
@@ -236,6 +285,101 @@ pub struct SeriesResponse {
     pub total: usize,
 }
 
+/// One version of a movie title (a playlist item)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TitleVariant {
+    /// Item id (playable via the item endpoints)
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub group: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub is_dubbed: bool,
+    pub is_subbed: bool,
+    pub is_multi_audio: bool,
+    /// Higher is better (0 = unknown)
+    #[serde(skip)]
+    pub quality_rank: i16,
+}
+
+/// A movie title clustering its versions across groups
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovieTitle {
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo: Option<String>,
+    /// Groups the variants come from, in playlist order
+    pub groups: Vec<String>,
+    /// Variant to play by default for the requesting device
+    pub default_variant_id: String,
+    pub variants: Vec<TitleVariant>,
+}
+
+/// Titles response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TitlesResponse {
+    pub titles: Vec<MovieTitle>,
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+    pub has_more: bool,
+}
+
+/// Search result: entity plus relevance score and matched spans
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub score: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<Highlight>,
+}
+
+/// One section of a unified search (top hits + total matches)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSection<T> {
+    pub items: Vec<SearchHit<T>>,
+    pub total: usize,
+}
+
+impl<T> Default for SearchSection<T> {
+    fn default() -> Self {
+        Self { items: Vec::new(), total: 0 }
+    }
+}
+
+/// Unified search: series (instead of their episodes), movies, live channels and groups
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub series: SearchSection<SeriesInfo>,
+    pub movies: SearchSection<PlaylistItem>,
+    pub live: SearchSection<PlaylistItem>,
+    pub groups: SearchSection<PlaylistGroup>,
+}
+
+/// Per-section result limits for unified search
+#[derive(Debug, Clone, Copy)]
+pub struct SearchLimits {
+    pub series: usize,
+    pub movies: usize,
+    pub live: usize,
+    pub groups: usize,
+}
+
 /// Query parameters for items endpoint
 #[derive(Debug, Deserialize)]
 pub struct ItemsQuery {
@@ -243,6 +387,30 @@ pub struct ItemsQuery {
     pub group: Option<String>,
     #[serde(default)]
     pub media_kind: Option<String>,
+    /// Parsed-title facets (e.g. ?quality=4K&dubbed=true&year_from=2020)
+    #[serde(default)]
+    pub quality: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub dubbed: Option<bool>,
+    #[serde(default)]
+    pub subbed: Option<bool>,
+    #[serde(default)]
+    pub multi_audio: Option<bool>,
+    #[serde(default)]
+    pub year_from: Option<i16>,
+    #[serde(default)]
+    pub year_to: Option<i16>,
+    /// Include facet counts in the response
+    #[serde(default)]
+    pub facets: bool,
+    /// Sort mode: default (playlist order), name, year, recent, quality
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// Opaque cursor from a previous page's `nextCursor` (offset is ignored)
+    #[serde(default)]
+    pub cursor: Option<String>,
     #[serde(default = "default_limit")]
     pub limit: usize,
     #[serde(default)]
@@ -252,3 +420,26 @@ pub struct ItemsQuery {
 fn default_limit() -> usize {
     50
 }
+
+/// Query parameters for titles endpoints
+///
+/// The default variant follows the device's stored preferences; explicit
+/// params override them for a single request.
+#[derive(Debug, Deserialize)]
+pub struct TitlesQuery {
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub device_id: Option<String>,
+    #[serde(default)]
+    pub max_quality: Option<String>,
+    /// dubbed | subbed | any
+    #[serde(default)]
+    pub audio: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    pub offset: usize,
+}
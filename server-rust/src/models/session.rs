@@ -4,8 +4,12 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Session {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub url: Option<String>,
+    /// TV that created the session (lets the phone pair as a remote)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    /// 6-digit code the phone can type instead of scanning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pairing_code: Option<String>,
     pub created_at: i64,
 }
 
@@ -0,0 +1,122 @@
+//! Device secret API endpoints
+//!
+//! A TV registers its device_id once and keeps the returned secret. Requests
+//! only the TV may make (its event stream, managing its paired remotes) send
+//! it as the `x-device-secret` header.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use std::sync::Arc;
+
+use crate::db::repository::devices;
+use crate::services::remote::{generate_token, hash_token};
+use crate::AppState;
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+const SECRET_HEADER: &str = "x-device-secret";
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("Failed to access device secret: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Failed to access device secret" })),
+    )
+}
+
+fn unauthorized(message: &str) -> ApiError {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({ "error": message, "code": "DEVICE_SECRET_REQUIRED" })),
+    )
+}
+
+fn validate_device_id(device_id: &str) -> Result<(), ApiError> {
+    if device_id.is_empty() || device_id.len() > 64 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid device_id" })),
+        ));
+    }
+    Ok(())
+}
+
+/// Secret sent with a request: the header, else `fallback` (for clients
+/// that can't set headers, such as EventSource)
+fn request_secret<'a>(headers: &'a HeaderMap, fallback: Option<&'a str>) -> Option<&'a str> {
+    headers
+        .get(SECRET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .or(fallback)
+        .map(str::trim)
+        .filter(|s| !s.is_empty() && s.len() <= 128)
+}
+
+/// Require the device's own secret
+pub async fn authenticate_device(
+    state: &AppState,
+    device_id: &str,
+    headers: &HeaderMap,
+    fallback: Option<&str>,
+) -> Result<(), ApiError> {
+    validate_device_id(device_id)?;
+    let secret = request_secret(headers, fallback).ok_or_else(|| unauthorized("Missing device secret"))?;
+
+    let stored = devices::secret_hash(&state.pool, device_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| unauthorized("Device isn't registered"))?;
+
+    if stored != hash_token(secret) {
+        return Err(unauthorized("Invalid device secret"));
+    }
+    Ok(())
+}
+
+/// POST /api/devices/:device_id/secret - Register a TV and issue its secret
+///
+/// The secret is only returned once; a device that already registered gets
+/// 409 and keeps using the secret it has.
+pub async fn register(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_device_id(&device_id)?;
+
+    let secret = generate_token();
+    if !devices::register_secret(&state.pool, &device_id, &hash_token(&secret))
+        .await
+        .map_err(internal_error)?
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "Device is already registered" })),
+        ));
+    }
+
+    tracing::info!("Device {} registered", device_id);
+    Ok((StatusCode::CREATED, Json(serde_json::json!({ "secret": secret }))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_secret() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_secret(&headers, None), None);
+        assert_eq!(request_secret(&headers, Some("from-query")), Some("from-query"));
+        assert_eq!(request_secret(&headers, Some("  ")), None);
+
+        headers.insert(SECRET_HEADER, " from-header ".parse().unwrap());
+        assert_eq!(request_secret(&headers, Some("from-query")), Some("from-header"));
+
+        headers.insert(SECRET_HEADER, "x".repeat(129).parse().unwrap());
+        assert_eq!(request_secret(&headers, None), None);
+    }
+}
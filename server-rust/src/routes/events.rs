@@ -0,0 +1,137 @@
+//! Push event stream (Server-Sent Events)
+//!
+//! One stream per TV replaces polling `/session/:id/poll` for arrival and
+//! `/api/playlist/:hash/status`. Events carry a `type` (also the SSE event
+//! name): sessionUrl, parseProgress, playlistReady, accountWarning,
+//! remotePaired or remoteCommand. The stream requires the device's secret
+//! (see `routes::devices`).
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::repository::playlists;
+use crate::routes::devices::authenticate_device;
+use crate::services::events::{
+    account_warnings, device_channel, playlist_channel, session_channel, PushEvent,
+};
+use crate::AppState;
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+/// Extra channels to follow besides the device's own
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// Pairing session waiting for a URL from the phone (created by this device)
+    pub session: Option<String>,
+    /// Playlist being parsed (the device's playlist is always followed)
+    pub hash: Option<String>,
+    /// Device secret, for clients that can't send the `x-device-secret` header
+    pub secret: Option<String>,
+}
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("Failed to open event stream: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Failed to open event stream" })),
+    )
+}
+
+fn to_sse(event: &PushEvent) -> Event {
+    Event::default()
+        .event(event.name())
+        .data(serde_json::to_string(event).unwrap_or_default())
+}
+
+/// GET /api/events/:device_id?session=&hash= - Event stream for a TV
+///
+/// Starts with the current state (a playlist already sent to the session,
+/// the playlist's parse progress, account warnings) so nothing published
+/// before the connection is missed, then follows live events.
+pub async fn device_events(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    authenticate_device(&state, &device_id, &headers, query.secret.as_deref()).await?;
+
+    // Only the TV that created a session may follow it
+    if let Some(session_id) = &query.session {
+        let session = state.redis.get_session(session_id).await.map_err(internal_error)?;
+        if session.and_then(|s| s.device_id).as_deref() != Some(device_id.as_str()) {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Session not found or expired" })),
+            ));
+        }
+    }
+
+    let playlist = playlists::find_by_device(&state.pool, &device_id)
+        .await
+        .map_err(internal_error)?;
+
+    let mut hashes: Vec<String> = query.hash.iter().cloned().collect();
+    if let Some(playlist) = &playlist {
+        if !hashes.contains(&playlist.hash) {
+            hashes.push(playlist.hash.clone());
+        }
+    }
+
+    let mut channels = vec![device_channel(&device_id)];
+    channels.extend(query.session.iter().map(|id| session_channel(id)));
+    channels.extend(hashes.iter().map(|hash| playlist_channel(hash)));
+
+    // Subscribe before reading the current state: an event in between is
+    // delivered twice rather than lost
+    let live = state.events.subscribe(&channels);
+
+    let mut snapshot = Vec::new();
+    if let Some(session_id) = &query.session {
+        if state.redis.has_session_url(session_id).await.map_err(internal_error)? {
+            snapshot.push(PushEvent::SessionUrl {
+                session_id: session_id.clone(),
+            });
+        }
+    }
+    for hash in &hashes {
+        if let Some(progress) = state.redis.get_parse_progress(hash).await.map_err(internal_error)? {
+            snapshot.push(PushEvent::ParseProgress {
+                hash: hash.clone(),
+                progress,
+            });
+        }
+    }
+    if let Some(playlist) = playlist.filter(|p| p.is_xtream()) {
+        snapshot.extend(
+            account_warnings(
+                &playlist.hash,
+                playlist.xtream_expires_at,
+                playlist.xtream_is_trial.unwrap_or(false),
+                chrono::Utc::now(),
+                state.config.account_warning_days,
+            )
+            .into_iter()
+            .map(PushEvent::AccountWarning),
+        );
+    }
+
+    tracing::debug!("Event stream opened for device {} ({} channels)", device_id, channels.len());
+
+    let keep_alive = KeepAlive::new().interval(Duration::from_secs(state.config.events_keepalive_seconds));
+
+    let stream = futures::stream::iter(snapshot)
+        .map(|event| Ok(to_sse(&event)))
+        .chain(live.map(|event| Ok(to_sse(&event))));
+
+    Ok(Sse::new(stream).keep_alive(keep_alive))
+}
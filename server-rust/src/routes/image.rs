@@ -0,0 +1,168 @@
+//! Logo/image proxy
+//!
+//! Channel logos from providers are often huge PNGs or dead links. This
+//! endpoint fetches, downscales and re-encodes them (WebP/PNG), caches the
+//! result on disk and falls back to an initials placeholder.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::services::logo::{
+    fetch_image, is_definitive_failure, placeholder, resize_image, ImageFormat,
+};
+use crate::services::m3u_parser::hash_url;
+use crate::AppState;
+
+/// Default placeholder size when no dimensions are requested
+const PLACEHOLDER_SIZE: u32 = 256;
+
+/// How long a definitively broken logo URL (4xx, undecodable) is skipped (1 hour)
+const FAILED_LOGO_TTL_SECONDS: u64 = 3600;
+
+/// How long clients may cache images (and placeholders for missing URLs)
+const IMAGE_MAX_AGE_SECONDS: u64 = 86400;
+
+/// How long clients may cache a placeholder standing in for a transient
+/// failure (timeout, 5xx, open circuit), so the logo shows up once it's back
+const TRANSIENT_PLACEHOLDER_MAX_AGE_SECONDS: u64 = 60;
+
+/// Query parameters for the image proxy
+#[derive(Deserialize)]
+pub struct ImageQuery {
+    /// Source image URL (optional: omitted or broken URLs yield a placeholder)
+    pub url: Option<String>,
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+    /// Name used for the initials placeholder
+    pub name: Option<String>,
+    /// Output format: webp or png (defaults to Accept negotiation)
+    pub format: Option<String>,
+}
+
+/// GET /api/image?url=<encoded>&w=&h=&name=&format=
+pub async fn image_proxy(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    let format = ImageFormat::negotiate(
+        query.format.as_deref(),
+        headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()),
+    );
+    let max_dim = state.config.image_max_dimension;
+    let w = query.w.map(|w| w.clamp(1, max_dim));
+    let h = query.h.map(|h| h.clamp(1, max_dim));
+
+    let url = query
+        .url
+        .as_deref()
+        .filter(|u| u.starts_with("http://") || u.starts_with("https://"));
+
+    // Placeholders are cached by clients only as long as the failure is expected to last
+    let mut placeholder_max_age = IMAGE_MAX_AGE_SECONDS;
+
+    if let Some(url) = url {
+        let key = hash_url(&format!("{}|{:?}|{:?}|{}", url, w, h, format.extension()));
+        if let Some(bytes) = state.logo_cache.get(&key).await {
+            return Ok(image_response(bytes, format, false, IMAGE_MAX_AGE_SECONDS));
+        }
+
+        let url_hash = hash_url(url);
+        let failed = state.redis.is_logo_failed(&url_hash).await.unwrap_or(false);
+        placeholder_max_age = FAILED_LOGO_TTL_SECONDS;
+
+        if !failed {
+            match fetch_and_resize(&state, url, w, h, format).await {
+                Ok(bytes) => {
+                    if let Err(e) = state.logo_cache.put(&key, format.extension(), &bytes).await {
+                        tracing::warn!("Failed to cache logo {}: {}", url, e);
+                    }
+                    return Ok(image_response(bytes, format, false, IMAGE_MAX_AGE_SECONDS));
+                }
+                Err(e) => {
+                    tracing::debug!("Logo fetch failed for {}: {}", url, e);
+                    // Transient errors (timeouts, 5xx, open circuit) are retried on the next request
+                    if is_definitive_failure(&e) {
+                        let _ = state
+                            .redis
+                            .mark_logo_failed(&url_hash, FAILED_LOGO_TTL_SECONDS)
+                            .await;
+                    } else {
+                        placeholder_max_age = TRANSIENT_PLACEHOLDER_MAX_AGE_SECONDS;
+                    }
+                }
+            }
+        }
+    }
+
+    // Placeholder with the initials of the channel/content name
+    let name = query.name.unwrap_or_default();
+    let (pw, ph) = match (w, h) {
+        (Some(w), Some(h)) => (w, h),
+        (Some(s), None) | (None, Some(s)) => (s, s),
+        (None, None) => (PLACEHOLDER_SIZE, PLACEHOLDER_SIZE),
+    };
+
+    let key = hash_url(&format!("placeholder|{}|{}x{}|{}", name, pw, ph, format.extension()));
+    if let Some(bytes) = state.logo_cache.get(&key).await {
+        return Ok(image_response(bytes, format, true, placeholder_max_age));
+    }
+
+    let bytes = tokio::task::spawn_blocking(move || placeholder(&name, pw, ph, format))
+        .await
+        .map_err(|e| e.into())
+        .and_then(|r| r)
+        .map_err(|e: anyhow::Error| {
+            tracing::error!("Failed to render placeholder: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Erro ao gerar imagem" })),
+            )
+        })?;
+
+    if let Err(e) = state.logo_cache.put(&key, format.extension(), &bytes).await {
+        tracing::warn!("Failed to cache placeholder: {}", e);
+    }
+
+    Ok(image_response(bytes, format, true, placeholder_max_age))
+}
+
+/// Fetch the source and resize it off the async runtime
+async fn fetch_and_resize(
+    state: &AppState,
+    url: &str,
+    w: Option<u32>,
+    h: Option<u32>,
+    format: ImageFormat,
+) -> anyhow::Result<Vec<u8>> {
+    let max_bytes = state.config.image_max_source_mb * 1024 * 1024;
+    let source = fetch_image(&state.upstream, url, max_bytes).await?;
+
+    let max_dim = state.config.image_max_dimension;
+    let (max_w, max_h) = (w.unwrap_or(max_dim), h.unwrap_or(max_dim));
+
+    tokio::task::spawn_blocking(move || resize_image(&source, max_w, max_h, format)).await?
+}
+
+fn image_response(bytes: Vec<u8>, format: ImageFormat, is_placeholder: bool, max_age: u64) -> Response {
+    (
+        [(header::CACHE_CONTROL, format!("public, max-age={}", max_age))],
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+            (header::VARY, "Accept"),
+            (
+                header::HeaderName::from_static("x-image-placeholder"),
+                if is_placeholder { "true" } else { "false" },
+            ),
+        ],
+        bytes,
+    )
+        .into_response()
+}
@@ -0,0 +1,657 @@
+//! Favorites and user lists API endpoints
+//!
+//! Lists are tied to device_id. `favorites` can be used as the list id for
+//! the device's built-in favorites list. Entries are resolved against the
+//! current playlist (by content identity, series id or Xtream stream id), so
+//! they survive refreshes and reinstalls. Every endpoint requires the
+//! device's secret.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::models::PlaylistRow;
+use crate::db::repository::lists::{self, ListEntryRow, ListRow, NewListEntry};
+use crate::db::repository::{items, playlists, series};
+use crate::models::{PlaylistItem, SeriesInfo};
+use crate::routes::devices::authenticate_device;
+use crate::routes::xtream::get_xtream_credentials;
+use crate::services::xtream::{XtreamClient, XtreamCredentials};
+use crate::AppState;
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+const MAX_NAME_LEN: usize = 128;
+const MAX_LOGO_LEN: usize = 2048;
+/// Lists per device, favorites included
+const MAX_LISTS_PER_DEVICE: i64 = 50;
+const MAX_ENTRIES_PER_LIST: i64 = 500;
+
+/// A list with its entry count
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserList {
+    pub id: String,
+    pub name: String,
+    pub is_favorites: bool,
+    pub position: i32,
+    pub item_count: i64,
+}
+
+impl From<ListRow> for UserList {
+    fn from(row: ListRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            name: row.name,
+            is_favorites: row.is_favorites,
+            position: row.position,
+            item_count: row.item_count,
+        }
+    }
+}
+
+/// Xtream reference of an entry
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XtreamRef {
+    pub stream_id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub play_url: Option<String>,
+}
+
+/// A list entry, resolved against the current playlist
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListEntry {
+    /// Stable entry id (content id, series id or Xtream stream key)
+    pub id: String,
+    pub media_kind: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo: Option<String>,
+    pub position: i32,
+    pub added_at: i64,
+    /// Whether the entry exists in the current playlist
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<PlaylistItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<SeriesInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xtream: Option<XtreamRef>,
+}
+
+/// List with its entries
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResponse {
+    #[serde(flatten)]
+    pub list: UserList,
+    pub entries: Vec<ListEntry>,
+}
+
+/// Query params for reading a list
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    /// Playlist to resolve entries in (defaults to the device's playlist)
+    pub hash: Option<String>,
+}
+
+/// Request to create or rename a list
+#[derive(Debug, Deserialize)]
+pub struct ListNameRequest {
+    pub name: String,
+}
+
+/// Request to add an entry: a playlist item, a series or an Xtream stream
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddEntryRequest {
+    /// Item id in `hash` (or the device's playlist)
+    pub item_id: Option<String>,
+    pub series_id: Option<String>,
+    pub xtream_stream_id: Option<i64>,
+    /// live | movie | series (required with xtreamStreamId)
+    pub media_kind: Option<String>,
+    pub name: Option<String>,
+    pub logo: Option<String>,
+    /// Container extension of Xtream VOD streams
+    pub extension: Option<String>,
+    pub hash: Option<String>,
+}
+
+/// Request to reorder a list's entries (entry ids) or a device's lists (list ids)
+#[derive(Debug, Deserialize)]
+pub struct ReorderRequest {
+    pub ids: Vec<String>,
+}
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("Failed to access lists: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Failed to access lists" })),
+    )
+}
+
+fn error(status: StatusCode, message: &str) -> ApiError {
+    (status, Json(serde_json::json!({ "error": message })))
+}
+
+fn validate_device_id(device_id: &str) -> Result<(), ApiError> {
+    if device_id.is_empty() || device_id.len() > 64 {
+        return Err(error(StatusCode::BAD_REQUEST, "Invalid device_id"));
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<&str, ApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(error(StatusCode::BAD_REQUEST, "Invalid list name"));
+    }
+    Ok(name)
+}
+
+/// Optional logo: an http(s) URL of reasonable length
+fn validate_logo(logo: Option<&str>) -> Result<Option<String>, ApiError> {
+    let Some(logo) = logo.map(str::trim).filter(|l| !l.is_empty()) else {
+        return Ok(None);
+    };
+
+    match url::Url::parse(logo) {
+        Ok(url) if logo.len() <= MAX_LOGO_LEN && matches!(url.scheme(), "http" | "https") => {
+            Ok(Some(logo.to_string()))
+        }
+        _ => Err(error(StatusCode::BAD_REQUEST, "Invalid logo URL")),
+    }
+}
+
+/// Container extension of an Xtream VOD stream (ends up in the play URL)
+fn valid_extension(extension: &str) -> bool {
+    !extension.is_empty() && extension.len() <= 16 && extension.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Resolve `:list_id` ("favorites" or a list UUID) for a device
+async fn find_list(state: &AppState, device_id: &str, list_id: &str) -> Result<ListRow, ApiError> {
+    validate_device_id(device_id)?;
+
+    if list_id == "favorites" {
+        return lists::favorites(&state.pool, device_id).await.map_err(internal_error);
+    }
+
+    let list_id = Uuid::parse_str(list_id)
+        .map_err(|_| error(StatusCode::BAD_REQUEST, "Invalid list id"))?;
+    lists::get_list(&state.pool, device_id, list_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "List not found"))
+}
+
+/// Playlist to resolve entries in: the given hash, else the device's playlist
+async fn find_playlist(
+    state: &AppState,
+    device_id: &str,
+    hash: Option<&str>,
+) -> Result<Option<PlaylistRow>, ApiError> {
+    match hash {
+        Some(hash) => playlists::find_by_hash_any(&state.pool, hash).await,
+        None => playlists::find_by_device(&state.pool, device_id).await,
+    }
+    .map_err(internal_error)
+}
+
+fn xtream_key(media_kind: &str, stream_id: i64) -> String {
+    format!("x_{}_{}", media_kind, stream_id)
+}
+
+/// Keys of the Xtream entries the server still offers
+///
+/// One catalog request per media kind present in `rows`; a kind whose
+/// catalog can't be fetched counts as unavailable.
+async fn xtream_available(creds: &XtreamCredentials, rows: &[ListEntryRow]) -> HashSet<String> {
+    let client = XtreamClient::from_credentials(creds);
+    let kinds: HashSet<&str> = rows
+        .iter()
+        .filter(|row| row.xtream_stream_id.is_some())
+        .map(|row| row.media_kind.as_str())
+        .collect();
+
+    let mut available = HashSet::new();
+    for kind in kinds {
+        let ids: Result<Vec<i64>, _> = match kind {
+            "live" => client
+                .get_live_streams()
+                .await
+                .map(|streams| streams.into_iter().map(|s| s.stream_id).collect()),
+            "movie" => client
+                .get_vod_streams()
+                .await
+                .map(|streams| streams.into_iter().map(|s| s.stream_id).collect()),
+            "series" => client
+                .get_series()
+                .await
+                .map(|series| series.into_iter().map(|s| s.series_id).collect()),
+            _ => continue,
+        };
+
+        match ids {
+            Ok(ids) => available.extend(ids.into_iter().map(|id| xtream_key(kind, id))),
+            Err(e) => tracing::warn!("Failed to check Xtream {} streams: {}", kind, e),
+        }
+    }
+    available
+}
+
+/// Resolve entries against a playlist (unavailable if missing from it)
+async fn resolve_entries(
+    state: &AppState,
+    playlist: Option<&PlaylistRow>,
+    rows: Vec<ListEntryRow>,
+) -> Result<Vec<ListEntry>, ApiError> {
+    let mut resolved_items: HashMap<String, PlaylistItem> = HashMap::new();
+    let mut resolved_series: HashMap<String, SeriesInfo> = HashMap::new();
+    let mut credentials = None;
+    let mut available_streams = HashSet::new();
+
+    if let Some(playlist) = playlist {
+        let (series_keys, item_keys): (Vec<String>, Vec<String>) = rows
+            .iter()
+            .filter(|row| row.xtream_stream_id.is_none())
+            .map(|row| row.entry_key.clone())
+            .partition(|key| key.starts_with("series_"));
+
+        if !item_keys.is_empty() {
+            for row in items::get_by_content_ids(&state.pool, playlist.id, &item_keys)
+                .await
+                .map_err(internal_error)?
+            {
+                let item: PlaylistItem = row.into();
+                resolved_items.insert(item.content_id.clone(), item);
+            }
+        }
+        if !series_keys.is_empty() {
+            for row in series::get_by_hashes(&state.pool, playlist.id, &series_keys)
+                .await
+                .map_err(internal_error)?
+            {
+                let info: SeriesInfo = row.into();
+                resolved_series.insert(info.id.clone(), info);
+            }
+        }
+        if playlist.is_xtream() && rows.iter().any(|row| row.xtream_stream_id.is_some()) {
+            credentials = get_xtream_credentials(&state.pool, playlist.id)
+                .await
+                .ok()
+                .map(|(creds, _)| creds);
+            if let Some(creds) = &credentials {
+                available_streams = xtream_available(creds, &rows).await;
+            }
+        }
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let item = resolved_items.remove(&row.entry_key);
+            let series = resolved_series.remove(&row.entry_key);
+            let stream_available = available_streams.contains(&row.entry_key);
+            let xtream = row.xtream_stream_id.map(|stream_id| XtreamRef {
+                stream_id,
+                play_url: credentials
+                    .as_ref()
+                    .filter(|_| stream_available)
+                    .and_then(|creds| match row.media_kind.as_str() {
+                        "live" => Some(creds.live_url_with_format(stream_id, None)),
+                        "movie" => Some(creds.vod_url(
+                            stream_id,
+                            row.xtream_extension.as_deref().unwrap_or("mp4"),
+                        )),
+                        _ => None,
+                    }),
+            });
+            let available = item.is_some() || series.is_some() || stream_available;
+
+            ListEntry {
+                id: row.entry_key,
+                media_kind: row.media_kind,
+                name: row.name,
+                logo: row.logo,
+                position: row.position,
+                added_at: row.added_at.timestamp_millis(),
+                available,
+                item,
+                series,
+                xtream,
+            }
+        })
+        .collect())
+}
+
+/// GET /api/devices/:device_id/lists - Lists of a device (favorites first)
+pub async fn get_lists(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+
+    // Make sure the favorites list always shows up
+    lists::favorites(&state.pool, &device_id).await.map_err(internal_error)?;
+    let rows = lists::get_lists(&state.pool, &device_id).await.map_err(internal_error)?;
+
+    let lists: Vec<UserList> = rows.into_iter().map(Into::into).collect();
+    Ok(Json(serde_json::json!({ "lists": lists, "total": lists.len() })))
+}
+
+/// POST /api/devices/:device_id/lists - Create a named list
+pub async fn create_list(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<ListNameRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let name = validate_name(&request.name)?;
+
+    let Some(list) = lists::create_list(&state.pool, &device_id, name, MAX_LISTS_PER_DEVICE)
+        .await
+        .map_err(internal_error)?
+    else {
+        let count = lists::count_lists(&state.pool, &device_id)
+            .await
+            .map_err(internal_error)?;
+        return Err(if count >= MAX_LISTS_PER_DEVICE {
+            error(StatusCode::BAD_REQUEST, "Too many lists")
+        } else {
+            error(StatusCode::CONFLICT, "A list with this name already exists")
+        });
+    };
+
+    Ok((StatusCode::CREATED, Json(UserList::from(list))))
+}
+
+/// PUT /api/devices/:device_id/lists - Reorder named lists
+///
+/// `ids` come first in the given order; lists not listed keep their relative
+/// order after them. The favorites list always stays first.
+pub async fn reorder_lists(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<ReorderRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let ids = request
+        .ids
+        .iter()
+        .map(|id| Uuid::parse_str(id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| error(StatusCode::BAD_REQUEST, "Invalid list id"))?;
+
+    lists::reorder_lists(&state.pool, &device_id, &ids)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// GET /api/devices/:device_id/lists/:list_id - List entries resolved to playlist items
+pub async fn get_list(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, list_id)): Path<(String, String)>,
+    Query(query): Query<ListQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let list = find_list(&state, &device_id, &list_id).await?;
+    let playlist = find_playlist(&state, &device_id, query.hash.as_deref()).await?;
+    let restrictions = state
+        .parental
+        .restrictions(Some(&device_id))
+        .await
+        .map_err(internal_error)?;
+
+    let rows = lists::get_entries(&state.pool, list.id).await.map_err(internal_error)?;
+    let mut entries = resolve_entries(&state, playlist.as_ref(), rows).await?;
+    entries.retain(|entry| {
+        let group = entry
+            .item
+            .as_ref()
+            .map(|i| i.group.as_str())
+            .or(entry.series.as_ref().map(|s| s.group.as_str()))
+            .unwrap_or("");
+        restrictions.allows(&entry.media_kind, group, &entry.name)
+    });
+
+    Ok(Json(ListResponse {
+        list: list.into(),
+        entries,
+    }))
+}
+
+/// PUT /api/devices/:device_id/lists/:list_id - Rename a list
+pub async fn rename_list(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, list_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<ListNameRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let list = find_list(&state, &device_id, &list_id).await?;
+    let name = validate_name(&request.name)?;
+
+    if !lists::rename_list(&state.pool, &device_id, list.id, name)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(error(StatusCode::CONFLICT, "A list with this name already exists"));
+    }
+
+    Ok(Json(UserList {
+        name: name.to_string(),
+        ..list.into()
+    }))
+}
+
+/// DELETE /api/devices/:device_id/lists/:list_id - Delete a named list
+pub async fn delete_list(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, list_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let list = find_list(&state, &device_id, &list_id).await?;
+    if list.is_favorites {
+        return Err(error(StatusCode::BAD_REQUEST, "The favorites list can't be deleted"));
+    }
+
+    lists::delete_list(&state.pool, &device_id, list.id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/devices/:device_id/lists/:list_id/items - Append an entry
+pub async fn add_entry(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, list_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<AddEntryRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let list = find_list(&state, &device_id, &list_id).await?;
+    if list.item_count >= MAX_ENTRIES_PER_LIST {
+        return Err(error(StatusCode::BAD_REQUEST, "The list is full"));
+    }
+
+    let entry = if let Some(stream_id) = request.xtream_stream_id {
+        let media_kind = request.media_kind.as_deref().unwrap_or_default();
+        if !matches!(media_kind, "live" | "movie" | "series") {
+            return Err(error(StatusCode::BAD_REQUEST, "mediaKind must be live, movie or series"));
+        }
+        let name = request
+            .name
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty() && n.len() <= 1024)
+            .ok_or_else(|| error(StatusCode::BAD_REQUEST, "name is required"))?;
+        NewListEntry {
+            entry_key: xtream_key(media_kind, stream_id),
+            media_kind: media_kind.to_string(),
+            name: name.to_string(),
+            logo: validate_logo(request.logo.as_deref())?,
+            xtream_stream_id: Some(stream_id),
+            xtream_extension: request.extension.clone().filter(|e| valid_extension(e)),
+        }
+    } else {
+        let playlist = find_playlist(&state, &device_id, request.hash.as_deref())
+            .await?
+            .ok_or_else(|| error(StatusCode::NOT_FOUND, "Playlist not found"))?;
+
+        if let Some(series_id) = &request.series_id {
+            let row = series::get_by_hash(&state.pool, playlist.id, series_id)
+                .await
+                .map_err(internal_error)?
+                .ok_or_else(|| error(StatusCode::NOT_FOUND, "Series not found"))?;
+            NewListEntry {
+                entry_key: row.series_hash,
+                media_kind: "series".to_string(),
+                name: row.name,
+                logo: row.logo,
+                xtream_stream_id: None,
+                xtream_extension: None,
+            }
+        } else if let Some(item_id) = &request.item_id {
+            let row = items::get_by_hash(&state.pool, playlist.id, item_id)
+                .await
+                .map_err(internal_error)?
+                .ok_or_else(|| error(StatusCode::NOT_FOUND, "Item not found"))?;
+            let item: PlaylistItem = row.into();
+            NewListEntry {
+                entry_key: item.content_id,
+                media_kind: item.media_kind.to_string(),
+                name: item.parsed_title.map(|t| t.title).unwrap_or(item.name),
+                logo: item.logo,
+                xtream_stream_id: None,
+                xtream_extension: None,
+            }
+        } else {
+            return Err(error(
+                StatusCode::BAD_REQUEST,
+                "itemId, seriesId or xtreamStreamId is required",
+            ));
+        }
+    };
+
+    let added = lists::add_entry(&state.pool, list.id, &entry, MAX_ENTRIES_PER_LIST)
+        .await
+        .map_err(internal_error)?;
+
+    Ok((
+        if added { StatusCode::CREATED } else { StatusCode::OK },
+        Json(serde_json::json!({ "id": entry.entry_key, "added": added })),
+    ))
+}
+
+/// DELETE /api/devices/:device_id/lists/:list_id/items/:entry_id - Remove an entry
+pub async fn remove_entry(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, list_id, entry_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let list = find_list(&state, &device_id, &list_id).await?;
+
+    let removed = lists::remove_entry(&state.pool, list.id, &entry_id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(serde_json::json!({ "success": removed })))
+}
+
+/// PUT /api/devices/:device_id/lists/:list_id/order - Reorder entries
+///
+/// `ids` come first in the given order; entries not listed keep their
+/// relative order after them.
+pub async fn reorder(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, list_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<ReorderRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let list = find_list(&state, &device_id, &list_id).await?;
+
+    lists::reorder(&state.pool, list.id, &request.ids)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert_eq!(validate_name("  Filmes  ").unwrap(), "Filmes");
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_logo() {
+        assert_eq!(validate_logo(None).unwrap(), None);
+        assert_eq!(validate_logo(Some("  ")).unwrap(), None);
+        assert_eq!(
+            validate_logo(Some("https://cdn.example.com/logo.png")).unwrap().as_deref(),
+            Some("https://cdn.example.com/logo.png")
+        );
+        assert!(validate_logo(Some("javascript:alert(1)")).is_err());
+        assert!(validate_logo(Some("not a url")).is_err());
+        let long = format!("https://example.com/{}", "a".repeat(MAX_LOGO_LEN));
+        assert!(validate_logo(Some(&long)).is_err());
+    }
+
+    #[test]
+    fn test_valid_extension() {
+        assert!(valid_extension("mkv"));
+        assert!(valid_extension("mp4"));
+        assert!(!valid_extension(""));
+        assert!(!valid_extension("mp4?x=1"));
+        assert!(!valid_extension("../ts"));
+    }
+
+    #[test]
+    fn test_xtream_key() {
+        assert_eq!(xtream_key("live", 42), "x_live_42");
+        assert_ne!(xtream_key("live", 42), xtream_key("movie", 42));
+    }
+
+    #[test]
+    fn test_user_list_from_row() {
+        let id = Uuid::new_v4();
+        let list = UserList::from(ListRow {
+            id,
+            name: "Filmes".to_string(),
+            is_favorites: false,
+            position: 3,
+            item_count: 7,
+        });
+        let json = serde_json::to_value(&list).unwrap();
+        assert_eq!(json["id"], id.to_string());
+        assert_eq!(json["isFavorites"], false);
+        assert_eq!(json["position"], 3);
+        assert_eq!(json["itemCount"], 7);
+    }
+}
@@ -0,0 +1,140 @@
+//! Metadata enrichment endpoints
+//!
+//! Detail screens for M3U movies and series: the stored item/series plus
+//! provider metadata (plot, poster, genres, cast, rating) when available.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::db::repository::{items, series};
+use crate::models::{MediaKind, PlaylistItem, SeriesInfo};
+use crate::routes::parental::playlist_restrictions;
+use crate::services::metadata::{MediaMetadata, MetadataKind};
+use crate::AppState;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemMetadataResponse {
+    pub item: PlaylistItem,
+    pub metadata: Option<MediaMetadata>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesMetadataResponse {
+    pub series: SeriesInfo,
+    pub metadata: Option<MediaMetadata>,
+}
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn not_found() -> ApiError {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "Item não encontrado" })),
+    )
+}
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("Metadata lookup failed: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Erro ao buscar metadados" })),
+    )
+}
+
+/// Provider errors degrade to "no metadata" instead of failing the screen
+async fn lookup(
+    state: &AppState,
+    kind: MetadataKind,
+    title: &str,
+    year: Option<u16>,
+) -> Option<MediaMetadata> {
+    match state.metadata.lookup(kind, title, year).await {
+        Ok(found) => found,
+        Err(e) => {
+            tracing::warn!("Metadata provider error for '{}': {}", title, e);
+            None
+        }
+    }
+}
+
+/// GET /api/playlist/:hash/items/:item_id/metadata
+pub async fn get_item_metadata(
+    State(state): State<Arc<AppState>>,
+    Path((hash, item_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let playlist_id = state
+        .db_cache
+        .get_playlist_id(&hash)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+
+    let item: PlaylistItem = items::get_by_hash(&state.pool, playlist_id, &item_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?
+        .into();
+
+    // Hidden content looks the same as missing content
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+    if !restrictions.allows(&item.media_kind.to_string(), &item.group, &item.name) {
+        return Err(not_found());
+    }
+
+    // Live channels have nothing to enrich
+    let metadata = match item.media_kind {
+        MediaKind::Live => None,
+        kind => {
+            let metadata_kind = if kind == MediaKind::Series {
+                MetadataKind::Series
+            } else {
+                MetadataKind::Movie
+            };
+            let (title, year) = match &item.parsed_title {
+                Some(parsed) => (parsed.title.as_str(), parsed.year),
+                None => (item.name.as_str(), None),
+            };
+            lookup(&state, metadata_kind, title, year).await
+        }
+    };
+
+    Ok(Json(ItemMetadataResponse { item, metadata }))
+}
+
+/// GET /api/playlist/:hash/series/:series_id/metadata
+pub async fn get_series_metadata(
+    State(state): State<Arc<AppState>>,
+    Path((hash, series_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let playlist_id = state
+        .db_cache
+        .get_playlist_id(&hash)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+
+    let series: SeriesInfo = series::get_by_hash(&state.pool, playlist_id, &series_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?
+        .into();
+
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+    if !restrictions.allows("series", &series.group, &series.name) {
+        return Err(not_found());
+    }
+
+    let metadata = lookup(&state, MetadataKind::Series, &series.name, series.year).await;
+
+    Ok(Json(SeriesMetadataResponse { series, metadata }))
+}
@@ -1,7 +1,18 @@
 pub mod admin;
+pub mod devices;
+pub mod events;
 pub mod health;
+pub mod image;
+pub mod lists;
+pub mod metadata;
+pub mod parental;
 pub mod playlist;
+pub mod preferences;
+pub mod probe;
 pub mod proxy;
+pub mod recordings;
+pub mod remote;
 pub mod session;
+pub mod titles;
 pub mod watch_history;
 pub mod xtream;
@@ -0,0 +1,289 @@
+//! Parental controls API endpoints
+//!
+//! Per-device restrictions protected by a PIN. Changing or removing the
+//! settings and unlocking the device all require the current PIN.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::repository::parental::{self, ParentalControlsRow};
+use crate::services::parental::{hash_pin, is_valid_pin, ContentRestrictions, PinCheck};
+use crate::AppState;
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+const KINDS: [&str; 4] = ["live", "movie", "series", "unknown"];
+const MAX_ENTRIES: usize = 100;
+const MAX_ENTRY_LEN: usize = 100;
+
+/// Requesting device, for endpoints that aren't keyed by device
+pub fn device_id_header(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-device-id")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty() && v.len() <= 64)
+}
+
+/// Restrictions for a playlist's device (or the requesting device if unbound)
+pub async fn playlist_restrictions(
+    state: &AppState,
+    hash: &str,
+    headers: &HeaderMap,
+) -> Result<ContentRestrictions, ApiError> {
+    state
+        .parental
+        .restrictions_for_playlist(hash, device_id_header(headers).as_deref())
+        .await
+        .map_err(internal_error)
+}
+
+/// Parental settings as exposed to clients (never the PIN hash)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParentalSettings {
+    /// Whether a PIN has been set for this device
+    pub enabled: bool,
+    pub hide_adult: bool,
+    pub blocked_kinds: Vec<String>,
+    pub blocked_groups: Vec<String>,
+    pub blocked_keywords: Vec<String>,
+    pub unlocked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unlock_expires_in: Option<u64>,
+}
+
+/// Update request; `pin` is the current PIN (or the new one on first setup)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateParentalRequest {
+    pub pin: String,
+    pub new_pin: Option<String>,
+    pub hide_adult: Option<bool>,
+    pub blocked_kinds: Option<Vec<String>>,
+    pub blocked_groups: Option<Vec<String>>,
+    pub blocked_keywords: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PinRequest {
+    pub pin: String,
+}
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("Failed to access parental controls: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Failed to access parental controls" })),
+    )
+}
+
+fn bad_request(message: &str) -> ApiError {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message })))
+}
+
+fn validate_device_id(device_id: &str) -> Result<(), ApiError> {
+    if device_id.is_empty() || device_id.len() > 64 {
+        return Err(bad_request("Invalid device_id"));
+    }
+    Ok(())
+}
+
+fn validate_list(values: &[String]) -> bool {
+    values.len() <= MAX_ENTRIES
+        && values.iter().all(|v| !v.trim().is_empty() && v.len() <= MAX_ENTRY_LEN)
+}
+
+async fn load(state: &AppState, device_id: &str) -> Result<Option<ParentalControlsRow>, ApiError> {
+    parental::get(&state.pool, device_id).await.map_err(internal_error)
+}
+
+async fn require_pin(
+    state: &AppState,
+    device_id: &str,
+    pin: &str,
+    row: &ParentalControlsRow,
+) -> Result<(), ApiError> {
+    match state
+        .parental
+        .check_pin(device_id, pin, &row.pin_hash)
+        .await
+        .map_err(internal_error)?
+    {
+        PinCheck::Correct => Ok(()),
+        PinCheck::Wrong { attempts_left } => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Invalid PIN", "attemptsLeft": attempts_left })),
+        )),
+        PinCheck::LockedOut => Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({ "error": "Too many invalid PIN attempts, try again later" })),
+        )),
+    }
+}
+
+async fn settings_response(
+    state: &AppState,
+    device_id: &str,
+    row: Option<&ParentalControlsRow>,
+) -> Result<ParentalSettings, ApiError> {
+    let unlock_expires_in =
+        state.parental.unlock_ttl(device_id).await.map_err(internal_error)?;
+
+    Ok(match row {
+        Some(row) => ParentalSettings {
+            enabled: true,
+            hide_adult: row.hide_adult,
+            blocked_kinds: row.blocked_kinds.clone(),
+            blocked_groups: row.blocked_groups.clone(),
+            blocked_keywords: row.blocked_keywords.clone(),
+            unlocked: unlock_expires_in.is_some(),
+            unlock_expires_in,
+        },
+        None => ParentalSettings {
+            enabled: false,
+            hide_adult: true,
+            blocked_kinds: Vec::new(),
+            blocked_groups: Vec::new(),
+            blocked_keywords: Vec::new(),
+            unlocked: unlock_expires_in.is_some(),
+            unlock_expires_in,
+        },
+    })
+}
+
+/// GET /api/parental/:device_id - Current settings (defaults if no PIN set)
+pub async fn get_settings(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_device_id(&device_id)?;
+    let row = load(&state, &device_id).await?;
+    Ok(Json(settings_response(&state, &device_id, row.as_ref()).await?))
+}
+
+/// PUT /api/parental/:device_id - Create or update settings (PIN required)
+pub async fn put_settings(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(request): Json<UpdateParentalRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_device_id(&device_id)?;
+
+    if let Some(kinds) = &request.blocked_kinds {
+        if kinds.iter().any(|k| !KINDS.contains(&k.to_lowercase().as_str())) {
+            return Err(bad_request("Invalid blockedKinds (live, movie, series, unknown)"));
+        }
+    }
+    let lists = [&request.blocked_groups, &request.blocked_keywords];
+    if lists.iter().any(|list| list.as_ref().is_some_and(|l| !validate_list(l))) {
+        return Err(bad_request("Invalid blockedGroups or blockedKeywords"));
+    }
+
+    let existing = load(&state, &device_id).await?;
+    let new_pin = match &existing {
+        Some(row) => {
+            require_pin(&state, &device_id, &request.pin, row).await?;
+            request.new_pin.as_deref()
+        }
+        // First setup: the PIN being set
+        None => Some(request.new_pin.as_deref().unwrap_or(&request.pin)),
+    };
+
+    let pin_hash = match new_pin {
+        Some(pin) => {
+            if !is_valid_pin(pin) {
+                return Err(bad_request("PIN must be 4 to 8 digits"));
+            }
+            let pin = pin.to_string();
+            tokio::task::spawn_blocking(move || hash_pin(&pin))
+                .await
+                .map_err(internal_error)?
+                .map_err(internal_error)?
+        }
+        None => existing.as_ref().map(|row| row.pin_hash.clone()).unwrap_or_default(),
+    };
+
+    let current = existing.as_ref();
+    let row = ParentalControlsRow {
+        device_id: device_id.clone(),
+        pin_hash,
+        hide_adult: request
+            .hide_adult
+            .unwrap_or_else(|| current.is_none_or(|row| row.hide_adult)),
+        blocked_kinds: request
+            .blocked_kinds
+            .map(|kinds| kinds.iter().map(|k| k.to_lowercase()).collect())
+            .unwrap_or_else(|| current.map(|row| row.blocked_kinds.clone()).unwrap_or_default()),
+        blocked_groups: request
+            .blocked_groups
+            .unwrap_or_else(|| current.map(|row| row.blocked_groups.clone()).unwrap_or_default()),
+        blocked_keywords: request
+            .blocked_keywords
+            .unwrap_or_else(|| current.map(|row| row.blocked_keywords.clone()).unwrap_or_default()),
+    };
+
+    parental::upsert(&state.pool, &row).await.map_err(internal_error)?;
+    tracing::info!("Parental controls updated for device {}", device_id);
+
+    Ok(Json(settings_response(&state, &device_id, Some(&row)).await?))
+}
+
+/// DELETE /api/parental/:device_id - Remove settings (PIN required)
+pub async fn delete_settings(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(request): Json<PinRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_device_id(&device_id)?;
+
+    let Some(row) = load(&state, &device_id).await? else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Parental controls not set" })),
+        ));
+    };
+    require_pin(&state, &device_id, &request.pin, &row).await?;
+
+    parental::delete(&state.pool, &device_id).await.map_err(internal_error)?;
+    state.parental.lock(&device_id).await.map_err(internal_error)?;
+    tracing::info!("Parental controls removed for device {}", device_id);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/parental/:device_id/unlock - Lift restrictions for a while (PIN required)
+pub async fn unlock(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(request): Json<PinRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_device_id(&device_id)?;
+
+    let Some(row) = load(&state, &device_id).await? else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Parental controls not set" })),
+        ));
+    };
+    require_pin(&state, &device_id, &request.pin, &row).await?;
+
+    let expires_in = state.parental.unlock(&device_id).await.map_err(internal_error)?;
+    Ok(Json(serde_json::json!({ "unlocked": true, "expiresIn": expires_in })))
+}
+
+/// POST /api/parental/:device_id/lock - Restore restrictions immediately
+pub async fn lock(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_device_id(&device_id)?;
+    state.parental.lock(&device_id).await.map_err(internal_error)?;
+    Ok(Json(serde_json::json!({ "unlocked": false })))
+}
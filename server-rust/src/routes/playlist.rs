@@ -1,19 +1,25 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
 use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use crate::db;
-use crate::db::repository::playlists;
+use crate::db::repository::items::{ItemCursor, ItemFilters, ItemSort, SearchFilters};
+use crate::db::repository::{parse_jobs, playlists};
 use crate::models::{GroupsResponse, ItemsQuery, ItemsResponse, ParseRequest, ParseResponse, SeriesResponse};
+use crate::models::{SearchLimits, SearchResults};
+use crate::routes::devices::authenticate_device;
+use crate::routes::parental::playlist_restrictions;
+use crate::services::events::{account_warnings, device_channel, PushEvent};
 use crate::services::m3u_parser::hash_url;
-use crate::services::redis::ParseProgress;
+use crate::services::parse_queue::{CancelResult, JobPriority};
 use crate::services::xtream::{self, XtreamUserInfo, XtreamServerInfo};
 use crate::AppState;
 
@@ -34,11 +40,17 @@ pub struct BackgroundParseResponse {
     pub source_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub playlist_id: Option<String>,
+    /// Background parse job (queued or running)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// Jobs queued ahead of this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<i64>,
 }
 
 /// POST /api/playlist/parse - Parse a playlist URL (background processing)
-/// Returns immediately with status "parsing" and spawns background task
-/// Frontend should poll /api/playlist/:hash/status for progress
+/// Returns immediately with status "parsing" and queues a background parse job
+/// Frontend should follow /api/events/:device_id (or poll /api/playlist/:hash/status) for progress
 ///
 /// Features:
 /// - Single playlist per device: If device_id is provided, deletes any existing playlist for that device
@@ -81,6 +93,26 @@ pub async fn parse_playlist(
                     Ok(playlist_id) => {
                         let hash = crate::services::m3u_parser::hash_url(&payload.url);
 
+                        if let Some(did) = device_id {
+                            let expires_at = auth
+                                .user_info
+                                .exp_timestamp()
+                                .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0));
+                            let warnings = account_warnings(
+                                &hash,
+                                expires_at,
+                                auth.user_info.is_trial_account(),
+                                Utc::now(),
+                                state.config.account_warning_days,
+                            );
+                            for warning in warnings {
+                                state
+                                    .events
+                                    .notify(&device_channel(did), PushEvent::AccountWarning(warning))
+                                    .await;
+                            }
+                        }
+
                         // Return Xtream-specific response with sourceType and playlistId
                         return Ok(Json(BackgroundParseResponse {
                             status: "complete".to_string(),
@@ -97,6 +129,8 @@ pub async fn parse_playlist(
                             groups: None, // Groups fetched dynamically from Xtream API
                             source_type: Some("xtream".to_string()),
                             playlist_id: Some(playlist_id.to_string()),
+                            job_id: None,
+                            queue_position: None,
                         }));
                     }
                     Err(e) => {
@@ -136,20 +170,30 @@ pub async fn parse_playlist(
     let device_id = payload.device_id.as_deref();
     let expires_at = Utc::now() + Duration::days(1);
 
-    // Check if already parsing (via Redis progress)
-    if let Ok(Some(progress)) = state.redis.get_parse_progress(&hash).await {
-        if progress.status == "parsing" || progress.status == "building_groups" {
-            tracing::info!("Already parsing {}", hash);
-            return Ok(Json(BackgroundParseResponse {
-                status: "parsing".to_string(),
-                hash,
-                message: Some("Already parsing this playlist".to_string()),
-                stats: None,
-                groups: None,
-                source_type: None,
-                playlist_id: None,
-            }));
-        }
+    let priority = match payload.priority.as_deref() {
+        Some(value) => JobPriority::parse(value).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Prioridade inválida" })),
+            )
+        })?,
+        None => JobPriority::Interactive,
+    };
+
+    // Check if already queued or parsing
+    if let Ok(Some(job)) = parse_jobs::find_active(&state.pool, &hash).await {
+        tracing::info!("Already parsing {}", hash);
+        return Ok(Json(BackgroundParseResponse {
+            status: "parsing".to_string(),
+            hash,
+            message: Some("Already parsing this playlist".to_string()),
+            stats: None,
+            groups: None,
+            source_type: None,
+            playlist_id: None,
+            queue_position: state.parse_queue.position(&job).await.ok(),
+            job_id: Some(job.id.to_string()),
+        }));
     }
 
     // SMART RE-IMPORT: First check if this hash already exists (BEFORE any delete!)
@@ -203,6 +247,8 @@ pub async fn parse_playlist(
                 groups: Some(groups),
                 source_type: Some("m3u".to_string()),
                 playlist_id: None,
+                job_id: None,
+                queue_position: None,
             }));
         } else {
             tracing::warn!("Found empty cache for {}, will re-parse", hash);
@@ -225,105 +271,84 @@ pub async fn parse_playlist(
         }
     }
 
-    // Initialize progress in Redis
-    let initial_progress = ParseProgress::new_parsing();
-    if let Err(e) = state.redis.set_parse_progress(&hash, &initial_progress).await {
-        tracing::warn!("Failed to set initial progress: {}", e);
-    }
-
-    // Spawn background parsing task
-    let state_clone = state.clone();
-    let url_clone = payload.url.clone();
-    let hash_clone = hash.clone();
-    let device_id_clone = payload.device_id.clone();
-
-    tokio::spawn(async move {
-        tracing::info!("Background parse started for {}", hash_clone);
-
-        // Acquire processing lock (10 minute TTL for large playlists)
-        let job_id = uuid::Uuid::new_v4().to_string();
-        if !state_clone
-            .redis
-            .acquire_processing_lock(&hash_clone, &job_id, 600)
-            .await
-            .unwrap_or(false)
-        {
-            tracing::warn!("Failed to acquire lock for {}", hash_clone);
-            let progress = ParseProgress::new_parsing().failed("Failed to acquire lock");
-            let _ = state_clone.redis.set_parse_progress(&hash_clone, &progress).await;
-            return;
-        }
-
-        // Parse and cache the playlist with progress reporting
-        match state_clone.parser.parse_and_cache_with_progress(&url_clone, &state_clone.redis).await {
-            Ok(metadata) => {
-                // Release processing lock
-                let _ = state_clone.redis.release_processing_lock(&hash_clone).await;
-
-                // Update playlist with device_id and 1-day TTL
-                let expires_at = Utc::now() + Duration::days(1);
-                if let Ok(Some(playlist)) = playlists::find_by_hash_any(&state_clone.pool, &hash_clone).await {
-                    if let Some(did) = &device_id_clone {
-                        if let Err(e) = playlists::update_device_and_ttl(&state_clone.pool, playlist.id, did, expires_at).await {
-                            tracing::warn!("Failed to set device_id and TTL for {}: {}", hash_clone, e);
-                        } else {
-                            tracing::info!("Set device_id {} and 1-day TTL for playlist {}", did, hash_clone);
-                        }
-                    } else {
-                        // No device_id, but still set 1-day TTL
-                        let _ = sqlx::query("UPDATE playlists SET expires_at = $2, updated_at = NOW() WHERE id = $1")
-                            .bind(playlist.id)
-                            .bind(expires_at)
-                            .execute(&state_clone.pool)
-                            .await;
-                        tracing::info!("Set 1-day TTL for playlist {} (no device)", hash_clone);
-                    }
-                }
-
-                // Mark progress as complete
-                let mut progress = ParseProgress::new_parsing();
-                progress.items_parsed = metadata.stats.total_items as u64;
-                progress.items_total = Some(metadata.stats.total_items as u64);
-                let progress = progress.complete(metadata.stats.group_count as u64, metadata.stats.series_count as u64);
-                let _ = state_clone.redis.set_parse_progress(&hash_clone, &progress).await;
-
-                tracing::info!(
-                    "Background parse complete for {}: {} items, {} groups",
-                    hash_clone,
-                    metadata.stats.total_items,
-                    metadata.stats.group_count
-                );
-            }
-            Err(e) => {
-                // Release processing lock
-                let _ = state_clone.redis.release_processing_lock(&hash_clone).await;
-
-                // Mark progress as failed
-                let progress = ParseProgress::new_parsing().failed(&e.to_string());
-                let _ = state_clone.redis.set_parse_progress(&hash_clone, &progress).await;
-
-                tracing::error!("Background parse failed for {}: {}", hash_clone, e);
-            }
-        }
-    });
+    // Queue the parse (workers pick it up by priority)
+    let (job, _) = state
+        .parse_queue
+        .enqueue(&hash, &payload.url, device_id, priority)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to queue parse for {}: {}", hash, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Erro ao iniciar importação" })),
+            )
+        })?;
+    let queue_position = state.parse_queue.position(&job).await.ok();
 
     // Return immediately
     Ok(Json(BackgroundParseResponse {
         status: "parsing".to_string(),
         hash,
-        message: Some("Parsing started in background".to_string()),
+        message: Some("Parsing queued in background".to_string()),
         stats: None,
         groups: None,
         source_type: Some("m3u".to_string()),
         playlist_id: None,
+        job_id: Some(job.id.to_string()),
+        queue_position,
     }))
 }
 
+/// Cancel parse request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelParseRequest {
+    pub device_id: String,
+}
+
+/// POST /api/playlist/:hash/cancel - Cancel a queued or running parse
+///
+/// Only the device that queued the parse may cancel it; it sends its device
+/// secret in the `x-device-secret` header.
+pub async fn cancel_parse(
+    State(state): State<Arc<AppState>>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<CancelParseRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    authenticate_device(&state, &payload.device_id, &headers, None).await?;
+
+    let result = state.parse_queue.cancel(&hash, &payload.device_id).await.map_err(|e| {
+        tracing::error!("Failed to cancel parse for {}: {}", hash, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "Erro ao cancelar importação" })),
+        )
+    })?;
+
+    match result {
+        CancelResult::Cancelled(job_id) => Ok(Json(serde_json::json!({
+            "status": "cancelled",
+            "hash": hash,
+            "jobId": job_id.to_string(),
+        }))),
+        CancelResult::NotFound => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Nenhuma importação em andamento" })),
+        )),
+        CancelResult::NotOwner => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Importação iniciada por outro dispositivo" })),
+        )),
+    }
+}
+
 /// GET /api/playlist/:hash/items - Get paginated items
 pub async fn get_items(
     State(state): State<Arc<AppState>>,
     Path(hash): Path<String>,
     Query(query): Query<ItemsQuery>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     // Check if cache exists AND is not expired (respects TTL)
     if !state.db_cache.is_cache_valid(&hash).await {
@@ -337,26 +362,67 @@ pub async fn get_items(
     let limit = query.limit.min(state.config.max_items_page);
     let offset = query.offset;
 
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+    let filters = ItemFilters {
+        group: query.group.as_deref(),
+        media_kind: query.media_kind.as_deref(),
+        quality: query.quality.as_deref(),
+        language: query.language.as_deref(),
+        dubbed: query.dubbed,
+        subbed: query.subbed,
+        multi_audio: query.multi_audio,
+        year_from: query.year_from,
+        year_to: query.year_to,
+        restrictions: Some(&restrictions),
+    };
+
+    let sort = match query.sort.as_deref() {
+        Some(value) => ItemSort::parse(value).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Ordenação inválida" })),
+            )
+        })?,
+        None => ItemSort::Default,
+    };
+    let cursor = match query.cursor.as_deref() {
+        Some(token) => Some(ItemCursor::decode(token, sort).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Cursor inválido" })),
+            )
+        })?),
+        None => None,
+    };
+
+    let internal_error = |e: anyhow::Error| {
+        tracing::error!("Failed to get items: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "Erro ao buscar itens" })),
+        )
+    };
+
     // Get items with filters (PostgreSQL)
-    let (items, total) = state
+    let (items, total, next_cursor) = state
         .db_cache
-        .read_items(
-            &hash,
-            offset,
-            limit,
-            query.group.as_deref(),
-            query.media_kind.as_deref(),
-        )
+        .read_items(&hash, offset, limit, &filters, sort, cursor.as_ref())
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to get items: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": "Erro ao buscar itens" })),
-            )
-        })?;
+        .map_err(internal_error)?;
 
-    let has_more = offset + items.len() < total;
+    let facets = if query.facets {
+        Some(
+            state
+                .db_cache
+                .item_facets(&hash, &filters)
+                .await
+                .map_err(internal_error)?,
+        )
+    } else {
+        None
+    };
+
+    let has_more = next_cursor.is_some();
 
     Ok(Json(ItemsResponse {
         items,
@@ -364,6 +430,8 @@ pub async fn get_items(
         limit,
         offset,
         has_more,
+        next_cursor,
+        facets,
     }))
 }
 
@@ -379,9 +447,10 @@ pub async fn get_groups(
     State(state): State<Arc<AppState>>,
     Path(hash): Path<String>,
     Query(query): Query<GroupsQuery>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     // Get groups from PostgreSQL (filtered if media_kind is provided)
-    let groups = if let Some(media_kind) = &query.media_kind {
+    let mut groups = if let Some(media_kind) = &query.media_kind {
         state
             .db_cache
             .get_groups_by_kind(&hash, media_kind)
@@ -403,6 +472,11 @@ pub async fn get_groups(
         })?
     };
 
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+    groups.retain(|g| {
+        restrictions.allows_kind(&g.media_kind.to_string()) && restrictions.allows_group(&g.name)
+    });
+
     Ok(Json(GroupsResponse {
         total: groups.len(),
         groups,
@@ -421,9 +495,10 @@ pub async fn get_series(
     State(state): State<Arc<AppState>>,
     Path(hash): Path<String>,
     Query(query): Query<SeriesQuery>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     // Get series from PostgreSQL (filtered if group is provided)
-    let series = if let Some(group) = &query.group {
+    let mut series = if let Some(group) = &query.group {
         state
             .db_cache
             .get_series_by_group(&hash, group)
@@ -445,6 +520,9 @@ pub async fn get_series(
         })?
     };
 
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+    series.retain(|s| restrictions.allows("series", &s.group, &s.name));
+
     Ok(Json(SeriesResponse {
         total: series.len(),
         series,
@@ -550,23 +628,74 @@ pub struct SeriesEpisodesQuery {
 #[derive(Deserialize)]
 pub struct SearchQuery {
     pub q: String,
+    /// Result limit (per section when `sections` is set)
     #[serde(default = "default_search_limit")]
     pub limit: usize,
+    /// `sections=1` groups results into series/movies/live/groups
+    #[serde(default)]
+    pub sections: Option<String>,
+    /// Per-section overrides (fall back to `limit`)
+    #[serde(default)]
+    pub series_limit: Option<usize>,
+    #[serde(default)]
+    pub movies_limit: Option<usize>,
+    #[serde(default)]
+    pub live_limit: Option<usize>,
+    #[serde(default)]
+    pub groups_limit: Option<usize>,
+    /// Filter by media kind (live, movie, series)
+    #[serde(default)]
+    pub media_kind: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub year: Option<i16>,
+}
+
+/// Query params for suggestions
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    pub q: String,
+    #[serde(default = "default_suggest_limit")]
+    pub limit: usize,
 }
 
 fn default_limit() -> usize {
     100
 }
 
+fn default_suggest_limit() -> usize {
+    10
+}
+
 fn default_search_limit() -> usize {
     50
 }
 
+impl SearchQuery {
+    /// Whether the client opted into the sectioned response
+    fn wants_sections(&self) -> bool {
+        matches!(self.sections.as_deref(), Some("1") | Some("true"))
+    }
+
+    /// Per-section limits (overrides fall back to `limit`, capped at 100)
+    fn section_limits(&self) -> SearchLimits {
+        let section_limit = |value: Option<usize>| value.unwrap_or(self.limit).min(100);
+        SearchLimits {
+            series: section_limit(self.series_limit),
+            movies: section_limit(self.movies_limit),
+            live: section_limit(self.live_limit),
+            groups: section_limit(self.groups_limit),
+        }
+    }
+}
+
 /// GET /api/playlist/:hash/series/:series_id/episodes - Get episodes for a series
 pub async fn get_series_episodes(
     State(state): State<Arc<AppState>>,
     Path((hash, series_id)): Path<(String, String)>,
     Query(query): Query<SeriesEpisodesQuery>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     // Check if cache exists AND is not expired (respects TTL)
     if !state.db_cache.is_cache_valid(&hash).await {
@@ -595,6 +724,15 @@ pub async fn get_series_episodes(
             )
         })?;
 
+    // Hidden series look the same as missing ones
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+    if !restrictions.allows("series", &series.group, &series.name) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Série não encontrada" })),
+        ));
+    }
+
     if let Some(ref seasons_data) = series.seasons_data {
         // Return pre-sorted episodes from database
         let all_episodes: Vec<_> = seasons_data
@@ -649,13 +787,26 @@ pub async fn get_series_episodes(
     }
 }
 
-/// GET /api/playlist/:hash/search - Fuzzy search items
-/// Uses PostgreSQL pg_trgm for efficient fuzzy matching
+/// Unified search response (one section per result type)
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    pub query: String,
+    #[serde(flatten)]
+    pub results: SearchResults,
+}
+
+/// GET /api/playlist/:hash/search - Accent-insensitive ranked search
+/// Full-text (unaccent + tsvector) over name/title with pg_trgm fallback;
+/// optional media_kind/group/year filters, results include highlight spans.
+/// With `sections=1` results are grouped into series/movies/live/groups
+/// sections, each with its own limit and total.
 pub async fn search_items(
     State(state): State<Arc<AppState>>,
     Path(hash): Path<String>,
     Query(query): Query<SearchQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    headers: HeaderMap,
+) -> Result<axum::response::Response, (StatusCode, Json<serde_json::Value>)> {
     // Validate query
     if query.q.trim().is_empty() {
         return Err((
@@ -664,32 +815,96 @@ pub async fn search_items(
         ));
     }
 
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+    let filters = SearchFilters {
+        media_kind: query.media_kind.as_deref(),
+        group: query.group.as_deref(),
+        year: query.year,
+        restrictions: Some(&restrictions),
+    };
+
+    let search_error = |e: anyhow::Error| {
+        tracing::error!("Search failed: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "Erro ao buscar itens" })),
+        )
+    };
+
+    if query.wants_sections() {
+        // Search using DbCacheService (PostgreSQL full-text + fuzzy search)
+        let results = state
+            .db_cache
+            .search(&hash, &query.q, &filters, query.section_limits())
+            .await
+            .map_err(search_error)?;
+
+        return Ok(Json(SearchResponse {
+            query: query.q,
+            results,
+        })
+        .into_response());
+    }
+
     // Apply limit
     let limit = query.limit.min(100);
 
-    // Search using DbCacheService (PostgreSQL fuzzy search)
     let items = state
         .db_cache
-        .search_items(&hash, &query.q, limit)
+        .search_items(&hash, &query.q, &filters, limit)
+        .await
+        .map_err(search_error)?;
+
+    Ok(Json(serde_json::json!({
+        "items": items,
+        "query": query.q,
+        "total": items.len(),
+        "limit": limit
+    }))
+    .into_response())
+}
+
+/// GET /api/playlist/:hash/suggest - Search-as-you-type completions
+/// Served from an in-memory prefix index (titles, series names, groups)
+pub async fn suggest(
+    State(state): State<Arc<AppState>>,
+    Path(hash): Path<String>,
+    Query(query): Query<SuggestQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let limit = query.limit.min(20);
+    let restrictions = playlist_restrictions(&state, &hash, &headers).await?;
+
+    // Over-fetch when some completions may be hidden
+    let fetch = if restrictions.is_unrestricted() { limit } else { limit * 2 };
+    let mut suggestions = state
+        .suggest
+        .suggest(&hash, &query.q, fetch)
         .await
         .map_err(|e| {
-            tracing::error!("Search failed: {}", e);
+            tracing::error!("Suggest failed: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": "Erro ao buscar itens" })),
+                Json(serde_json::json!({ "error": "Erro ao buscar sugestões" })),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Playlist não encontrada ou expirada" })),
             )
         })?;
+    restrictions.apply_to_suggestions(&mut suggestions);
+    suggestions.truncate(limit);
 
     Ok(Json(serde_json::json!({
-        "items": items,
         "query": query.q,
-        "total": items.len(),
-        "limit": limit
+        "suggestions": suggestions
     })))
 }
 
 /// Response for parse status endpoint
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ParseStatusResponse {
     pub status: String,
@@ -708,6 +923,20 @@ pub struct ParseStatusResponse {
     pub can_navigate: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub elapsed_ms: Option<i64>,
+    /// Bytes downloaded so far (compressed size for gzip responses)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_read: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_per_second: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_ms: Option<i64>,
+    /// Duration of each finished phase (download, parse, groups, series, commit)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub phase_ms: BTreeMap<String, u64>,
 }
 
 /// GET /api/playlist/:hash/status - Get real-time parsing status
@@ -732,6 +961,12 @@ pub async fn get_parse_status(
                 error: progress.error,
                 can_navigate,
                 elapsed_ms: Some(now - progress.started_at),
+                bytes_read: (progress.bytes_read > 0).then_some(progress.bytes_read),
+                bytes_total: progress.bytes_total,
+                percent: progress.percent,
+                bytes_per_second: progress.bytes_per_second,
+                eta_ms: progress.eta_ms,
+                phase_ms: progress.phase_ms,
             })
         }
         Ok(None) => {
@@ -749,6 +984,7 @@ pub async fn get_parse_status(
                         error: None,
                         can_navigate: true,
                         elapsed_ms: None,
+                        ..Default::default()
                     })
                 }
                 _ => {
@@ -762,6 +998,7 @@ pub async fn get_parse_status(
                         error: Some("Playlist not found or not started".to_string()),
                         can_navigate: false,
                         elapsed_ms: None,
+                        ..Default::default()
                     })
                 }
             }
@@ -777,7 +1014,64 @@ pub async fn get_parse_status(
                 error: Some(e.to_string()),
                 can_navigate: false,
                 elapsed_ms: None,
+                ..Default::default()
             })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SearchSection;
+
+    fn search_query(params: &str) -> SearchQuery {
+        let uri = format!("http://localhost/search?{}", params).parse().unwrap();
+        Query::<SearchQuery>::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn test_search_defaults_to_flat_results_with_limit_50() {
+        let query = search_query("q=matrix");
+        assert!(!query.wants_sections());
+        assert_eq!(query.limit, 50);
+    }
+
+    #[test]
+    fn test_search_sections_opt_in() {
+        assert!(search_query("q=matrix&sections=1").wants_sections());
+        assert!(search_query("q=matrix&sections=true").wants_sections());
+        assert!(!search_query("q=matrix&sections=0").wants_sections());
+    }
+
+    #[test]
+    fn test_section_limits_fall_back_to_limit_and_are_capped() {
+        let limits = search_query("q=a&sections=1&limit=30&movies_limit=5&live_limit=500").section_limits();
+        assert_eq!(limits.series, 30);
+        assert_eq!(limits.movies, 5);
+        assert_eq!(limits.live, 100);
+        assert_eq!(limits.groups, 30);
+
+        let limits = search_query("q=a&sections=1&limit=1000").section_limits();
+        assert_eq!(limits.series, 100);
+    }
+
+    #[test]
+    fn test_sectioned_response_shape() {
+        let response = SearchResponse {
+            query: "matrix".to_string(),
+            results: SearchResults {
+                movies: SearchSection { items: Vec::new(), total: 3 },
+                ..Default::default()
+            },
+        };
+        let value = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(value["query"], "matrix");
+        assert_eq!(value["movies"]["total"], 3);
+        for section in ["series", "movies", "live", "groups"] {
+            assert!(value[section]["items"].is_array(), "missing section {}", section);
+        }
+        assert!(value.get("items").is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Device preferences API endpoints
+//!
+//! Playback preferences tied to device_id, used to pick the default variant
+//! of a movie title (max quality, dubbed/subbed, language), and the default
+//! HLS variant hints applied by the proxy.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use std::sync::Arc;
+
+use crate::db::repository::preferences;
+use crate::routes::proxy::{normalize_codecs, VariantHints};
+use crate::services::titles::VariantPreferences;
+use crate::AppState;
+
+fn internal_error(e: sqlx::Error) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Failed to access device preferences: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Failed to access device preferences" })),
+    )
+}
+
+/// GET /api/devices/:device_id/preferences - Defaults if never set
+pub async fn get_preferences(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let prefs: VariantPreferences = preferences::get(&state.pool, &device_id)
+        .await
+        .map_err(internal_error)?
+        .map(Into::into)
+        .unwrap_or_default();
+
+    Ok(Json(prefs))
+}
+
+/// PUT /api/devices/:device_id/preferences - Replace device preferences
+pub async fn put_preferences(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(prefs): Json<VariantPreferences>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if device_id.is_empty() || device_id.len() > 64 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid device_id" })),
+        ));
+    }
+
+    let too_long = |value: &Option<String>| value.as_ref().is_some_and(|v| v.len() > 16);
+    if too_long(&prefs.max_quality) || too_long(&prefs.language) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid preferences" })),
+        ));
+    }
+
+    preferences::upsert(
+        &state.pool,
+        &device_id,
+        prefs.max_quality.as_deref(),
+        prefs.audio.as_str(),
+        prefs.language.as_deref(),
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok(Json(prefs))
+}
+
+/// GET /api/devices/:device_id/playback-hints - No filtering if never set
+pub async fn get_playback_hints(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let hints: VariantHints = preferences::get_playback_hints(&state.pool, &device_id)
+        .await
+        .map_err(internal_error)?
+        .map(Into::into)
+        .unwrap_or_default();
+
+    Ok(Json(hints))
+}
+
+/// PUT /api/devices/:device_id/playback-hints - Replace the proxy's variant hints
+pub async fn put_playback_hints(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(hints): Json<VariantHints>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if device_id.is_empty() || device_id.len() > 64 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid device_id" })),
+        ));
+    }
+
+    let max_height = hints.max_height.map(i32::try_from).transpose();
+    let max_bandwidth = hints.max_bandwidth.map(i64::try_from).transpose();
+    let (Ok(max_height), Ok(max_bandwidth)) = (max_height, max_bandwidth) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid playback hints" })),
+        ));
+    };
+    if hints.codecs.len() > 16 || hints.codecs.iter().any(|c| c.len() > 32) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid playback hints" })),
+        ));
+    }
+
+    let hints = VariantHints {
+        codecs: normalize_codecs(hints.codecs.iter().map(String::as_str)),
+        ..hints
+    };
+
+    preferences::upsert_playback_hints(
+        &state.pool,
+        &device_id,
+        max_height,
+        max_bandwidth,
+        &hints.codecs,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok(Json(hints))
+}
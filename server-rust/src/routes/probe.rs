@@ -0,0 +1,89 @@
+//! Stream probe endpoint
+//!
+//! Lets the TV check codecs, resolution and tracks of a stream before
+//! playing it (webOS fails silently on unsupported codecs).
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::services::m3u_parser::hash_url;
+use crate::services::probe::{probe_url, StreamProbe};
+use crate::AppState;
+
+/// Query parameters for the probe endpoint
+#[derive(Deserialize)]
+pub struct ProbeQuery {
+    pub url: String,
+    /// Ignore the cached result and probe again
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeResponse {
+    pub url: String,
+    #[serde(flatten)]
+    pub probe: StreamProbe,
+    pub cached: bool,
+}
+
+/// GET /api/probe?url=<encoded>&refresh=<optional>
+pub async fn probe_stream(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ProbeQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if !query.url.starts_with("http://") && !query.url.starts_with("https://") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Parâmetro url inválido" })),
+        ));
+    }
+
+    let url_hash = hash_url(&query.url);
+
+    if !query.refresh {
+        match state.redis.get_probe(&url_hash).await {
+            Ok(Some(probe)) => {
+                return Ok(Json(ProbeResponse {
+                    url: query.url,
+                    probe,
+                    cached: true,
+                }));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to read probe cache: {}", e),
+        }
+    }
+
+    let probe = probe_url(&state.upstream, &query.url).await.map_err(|e| {
+        tracing::error!("Stream probe failed for {}: {}", query.url, e);
+        (
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!({
+                "error": "Falha ao analisar stream",
+                "detail": e.to_string()
+            })),
+        )
+    })?;
+
+    if let Err(e) = state
+        .redis
+        .set_probe(&url_hash, &probe, state.config.probe_cache_ttl_seconds)
+        .await
+    {
+        tracing::warn!("Failed to cache probe result: {}", e);
+    }
+
+    Ok(Json(ProbeResponse {
+        url: query.url,
+        probe,
+        cached: false,
+    }))
+}
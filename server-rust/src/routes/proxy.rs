@@ -5,13 +5,15 @@ use axum::{
     response::Response,
     Json,
 };
-use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 use tokio::time::timeout;
 
+use crate::db::repository::preferences::PlaybackHintsRow;
+use crate::services::hls::{parse_resolution, tag_attributes};
+use crate::services::upstream::{UpstreamError, UpstreamOutcome};
 use crate::AppState;
 
 // Re-export reqwest header module to avoid version conflicts
@@ -28,6 +30,233 @@ pub struct HlsProxyQuery {
     pub url: String,
     #[serde(default)]
     pub referer: Option<String>,
+    /// Maximum variant height in pixels (e.g. 1080)
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    /// Maximum variant bandwidth in bits per second
+    #[serde(default)]
+    pub max_bandwidth: Option<u64>,
+    /// Comma-separated list of allowed codec prefixes (e.g. "avc1,mp4a")
+    #[serde(default)]
+    pub codecs: Option<String>,
+    /// Device whose stored playback hints are used as defaults
+    #[serde(default)]
+    pub device_id: Option<String>,
+}
+
+/// Device capability hints used to filter variants in HLS master playlists
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantHints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bandwidth: Option<u64>,
+    #[serde(default)]
+    pub codecs: Vec<String>,
+}
+
+impl From<PlaybackHintsRow> for VariantHints {
+    fn from(row: PlaybackHintsRow) -> Self {
+        Self {
+            max_height: row.max_height.and_then(|h| u32::try_from(h).ok()),
+            max_bandwidth: row.max_bandwidth.and_then(|b| u64::try_from(b).ok()),
+            codecs: normalize_codecs(row.codecs.iter().map(String::as_str)),
+        }
+    }
+}
+
+/// Lowercase, trim and drop empty codec prefixes
+pub fn normalize_codecs<'a>(codecs: impl Iterator<Item = &'a str>) -> Vec<String> {
+    codecs
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+impl VariantHints {
+    pub fn from_query(query: &HlsProxyQuery) -> Self {
+        let codecs = query
+            .codecs
+            .as_deref()
+            .map(|c| normalize_codecs(c.split(',')))
+            .unwrap_or_default();
+
+        Self {
+            max_height: query.max_height,
+            max_bandwidth: query.max_bandwidth,
+            codecs,
+        }
+    }
+
+    /// Per-request hints override the device defaults field by field
+    pub fn or_defaults(self, defaults: VariantHints) -> Self {
+        Self {
+            max_height: self.max_height.or(defaults.max_height),
+            max_bandwidth: self.max_bandwidth.or(defaults.max_bandwidth),
+            codecs: if self.codecs.is_empty() { defaults.codecs } else { self.codecs },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_height.is_none() && self.max_bandwidth.is_none() && self.codecs.is_empty()
+    }
+
+    /// Query string suffix so nested manifests keep the same hints
+    fn to_query_suffix(&self) -> String {
+        let mut suffix = String::new();
+        if let Some(h) = self.max_height {
+            suffix.push_str(&format!("&max_height={}", h));
+        }
+        if let Some(b) = self.max_bandwidth {
+            suffix.push_str(&format!("&max_bandwidth={}", b));
+        }
+        if !self.codecs.is_empty() {
+            suffix.push_str(&format!("&codecs={}", urlencoding::encode(&self.codecs.join(","))));
+        }
+        suffix
+    }
+
+    fn allows(&self, variant: &StreamVariant) -> bool {
+        if let (Some(max), Some(height)) = (self.max_height, variant.height) {
+            if height > max {
+                return false;
+            }
+        }
+        if let (Some(max), Some(bandwidth)) = (self.max_bandwidth, variant.bandwidth) {
+            if bandwidth > max {
+                return false;
+            }
+        }
+        if !self.codecs.is_empty() {
+            let supported = variant.codecs.iter().all(|codec| {
+                let codec = codec.to_lowercase();
+                self.codecs.iter().any(|allowed| codec.starts_with(allowed.as_str()))
+            });
+            if !supported {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A single `#EXT-X-STREAM-INF` entry with the lines that make it up
+#[derive(Debug)]
+struct StreamVariant {
+    lines: Vec<String>,
+    bandwidth: Option<u64>,
+    height: Option<u32>,
+    codecs: Vec<String>,
+}
+
+fn parse_stream_variant(tag: &str) -> StreamVariant {
+    let mut variant = StreamVariant {
+        lines: Vec::new(),
+        bandwidth: None,
+        height: None,
+        codecs: Vec::new(),
+    };
+
+    for (key, value) in tag_attributes(tag) {
+        match key.as_str() {
+            "BANDWIDTH" => variant.bandwidth = value.parse().ok(),
+            "RESOLUTION" => {
+                variant.height = parse_resolution(&value).map(|(_, h)| h);
+            }
+            "CODECS" => {
+                variant.codecs = value
+                    .split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    variant
+}
+
+/// Filter and reorder `#EXT-X-STREAM-INF` variants of a master playlist according to hints.
+/// Allowed variants are emitted highest bandwidth first, at the position of the first variant.
+/// If nothing matches, the lowest bandwidth variant is kept so playback never breaks.
+fn filter_variants(manifest: &str, hints: &VariantHints) -> String {
+    if hints.is_empty() || !manifest.contains("#EXT-X-STREAM-INF") {
+        return manifest.to_string();
+    }
+
+    let mut head: Vec<String> = Vec::new();
+    let mut tail: Vec<String> = Vec::new();
+    let mut variants: Vec<StreamVariant> = Vec::new();
+    let mut pending: Option<StreamVariant> = None;
+
+    for line in manifest.lines() {
+        let trimmed = line.trim();
+
+        if let Some(ref mut variant) = pending {
+            variant.lines.push(line.to_string());
+            // The variant ends with its URI line; tags in between belong to it
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                variants.push(pending.take().unwrap());
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("#EXT-X-STREAM-INF") {
+            let mut variant = parse_stream_variant(trimmed);
+            variant.lines.push(line.to_string());
+            pending = Some(variant);
+        } else if variants.is_empty() {
+            head.push(line.to_string());
+        } else {
+            tail.push(line.to_string());
+        }
+    }
+
+    // Malformed trailing tag without URI: keep it untouched
+    if let Some(variant) = pending {
+        tail.extend(variant.lines);
+    }
+
+    if variants.is_empty() {
+        return manifest.to_string();
+    }
+
+    let total = variants.len();
+    let (mut allowed, rejected): (Vec<_>, Vec<_>) =
+        variants.into_iter().partition(|v| hints.allows(v));
+
+    if allowed.is_empty() {
+        if let Some(lowest) = rejected
+            .into_iter()
+            .min_by_key(|v| v.bandwidth.unwrap_or(u64::MAX))
+        {
+            allowed.push(lowest);
+        }
+    }
+
+    allowed.sort_by_key(|v| std::cmp::Reverse(v.bandwidth.unwrap_or(0)));
+
+    tracing::debug!(
+        "HLS variant filter kept {} of {} variants (max_height={:?}, max_bandwidth={:?}, codecs={:?})",
+        allowed.len(),
+        total,
+        hints.max_height,
+        hints.max_bandwidth,
+        hints.codecs
+    );
+
+    let mut out = String::with_capacity(manifest.len());
+    for line in head
+        .iter()
+        .chain(allowed.iter().flat_map(|v| v.lines.iter()))
+        .chain(tail.iter())
+    {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
 }
 
 /// Guess content type from URL
@@ -71,15 +300,36 @@ fn is_hls_manifest(content_type: &str, url: &str) -> bool {
     false
 }
 
+/// Tags whose URI (attribute or following line) points to another playlist.
+/// Only those URLs carry the variant hints; segments, keys and init maps don't need them.
+fn tag_references_playlist(tag: &str) -> bool {
+    tag.starts_with("#EXT-X-STREAM-INF")
+        || tag.starts_with("#EXT-X-I-FRAME-STREAM-INF")
+        || tag.starts_with("#EXT-X-MEDIA:")
+}
+
 /// Rewrite URLs in HLS manifest to go through proxy
 /// This is essential for LG webOS TVs where Luna Service doesn't proxy sub-requests
-fn rewrite_manifest_urls(manifest: &str, base_url: &str, proxy_base: &str, referer: Option<&str>) -> String {
+/// Variants of master playlists are filtered according to the device hints
+fn rewrite_manifest_urls(
+    manifest: &str,
+    base_url: &str,
+    proxy_base: &str,
+    referer: Option<&str>,
+    hints: &VariantHints,
+) -> String {
     let base = match Url::parse(base_url) {
         Ok(u) => u,
         Err(_) => return manifest.to_string(),
     };
 
+    let filtered = filter_variants(manifest, hints);
+    let manifest = filtered.as_str();
+
+    let no_hints = VariantHints::default();
     let mut result = String::with_capacity(manifest.len() * 2);
+    // Set by #EXT-X-STREAM-INF: the next URI line is a variant playlist
+    let mut next_is_playlist = false;
 
     for line in manifest.lines() {
         let trimmed = line.trim();
@@ -91,9 +341,13 @@ fn rewrite_manifest_urls(manifest: &str, base_url: &str, proxy_base: &str, refer
 
         // Lines starting with # are tags/comments
         if trimmed.starts_with('#') {
+            if trimmed.starts_with("#EXT-X-STREAM-INF") {
+                next_is_playlist = true;
+            }
             // Check for URI= attributes in tags (e.g., #EXT-X-KEY:URI="...")
             if trimmed.contains("URI=") {
-                let rewritten = rewrite_uri_attribute(trimmed, &base, proxy_base, referer);
+                let uri_hints = if tag_references_playlist(trimmed) { hints } else { &no_hints };
+                let rewritten = rewrite_uri_attribute(trimmed, &base, proxy_base, referer, uri_hints);
                 result.push_str(&rewritten);
             } else {
                 result.push_str(line);
@@ -103,8 +357,9 @@ fn rewrite_manifest_urls(manifest: &str, base_url: &str, proxy_base: &str, refer
         }
 
         // Regular lines are URLs (relative or absolute)
+        let uri_hints = if std::mem::take(&mut next_is_playlist) { hints } else { &no_hints };
         let absolute_url = resolve_url(trimmed, &base);
-        let proxied = build_proxy_url(&absolute_url, proxy_base, referer);
+        let proxied = build_proxy_url(&absolute_url, proxy_base, referer, uri_hints);
         result.push_str(&proxied);
         result.push('\n');
     }
@@ -127,16 +382,23 @@ fn resolve_url(url: &str, base: &Url) -> String {
 }
 
 /// Build a proxy URL for a given target URL
-fn build_proxy_url(target_url: &str, proxy_base: &str, referer: Option<&str>) -> String {
+fn build_proxy_url(target_url: &str, proxy_base: &str, referer: Option<&str>, hints: &VariantHints) -> String {
     let encoded = urlencoding::encode(target_url);
-    match referer {
+    let base = match referer {
         Some(r) => format!("{}/api/proxy/hls?url={}&referer={}", proxy_base, encoded, urlencoding::encode(r)),
         None => format!("{}/api/proxy/hls?url={}", proxy_base, encoded),
-    }
+    };
+    format!("{}{}", base, hints.to_query_suffix())
 }
 
 /// Rewrite URI= attribute in HLS tags
-fn rewrite_uri_attribute(line: &str, base: &Url, proxy_base: &str, referer: Option<&str>) -> String {
+fn rewrite_uri_attribute(
+    line: &str,
+    base: &Url,
+    proxy_base: &str,
+    referer: Option<&str>,
+    hints: &VariantHints,
+) -> String {
     // Find URI="..." pattern
     let uri_start = match line.find("URI=\"") {
         Some(pos) => pos + 5,
@@ -151,7 +413,7 @@ fn rewrite_uri_attribute(line: &str, base: &Url, proxy_base: &str, referer: Opti
 
     let uri = &rest[..uri_end];
     let absolute_url = resolve_url(uri, base);
-    let proxied = build_proxy_url(&absolute_url, proxy_base, referer);
+    let proxied = build_proxy_url(&absolute_url, proxy_base, referer, hints);
 
     format!("{}URI=\"{}\"{}",
         &line[..uri_start],
@@ -159,7 +421,25 @@ fn rewrite_uri_attribute(line: &str, base: &Url, proxy_base: &str, referer: Opti
         &line[uri_start + uri_end..])
 }
 
-/// GET /api/proxy/hls?url=<encoded>&referer=<optional>
+/// Resolve the hints for a manifest request: query values over the device defaults
+async fn resolve_hints(state: &AppState, query: &HlsProxyQuery) -> VariantHints {
+    let hints = VariantHints::from_query(query);
+    let Some(device_id) = query.device_id.as_deref().filter(|d| !d.is_empty()) else {
+        return hints;
+    };
+
+    match crate::db::repository::preferences::get_playback_hints(&state.pool, device_id).await {
+        Ok(Some(row)) => hints.or_defaults(row.into()),
+        Ok(None) => hints,
+        Err(e) => {
+            // Missing defaults must never break playback
+            tracing::warn!("Failed to load playback hints for {}: {}", device_id, e);
+            hints
+        }
+    }
+}
+
+/// GET /api/proxy/hls?url=<encoded>&referer=<optional>&max_height=&max_bandwidth=&codecs=&device_id=
 /// Lightweight proxy for HLS (manifest/segments) with passthrough of essential headers.
 /// Purpose: bypass CORS and ensure correct Content-Type without storing data in memory/disk.
 pub async fn hls_proxy(
@@ -175,29 +455,33 @@ pub async fn hls_proxy(
         ));
     }
 
-    // Create client optimized for streaming:
-    // - TCP keepalive prevents NAT/firewall from closing idle connections
-    // - Connect timeout ensures we don't hang on unreachable servers
-    // - Pool idle timeout keeps connections alive for reuse
-    // - No read timeout allows indefinite streaming for live content
-    let client = Client::builder()
-        .user_agent(&state.config.user_agent)
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .tcp_keepalive(Duration::from_secs(30))
-        .connect_timeout(Duration::from_secs(10))
-        .pool_idle_timeout(Duration::from_secs(90))
-        .pool_max_idle_per_host(5)
-        .build()
-        .map_err(|e| {
-            tracing::error!("Failed to create HTTP client: {}", e);
+    // Shared per-host client; hosts with an open circuit fail fast
+    let upstream = state.upstream.acquire(&query.url).map_err(|e| match e {
+        UpstreamError::CircuitOpen(_) => {
+            tracing::warn!("HLS proxy rejected {}: {}", query.url, e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error": "Servidor de origem indisponível",
+                    "detail": e.to_string()
+                })),
+            )
+        }
+        UpstreamError::InvalidUrl => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Parâmetro url inválido" })),
+        ),
+        UpstreamError::Client(_) => {
+            tracing::error!("{}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({ "error": "Erro interno" })),
             )
-        })?;
+        }
+    })?;
 
     // Build upstream request
-    let mut request = client.get(&query.url);
+    let mut request = upstream.client.get(&query.url);
 
     // Forward essential headers (using reqwest's header constants)
     if let Some(accept) = headers.get(header::ACCEPT) {
@@ -224,40 +508,52 @@ pub async fn hls_proxy(
     let looks_like_manifest = query.url.to_lowercase().contains(".m3u");
 
     // Execute request (manifest fetch wrapped with timeout, segments stream indefinitely)
-    let upstream_response = if looks_like_manifest {
-        timeout(
+    let result = if looks_like_manifest {
+        match timeout(
             Duration::from_millis(state.config.hls_proxy_timeout_ms),
             request.send(),
         )
         .await
-        .map_err(|_| {
-            tracing::error!("HLS proxy timeout for manifest {}", query.url);
-            (
-                StatusCode::GATEWAY_TIMEOUT,
+        {
+            Ok(result) => result,
+            Err(_) => {
+                upstream.finish(UpstreamOutcome::Timeout);
+                tracing::error!("HLS proxy timeout for manifest {}", query.url);
+                return Err((
+                    StatusCode::GATEWAY_TIMEOUT,
+                    Json(serde_json::json!({
+                        "error": "Falha ao proxyficar HLS",
+                        "detail": "Timeout ao baixar manifest"
+                    })),
+                ));
+            }
+        }
+    } else {
+        request.send().await
+    };
+
+    let upstream_response = match result {
+        Ok(response) => {
+            upstream.finish(UpstreamOutcome::from_status(response.status()));
+            response
+        }
+        Err(e) => {
+            upstream.finish(UpstreamOutcome::from_error(&e));
+            let status = if e.is_timeout() {
+                StatusCode::GATEWAY_TIMEOUT
+            } else {
+                StatusCode::BAD_GATEWAY
+            };
+            tracing::error!("HLS proxy error for {}: {}", query.url, e);
+            return Err((
+                status,
                 Json(serde_json::json!({
                     "error": "Falha ao proxyficar HLS",
-                    "detail": "Timeout ao baixar manifest"
+                    "detail": e.to_string()
                 })),
-            )
-        })?
-    } else {
-        request.send().await
-    }
-    .map_err(|e| {
-        let status = if e.is_timeout() {
-            StatusCode::GATEWAY_TIMEOUT
-        } else {
-            StatusCode::BAD_GATEWAY
-        };
-        tracing::error!("HLS proxy error for {}: {}", query.url, e);
-        (
-            status,
-            Json(serde_json::json!({
-                "error": "Falha ao proxyficar HLS",
-                "detail": e.to_string()
-            })),
-        )
-    })?;
+            ));
+        }
+    };
 
     let upstream_status = upstream_response.status();
 
@@ -303,12 +599,14 @@ pub async fn hls_proxy(
 
         let manifest_text = String::from_utf8_lossy(&manifest_bytes);
 
-        // Rewrite URLs in manifest to go through proxy
+        // Rewrite URLs in manifest to go through proxy (and drop variants the device can't play)
+        let hints = resolve_hints(&state, &query).await;
         let rewritten = rewrite_manifest_urls(
             &manifest_text,
             &query.url,
             proxy_base,
             query.referer.as_deref(),
+            &hints,
         );
 
         tracing::debug!("Rewritten HLS manifest for {}", query.url);
@@ -404,3 +702,95 @@ pub async fn hls_proxy(
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: &str = "#EXTM3U\n\
+#EXT-X-VERSION:3\n\
+#EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e,mp4a.40.2\"\n\
+low.m3u8\n\
+#EXT-X-STREAM-INF:BANDWIDTH=15000000,RESOLUTION=3840x2160,CODECS=\"hvc1.2.4.L150,mp4a.40.2\"\n\
+uhd.m3u8\n\
+#EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,CODECS=\"avc1.640028,mp4a.40.2\"\n\
+fhd.m3u8\n";
+
+    fn uris(manifest: &str) -> Vec<&str> {
+        manifest
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_variants_without_hints_is_noop() {
+        assert_eq!(filter_variants(MASTER, &VariantHints::default()), MASTER);
+    }
+
+    #[test]
+    fn test_filter_variants_by_height_and_codec() {
+        let hints = VariantHints {
+            max_height: Some(1080),
+            codecs: vec!["avc1".to_string(), "mp4a".to_string()],
+            ..Default::default()
+        };
+        let out = filter_variants(MASTER, &hints);
+        assert_eq!(uris(&out), vec!["fhd.m3u8", "low.m3u8"]);
+        assert!(out.starts_with("#EXTM3U\n#EXT-X-VERSION:3\n"));
+    }
+
+    #[test]
+    fn test_filter_variants_keeps_lowest_when_nothing_matches() {
+        let hints = VariantHints {
+            max_bandwidth: Some(100_000),
+            ..Default::default()
+        };
+        let out = filter_variants(MASTER, &hints);
+        assert_eq!(uris(&out), vec!["low.m3u8"]);
+    }
+
+    #[test]
+    fn test_rewrite_propagates_hints() {
+        let hints = VariantHints {
+            max_height: Some(720),
+            ..Default::default()
+        };
+        let out = rewrite_manifest_urls(MASTER, "http://cdn.example/live/master.m3u8", "http://proxy", None, &hints);
+        let lines = uris(&out);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("http://proxy/api/proxy/hls?url=http%3A%2F%2Fcdn.example%2Flive%2Flow.m3u8"));
+        assert!(lines[0].ends_with("&max_height=720"));
+    }
+
+    #[test]
+    fn test_rewrite_keeps_hints_off_segments_and_keys() {
+        let media = "#EXTM3U\n\
+#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n\
+#EXTINF:6.0,\n\
+seg1.ts\n";
+        let hints = VariantHints {
+            max_height: Some(720),
+            ..Default::default()
+        };
+        let out = rewrite_manifest_urls(media, "http://cdn.example/live/index.m3u8", "http://proxy", None, &hints);
+        assert!(!out.contains("max_height"));
+    }
+
+    #[test]
+    fn test_request_hints_override_device_defaults() {
+        let request = VariantHints {
+            max_height: Some(720),
+            ..Default::default()
+        };
+        let device = VariantHints {
+            max_height: Some(1080),
+            max_bandwidth: Some(4_000_000),
+            codecs: vec!["avc1".to_string()],
+        };
+        let merged = request.or_defaults(device);
+        assert_eq!(merged.max_height, Some(720));
+        assert_eq!(merged.max_bandwidth, Some(4_000_000));
+        assert_eq!(merged.codecs, vec!["avc1".to_string()]);
+    }
+}
@@ -0,0 +1,442 @@
+//! DVR recording API endpoints
+//!
+//! Recordings are scheduled per device, either by explicit time range or by
+//! an Xtream EPG entry, captured by the background recorder and served back
+//! as VOD with HTTP Range support.
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::db::repository::recordings::{self, NewRecording, RecordingRow};
+use crate::routes::xtream::get_xtream_credentials;
+use crate::services::xtream::{decode_base64_if_needed, timestamp_to_datetime, XtreamClient};
+use crate::AppState;
+
+/// Request to schedule a recording
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateRecordingRequest {
+    pub device_id: String,
+    /// Xtream playlist + live stream to record
+    pub playlist_id: Option<String>,
+    pub stream_id: Option<i64>,
+    /// Direct stream URL (M3U channels)
+    pub url: Option<String>,
+    pub channel_name: Option<String>,
+    pub title: Option<String>,
+    /// Explicit time range (defaults to now for start)
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    /// EPG entry id (from /api/xtream/:playlist_id/epg/:stream_id) to take the range from
+    pub epg_id: Option<String>,
+}
+
+/// Recording as returned by the API
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingResponse {
+    pub id: String,
+    pub channel_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub playlist_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_id: Option<i64>,
+    pub start_at: String,
+    pub end_at: String,
+    pub status: String,
+    pub size_bytes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Playback URL (relative to server base URL)
+    pub play_url: String,
+    pub created_at: String,
+}
+
+impl From<RecordingRow> for RecordingResponse {
+    fn from(row: RecordingRow) -> Self {
+        Self {
+            play_url: format!("/api/recordings/{}/{}/stream", row.device_id, row.id),
+            id: row.id.to_string(),
+            channel_name: row.channel_name,
+            title: row.title,
+            playlist_id: row.playlist_id.map(|id| id.to_string()),
+            stream_id: row.stream_id,
+            start_at: row.start_at.to_rfc3339(),
+            end_at: row.end_at.to_rfc3339(),
+            status: row.status,
+            size_bytes: row.size_bytes,
+            error: row.error,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Response for recording listings
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingsListResponse {
+    pub recordings: Vec<RecordingResponse>,
+    pub total: usize,
+    pub used_bytes: i64,
+    pub quota_bytes: u64,
+}
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn bad_request(msg: &str) -> ApiError {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": msg })))
+}
+
+fn db_error(e: sqlx::Error) -> ApiError {
+    tracing::error!("Recordings database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Database error" })),
+    )
+}
+
+fn parse_recording_id(s: &str) -> Result<Uuid, ApiError> {
+    Uuid::parse_str(s).map_err(|_| bad_request("Invalid recording ID format"))
+}
+
+async fn find_recording(
+    state: &AppState,
+    device_id: &str,
+    id: Uuid,
+) -> Result<RecordingRow, ApiError> {
+    recordings::find_for_device(&state.pool, device_id, id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Gravação não encontrada" })),
+            )
+        })
+}
+
+/// Parse a single `bytes=start-end` range against a file length.
+/// Returns the inclusive (start, end) or None if unsatisfiable/unsupported.
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') || len == 0 {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+
+    let (start, end) = if start.is_empty() {
+        // Suffix range: last N bytes
+        let n: u64 = end.parse().ok()?;
+        if n == 0 {
+            return None;
+        }
+        (len.saturating_sub(n), len - 1)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() {
+            len - 1
+        } else {
+            end.parse::<u64>().ok()?.min(len - 1)
+        };
+        (start, end)
+    };
+
+    if start > end || start >= len {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// POST /api/recordings - Schedule a recording
+pub async fn create_recording(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateRecordingRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if payload.device_id.is_empty() {
+        return Err(bad_request("device_id is required"));
+    }
+
+    let mut title = payload.title.clone();
+    let mut start_at = payload.start_at;
+    let mut end_at = payload.end_at;
+
+    // Resolve stream URL: Xtream live stream or direct URL
+    let (playlist_id, stream_url) = match (&payload.playlist_id, payload.stream_id, &payload.url) {
+        (Some(playlist_id), Some(stream_id), _) => {
+            let playlist_uuid =
+                Uuid::parse_str(playlist_id).map_err(|_| bad_request("Invalid playlist ID format"))?;
+            let (creds, _) = get_xtream_credentials(&state.pool, playlist_uuid).await?;
+
+            if let Some(ref epg_id) = payload.epg_id {
+                let client = XtreamClient::from_credentials(&creds);
+                let epg = client.get_short_epg(stream_id, Some(50)).await.map_err(|e| {
+                    tracing::error!("Xtream EPG error: {}", e);
+                    (
+                        StatusCode::BAD_GATEWAY,
+                        Json(serde_json::json!({"error": format!("Xtream API error: {}", e)})),
+                    )
+                })?;
+
+                let entry = epg
+                    .epg_listings
+                    .into_iter()
+                    .find(|e| &e.id == epg_id || &e.epg_id == epg_id)
+                    .ok_or_else(|| {
+                        (
+                            StatusCode::NOT_FOUND,
+                            Json(serde_json::json!({ "error": "Programa não encontrado no EPG" })),
+                        )
+                    })?;
+
+                start_at = timestamp_to_datetime(&Some(entry.start_timestamp.clone()));
+                end_at = timestamp_to_datetime(&Some(entry.stop_timestamp.clone()));
+                if title.is_none() {
+                    title = Some(decode_base64_if_needed(&entry.title));
+                }
+            }
+
+            // Raw TS is the simplest format to capture
+            (Some(playlist_uuid), creds.live_url_with_format(stream_id, Some("ts")))
+        }
+        (_, _, Some(url)) if url.starts_with("http://") || url.starts_with("https://") => {
+            (None, url.clone())
+        }
+        _ => return Err(bad_request("Informe playlistId + streamId ou uma url válida")),
+    };
+
+    let now = Utc::now();
+    let start_at = start_at.unwrap_or(now).max(now - Duration::minutes(1));
+    let end_at = end_at.ok_or_else(|| bad_request("endAt (ou epgId) é obrigatório"))?;
+
+    if end_at <= start_at || end_at <= now {
+        return Err(bad_request("Intervalo de gravação inválido"));
+    }
+    if end_at - start_at > Duration::minutes(state.config.recording_max_minutes as i64) {
+        return Err(bad_request("Gravação excede a duração máxima permitida"));
+    }
+
+    let used = recordings::used_bytes(&state.pool, &payload.device_id)
+        .await
+        .map_err(db_error)?;
+    if used as u64 >= state.recorder.quota_bytes() {
+        return Err((
+            StatusCode::INSUFFICIENT_STORAGE,
+            Json(serde_json::json!({ "error": "Cota de armazenamento excedida" })),
+        ));
+    }
+
+    let channel_name = payload
+        .channel_name
+        .clone()
+        .or_else(|| title.clone())
+        .unwrap_or_else(|| "Gravação".to_string());
+
+    let row = recordings::insert(
+        &state.pool,
+        &NewRecording {
+            device_id: payload.device_id.clone(),
+            playlist_id,
+            stream_id: payload.stream_id,
+            channel_name,
+            title,
+            stream_url,
+            start_at,
+            end_at,
+        },
+    )
+    .await
+    .map_err(db_error)?;
+
+    tracing::info!(
+        "Scheduled recording {} for device {} ({} - {})",
+        row.id,
+        row.device_id,
+        row.start_at,
+        row.end_at
+    );
+
+    Ok((StatusCode::CREATED, Json(RecordingResponse::from(row))))
+}
+
+/// GET /api/recordings/:device_id - List recordings for a device
+pub async fn list_recordings(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    if device_id.is_empty() {
+        return Err(bad_request("device_id is required"));
+    }
+
+    let rows = recordings::list_by_device(&state.pool, &device_id)
+        .await
+        .map_err(db_error)?;
+    // Same usage the quota is enforced against (in-progress captures count what they reserved)
+    let used_bytes = recordings::used_bytes(&state.pool, &device_id)
+        .await
+        .map_err(db_error)?;
+    let recordings: Vec<RecordingResponse> = rows.into_iter().map(Into::into).collect();
+
+    Ok(Json(RecordingsListResponse {
+        total: recordings.len(),
+        recordings,
+        used_bytes,
+        quota_bytes: state.recorder.quota_bytes(),
+    }))
+}
+
+/// POST /api/recordings/:device_id/:id/stop - End a recording now (keeps what was captured)
+pub async fn stop_recording(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let id = parse_recording_id(&id)?;
+    let rec = find_recording(&state, &device_id, id).await?;
+
+    if rec.status != recordings::STATUS_SCHEDULED && rec.status != recordings::STATUS_RECORDING {
+        return Err(bad_request("Gravação já finalizada"));
+    }
+
+    recordings::stop_now(&state.pool, id).await.map_err(db_error)?;
+    state.recorder.cancel(id);
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+/// DELETE /api/recordings/:device_id/:id - Cancel and delete a recording
+pub async fn delete_recording(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let id = parse_recording_id(&id)?;
+    find_recording(&state, &device_id, id).await?;
+
+    state.recorder.cancel(id);
+    let deleted = recordings::delete(&state.pool, id).await.map_err(db_error)?;
+    let _ = tokio::fs::remove_file(state.recorder.file_path(id)).await;
+
+    tracing::info!("Deleted recording {} for device {}", id, device_id);
+
+    Ok(Json(serde_json::json!({
+        "success": deleted > 0,
+        "deleted": deleted
+    })))
+}
+
+/// GET /api/recordings/:device_id/:id/stream - Play a recording (supports Range)
+pub async fn stream_recording(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let id = parse_recording_id(&id)?;
+    let rec = find_recording(&state, &device_id, id).await?;
+    let path = rec
+        .file_path
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| state.recorder.file_path(id));
+
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Arquivo da gravação não encontrado" })),
+        )
+    };
+
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|_| not_found())?;
+    let len = file.metadata().await.map_err(|_| not_found())?.len();
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, "video/MP2T")
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, "no-store");
+
+    let body = match range {
+        Some(value) => {
+            let (start, end) = match parse_range(value, len) {
+                Some(r) => r,
+                None => {
+                    return Response::builder()
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                        .body(Body::empty())
+                        .map_err(|_| {
+                            (
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                Json(serde_json::json!({ "error": "Erro interno" })),
+                            )
+                        });
+                }
+            };
+
+            file.seek(std::io::SeekFrom::Start(start)).await.map_err(|e| {
+                tracing::error!("Failed to seek recording {}: {}", id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({ "error": "Erro interno" })),
+                )
+            })?;
+
+            let length = end - start + 1;
+            response = response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len))
+                .header(header::CONTENT_LENGTH, length);
+            Body::from_stream(ReaderStream::new(file.take(length)))
+        }
+        None => {
+            response = response
+                .status(StatusCode::OK)
+                .header(header::CONTENT_LENGTH, len);
+            Body::from_stream(ReaderStream::new(file))
+        }
+    };
+
+    response.body(body).map_err(|e| {
+        tracing::error!("Failed to build response: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "Erro interno" })),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(parse_range("bytes=500-", 1000), Some((500, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some((900, 999)));
+    }
+
+    #[test]
+    fn test_parse_range_unsatisfiable() {
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=10-5", 1000), None);
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+    }
+}
@@ -0,0 +1,408 @@
+//! Phone-as-remote API endpoints
+//!
+//! The phone pairs through the TV's QR session and then authenticates with
+//! the `x-remote-token` header. It browses the TV's playlist through the
+//! regular playlist/Xtream endpoints and sends commands that reach the TV
+//! over its event stream.
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::db::repository::playlists;
+use crate::db::repository::remotes::{self, RemoteRow};
+use crate::routes::devices::authenticate_device;
+use crate::routes::session::{lookup_session, request_ip};
+use crate::services::events::{device_channel, PushEvent};
+use crate::services::remote::{generate_token, hash_token, RemoteCommand};
+use crate::AppState;
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+const MAX_NAME_LEN: usize = 64;
+
+/// Request to pair a phone with the session's TV
+#[derive(Debug, Deserialize)]
+pub struct PairRequest {
+    pub name: Option<String>,
+}
+
+/// A paired phone as shown to the TV
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteInfo {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+    pub last_used_at: i64,
+}
+
+impl From<RemoteRow> for RemoteInfo {
+    fn from(row: RemoteRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            name: row.name,
+            created_at: row.created_at.timestamp_millis(),
+            last_used_at: row.last_used_at.timestamp_millis(),
+        }
+    }
+}
+
+/// The TV's playlist, for browsing from the phone
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemotePlaylist {
+    pub hash: String,
+    pub source_type: String,
+    pub playlist_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("Failed to access remote pairing: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Failed to access remote pairing" })),
+    )
+}
+
+fn error(status: StatusCode, message: &str) -> ApiError {
+    (status, Json(serde_json::json!({ "error": message })))
+}
+
+/// Pairing of the requesting phone (from the `x-remote-token` header)
+async fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<RemoteRow, ApiError> {
+    let token = headers
+        .get("x-remote-token")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|t| !t.is_empty() && t.len() <= 128)
+        .ok_or_else(|| error(StatusCode::UNAUTHORIZED, "Missing remote token"))?;
+
+    remotes::touch_by_token_hash(&state.pool, &hash_token(token))
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| error(StatusCode::UNAUTHORIZED, "Invalid remote token"))
+}
+
+/// POST /session/:id/pair - Pair the phone with the TV that created the session
+///
+/// Only sessions the TV created with its device secret carry its device_id,
+/// so a phone can't pair with a TV whose QR code it never saw. Returns the
+/// token the phone sends as `x-remote-token` from then on.
+pub async fn pair_session(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(request): Json<PairRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let ip = request_ip(&state, &headers, peer);
+    let session = lookup_session(&state, &id, &ip)
+        .await?
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, "Session not found or expired"))?;
+    let device_id = session
+        .device_id
+        .ok_or_else(|| error(StatusCode::BAD_REQUEST, "Session isn't linked to a TV"))?;
+
+    let name = request
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(|n| n.chars().take(MAX_NAME_LEN).collect::<String>())
+        .unwrap_or_else(|| "Celular".to_string());
+
+    let token = generate_token();
+    let row = remotes::create(&state.pool, &device_id, &hash_token(&token), &name)
+        .await
+        .map_err(internal_error)?;
+
+    state
+        .events
+        .notify(
+            &device_channel(&device_id),
+            PushEvent::RemotePaired {
+                remote_id: row.id.to_string(),
+                name: row.name.clone(),
+            },
+        )
+        .await;
+    tracing::info!("Remote {} paired with device {}", row.id, device_id);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "token": token,
+            "remote": RemoteInfo::from(row),
+        })),
+    ))
+}
+
+/// GET /api/remote - The paired TV and its playlist
+pub async fn get_remote(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let remote = authenticate(&state, &headers).await?;
+
+    let playlist = playlists::find_by_device(&state.pool, &remote.device_id)
+        .await
+        .map_err(internal_error)?
+        .map(|p| RemotePlaylist {
+            source_type: if p.is_xtream() { "xtream" } else { "m3u" }.to_string(),
+            playlist_id: p.id.to_string(),
+            hash: p.hash,
+            name: p.name,
+        });
+
+    Ok(Json(serde_json::json!({
+        "remote": RemoteInfo::from(remote),
+        "playlist": playlist,
+    })))
+}
+
+/// POST /api/remote/command - Send a command to the paired TV
+pub async fn send_command(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(command): Json<RemoteCommand>,
+) -> Result<impl IntoResponse, ApiError> {
+    let remote = authenticate(&state, &headers).await?;
+    command
+        .validate()
+        .map_err(|message| error(StatusCode::BAD_REQUEST, message))?;
+
+    let event = PushEvent::RemoteCommand {
+        remote_id: remote.id.to_string(),
+        command,
+    };
+    state
+        .events
+        .publish(&device_channel(&remote.device_id), &event)
+        .await
+        .map_err(internal_error)?;
+
+    Ok((StatusCode::ACCEPTED, Json(serde_json::json!({ "success": true }))))
+}
+
+/// DELETE /api/remote - Unpair the requesting phone
+pub async fn unpair(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let remote = authenticate(&state, &headers).await?;
+    remotes::delete(&state.pool, &remote.device_id, remote.id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/devices/:device_id/remotes - Phones paired with the TV
+pub async fn list_remotes(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+
+    let rows = remotes::list_by_device(&state.pool, &device_id)
+        .await
+        .map_err(internal_error)?;
+    let remotes: Vec<RemoteInfo> = rows.into_iter().map(Into::into).collect();
+
+    Ok(Json(serde_json::json!({ "remotes": remotes })))
+}
+
+/// DELETE /api/devices/:device_id/remotes/:remote_id - Unpair a phone from the TV
+pub async fn remove_remote(
+    State(state): State<Arc<AppState>>,
+    Path((device_id, remote_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    authenticate_device(&state, &device_id, &headers, None).await?;
+    let remote_id = uuid::Uuid::parse_str(&remote_id)
+        .map_err(|_| error(StatusCode::NOT_FOUND, "Remote not found"))?;
+
+    if remotes::delete(&state.pool, &device_id, remote_id)
+        .await
+        .map_err(internal_error)?
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(error(StatusCode::NOT_FOUND, "Remote not found"))
+    }
+}
+
+/// GET /r - Remote control page for a paired phone (token kept in localStorage)
+pub async fn remote_page() -> impl IntoResponse {
+    Html(REMOTE_HTML)
+}
+
+const REMOTE_HTML: &str = r#"<!DOCTYPE html>
+<html lang="pt-BR">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>AtivePlay - Controle Remoto</title>
+    <style>
+        * { margin: 0; padding: 0; box-sizing: border-box; }
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+            background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);
+            min-height: 100vh;
+            color: #fff;
+            padding: 20px;
+        }
+        h1 { font-size: 22px; margin-bottom: 4px; }
+        .subtitle { color: rgba(255,255,255,0.6); font-size: 14px; margin-bottom: 16px; }
+        .search { display: flex; gap: 8px; margin-bottom: 16px; }
+        input {
+            flex: 1;
+            padding: 14px 16px;
+            border-radius: 8px;
+            border: 1px solid rgba(255,255,255,0.2);
+            background: rgba(255,255,255,0.1);
+            color: #fff;
+            font-size: 16px;
+        }
+        input:focus { outline: none; border-color: #6366f1; }
+        button {
+            padding: 10px 14px;
+            border-radius: 8px;
+            border: none;
+            background: #6366f1;
+            color: #fff;
+            font-size: 14px;
+            font-weight: 600;
+        }
+        button.secondary { background: rgba(255,255,255,0.12); }
+        .result {
+            display: flex;
+            align-items: center;
+            gap: 8px;
+            padding: 10px 0;
+            border-bottom: 1px solid rgba(255,255,255,0.08);
+        }
+        .result span { flex: 1; font-size: 15px; }
+        .result small { display: block; color: rgba(255,255,255,0.5); }
+        .status { text-align: center; margin-top: 16px; font-size: 14px; min-height: 20px; }
+        .status.error { color: #ef4444; }
+        .status.success { color: #22c55e; }
+    </style>
+</head>
+<body>
+    <h1>AtivePlay</h1>
+    <p class="subtitle" id="subtitle">Controle remoto</p>
+    <form class="search" id="form">
+        <input type="search" id="q" placeholder="Buscar filmes, séries e canais" autocomplete="off">
+        <button type="submit">Buscar</button>
+    </form>
+    <button class="secondary" id="tv-search">Buscar na TV</button>
+    <div id="results"></div>
+    <p class="status" id="status"></p>
+    <script>
+        const token = localStorage.getItem('ativeplay_remote_token');
+        const statusEl = document.getElementById('status');
+        const results = document.getElementById('results');
+        let playlist = null;
+
+        function show(message, kind) {
+            statusEl.textContent = message;
+            statusEl.className = 'status ' + (kind || '');
+        }
+
+        async function api(path, options) {
+            const res = await fetch(path, Object.assign({}, options, {
+                headers: { 'Content-Type': 'application/json', 'x-remote-token': token }
+            }));
+            if (res.status === 401) {
+                localStorage.removeItem('ativeplay_remote_token');
+                throw new Error('Pareamento removido. Escaneie o QR code da TV novamente.');
+            }
+            if (!res.ok) throw new Error('Falha na comunicação com a TV');
+            return res.status === 204 ? null : res.json();
+        }
+
+        async function send(command, label) {
+            try {
+                await api('/api/remote/command', { method: 'POST', body: JSON.stringify(command) });
+                show(label + ' enviado para a TV', 'success');
+            } catch (err) {
+                show(err.message, 'error');
+            }
+        }
+
+        function row(name, detail, actions) {
+            const div = document.createElement('div');
+            div.className = 'result';
+            const span = document.createElement('span');
+            span.textContent = name;
+            const small = document.createElement('small');
+            small.textContent = detail;
+            span.appendChild(small);
+            div.appendChild(span);
+            for (const [label, command] of actions) {
+                const button = document.createElement('button');
+                button.textContent = label;
+                button.addEventListener('click', () => send(command, label));
+                div.appendChild(button);
+            }
+            results.appendChild(div);
+        }
+
+        async function search(q) {
+            results.innerHTML = '';
+            if (!playlist || playlist.sourceType !== 'm3u') {
+                return send({ action: 'search', query: q }, 'Busca');
+            }
+            const data = await fetch('/api/playlist/' + playlist.hash + '/search?limit=10&q=' + encodeURIComponent(q))
+                .then(r => r.json());
+            for (const s of data.series.items) {
+                row(s.name, 'Série', [
+                    ['Abrir', { action: 'openSeries', seriesId: s.id }],
+                    ['★', { action: 'addFavorite', seriesId: s.id }]
+                ]);
+            }
+            for (const item of data.movies.items.concat(data.live.items)) {
+                row(item.name, item.group, [
+                    ['Assistir', { action: 'play', itemId: item.id }],
+                    ['★', { action: 'addFavorite', itemId: item.id }]
+                ]);
+            }
+            if (!results.children.length) show('Nada encontrado');
+        }
+
+        document.getElementById('form').addEventListener('submit', (e) => {
+            e.preventDefault();
+            const q = document.getElementById('q').value.trim();
+            if (!q) return;
+            show('');
+            search(q).catch(err => show(err.message, 'error'));
+        });
+
+        document.getElementById('tv-search').addEventListener('click', () => {
+            const q = document.getElementById('q').value.trim();
+            if (q) send({ action: 'search', query: q }, 'Busca');
+        });
+
+        if (!token) {
+            show('Escaneie o QR code da TV para parear este celular.', 'error');
+        } else {
+            api('/api/remote').then(data => {
+                playlist = data.playlist;
+                document.getElementById('subtitle').textContent =
+                    'Controlando a TV' + (playlist && playlist.name ? ' - ' + playlist.name : '');
+            }).catch(err => show(err.message, 'error'));
+        }
+    </script>
+</body>
+</html>"#;
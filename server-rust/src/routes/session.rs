@@ -1,6 +1,6 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse},
     Json,
 };
@@ -8,11 +8,23 @@ use base64::{engine::general_purpose::STANDARD, Engine};
 use image::Luma;
 use qrcode::QrCode;
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use uuid::Uuid;
 
+use crate::models::Session;
+use crate::routes::devices::authenticate_device;
+use crate::services::events::{session_channel, PushEvent};
+use crate::services::session_guard::{
+    client_ip, generate_pairing_code, generate_session_id, normalize_pairing_code, SessionAction,
+};
+use crate::services::xtream::{self, XtreamCredentials};
 use crate::AppState;
 
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+/// Attempts at finding a free pairing code before giving up on it
+const PAIRING_CODE_ATTEMPTS: usize = 5;
+
 /// Response for session creation
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -20,6 +32,9 @@ struct CreateSessionResponse {
     session_id: String,
     qr_data_url: String,
     mobile_url: String,
+    /// 6-digit code to type at `/s` (only when requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pairing_code: Option<String>,
     expires_at: i64,
 }
 
@@ -30,10 +45,37 @@ struct PollSessionResponse {
     received: bool,
 }
 
-/// Request to send URL
+/// Optional body of session creation
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSessionRequest {
+    /// The TV creating the session, so a phone can pair with it as a remote
+    /// (requires the device's `x-device-secret`)
+    pub device_id: Option<String>,
+    /// Also issue a 6-digit code for phones that can't scan the QR code
+    #[serde(default)]
+    pub pairing_code: bool,
+}
+
+/// Request to find a session by its typed code
+#[derive(Deserialize)]
+pub struct ResolveCodeRequest {
+    pub code: String,
+}
+
+/// Request to send a playlist: a URL or Xtream credentials
 #[derive(Deserialize)]
 pub struct SendUrlRequest {
-    pub url: String,
+    pub url: Option<String>,
+    pub xtream: Option<XtreamLogin>,
+}
+
+/// Xtream account as given by providers (server, username, password)
+#[derive(Deserialize)]
+pub struct XtreamLogin {
+    pub server: String,
+    pub username: String,
+    pub password: String,
 }
 
 /// Generate QR code as data URL
@@ -64,12 +106,119 @@ fn generate_qr_data_url(content: &str) -> Result<String, Box<dyn std::error::Err
     Ok(format!("data:image/png;base64,{}", base64_data))
 }
 
+fn internal_error(message: &str, e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("{}: {}", message, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Erro ao buscar sessão" })),
+    )
+}
+
+fn too_many_requests(retry_after: u64) -> ApiError {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(serde_json::json!({
+            "error": "Muitas tentativas. Aguarde e tente novamente.",
+            "code": "RATE_LIMITED",
+            "retryAfter": retry_after
+        })),
+    )
+}
+
+/// Client IP used for the session limits
+pub fn request_ip(state: &AppState, headers: &HeaderMap, peer: SocketAddr) -> String {
+    client_ip(headers, peer, state.config.trust_proxy_headers)
+}
+
+/// Refuse IPs locked out after too many failed lookups
+async fn check_lockout(state: &AppState, ip: &str) -> Result<(), ApiError> {
+    match state.session_guard.lockout(ip).await {
+        Ok(None) => Ok(()),
+        Ok(Some(retry_after)) => Err(too_many_requests(retry_after)),
+        Err(e) => Err(internal_error("Failed to check session lockout", e)),
+    }
+}
+
+async fn check_rate(state: &AppState, action: SessionAction, ip: &str) -> Result<(), ApiError> {
+    match state.session_guard.check_rate(action, ip).await {
+        Ok(None) => Ok(()),
+        Ok(Some(retry_after)) => Err(too_many_requests(retry_after)),
+        Err(e) => Err(internal_error("Failed to check session rate limit", e)),
+    }
+}
+
+async fn record_failed_lookup(state: &AppState, ip: &str) {
+    if let Err(e) = state.session_guard.record_failed_lookup(ip).await {
+        tracing::warn!("Failed to record session lookup failure: {}", e);
+    }
+}
+
+/// Session looked up from a phone
+///
+/// Locked-out IPs are refused and lookups of unknown sessions count towards
+/// the lockout.
+pub async fn lookup_session(state: &AppState, id: &str, ip: &str) -> Result<Option<Session>, ApiError> {
+    check_lockout(state, ip).await?;
+
+    let session = state
+        .redis
+        .get_session(id)
+        .await
+        .map_err(|e| internal_error("Failed to get session", e))?;
+    if session.is_none() {
+        record_failed_lookup(state, ip).await;
+    }
+    Ok(session)
+}
+
+/// Reserve a free pairing code for a session (None if none was found)
+async fn reserve_pairing_code(state: &AppState, session_id: &str) -> Result<Option<String>, ApiError> {
+    for _ in 0..PAIRING_CODE_ATTEMPTS {
+        let code = generate_pairing_code();
+        let reserved = state
+            .redis
+            .reserve_pairing_code(&code, session_id, state.config.session_ttl_seconds)
+            .await
+            .map_err(|e| internal_error("Failed to reserve pairing code", e))?;
+        if reserved {
+            return Ok(Some(code));
+        }
+    }
+
+    tracing::warn!("No free pairing code for session {}", session_id);
+    Ok(None)
+}
+
 /// POST /session/create - Create a new session and return QR code
 pub async fn create_session(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    payload: Option<Json<CreateSessionRequest>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    // Generate unique session ID (12 hex characters)
-    let session_id = Uuid::new_v4().to_string()[..12].to_string();
+    let ip = request_ip(&state, &headers, peer);
+    check_rate(&state, SessionAction::Create, &ip).await?;
+
+    let (device_id, wants_code) = match payload {
+        Some(Json(payload)) => (payload.device_id, payload.pairing_code),
+        None => (None, false),
+    };
+    let device_id = device_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty() && id.len() <= 64);
+
+    // A session linked to a TV lets phones pair with it: only that TV may link it
+    if let Some(device_id) = &device_id {
+        authenticate_device(&state, device_id, &headers, None).await?;
+    }
+
+    // Generate unguessable session ID (32 hex characters)
+    let session_id = generate_session_id();
+    let pairing_code = if wants_code {
+        reserve_pairing_code(&state, &session_id).await?
+    } else {
+        None
+    };
 
     // Calculate expiration time (15 minutes)
     let now = chrono::Utc::now().timestamp_millis();
@@ -81,7 +230,12 @@ pub async fn create_session(
     // Create session in Redis
     state
         .redis
-        .create_session(&session_id, state.config.session_ttl_seconds)
+        .create_session(
+            &session_id,
+            device_id.as_deref(),
+            pairing_code.as_deref(),
+            state.config.session_ttl_seconds,
+        )
         .await
         .map_err(|e| {
             tracing::error!("Failed to create session: {}", e);
@@ -106,90 +260,150 @@ pub async fn create_session(
         session_id,
         qr_data_url,
         mobile_url,
+        pairing_code,
         expires_at,
     }))
 }
 
 /// GET /session/:id/poll - TV polls for URL from mobile
+///
+/// Rate limited per IP, and polling unknown sessions counts towards the
+/// lookup lockout like it does on the phone side.
 pub async fn poll_session(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    // Get session from Redis
+    let ip = request_ip(&state, &headers, peer);
+    check_rate(&state, SessionAction::Poll, &ip).await?;
+    check_lockout(&state, &ip).await?;
+
+    // The URL can only be taken once; taking it ends the session
+    let url = state
+        .redis
+        .take_session_url(&id)
+        .await
+        .map_err(|e| internal_error("Failed to take session URL", e))?;
+    if let Some(url) = url {
+        tracing::info!("Session {} - URL received by TV", id);
+        return Ok(Json(PollSessionResponse {
+            url: Some(url),
+            received: true,
+        }));
+    }
+
     let session = state
         .redis
         .get_session(&id)
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to get session: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": "Erro ao buscar sessão" })),
-            )
-        })?;
+        .map_err(|e| internal_error("Failed to get session", e))?;
 
     match session {
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "Sessão não encontrada ou expirada" })),
-        )),
-        Some(session) => {
-            if let Some(url) = session.url {
-                tracing::info!("Session {} - URL received by TV", id);
-                // Delete session after URL is retrieved
-                let _ = state.redis.del(&format!("session:{}", id)).await;
-                Ok(Json(PollSessionResponse {
-                    url: Some(url),
-                    received: true,
-                }))
-            } else {
-                Ok(Json(PollSessionResponse {
-                    url: None,
-                    received: false,
-                }))
-            }
+        None => {
+            record_failed_lookup(&state, &ip).await;
+            Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Sessão não encontrada ou expirada" })),
+            ))
         }
+        Some(_) => Ok(Json(PollSessionResponse {
+            url: None,
+            received: false,
+        })),
     }
 }
 
-/// POST /session/:id/send - Mobile sends URL
+fn already_sent_error() -> ApiError {
+    (
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({ "error": "Uma playlist já foi enviada para esta sessão" })),
+    )
+}
+
+fn bad_request(message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": message })))
+}
+
+/// POST /session/:id/send - Mobile sends a URL or Xtream credentials
+///
+/// Xtream accounts (structured credentials or a get.php URL) are validated
+/// here, so a wrong password is reported on the phone instead of the TV.
 pub async fn send_url(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Path(id): Path<String>,
     Json(payload): Json<SendUrlRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    // Validate URL
-    if payload.url.is_empty() || !payload.url.starts_with("http") {
+    let ip = request_ip(&state, &headers, peer);
+    check_rate(&state, SessionAction::Send, &ip).await?;
+
+    let (url, creds) = match (payload.url, payload.xtream) {
+        (_, Some(login)) => {
+            let server = xtream::normalize_server(&login.server)
+                .ok_or_else(|| bad_request("Endereço do servidor inválido"))?;
+            let username = login.username.trim();
+            // Passwords are taken as typed: spaces can be part of them
+            let password = login.password.as_str();
+            if username.is_empty() || password.is_empty() {
+                return Err(bad_request("Informe usuário e senha"));
+            }
+            let creds = XtreamCredentials {
+                server,
+                username: username.to_string(),
+                password: password.to_string(),
+                preferred_live_format: "ts".to_string(),
+            };
+            (creds.m3u_url(), Some(creds))
+        }
+        (Some(url), None) => {
+            let url = url.trim().to_string();
+            // Validate URL
+            if url.is_empty() || !url.starts_with("http") {
+                return Err(bad_request("URL inválida"));
+            }
+            let creds = xtream::extract_credentials(&url);
+            (url, creds)
+        }
+        (None, None) => return Err(bad_request("URL inválida")),
+    };
+
+    // Check if session exists
+    if lookup_session(&state, &id, &ip).await?.is_none() {
         return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "URL inválida" })),
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Sessão não encontrada ou expirada" })),
         ));
     }
 
-    // Check if session exists
-    let session = state
+    // Checked before validating credentials, and again when storing
+    let already_sent = state
         .redis
-        .get_session(&id)
+        .has_session_url(&id)
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to get session: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": "Erro ao buscar sessão" })),
-            )
-        })?;
+        .map_err(|e| internal_error("Failed to get session URL", e))?;
+    if already_sent {
+        return Err(already_sent_error());
+    }
 
-    if session.is_none() {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "Sessão não encontrada ou expirada" })),
-        ));
+    if let Some(creds) = &creds {
+        if let Err(e) = xtream::validate_credentials(creds).await {
+            tracing::info!("Session {} - Xtream validation failed: {}", id, e);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": e.user_message(),
+                    "code": "XTREAM_VALIDATION_FAILED"
+                })),
+            ));
+        }
     }
 
-    // Update session with URL
-    state
+    // Update session with URL (only the first one is kept)
+    let stored = state
         .redis
-        .set_session_url(&id, &payload.url, state.config.session_ttl_seconds)
+        .set_session_url(&id, &url, state.config.session_ttl_seconds)
         .await
         .map_err(|e| {
             tracing::error!("Failed to set session URL: {}", e);
@@ -198,9 +412,23 @@ pub async fn send_url(
                 Json(serde_json::json!({ "error": "Erro ao enviar URL" })),
             )
         })?;
+    if !stored {
+        return Err(already_sent_error());
+    }
 
     tracing::info!("Session {} - URL sent by mobile", id);
 
+    // Push to a TV listening on the session (polling keeps working)
+    state
+        .events
+        .notify(
+            &session_channel(&id),
+            PushEvent::SessionUrl {
+                session_id: id.clone(),
+            },
+        )
+        .await;
+
     Ok(Json(serde_json::json!({
         "success": true,
         "message": "URL enviada com sucesso!"
@@ -210,19 +438,61 @@ pub async fn send_url(
 /// GET /s/:id - Mobile HTML page to enter playlist URL
 pub async fn mobile_page(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    // Check if session exists in Redis
-    let session = state.redis.get_session(&id).await.ok().flatten();
+    let ip = request_ip(&state, &headers, peer);
 
-    if session.is_none() {
-        return Html(expired_html());
+    match lookup_session(&state, &id, &ip).await {
+        Ok(Some(session)) => (StatusCode::OK, Html(form_html(&id, session.device_id.is_some()))),
+        Ok(None) => (StatusCode::NOT_FOUND, Html(expired_html())),
+        Err((status, _)) if status == StatusCode::TOO_MANY_REQUESTS => (status, Html(locked_html())),
+        Err((status, _)) => (status, Html(expired_html())),
     }
+}
+
+/// POST /session/code - Find a session by the code shown on the TV
+pub async fn resolve_code(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<ResolveCodeRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let ip = request_ip(&state, &headers, peer);
+    check_lockout(&state, &ip).await?;
+
+    let session_id = match normalize_pairing_code(&payload.code) {
+        Some(code) => state
+            .redis
+            .resolve_pairing_code(&code)
+            .await
+            .map_err(|e| internal_error("Failed to resolve pairing code", e))?,
+        None => None,
+    };
+
+    let Some(session_id) = session_id else {
+        record_failed_lookup(&state, &ip).await;
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Código inválido ou expirado" })),
+        ));
+    };
+
+    Ok(Json(serde_json::json!({
+        "sessionId": session_id,
+        "mobileUrl": format!("{}/s/{}", state.config.base_url, session_id),
+    })))
+}
 
-    Html(form_html(&id))
+/// GET /s - Mobile HTML page to type the code shown on the TV
+pub async fn code_page() -> impl IntoResponse {
+    Html(CODE_HTML)
 }
 
-fn form_html(session_id: &str) -> String {
+fn form_html(session_id: &str, can_pair: bool) -> String {
+    // Pairing needs to know which TV created the session
+    let pair_display = if can_pair { "block" } else { "none" };
     format!(r#"<!DOCTYPE html>
 <html lang="pt-BR">
 <head>
@@ -309,35 +579,107 @@ fn form_html(session_id: &str) -> String {
         }}
         .status.success {{ color: #22c55e; }}
         .status.error {{ color: #ef4444; }}
+        .tabs {{
+            display: flex;
+            gap: 8px;
+            margin-bottom: 16px;
+        }}
+        .tabs button {{
+            margin-top: 0;
+            padding: 10px;
+            font-size: 14px;
+            background: transparent;
+            border: 1px solid rgba(255,255,255,0.3);
+        }}
+        .tabs button.active {{ background: #6366f1; border-color: #6366f1; }}
+        #xtream-fields {{ display: none; }}
+        #pair {{
+            display: {pair_display};
+            background: transparent;
+            border: 1px solid rgba(255,255,255,0.3);
+            margin-top: 16px;
+        }}
     </style>
 </head>
 <body>
     <div class="container">
         <h1>AtivePlay</h1>
-        <p class="subtitle">Insira o link da sua playlist M3U</p>
+        <p class="subtitle">Insira o link da sua playlist M3U ou os dados da sua conta Xtream</p>
+        <div class="tabs">
+            <button type="button" id="tab-url" class="active">Link M3U</button>
+            <button type="button" id="tab-xtream">Xtream</button>
+        </div>
         <form id="form">
-            <div class="input-group">
-                <label for="url">URL da Playlist</label>
-                <input type="url" id="url" name="url"
-                    placeholder="http://exemplo.com/playlist.m3u" required>
+            <div id="url-fields">
+                <div class="input-group">
+                    <label for="url">URL da Playlist</label>
+                    <input type="url" id="url" name="url"
+                        placeholder="http://exemplo.com/playlist.m3u" required>
+                </div>
+            </div>
+            <div id="xtream-fields">
+                <div class="input-group">
+                    <label for="server">Servidor</label>
+                    <input type="text" id="server" name="server"
+                        placeholder="http://servidor.com:8080" autocapitalize="none">
+                </div>
+                <div class="input-group">
+                    <label for="username">Usuário</label>
+                    <input type="text" id="username" name="username"
+                        autocapitalize="none" autocomplete="username">
+                </div>
+                <div class="input-group">
+                    <label for="password">Senha</label>
+                    <input type="password" id="password" name="password"
+                        autocomplete="current-password">
+                </div>
             </div>
             <button type="submit" id="submit">Enviar para TV</button>
         </form>
         <p class="status" id="status"></p>
+        <button type="button" id="pair">Usar como controle remoto</button>
     </div>
     <script>
         const form = document.getElementById('form');
         const status = document.getElementById('status');
         const submit = document.getElementById('submit');
         const sessionId = '{session_id}';
+        let mode = 'url';
+
+        function setMode(next) {{
+            mode = next;
+            document.getElementById('tab-url').classList.toggle('active', mode === 'url');
+            document.getElementById('tab-xtream').classList.toggle('active', mode === 'xtream');
+            document.getElementById('url-fields').style.display = mode === 'url' ? 'block' : 'none';
+            document.getElementById('xtream-fields').style.display = mode === 'xtream' ? 'block' : 'none';
+            document.getElementById('url').required = mode === 'url';
+            ['server', 'username', 'password'].forEach((id) => {{
+                document.getElementById(id).required = mode === 'xtream';
+            }});
+            status.textContent = '';
+        }}
+        document.getElementById('tab-url').addEventListener('click', () => setMode('url'));
+        document.getElementById('tab-xtream').addEventListener('click', () => setMode('xtream'));
 
         form.addEventListener('submit', async (e) => {{
             e.preventDefault();
-            const url = document.getElementById('url').value.trim();
-            if (!url) return;
+            let body;
+            if (mode === 'xtream') {{
+                const xtream = {{
+                    server: document.getElementById('server').value.trim(),
+                    username: document.getElementById('username').value.trim(),
+                    password: document.getElementById('password').value
+                }};
+                if (!xtream.server || !xtream.username || !xtream.password) return;
+                body = {{ xtream }};
+            }} else {{
+                const url = document.getElementById('url').value.trim();
+                if (!url) return;
+                body = {{ url }};
+            }}
 
             submit.disabled = true;
-            submit.textContent = 'Enviando...';
+            submit.textContent = mode === 'xtream' ? 'Verificando conta...' : 'Enviando...';
             status.textContent = '';
             status.className = 'status';
 
@@ -345,7 +687,7 @@ fn form_html(session_id: &str) -> String {
                 const res = await fetch('/session/' + sessionId + '/send', {{
                     method: 'POST',
                     headers: {{ 'Content-Type': 'application/json' }},
-                    body: JSON.stringify({{ url }})
+                    body: JSON.stringify(body)
                 }});
 
                 if (res.ok) {{
@@ -353,29 +695,63 @@ fn form_html(session_id: &str) -> String {
                     status.className = 'status success';
                     submit.textContent = 'Enviado!';
                 }} else {{
-                    throw new Error('Falha ao enviar');
+                    const data = await res.json().catch(() => ({{}}));
+                    throw new Error(data.error || 'Erro ao enviar. Tente novamente.');
                 }}
             }} catch (err) {{
-                status.textContent = 'Erro ao enviar. Tente novamente.';
+                status.textContent = err.message || 'Erro ao enviar. Tente novamente.';
                 status.className = 'status error';
                 submit.disabled = false;
                 submit.textContent = 'Enviar para TV';
             }}
         }});
+
+        document.getElementById('pair').addEventListener('click', async () => {{
+            try {{
+                const res = await fetch('/session/' + sessionId + '/pair', {{
+                    method: 'POST',
+                    headers: {{ 'Content-Type': 'application/json' }},
+                    body: JSON.stringify({{}})
+                }});
+                if (!res.ok) throw new Error('Falha ao parear');
+                const data = await res.json();
+                localStorage.setItem('ativeplay_remote_token', data.token);
+                window.location.href = '/r';
+            }} catch (err) {{
+                status.textContent = 'Erro ao parear. Tente novamente.';
+                status.className = 'status error';
+            }}
+        }});
     </script>
 </body>
 </html>"#)
 }
 
 fn expired_html() -> String {
-    r#"<!DOCTYPE html>
+    notice_html(
+        "&#8987;",
+        "Sessao Expirada",
+        "Gere um novo QR code na sua TV e escaneie novamente.",
+    )
+}
+
+fn locked_html() -> String {
+    notice_html(
+        "&#128274;",
+        "Muitas tentativas",
+        "Aguarde alguns minutos e tente novamente.",
+    )
+}
+
+fn notice_html(icon: &str, title: &str, message: &str) -> String {
+    format!(r#"<!DOCTYPE html>
 <html lang="pt-BR">
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Sessao Expirada</title>
+    <title>{title}</title>
     <style>
-        body {
+        body {{
             font-family: -apple-system, sans-serif;
             background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);
             min-height: 100vh;
@@ -385,18 +761,121 @@ fn expired_html() -> String {
             color: #fff;
             text-align: center;
             padding: 20px;
-        }
-        .icon { font-size: 48px; margin-bottom: 16px; }
-        h2 { margin-bottom: 8px; }
-        p { color: rgba(255,255,255,0.6); }
+        }}
+        .icon {{ font-size: 48px; margin-bottom: 16px; }}
+        h2 {{ margin-bottom: 8px; }}
+        p {{ color: rgba(255,255,255,0.6); }}
     </style>
 </head>
 <body>
     <div>
-        <div class="icon">&#8987;</div>
-        <h2>Sessao Expirada</h2>
-        <p>Gere um novo QR code na sua TV e escaneie novamente.</p>
+        <div class="icon">{icon}</div>
+        <h2>{title}</h2>
+        <p>{message}</p>
     </div>
 </body>
-</html>"#.to_string()
+</html>"#)
 }
+
+/// Page for typing the 6-digit code shown on the TV
+const CODE_HTML: &str = r#"<!DOCTYPE html>
+<html lang="pt-BR">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>AtivePlay - Código da TV</title>
+    <style>
+        * { margin: 0; padding: 0; box-sizing: border-box; }
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+            background: linear-gradient(135deg, #1a1a2e 0%, #16213e 100%);
+            min-height: 100vh;
+            display: flex;
+            align-items: center;
+            justify-content: center;
+            padding: 20px;
+        }
+        .container {
+            background: rgba(255,255,255,0.05);
+            border-radius: 16px;
+            padding: 32px;
+            width: 100%;
+            max-width: 400px;
+            border: 1px solid rgba(255,255,255,0.1);
+        }
+        h1 { color: #fff; font-size: 24px; margin-bottom: 8px; text-align: center; }
+        .subtitle {
+            color: rgba(255,255,255,0.6);
+            font-size: 14px;
+            text-align: center;
+            margin-bottom: 24px;
+        }
+        input {
+            width: 100%;
+            padding: 14px 16px;
+            border-radius: 8px;
+            border: 1px solid rgba(255,255,255,0.2);
+            background: rgba(255,255,255,0.1);
+            color: #fff;
+            font-size: 28px;
+            letter-spacing: 8px;
+            text-align: center;
+        }
+        input:focus { outline: none; border-color: #6366f1; }
+        button {
+            width: 100%;
+            padding: 14px;
+            border-radius: 8px;
+            border: none;
+            background: #6366f1;
+            color: #fff;
+            font-size: 16px;
+            font-weight: 600;
+            cursor: pointer;
+            margin-top: 16px;
+        }
+        button:disabled { background: #4b4b5c; cursor: not-allowed; }
+        .status { text-align: center; margin-top: 16px; font-size: 14px; min-height: 20px; color: #ef4444; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>AtivePlay</h1>
+        <p class="subtitle">Digite o código de 6 dígitos exibido na sua TV</p>
+        <form id="form">
+            <input type="text" id="code" inputmode="numeric" autocomplete="one-time-code"
+                maxlength="7" placeholder="000000" required>
+            <button type="submit" id="submit">Continuar</button>
+        </form>
+        <p class="status" id="status"></p>
+    </div>
+    <script>
+        const form = document.getElementById('form');
+        const status = document.getElementById('status');
+        const submit = document.getElementById('submit');
+
+        form.addEventListener('submit', async (e) => {
+            e.preventDefault();
+            const code = document.getElementById('code').value.trim();
+            if (!code) return;
+
+            submit.disabled = true;
+            status.textContent = '';
+
+            try {
+                const res = await fetch('/session/code', {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'application/json' },
+                    body: JSON.stringify({ code })
+                });
+                const data = await res.json().catch(() => ({}));
+                if (!res.ok) throw new Error(data.error || 'Código inválido ou expirado');
+                window.location.href = '/s/' + data.sessionId;
+            } catch (err) {
+                status.textContent = err.message;
+                submit.disabled = false;
+            }
+        });
+    </script>
+</body>
+</html>"#;
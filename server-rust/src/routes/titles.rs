@@ -0,0 +1,138 @@
+//! Movie titles endpoints
+//!
+//! VOD presented as titles: versions of the same movie (quality, dubbed /
+//! subbed, language) across groups are variants of a single title.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use std::sync::Arc;
+
+use crate::db::repository::preferences;
+use crate::models::{TitlesQuery, TitlesResponse};
+use crate::routes::parental::playlist_restrictions;
+use crate::services::titles::{AudioPreference, VariantPreferences};
+use crate::AppState;
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    tracing::error!("Failed to get titles: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Erro ao buscar títulos" })),
+    )
+}
+
+/// Stored device preferences, overridden by explicit query params
+async fn resolve_preferences(
+    state: &AppState,
+    query: &TitlesQuery,
+) -> Result<VariantPreferences, ApiError> {
+    let mut prefs = match query.device_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(device_id) => preferences::get(&state.pool, device_id)
+            .await
+            .map_err(internal_error)?
+            .map(Into::into)
+            .unwrap_or_default(),
+        None => VariantPreferences::default(),
+    };
+
+    if let Some(max_quality) = &query.max_quality {
+        prefs.max_quality = Some(max_quality.clone());
+    }
+    if let Some(audio) = &query.audio {
+        prefs.audio = AudioPreference::parse(audio).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": "Preferência de áudio inválida" })),
+            )
+        })?;
+    }
+    if let Some(language) = &query.language {
+        prefs.language = Some(language.clone());
+    }
+
+    Ok(prefs)
+}
+
+/// GET /api/playlist/:hash/titles
+pub async fn get_titles(
+    State(state): State<Arc<AppState>>,
+    Path(hash): Path<String>,
+    Query(query): Query<TitlesQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    if !state.db_cache.is_cache_valid(&hash).await {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Playlist não encontrada ou expirada" })),
+        ));
+    }
+
+    let prefs = resolve_preferences(&state, &query).await?;
+    let limit = query.limit.min(state.config.max_items_page);
+    let offset = query.offset;
+
+    let (mut titles, total) = state
+        .db_cache
+        .get_titles(&hash, query.group.as_deref(), offset, limit, &prefs)
+        .await
+        .map_err(internal_error)?;
+
+    let has_more = offset + titles.len() < total;
+    let page_len = titles.len();
+    playlist_restrictions(&state, &hash, &headers)
+        .await?
+        .apply_to_titles(&mut titles, &prefs);
+    let total = total - (page_len - titles.len());
+
+    Ok(Json(TitlesResponse {
+        titles,
+        total,
+        limit,
+        offset,
+        has_more,
+    }))
+}
+
+/// GET /api/playlist/:hash/titles/:title_id
+pub async fn get_title(
+    State(state): State<Arc<AppState>>,
+    Path((hash, title_id)): Path<(String, String)>,
+    Query(query): Query<TitlesQuery>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    if !state.db_cache.is_cache_valid(&hash).await {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Playlist não encontrada ou expirada" })),
+        ));
+    }
+
+    let prefs = resolve_preferences(&state, &query).await?;
+
+    let mut titles: Vec<_> = state
+        .db_cache
+        .get_title(&hash, &title_id, &prefs)
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .collect();
+
+    playlist_restrictions(&state, &hash, &headers)
+        .await?
+        .apply_to_titles(&mut titles, &prefs);
+
+    let title = titles.pop().ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Título não encontrado" })),
+        )
+    })?;
+
+    Ok(Json(title))
+}
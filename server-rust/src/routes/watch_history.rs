@@ -1,8 +1,9 @@
 //! Watch history API endpoints
 //!
 //! Provides endpoints for syncing and retrieving watch history.
-//! Watch history is tied to device_id, not playlist, so it persists
-//! across playlist changes.
+//! Watch history is tied to device_id (or the account the device is linked
+//! to), not playlist, so it persists across playlist changes. Clients sync
+//! deltas using server-assigned revisions.
 
 use axum::{
     extract::{Path, Query, State},
@@ -10,10 +11,16 @@ use axum::{
     response::IntoResponse,
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use uuid::Uuid;
 
-use crate::db::repository::watch_history;
+use crate::db::models::{EpisodeRow, SeriesRow};
+use crate::db::repository::{items, playlists, series, watch_history};
+use crate::models::PlaylistItem;
+use crate::services::continue_watching::{up_next, UpNext};
 use crate::AppState;
 
 /// Request to sync watch history
@@ -22,6 +29,12 @@ use crate::AppState;
 pub struct SyncHistoryRequest {
     pub device_id: String,
     pub items: Vec<watch_history::WatchHistoryItem>,
+    /// Entries deleted on the device since its last sync
+    #[serde(default)]
+    pub deleted: Vec<watch_history::DeletedHistoryItem>,
+    /// Revision of the device's last sync; changes after it are returned
+    #[serde(default)]
+    pub since: i64,
 }
 
 /// Response for sync operation
@@ -30,6 +43,13 @@ pub struct SyncHistoryRequest {
 pub struct SyncResponse {
     pub success: bool,
     pub synced: usize,
+    /// Items older than the server's entry (last writer wins by watchedAt)
+    pub ignored: usize,
+    pub deleted: u64,
+    /// Changes after the request's `since` (other devices' included)
+    pub changes: HistoryResponse,
+    /// Same as `changes.revision`; pass as `since` on the next sync
+    pub revision: i64,
 }
 
 /// Query params for getting history
@@ -37,21 +57,85 @@ pub struct SyncResponse {
 pub struct HistoryQuery {
     #[serde(default = "default_limit")]
     pub limit: i64,
+    /// Only changes after this revision (tombstones included)
+    pub since: Option<i64>,
 }
 
 fn default_limit() -> i64 {
     50
 }
 
+/// Most changes returned by one delta request
+const MAX_DELTA_LIMIT: i64 = 100;
+
 /// Response for get history
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HistoryResponse {
     pub items: Vec<watch_history::WatchHistoryItem>,
     pub total: usize,
+    /// Deleted entries (delta requests only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<Vec<watch_history::DeletedHistoryItem>>,
+    /// Revision to pass as `since` on the next request
+    pub revision: i64,
+    /// More changes are pending after `revision` (delta requests only)
+    pub has_more: bool,
+}
+
+/// Request to link a device to an account
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkAccountRequest {
+    pub account_id: String,
+}
+
+fn internal_error(e: impl std::fmt::Display) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Failed to access watch history: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(serde_json::json!({ "error": "Failed to access watch history" })),
+    )
+}
+
+/// Changes after `since` as a delta response
+async fn changes_since(
+    state: &AppState,
+    device_id: &str,
+    since: i64,
+    limit: i64,
+) -> Result<HistoryResponse, (StatusCode, Json<serde_json::Value>)> {
+    let page = watch_history::get_changes(&state.pool, device_id, since, limit)
+        .await
+        .map_err(internal_error)?;
+
+    let (removed, live): (Vec<_>, Vec<_>) =
+        page.rows.into_iter().partition(|row| row.deleted_at.is_some());
+    let items: Vec<watch_history::WatchHistoryItem> = live.into_iter().map(Into::into).collect();
+    let total = items.len();
+
+    Ok(HistoryResponse {
+        items,
+        total,
+        deleted: Some(removed.into_iter().map(Into::into).collect()),
+        revision: page.revision,
+        has_more: page.has_more,
+    })
+}
+
+/// Account ids are client-generated secrets shared between a user's devices
+fn is_valid_account_id(account_id: &str) -> bool {
+    (16..=64).contains(&account_id.len())
+        && account_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
 }
 
 /// POST /api/watch-history/sync - Sync watch history items from client
+///
+/// Each item is applied only if it's newer (by watchedAt) than the server's
+/// entry, so a device coming back online can't overwrite newer progress.
+/// The response carries the changes after `since`, the device's own included.
 pub async fn sync_watch_history(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<SyncHistoryRequest>,
@@ -75,19 +159,39 @@ pub async fn sync_watch_history(
             )
         })?;
 
+    let mut deleted = 0;
+    for item in &payload.deleted {
+        let deleted_at = DateTime::from_timestamp_millis(item.deleted_at).unwrap_or_else(Utc::now);
+        let key = item.content_id.as_deref().unwrap_or(&item.item_hash);
+        deleted += watch_history::delete_item(&state.pool, &payload.device_id, key, deleted_at)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    let changes = changes_since(&state, &payload.device_id, payload.since, MAX_DELTA_LIMIT).await?;
+
     tracing::info!(
-        "Synced {} watch history items for device {}",
+        "Synced {} watch history items for device {} ({} ignored, {} deleted)",
         synced,
-        payload.device_id
+        payload.device_id,
+        payload.items.len() - synced,
+        deleted
     );
 
     Ok(Json(SyncResponse {
         success: true,
         synced,
+        ignored: payload.items.len() - synced,
+        deleted,
+        revision: changes.revision,
+        changes,
     }))
 }
 
 /// GET /api/watch-history/:device_id - Get watch history for a device
+///
+/// With `since`, returns only entries changed after that revision (oldest
+/// first, including deletions) for delta sync.
 pub async fn get_watch_history(
     State(state): State<Arc<AppState>>,
     Path(device_id): Path<String>,
@@ -102,7 +206,16 @@ pub async fn get_watch_history(
     }
 
     // Apply limit (max 100)
-    let limit = query.limit.min(100);
+    let limit = query.limit.clamp(1, MAX_DELTA_LIMIT);
+
+    if let Some(since) = query.since {
+        return Ok(Json(changes_since(&state, &device_id, since, limit).await?));
+    }
+
+    // Read the high-water mark first: changes made while listing are re-sent by the next delta
+    let revision = watch_history::high_water(&state.pool, &device_id)
+        .await
+        .map_err(internal_error)?;
 
     // Get history from database
     let rows = watch_history::get_recent(&state.pool, &device_id, limit)
@@ -118,7 +231,13 @@ pub async fn get_watch_history(
     let items: Vec<watch_history::WatchHistoryItem> = rows.into_iter().map(Into::into).collect();
     let total = items.len();
 
-    Ok(Json(HistoryResponse { items, total }))
+    Ok(Json(HistoryResponse {
+        items,
+        total,
+        deleted: None,
+        revision,
+        has_more: false,
+    }))
 }
 
 /// DELETE /api/watch-history/:device_id - Clear watch history for a device
@@ -171,7 +290,7 @@ pub async fn delete_history_item(
     }
 
     // Delete item from database
-    let deleted = watch_history::delete_item(&state.pool, &device_id, &item_hash)
+    let deleted = watch_history::delete_item(&state.pool, &device_id, &item_hash, Utc::now())
         .await
         .map_err(|e| {
             tracing::error!("Failed to delete history item: {}", e);
@@ -186,3 +305,302 @@ pub async fn delete_history_item(
         "deleted": deleted
     })))
 }
+
+/// GET /api/watch-history/:device_id/account - Whether the device's history is shared with an account
+pub async fn get_account(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let account_id = watch_history::get_account(&state.pool, &device_id)
+        .await
+        .map_err(internal_error)?;
+
+    // The account id is the secret shared between devices: never echo it
+    Ok(Json(serde_json::json!({ "linked": account_id.is_some() })))
+}
+
+/// PUT /api/watch-history/:device_id/account - Share history with an account
+///
+/// The device's own history is merged into the account's. Clients should
+/// resync from revision 0 afterwards.
+pub async fn link_account(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(request): Json<LinkAccountRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if device_id.is_empty() || device_id.len() > 64 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid device_id" })),
+        ));
+    }
+    if !is_valid_account_id(&request.account_id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "accountId must be 16 to 64 letters, digits, '-' or '_'"
+            })),
+        ));
+    }
+
+    let merged = watch_history::link_account(&state.pool, &device_id, &request.account_id)
+        .await
+        .map_err(internal_error)?;
+
+    tracing::info!(
+        "Linked device {} to a history account ({} entries merged)",
+        device_id,
+        merged
+    );
+
+    Ok(Json(serde_json::json!({
+        "linked": true,
+        "merged": merged
+    })))
+}
+
+/// DELETE /api/watch-history/:device_id/account - Stop sharing history
+///
+/// The account keeps its history; the device starts with an empty one.
+/// Clients should resync from revision 0 afterwards.
+pub async fn unlink_account(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let unlinked = watch_history::unlink_account(&state.pool, &device_id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(serde_json::json!({ "success": unlinked })))
+}
+
+/// Query params for continue watching
+#[derive(Debug, Deserialize)]
+pub struct ContinueQuery {
+    /// Playlist to resolve items in (defaults to the device's playlist)
+    pub hash: Option<String>,
+    #[serde(default = "default_continue_limit")]
+    pub limit: usize,
+}
+
+fn default_continue_limit() -> usize {
+    20
+}
+
+/// Series position of a continue-watching entry
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueSeries {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo: Option<String>,
+    pub season: i16,
+    pub episode: i16,
+}
+
+/// One "Up next" entry with a ready-to-play item
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueWatchingItem {
+    /// "resume" (continue this item) or "next" (next episode of a finished one)
+    pub reason: &'static str,
+    pub item: PlaylistItem,
+    /// Position to start at (0 for a next episode)
+    pub position_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    pub watched_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<ContinueSeries>,
+}
+
+/// Response for continue watching
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueWatchingResponse {
+    pub items: Vec<ContinueWatchingItem>,
+    pub total: usize,
+}
+
+/// GET /api/watch-history/:device_id/continue - "Up next" row
+///
+/// One entry per series (the next episode once the last watched one is
+/// finished, crossing seasons) or movie, resolved against the playlist.
+pub async fn continue_watching(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Query(query): Query<ContinueQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if device_id.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "device_id is required" })),
+        ));
+    }
+
+    let internal_error = |e: sqlx::Error| {
+        tracing::error!("Failed to get continue watching: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "Failed to get continue watching" })),
+        )
+    };
+
+    let playlist = match &query.hash {
+        Some(hash) => playlists::find_by_hash_any(&state.pool, hash).await,
+        None => playlists::find_by_device(&state.pool, &device_id).await,
+    }
+    .map_err(internal_error)?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Playlist not found" })),
+        )
+    })?;
+
+    let restrictions = state
+        .parental
+        .restrictions_for_playlist(&playlist.hash, Some(&device_id))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get parental restrictions: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "Failed to get continue watching" })),
+            )
+        })?;
+
+    let limit = query.limit.min(50);
+    let rows = watch_history::get_progress(&state.pool, &device_id, playlist.id, 200)
+        .await
+        .map_err(internal_error)?;
+    let candidates = up_next(rows, state.config.watch_finished_percent);
+
+    // Resolve next episodes, items and series in one query each
+    let positions: Vec<(Uuid, i16, i16)> = candidates
+        .iter()
+        .filter_map(|next| match next {
+            UpNext::NextEpisode(row) => {
+                let episode = row.episode?;
+                Some((row.series_id?, row.season?, row.episode_end.unwrap_or(episode)))
+            }
+            UpNext::Resume(_) => None,
+        })
+        .collect();
+    let next_episodes: HashMap<Uuid, EpisodeRow> = series::next_episodes(&state.pool, &positions)
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|row| (row.series_id, row))
+        .collect();
+
+    let targets: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|next| {
+            let target = match &next {
+                UpNext::Resume(row) => (
+                    row.item_hash.clone(),
+                    row.position_ms,
+                    row.duration_ms,
+                    row.season,
+                    row.episode,
+                ),
+                // Finished the last episode: nothing up next for this series
+                UpNext::NextEpisode(row) => {
+                    let episode = next_episodes.get(&row.series_id?)?;
+                    (
+                        episode.item_hash.clone(),
+                        0,
+                        None,
+                        Some(episode.season),
+                        Some(episode.episode),
+                    )
+                }
+            };
+            Some((next, target))
+        })
+        .collect();
+
+    let item_hashes: Vec<String> = targets.iter().map(|(_, t)| t.0.clone()).collect();
+    let mut playlist_items: HashMap<String, PlaylistItem> =
+        items::get_by_hashes(&state.pool, playlist.id, &item_hashes)
+            .await
+            .map_err(internal_error)?
+            .into_iter()
+            .map(|row| (row.item_hash.clone(), row.into()))
+            .collect();
+
+    let series_ids: Vec<Uuid> = targets.iter().filter_map(|(next, _)| next.progress().series_id).collect();
+    let series_rows: HashMap<Uuid, SeriesRow> = series::get_by_ids(&state.pool, &series_ids)
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|row| (row.id, row))
+        .collect();
+
+    let mut entries = Vec::new();
+    for (next, (item_hash, position_ms, duration_ms, season, episode)) in targets {
+        if entries.len() >= limit {
+            break;
+        }
+
+        // Items no longer in the playlist can't be played
+        let Some(item) = playlist_items.remove(&item_hash) else {
+            continue;
+        };
+
+        let progress = next.progress();
+        let series = match (progress.series_id, season, episode) {
+            (Some(series_id), Some(season), Some(episode)) => {
+                series_rows.get(&series_id).map(|row| ContinueSeries {
+                    id: row.series_hash.clone(),
+                    name: row.name.clone(),
+                    logo: row.logo.clone(),
+                    season,
+                    episode,
+                })
+            }
+            _ => None,
+        };
+
+        let kind = item.media_kind.to_string();
+        let hidden = !restrictions.allows(&kind, &item.group, &item.name)
+            || series.as_ref().is_some_and(|s| !restrictions.allows(&kind, &item.group, &s.name));
+        if hidden {
+            continue;
+        }
+
+        entries.push(ContinueWatchingItem {
+            reason: match next {
+                UpNext::Resume(_) => "resume",
+                UpNext::NextEpisode(_) => "next",
+            },
+            watched_at: progress.watched_at.timestamp_millis(),
+            item,
+            position_ms,
+            duration_ms,
+            series,
+        });
+    }
+
+    let total = entries.len();
+    Ok(Json(ContinueWatchingResponse {
+        items: entries,
+        total,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_account_id() {
+        assert!(is_valid_account_id("3f2b9c1e-7a4d-4e8b-9c0a-1b2c3d4e5f60"));
+        assert!(is_valid_account_id("family_account_01"));
+        assert!(!is_valid_account_id("short"));
+        assert!(!is_valid_account_id("has spaces in the account id"));
+        assert!(!is_valid_account_id(&"a".repeat(65)));
+    }
+}
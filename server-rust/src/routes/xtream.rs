@@ -12,17 +12,19 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::db::models::SourceType;
 use crate::db::repository::playlists;
+use crate::routes::parental::device_id_header;
+use crate::services::parental::ContentRestrictions;
 use crate::services::xtream::{
     decode_base64_if_needed, generate_seasons_from_episodes, parse_duration_to_secs,
     parse_rating, split_csv, timestamp_to_iso, XtreamClient, XtreamCredentials,
@@ -271,7 +273,7 @@ fn parse_uuid(s: &str) -> Result<Uuid, (StatusCode, Json<serde_json::Value>)> {
     })
 }
 
-async fn get_xtream_credentials(
+pub(crate) async fn get_xtream_credentials(
     pool: &sqlx::PgPool,
     playlist_id: Uuid,
 ) -> Result<(XtreamCredentials, crate::db::models::PlaylistRow), (StatusCode, Json<serde_json::Value>)> {
@@ -352,14 +354,45 @@ pub async fn get_playlist_info(
     }))
 }
 
+/// Parental restrictions for the playlist's device (or the requesting one)
+async fn parental_restrictions(
+    state: &AppState,
+    playlist: &crate::db::models::PlaylistRow,
+    headers: &HeaderMap,
+) -> Result<ContentRestrictions, (StatusCode, Json<serde_json::Value>)> {
+    let request_device = device_id_header(headers);
+    state
+        .parental
+        .restrictions(playlist.device_id.as_deref().or(request_device.as_deref()))
+        .await
+        .map_err(|e| {
+            tracing::error!("Parental controls error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Database error"})),
+            )
+        })
+}
+
+/// Media kind of an Xtream media type, as used by parental controls
+fn xtream_media_kind(media_type: &str) -> &'static str {
+    match media_type {
+        "live" => "live",
+        "vod" => "movie",
+        _ => "series",
+    }
+}
+
 /// GET /api/xtream/:playlist_id/categories/:type
 pub async fn get_categories(
     State(state): State<Arc<AppState>>,
     Path((playlist_id, media_type)): Path<(String, String)>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     let playlist_uuid = parse_uuid(&playlist_id)?;
-    let (creds, _) = get_xtream_credentials(&state.pool, playlist_uuid).await?;
+    let (creds, playlist) = get_xtream_credentials(&state.pool, playlist_uuid).await?;
     let client = XtreamClient::from_credentials(&creds);
+    let restrictions = parental_restrictions(&state, &playlist, &headers).await?;
 
     let categories = match media_type.as_str() {
         "live" => client.get_live_categories().await,
@@ -380,8 +413,10 @@ pub async fn get_categories(
         )
     })?;
 
+    let kind_allowed = restrictions.allows_kind(xtream_media_kind(&media_type));
     let items: Vec<CategoryItem> = categories
         .into_iter()
+        .filter(|c| kind_allowed && restrictions.allows_group(&c.category_name))
         .map(|c| CategoryItem {
             id: c.category_id,
             name: c.category_name,
@@ -400,12 +435,14 @@ pub async fn get_streams(
     State(state): State<Arc<AppState>>,
     Path((playlist_id, media_type)): Path<(String, String)>,
     Query(query): Query<StreamsQuery>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     let playlist_uuid = parse_uuid(&playlist_id)?;
-    let (creds, _) = get_xtream_credentials(&state.pool, playlist_uuid).await?;
+    let (creds, playlist) = get_xtream_credentials(&state.pool, playlist_uuid).await?;
     let client = XtreamClient::from_credentials(&creds);
+    let restrictions = parental_restrictions(&state, &playlist, &headers).await?;
 
-    let items: Vec<StreamItem> = match media_type.as_str() {
+    let mut items: Vec<StreamItem> = match media_type.as_str() {
         "live" => {
             let streams = if let Some(cat_id) = query.category_id {
                 client.get_live_streams_by_category(&cat_id).await
@@ -422,6 +459,7 @@ pub async fn get_streams(
 
             streams
                 .into_iter()
+                .filter(|s| !(restrictions.hide_adult && s.is_adult.as_deref() == Some("1")))
                 .map(|s| StreamItem {
                     id: s.stream_id.to_string(),
                     name: s.name,
@@ -507,6 +545,47 @@ pub async fn get_streams(
         }
     };
 
+    if !restrictions.is_unrestricted() {
+        let kind = xtream_media_kind(&media_type);
+        // Category names are only needed when a rule can hide a whole group;
+        // kind rules are checked per item
+        let hidden: HashSet<String> = if restrictions.hide_adult || restrictions.has_group_rules() {
+            let categories = match media_type.as_str() {
+                "live" => client.get_live_categories().await,
+                "vod" => client.get_vod_categories().await,
+                _ => client.get_series_categories().await,
+            };
+            match categories {
+                Ok(categories) => categories
+                    .into_iter()
+                    .filter(|c| !restrictions.allows_group(&c.category_name))
+                    .map(|c| c.category_id)
+                    .collect(),
+                // Blocked groups and keywords can't be enforced without the
+                // names, so nothing can be shown safely
+                Err(e) if restrictions.has_group_rules() => {
+                    tracing::error!("Xtream API error fetching categories for restrictions: {}", e);
+                    return Err((
+                        StatusCode::BAD_GATEWAY,
+                        Json(serde_json::json!({"error": format!("Xtream API error: {}", e)})),
+                    ));
+                }
+                // Adult content alone is also recognised by item name
+                Err(e) => {
+                    tracing::warn!("Xtream categories unavailable, filtering by name only: {}", e);
+                    HashSet::new()
+                }
+            }
+        } else {
+            HashSet::new()
+        };
+
+        items.retain(|item| {
+            let category_hidden = item.category_id.as_ref().is_some_and(|id| hidden.contains(id));
+            !category_hidden && restrictions.allows(kind, "", &item.name)
+        });
+    }
+
     Ok(Json(StreamsResponse {
         total: items.len(),
         items,
@@ -90,6 +90,44 @@ impl StreamingItemWriter {
     }
 }
 
+/// Cached entry considered for eviction
+#[derive(Debug, Clone)]
+pub struct EvictionCandidate {
+    pub key: String,
+    pub created_at: i64,
+    pub size: u64,
+}
+
+/// Eviction policy shared by the disk caches: oldest entries go first, until at
+/// most `max_entries` remain and `total_size` fits in `max_bytes`.
+/// Returns the keys evicted for the entry cap and for the size cap.
+pub fn plan_evictions(
+    mut candidates: Vec<EvictionCandidate>,
+    total_size: u64,
+    max_entries: Option<usize>,
+    max_bytes: Option<u64>,
+) -> (Vec<String>, Vec<String>) {
+    candidates.sort_by_key(|c| c.created_at);
+
+    let excess = max_entries.map_or(0, |max| candidates.len().saturating_sub(max));
+    let mut remaining = total_size;
+    let mut by_count = Vec::new();
+    let mut by_size = Vec::new();
+
+    for (i, candidate) in candidates.into_iter().enumerate() {
+        if i < excess {
+            by_count.push(candidate.key);
+        } else if max_bytes.is_some_and(|max| remaining > max) {
+            by_size.push(candidate.key);
+        } else {
+            break;
+        }
+        remaining = remaining.saturating_sub(candidate.size);
+    }
+
+    (by_count, by_size)
+}
+
 /// Disk-based cache service for playlist data
 /// Uses .ndjson for items (newline-delimited JSON) and .meta.json for metadata
 pub struct CacheService {
@@ -362,52 +400,35 @@ impl CacheService {
         Ok(count)
     }
 
-    /// Evict oldest caches when exceeding max_entries
-    pub async fn cleanup_over_limit(&self, max_entries: usize) -> Result<usize> {
-        let index = self.index.read().await;
-        let total = index.len();
-        if total <= max_entries {
-            return Ok(0);
-        }
-
-        let mut metas: Vec<CacheMetadata> = index.values().cloned().collect();
-        drop(index);
-
-        metas.sort_by_key(|m| m.created_at);
-        let excess = total - max_entries;
-        let mut removed = 0usize;
-
-        for meta in metas.into_iter().take(excess) {
-            self.delete_cache_files(&meta.hash).await?;
-            removed += 1;
-        }
-
-        Ok(removed)
-    }
-
-    /// Evict oldest caches until total size <= max_bytes
-    pub async fn cleanup_over_size(&self, max_bytes: u64) -> Result<usize> {
-        let mut metas = {
+    /// Evict oldest caches over the entry/size caps (see `plan_evictions`)
+    async fn evict_over_limits(&self) -> Result<(usize, usize)> {
+        let hashes: Vec<(String, i64)> = {
             let index = self.index.read().await;
-            index.values().cloned().collect::<Vec<_>>()
+            index.values().map(|m| (m.hash.clone(), m.created_at)).collect()
         };
 
-        metas.sort_by_key(|m| m.created_at);
-
-        let mut removed = 0usize;
-        let mut total_size = self.get_cache_size().await.unwrap_or(0);
-
-        for meta in metas {
-            if total_size <= max_bytes {
-                break;
+        let mut candidates = Vec::with_capacity(hashes.len());
+        for (hash, created_at) in hashes {
+            let mut size = 0;
+            for path in [self.items_path(&hash), self.meta_path(&hash)] {
+                if let Ok(metadata) = fs::metadata(&path).await {
+                    size += metadata.len();
+                }
             }
+            candidates.push(EvictionCandidate { key: hash, created_at, size });
+        }
+
+        let total_size = match self.max_bytes {
+            Some(_) => self.get_cache_size().await.unwrap_or(0),
+            None => 0,
+        };
+        let (by_count, by_size) = plan_evictions(candidates, total_size, self.max_entries, self.max_bytes);
 
-            self.delete_cache_files(&meta.hash).await?;
-            removed += 1;
-            total_size = self.get_cache_size().await.unwrap_or(0);
+        for hash in by_count.iter().chain(by_size.iter()) {
+            self.delete_cache_files(hash).await?;
         }
 
-        Ok(removed)
+        Ok((by_count.len(), by_size.len()))
     }
 
     async fn enforce_limits(&self) -> Result<()> {
@@ -417,18 +438,12 @@ impl CacheService {
             tracing::info!(cache_gc_expired = expired, msg = "expired cache entries removed");
         }
 
-        if let Some(max_entries) = self.max_entries {
-            let removed = self.cleanup_over_limit(max_entries).await?;
-            if removed > 0 {
-                tracing::info!(cache_gc_evicted = removed, max_entries = max_entries, msg = "cache entries evicted by LRU");
-            }
+        let (by_count, by_size) = self.evict_over_limits().await?;
+        if by_count > 0 {
+            tracing::info!(cache_gc_evicted = by_count, max_entries = self.max_entries, msg = "cache entries evicted by LRU");
         }
-
-        if let Some(max_bytes) = self.max_bytes {
-            let removed = self.cleanup_over_size(max_bytes).await?;
-            if removed > 0 {
-                tracing::info!(cache_gc_evicted = removed, max_bytes = max_bytes, msg = "cache entries evicted by size");
-            }
+        if by_size > 0 {
+            tracing::info!(cache_gc_evicted = by_size, max_bytes = self.max_bytes, msg = "cache entries evicted by size");
         }
 
         Ok(())
@@ -497,3 +512,32 @@ impl Clone for CacheService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(key: &str, created_at: i64, size: u64) -> EvictionCandidate {
+        EvictionCandidate {
+            key: key.to_string(),
+            created_at,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_plan_evictions_oldest_first() {
+        let candidates = vec![candidate("c", 3, 10), candidate("a", 1, 10), candidate("b", 2, 10)];
+
+        let (by_count, by_size) = plan_evictions(candidates.clone(), 30, Some(2), None);
+        assert_eq!(by_count, vec!["a"]);
+        assert!(by_size.is_empty());
+
+        let (by_count, by_size) = plan_evictions(candidates.clone(), 30, Some(2), Some(15));
+        assert_eq!(by_count, vec!["a"]);
+        assert_eq!(by_size, vec!["b"]);
+
+        let (by_count, by_size) = plan_evictions(candidates, 30, None, None);
+        assert!(by_count.is_empty() && by_size.is_empty());
+    }
+}
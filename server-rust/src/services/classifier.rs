@@ -1,3 +1,4 @@
+use chrono::Datelike;
 use lazy_static::lazy_static;
 use lru::LruCache;
 use regex::Regex;
@@ -5,10 +6,11 @@ use std::num::NonZeroUsize;
 use std::sync::Mutex;
 
 use crate::models::{ExtractedSeriesInfo, MediaKind, ParsedTitle};
+use crate::services::search::normalize;
 
 // Cache for extractSeriesInfo (LRU with 10k max entries)
 lazy_static! {
-    static ref SERIES_CACHE: Mutex<LruCache<String, Option<ExtractedSeriesInfo>>> =
+    static ref SERIES_CACHE: Mutex<LruCache<String, Option<DetectedSeries>>> =
         Mutex::new(LruCache::new(NonZeroUsize::new(10000).unwrap()));
 
     // ============ GROUP PATTERNS ============
@@ -76,7 +78,7 @@ lazy_static! {
         Regex::new(r"(?i)\b\d{1,2}x\d{1,2}\b").unwrap(),
         Regex::new(r"(?i)\bT\d{1,2}[\s._-]?E\d{1,2}\b").unwrap(),
         Regex::new(r"(?i)\btemporada\s*\d+").unwrap(),
-        Regex::new(r"(?i)\bepisodio\s*\d+").unwrap(),
+        Regex::new(r"(?i)\bepis[oó]dio\s*\d+").unwrap(),
         Regex::new(r"(?i)\bseason\s*\d+").unwrap(),
         Regex::new(r"(?i)\bepisode\s*\d+").unwrap(),
         Regex::new(r"(?i)\bcap[ií]tulo\s*\d+").unwrap(),
@@ -97,12 +99,30 @@ lazy_static! {
     static ref EXTRACTOR_LANGUAGE: Regex = Regex::new(r"(?i)\b(pt|por|ptbr|pt-br|en|eng|es|esp|fr|fra|de|deu|it|ita|ja|jpn)\b").unwrap();
 
     // ============ SERIES INFO PATTERNS ============
-    static ref SERIES_MAIN_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+S(\d{1,2})E(\d{1,3})").unwrap();
-    static ref SERIES_ALT_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+(\d{1,2})x(\d{1,3})\b").unwrap();
-    static ref SERIES_PT_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+T(\d{1,2})E(\d{1,3})").unwrap();
+    // Captures: name, season, episode, optional range end ("S01E01-E02", "S01E01E02")
+    static ref SERIES_MAIN_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+S(\d{1,2})\s?E(\d{1,4})(?:(?:\s*-\s*E?|E)(\d{1,4}))?").unwrap();
+    static ref SERIES_ALT_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+(\d{1,2})x(\d{1,4})(?:-(\d{1,4}))?\b").unwrap();
+    static ref SERIES_PT_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+T(\d{1,2})\s?E(\d{1,4})(?:(?:\s*-\s*E?|E)(\d{1,4}))?").unwrap();
+    // "Temporada 2 Episódio 5", "Season 1 Ep 3", "Temp. 2 Cap. 14"
+    static ref SERIES_WORDS_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+(?:temporada|season|temp\.?)\s*(\d{1,2})\s*[-–:|,]?\s*(?:epis[oó]dio|episode|ep\.?|cap[ií]tulo|cap\.?)\s*(\d{1,4})(?:\s*(?:-|–|a)\s*(\d{1,4}))?").unwrap();
+    // Single-season shows and novelas: "Ep. 12", "Capítulo 30", "Episódio 7 a 8"
+    static ref SERIES_EPISODE_PATTERN: Regex = Regex::new(r"(?i)(.+?)\s+(?:epis[oó]dio|episode|ep\.?|cap[ií]tulo|cap\.?|chapter)\s*(\d{1,4})(?:\s*(?:-|–|a)\s*(\d{1,4}))?\b").unwrap();
+    // Daily shows: "Jornal 2024-03-15", "Programa - 15/03/2024"
+    static ref SERIES_DATE_YMD_PATTERN: Regex = Regex::new(r"(.+?)\s+[-–:|]?\s*(\d{4})[-./](\d{1,2})[-./](\d{1,2})\b").unwrap();
+    static ref SERIES_DATE_DMY_PATTERN: Regex = Regex::new(r"(.+?)\s+[-–:|]?\s*(\d{1,2})[-./](\d{1,2})[-./](\d{4})\b").unwrap();
+    // Anime absolute numbering: "One Piece - 1071", "Naruto - #12 [FHD]"
+    // (episode-only, dated and absolute numbering also fit movie sequels and
+    // parts, so they are only trusted inside series groups)
+    static ref SERIES_ABSOLUTE_PATTERN: Regex = Regex::new(r"^(.+?)\s+[-–]\s+#?(\d{1,4})(?:$|[\s\[(])").unwrap();
+    static ref SERIES_NAME_TRIM: Regex = Regex::new(r"^[\s\-–:|.]+|[\s\-–:|.]+$").unwrap();
 
     // ============ SPECIAL PATTERNS ============
     static ref ADULT_CONTENT: Regex = Regex::new(r"(?i)xxx|onlyfans|adulto|\+18").unwrap();
+    // Titles are checked more narrowly ("adulto" alone is a common word in movie names)
+    static ref ADULT_TITLE: Regex = Regex::new(r"(?i)xxx|onlyfans|\+18").unwrap();
+    // Audio variant of a title or group ("Séries Dubladas", "Dark [L]")
+    static ref AUDIO_DUBBED: Regex = Regex::new(r"(?i)\b(dub|dublad[oa]s?|dubbed|nacional)\b|\[D\]").unwrap();
+    static ref AUDIO_SUBBED: Regex = Regex::new(r"(?i)\b(leg|legendad[oa]s?|subbed|sub)\b|\[L\]").unwrap();
     static ref TS_STREAM: Regex = Regex::new(r"(?i)/ts(\?|$)").unwrap();
     static ref PATTERN_24H: Regex = Regex::new(r"(?i)\b24h(rs)?\b").unwrap();
     static ref PATTERN_24_7: Regex = Regex::new(r"24/7").unwrap();
@@ -123,6 +143,13 @@ lazy_static! {
     static ref NUMBERING_CLEANER: Regex = Regex::new(r"^\d+\.\s+").unwrap();
 }
 
+/// Series info plus whether it may only be trusted inside a series group
+#[derive(Debug, Clone)]
+struct DetectedSeries {
+    info: ExtractedSeriesInfo,
+    needs_series_group: bool,
+}
+
 /// Content classifier for IPTV items
 pub struct ContentClassifier;
 
@@ -183,6 +210,11 @@ impl ContentClassifier {
         Self::classify_by_title(name, group)
     }
 
+    /// Adult content by group or title (mirrors `is_adult` in migration 015)
+    pub fn is_adult(name: &str, group: &str) -> bool {
+        ADULT_CONTENT.is_match(group) || ADULT_TITLE.is_match(name)
+    }
+
     /// Classify based on group name
     pub fn classify_by_group(group: &str) -> MediaKind {
         if group.is_empty() {
@@ -380,66 +412,156 @@ impl ContentClassifier {
         result.trim().to_string()
     }
 
-    /// Extract series info from name (detects SxxExx pattern)
-    /// Returns None if not detected as series
+    /// Extract series info from name
+    ///
+    /// Recognises SxxExx, 1x01 and TxxExx (with multi-episode ranges) and
+    /// "Temporada 2 Episódio 5" anywhere. Episode-only numbering ("Ep. 12",
+    /// "Capítulo 30") for single-season shows, dated episodes of daily shows
+    /// and anime absolute numbering ("Name - 123") are only accepted when the
+    /// group is a series group, since "Velozes e Furiosos - 10" or
+    /// "Star Wars: Episódio 4" are movies.
+    /// Returns None if not detected as series.
     /// Optimized with LRU cache (30-40% faster)
-    pub fn extract_series_info(name: &str) -> Option<ExtractedSeriesInfo> {
-        // Check cache first
-        {
+    pub fn extract_series_info(name: &str, group: &str) -> Option<ExtractedSeriesInfo> {
+        let detected = {
             let mut cache = SERIES_CACHE.lock().unwrap();
-            if let Some(cached) = cache.get(&name.to_string()) {
-                return cached.clone();
+            cache.get(&name.to_string()).cloned()
+        };
+        let detected = detected.unwrap_or_else(|| {
+            // Remove common prefixes before trying match
+            let clean_name = Self::remove_prefixes(name);
+            let result = Self::detect_series(&clean_name);
+
+            let mut cache = SERIES_CACHE.lock().unwrap();
+            cache.put(name.to_string(), result.clone());
+            result
+        })?;
+
+        if detected.needs_series_group && Self::classify_by_group(group) != MediaKind::Series {
+            return None;
+        }
+        Some(detected.info)
+    }
+
+    /// Audio variant of an item from its name or group: "dub", "leg", or None
+    /// when unknown or both (multi-audio)
+    pub fn audio_variant(name: &str, group: &str) -> Option<&'static str> {
+        let dubbed = AUDIO_DUBBED.is_match(name) || AUDIO_DUBBED.is_match(group);
+        let subbed = AUDIO_SUBBED.is_match(name) || AUDIO_SUBBED.is_match(group);
+        match (dubbed, subbed) {
+            (true, false) => Some("dub"),
+            (false, true) => Some("leg"),
+            _ => None,
+        }
+    }
+
+    /// Pattern matching behind `extract_series_info`, most specific first
+    fn detect_series(name: &str) -> Option<DetectedSeries> {
+        Self::detect_season_episode(name)
+            .map(|info| DetectedSeries { info, needs_series_group: false })
+            .or_else(|| {
+                Self::detect_numbered_episode(name).map(|info| DetectedSeries { info, needs_series_group: true })
+            })
+    }
+
+    /// Explicit season + episode markers (+ optional range end)
+    fn detect_season_episode(name: &str) -> Option<ExtractedSeriesInfo> {
+        let num = |caps: &regex::Captures, i: usize| caps.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+
+        for pattern in [
+            &*SERIES_MAIN_PATTERN,
+            &*SERIES_PT_PATTERN,
+            &*SERIES_ALT_PATTERN,
+            &*SERIES_WORDS_PATTERN,
+        ] {
+            if let Some(caps) = pattern.captures(name) {
+                if let Some(info) = Self::series_info(&caps[1], num(&caps, 2), num(&caps, 3), num(&caps, 4)) {
+                    return Some(info);
+                }
             }
         }
 
-        // Remove common prefixes before trying match
-        let clean_name = Self::remove_prefixes(name);
+        None
+    }
 
-        // Main pattern: Name + SxxExx (ex: "Breaking Bad S01E01")
-        if let Some(caps) = SERIES_MAIN_PATTERN.captures(&clean_name) {
-            let result = Some(ExtractedSeriesInfo {
-                series_name: caps.get(1).map(|m| m.as_str().trim().to_string()).unwrap_or_default(),
-                season: caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                episode: caps.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                is_series: true,
-            });
-            let mut cache = SERIES_CACHE.lock().unwrap();
-            cache.put(name.to_string(), result.clone());
-            return result;
+    /// Episode-only, dated and absolute numbering (ambiguous outside series groups)
+    fn detect_numbered_episode(name: &str) -> Option<ExtractedSeriesInfo> {
+        let num = |caps: &regex::Captures, i: usize| caps.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+
+        // Episode only (single season)
+        if let Some(caps) = SERIES_EPISODE_PATTERN.captures(name) {
+            if let Some(info) = Self::series_info(&caps[1], Some(1), num(&caps, 2), num(&caps, 3)) {
+                return Some(info);
+            }
         }
 
-        // Alternative pattern: Name + 1x01 (ex: "Breaking Bad 1x01")
-        if let Some(caps) = SERIES_ALT_PATTERN.captures(&clean_name) {
-            let result = Some(ExtractedSeriesInfo {
-                series_name: caps.get(1).map(|m| m.as_str().trim().to_string()).unwrap_or_default(),
-                season: caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                episode: caps.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                is_series: true,
+        // Dated episodes: two-digit year as season, day of year as episode
+        let dated = SERIES_DATE_YMD_PATTERN
+            .captures(name)
+            .and_then(|caps| Some((caps.get(1)?.as_str(), num(&caps, 2)?, num(&caps, 3)?, num(&caps, 4)?)))
+            .or_else(|| {
+                SERIES_DATE_DMY_PATTERN
+                    .captures(name)
+                    .and_then(|caps| Some((caps.get(1)?.as_str(), num(&caps, 4)?, num(&caps, 3)?, num(&caps, 2)?)))
             });
-            let mut cache = SERIES_CACHE.lock().unwrap();
-            cache.put(name.to_string(), result.clone());
-            return result;
+        if let Some((series_name, year, month, day)) = dated {
+            if let Some(date) = chrono::NaiveDate::from_ymd_opt(year as i32, month, day) {
+                let mut info = Self::series_info(series_name, Some(year % 100), Some(date.ordinal()), None)?;
+                info.air_date = Some(date);
+                return Some(info);
+            }
         }
 
-        // PT-BR/Spanish pattern: Name + T01E01 (ex: "La Casa de Papel T01E01")
-        if let Some(caps) = SERIES_PT_PATTERN.captures(&clean_name) {
-            let result = Some(ExtractedSeriesInfo {
-                series_name: caps.get(1).map(|m| m.as_str().trim().to_string()).unwrap_or_default(),
-                season: caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                episode: caps.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                is_series: true,
-            });
-            let mut cache = SERIES_CACHE.lock().unwrap();
-            cache.put(name.to_string(), result.clone());
-            return result;
+        // Absolute numbering (a 4-digit number that looks like a year is not an episode)
+        if let Some(caps) = SERIES_ABSOLUTE_PATTERN.captures(name) {
+            let episode = num(&caps, 2).filter(|n| !(1900..=2099).contains(n));
+            if let Some(info) = Self::series_info(&caps[1], Some(1), episode, None) {
+                return Some(info);
+            }
         }
 
-        // Not a series - cache null result too
-        let mut cache = SERIES_CACHE.lock().unwrap();
-        cache.put(name.to_string(), None);
         None
     }
 
+    /// Build series info from captured parts; None if the name is empty or numbers are out of range
+    fn series_info(
+        raw_name: &str,
+        season: Option<u32>,
+        episode: Option<u32>,
+        episode_end: Option<u32>,
+    ) -> Option<ExtractedSeriesInfo> {
+        let series_name = SERIES_NAME_TRIM.replace_all(raw_name, "").to_string();
+        if series_name.is_empty() {
+            return None;
+        }
+        let season = u8::try_from(season?).ok()?;
+        let episode = u16::try_from(episode?).ok()?;
+        // A short ascending range is a multi-episode file; anything else is noise
+        let episode_end = episode_end
+            .and_then(|end| u16::try_from(end).ok())
+            .filter(|end| *end > episode && *end - episode <= 10);
+
+        Some(ExtractedSeriesInfo {
+            series_name,
+            season,
+            episode,
+            episode_end,
+            air_date: None,
+            is_series: true,
+        })
+    }
+
+    /// Key identifying a show across groups: cleaned, accent/case-insensitive name
+    pub fn series_key(series_name: &str) -> String {
+        let cleaned = Self::clean_title(series_name);
+        let key = normalize(if cleaned.is_empty() { series_name } else { &cleaned });
+        if key.is_empty() {
+            series_name.to_lowercase()
+        } else {
+            key
+        }
+    }
+
     /// Clean title removing tags and special characters
     pub fn clean_title(title: &str) -> String {
         lazy_static! {
@@ -506,10 +628,75 @@ mod tests {
 
     #[test]
     fn test_extract_series_info() {
-        let info = ContentClassifier::extract_series_info("Breaking Bad S02E10").unwrap();
+        let info = ContentClassifier::extract_series_info("Breaking Bad S02E10", "").unwrap();
         assert_eq!(info.series_name, "Breaking Bad");
         assert_eq!(info.season, 2);
         assert_eq!(info.episode, 10);
         assert!(info.is_series);
     }
+
+    #[test]
+    fn test_extract_series_info_extended_formats() {
+        let info = ContentClassifier::extract_series_info("Pantanal Temporada 2 Episódio 5", "").unwrap();
+        assert_eq!((info.series_name.as_str(), info.season, info.episode), ("Pantanal", 2, 5));
+
+        let info = ContentClassifier::extract_series_info("Terra e Paixão - Capítulo 30", "Novelas").unwrap();
+        assert_eq!((info.series_name.as_str(), info.season, info.episode), ("Terra e Paixão", 1, 30));
+
+        let info = ContentClassifier::extract_series_info("The Office Ep. 12", "Séries | Comédia").unwrap();
+        assert_eq!((info.season, info.episode), (1, 12));
+
+        let info = ContentClassifier::extract_series_info("Lost S01E01-E02", "").unwrap();
+        assert_eq!((info.episode, info.episode_end), (1, Some(2)));
+
+        let info = ContentClassifier::extract_series_info("One Piece - 1071 [FHD]", "Séries | Animes").unwrap();
+        assert_eq!((info.series_name.as_str(), info.season, info.episode), ("One Piece", 1, 1071));
+
+        let info = ContentClassifier::extract_series_info("Mais Você 15/03/2024", "Séries | Programas").unwrap();
+        assert_eq!(info.series_name, "Mais Você");
+        assert_eq!(info.air_date, chrono::NaiveDate::from_ymd_opt(2024, 3, 15));
+        assert_eq!((info.season, info.episode), (24, 75));
+
+        assert!(ContentClassifier::extract_series_info("Capitão América", "Séries").is_none());
+        assert!(ContentClassifier::extract_series_info("Show - 2023", "Séries").is_none());
+    }
+
+    #[test]
+    fn test_numbered_titles_outside_series_groups_are_not_episodes() {
+        for (name, group) in [
+            ("Velozes e Furiosos - 10", "Filmes | Ação"),
+            ("Star Wars: Episódio 4", "Filmes"),
+            ("Star Wars: Episódio 4", ""),
+            ("Jogos Mortais - 7", ""),
+            ("Show da Virada 31/12/2023", "Filmes"),
+        ] {
+            assert!(
+                ContentClassifier::extract_series_info(name, group).is_none(),
+                "{} in {:?} detected as an episode",
+                name,
+                group
+            );
+        }
+        assert_eq!(ContentClassifier::classify("Velozes e Furiosos - 10", "Filmes | Ação"), MediaKind::Movie);
+
+        // Explicit season markers don't need the group
+        assert!(ContentClassifier::extract_series_info("Dark S01E01", "Lançamentos").is_some());
+    }
+
+    #[test]
+    fn test_audio_variant() {
+        assert_eq!(ContentClassifier::audio_variant("Dark S01E01", "Séries Dubladas"), Some("dub"));
+        assert_eq!(ContentClassifier::audio_variant("Dark S01E01 [L]", "Séries"), Some("leg"));
+        assert_eq!(ContentClassifier::audio_variant("Dark S01E01", "Séries Legendadas"), Some("leg"));
+        assert_eq!(ContentClassifier::audio_variant("Dark S01E01 Dublado e Legendado", "Séries"), None);
+        assert_eq!(ContentClassifier::audio_variant("Dark S01E01", "Séries"), None);
+    }
+
+    #[test]
+    fn test_series_key_ignores_tags_and_accents() {
+        assert_eq!(
+            ContentClassifier::series_key("Avenida Brasil [L]"),
+            ContentClassifier::series_key("AVENIDA BRASÍL")
+        );
+    }
 }
@@ -2,7 +2,10 @@
 //!
 //! Runs as a background task on startup, then periodically.
 //! - Deletes playlists where expires_at < NOW()
-//! - Cleans up old watch history entries (keeps last N per device)
+//! - Cleans up old watch history entries (keeps last N per device, and
+//!   deletion tombstones until every device should have synced them)
+//! - Removes remote control pairings unused for a long time
+//! - Removes finished parse jobs
 
 use chrono::Utc;
 use sqlx::PgPool;
@@ -15,6 +18,16 @@ pub struct CleanupConfig {
     pub interval_secs: u64,
     /// Maximum watch history items to keep per device
     pub max_watch_history_per_device: i64,
+    /// Days watch history tombstones are kept; a device that hasn't synced
+    /// for longer must resync from revision 0 to drop deleted entries
+    pub watch_history_tombstone_days: i64,
+    /// Days a remote pairing can go unused before it's removed
+    pub remote_max_idle_days: i64,
+    /// Days finished parse jobs are kept
+    pub parse_job_max_age_days: i64,
+    /// Days a playlist's content keeps its first-seen time after it was last
+    /// imported (for the "recent" sort)
+    pub content_seen_max_age_days: i64,
 }
 
 impl Default for CleanupConfig {
@@ -22,6 +35,10 @@ impl Default for CleanupConfig {
         Self {
             interval_secs: 3600, // Run every hour
             max_watch_history_per_device: 100,
+            watch_history_tombstone_days: 180,
+            remote_max_idle_days: 90,
+            parse_job_max_age_days: 7,
+            content_seen_max_age_days: 30,
         }
     }
 }
@@ -47,15 +64,19 @@ pub async fn cleanup_expired_playlists(pool: &PgPool) -> Result<i64, sqlx::Error
 }
 
 /// Cleanup old watch history entries, keeping only the most recent N per device
-/// Returns the number of deleted entries
+///
+/// Older entries become tombstones so other devices drop them too; tombstones
+/// are deleted after `tombstone_days`. Returns the number of entries removed.
 pub async fn cleanup_watch_history(
     pool: &PgPool,
     keep_count: i64,
+    tombstone_days: i64,
 ) -> Result<i64, sqlx::Error> {
     // Uses the cleanup_watch_history database function if it exists,
     // otherwise falls back to a manual query
-    let result: Result<(i32,), _> = sqlx::query_as("SELECT cleanup_watch_history($1)")
+    let result: Result<(i32,), _> = sqlx::query_as("SELECT cleanup_watch_history($1, $2)")
         .bind(keep_count as i32)
+        .bind(tombstone_days as i32)
         .fetch_one(pool)
         .await;
 
@@ -64,14 +85,15 @@ pub async fn cleanup_watch_history(
         Err(_) => {
             // Function doesn't exist, use manual cleanup
             // This is less efficient but works without the function
-            let result = sqlx::query(
+            let trimmed = sqlx::query(
                 r#"
                 WITH ranked AS (
                     SELECT id,
-                           ROW_NUMBER() OVER (PARTITION BY device_id ORDER BY watched_at DESC) as rn
+                           ROW_NUMBER() OVER (PARTITION BY owner_id ORDER BY watched_at DESC) as rn
                     FROM watch_history
+                    WHERE deleted_at IS NULL
                 )
-                DELETE FROM watch_history
+                UPDATE watch_history SET deleted_at = NOW(), position_ms = 0
                 WHERE id IN (SELECT id FROM ranked WHERE rn > $1)
                 "#,
             )
@@ -79,7 +101,17 @@ pub async fn cleanup_watch_history(
             .execute(pool)
             .await?;
 
-            Ok(result.rows_affected() as i64)
+            let purged = sqlx::query(
+                r#"
+                DELETE FROM watch_history
+                WHERE deleted_at IS NOT NULL AND deleted_at < NOW() - make_interval(days => $1)
+                "#,
+            )
+            .bind(tombstone_days as i32)
+            .execute(pool)
+            .await?;
+
+            Ok((trimmed.rows_affected() + purged.rows_affected()) as i64)
         }
     }
 }
@@ -103,7 +135,13 @@ pub async fn run_cleanup(pool: &PgPool, config: &CleanupConfig) -> CleanupResult
     }
 
     // Cleanup old watch history
-    match cleanup_watch_history(pool, config.max_watch_history_per_device).await {
+    match cleanup_watch_history(
+        pool,
+        config.max_watch_history_per_device,
+        config.watch_history_tombstone_days,
+    )
+    .await
+    {
         Ok(count) => {
             result.watch_history_deleted = count;
             if count > 0 {
@@ -116,6 +154,48 @@ pub async fn run_cleanup(pool: &PgPool, config: &CleanupConfig) -> CleanupResult
         }
     }
 
+    // Cleanup idle remote pairings
+    match crate::db::repository::remotes::delete_idle(pool, config.remote_max_idle_days).await {
+        Ok(count) => {
+            result.remotes_deleted = count as i64;
+            if count > 0 {
+                tracing::info!("Cleanup: removed {} idle remote pairings", count);
+            }
+        }
+        Err(e) => {
+            result.errors.push(format!("Remote pairing cleanup failed: {}", e));
+            tracing::error!("Cleanup: remote pairing cleanup failed: {}", e);
+        }
+    }
+
+    // Cleanup finished parse jobs
+    match crate::db::repository::parse_jobs::delete_finished(pool, config.parse_job_max_age_days).await {
+        Ok(count) => {
+            result.parse_jobs_deleted = count as i64;
+            if count > 0 {
+                tracing::info!("Cleanup: removed {} finished parse jobs", count);
+            }
+        }
+        Err(e) => {
+            result.errors.push(format!("Parse job cleanup failed: {}", e));
+            tracing::error!("Cleanup: parse job cleanup failed: {}", e);
+        }
+    }
+
+    // Cleanup first-seen times of content no longer in any import
+    match crate::db::repository::items::delete_unseen_content(pool, config.content_seen_max_age_days).await {
+        Ok(count) => {
+            result.content_seen_deleted = count as i64;
+            if count > 0 {
+                tracing::info!("Cleanup: forgot {} unseen playlist entries", count);
+            }
+        }
+        Err(e) => {
+            result.errors.push(format!("Content seen cleanup failed: {}", e));
+            tracing::error!("Cleanup: content seen cleanup failed: {}", e);
+        }
+    }
+
     result
 }
 
@@ -124,6 +204,9 @@ pub async fn run_cleanup(pool: &PgPool, config: &CleanupConfig) -> CleanupResult
 pub struct CleanupResult {
     pub playlists_deleted: i64,
     pub watch_history_deleted: i64,
+    pub remotes_deleted: i64,
+    pub parse_jobs_deleted: i64,
+    pub content_seen_deleted: i64,
     pub errors: Vec<String>,
 }
 
@@ -133,7 +216,11 @@ impl CleanupResult {
     }
 
     pub fn total_deleted(&self) -> i64 {
-        self.playlists_deleted + self.watch_history_deleted
+        self.playlists_deleted
+            + self.watch_history_deleted
+            + self.remotes_deleted
+            + self.parse_jobs_deleted
+            + self.content_seen_deleted
     }
 }
 
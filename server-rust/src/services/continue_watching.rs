@@ -0,0 +1,111 @@
+//! Continue watching ("Up next")
+//!
+//! Collapses a device's watch history into one entry per series or movie:
+//! unfinished items are resumed, and a finished episode is followed by the
+//! next one (resolved by the caller from `series_episodes`).
+
+use std::collections::HashSet;
+
+use crate::db::repository::watch_history::ProgressRow;
+
+/// What to play for a history entry
+#[derive(Debug, Clone)]
+pub enum UpNext {
+    /// Resume the item at its saved position
+    Resume(ProgressRow),
+    /// The episode was finished: play the one after it
+    NextEpisode(ProgressRow),
+}
+
+impl UpNext {
+    pub fn progress(&self) -> &ProgressRow {
+        match self {
+            UpNext::Resume(row) | UpNext::NextEpisode(row) => row,
+        }
+    }
+}
+
+/// Whether playback reached `finished_percent` of the duration
+///
+/// Without a known duration an item is never considered finished.
+pub fn is_finished(position_ms: i64, duration_ms: Option<i64>, finished_percent: u8) -> bool {
+    match duration_ms {
+        Some(duration) if duration > 0 => {
+            position_ms.saturating_mul(100) >= duration.saturating_mul(finished_percent as i64)
+        }
+        _ => false,
+    }
+}
+
+/// Collapse progress rows (most recent first) into up-next entries
+///
+/// Only the most recently watched episode of each series counts. Live
+/// channels, finished movies and unstarted items are dropped.
+pub fn up_next(rows: Vec<ProgressRow>, finished_percent: u8) -> Vec<UpNext> {
+    let mut seen_series = HashSet::new();
+
+    rows.into_iter()
+        .filter(|row| row.media_kind != "live")
+        .filter(|row| row.series_id.is_none_or(|id| seen_series.insert(id)))
+        .filter_map(|row| {
+            let finished = is_finished(row.position_ms, row.duration_ms, finished_percent);
+            match (finished, row.series_id.is_some()) {
+                (true, true) => Some(UpNext::NextEpisode(row)),
+                (true, false) => None,
+                (false, _) if row.position_ms > 0 => Some(UpNext::Resume(row)),
+                (false, _) => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    fn row(hash: &str, kind: &str, position: i64, series: Option<Uuid>, age_min: i64) -> ProgressRow {
+        ProgressRow {
+            item_hash: hash.to_string(),
+            media_kind: kind.to_string(),
+            position_ms: position,
+            duration_ms: Some(1_000),
+            watched_at: Utc::now() - Duration::minutes(age_min),
+            series_id: series,
+            season: series.map(|_| 1),
+            episode: series.map(|_| 1),
+            episode_end: None,
+        }
+    }
+
+    #[test]
+    fn test_is_finished() {
+        assert!(is_finished(900, Some(1_000), 90));
+        assert!(!is_finished(899, Some(1_000), 90));
+        assert!(!is_finished(5_000, None, 90));
+        assert!(!is_finished(5_000, Some(0), 90));
+    }
+
+    #[test]
+    fn test_up_next_collapses_series() {
+        let show = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let rows = vec![
+            row("s1e2", "series", 950, Some(show), 1),
+            row("s1e1", "series", 400, Some(show), 5),
+            row("movie", "movie", 300, None, 10),
+            row("done", "movie", 990, None, 15),
+            row("channel", "live", 300, None, 20),
+            row("other", "series", 100, Some(other), 25),
+            row("unstarted", "movie", 0, None, 30),
+        ];
+
+        let next = up_next(rows, 90);
+        let summary: Vec<_> = next
+            .iter()
+            .map(|e| (matches!(e, UpNext::Resume(_)), e.progress().item_hash.as_str()))
+            .collect();
+        assert_eq!(summary, vec![(false, "s1e2"), (true, "movie"), (true, "other")]);
+    }
+}
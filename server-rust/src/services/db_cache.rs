@@ -8,10 +8,14 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::db::models::{NewGroup, NewPlaylist, NewSeries, NewEpisode};
-use crate::db::repository::{groups, items, playlists, series, StreamingDbWriter};
+use crate::db::repository::items::{ItemCursor, ItemFilters, ItemSort, SearchFilters};
+use crate::db::repository::{groups, items, playlists, series, titles, StreamingDbWriter};
 use crate::models::playlist::{
-    CacheMetadata, PlaylistGroup, PlaylistItem, PlaylistStats, SeriesInfo,
+    CacheMetadata, FacetValue, ItemFacets, PlaylistGroup, PlaylistItem, PlaylistStats, SearchHit, SearchLimits, SearchResults,
+    SearchSection, SeriesInfo, MovieTitle,
 };
+use crate::services::search;
+use crate::services::titles::{build_titles, VariantPreferences};
 
 /// PostgreSQL-based cache service for playlist data
 #[derive(Clone)]
@@ -25,6 +29,11 @@ impl DbCacheService {
         Self { pool }
     }
 
+    /// Underlying connection pool
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
     /// Get cache metadata by hash
     pub async fn get_metadata(&self, hash: &str) -> Result<Option<CacheMetadata>> {
         let playlist = match playlists::find_by_hash_any(&self.pool, hash).await? {
@@ -188,6 +197,8 @@ impl DbCacheService {
                             item_hash: ep.item_id.clone(),
                             season: season.season_number as i16,
                             episode: ep.episode as i16,
+                            episode_end: ep.episode_end.map(|e| e as i16),
+                            air_date: ep.air_date,
                             name: ep.name.clone(),
                             url: ep.url.clone(),
                         });
@@ -223,55 +234,239 @@ impl DbCacheService {
         Ok(playlist_id)
     }
 
-    /// Read items with pagination and optional filters
+    /// Read a page of items with optional filters and sort mode
+    /// Returns items, total matches and the encoded cursor of the next page
     pub async fn read_items(
         &self,
         hash: &str,
         offset: usize,
         limit: usize,
-        group_filter: Option<&str>,
-        media_kind_filter: Option<&str>,
-    ) -> Result<(Vec<PlaylistItem>, usize)> {
+        filters: &ItemFilters<'_>,
+        sort: ItemSort,
+        cursor: Option<&ItemCursor>,
+    ) -> Result<(Vec<PlaylistItem>, usize, Option<String>)> {
         let playlist_id = self.get_playlist_id(hash)
             .await?
             .context("Playlist not found")?;
 
-        let item_rows = items::get_items(
+        let (item_rows, next_cursor) = items::get_items(
             &self.pool,
             playlist_id,
-            group_filter,
-            media_kind_filter,
+            filters,
+            sort,
+            cursor,
             limit as i64,
             offset as i64,
         ).await?;
 
-        let total = items::count_items(
-            &self.pool,
-            playlist_id,
-            group_filter,
-            media_kind_filter,
-        ).await? as usize;
+        let total = items::count_items(&self.pool, playlist_id, filters).await? as usize;
 
         let playlist_items: Vec<PlaylistItem> = item_rows.into_iter().map(Into::into).collect();
 
-        Ok((playlist_items, total))
+        Ok((playlist_items, total, next_cursor.map(|c| c.encode())))
     }
 
-    /// Search items using fuzzy matching
+    /// Facet counts (quality, language, year, dubbed/subbed/multi-audio) for filtered items
+    pub async fn item_facets(&self, hash: &str, filters: &ItemFilters<'_>) -> Result<ItemFacets> {
+        let playlist_id = self.get_playlist_id(hash)
+            .await?
+            .context("Playlist not found")?;
+
+        let rows = items::facet_counts(&self.pool, playlist_id, filters).await?;
+
+        let mut facets = ItemFacets::default();
+        for (facet, value, count) in rows {
+            let count = count as usize;
+            match (facet.as_str(), value) {
+                ("quality", Some(value)) => facets.quality.push(FacetValue { value, count }),
+                ("language", Some(value)) => facets.language.push(FacetValue { value, count }),
+                ("year", Some(value)) => facets.year.push(FacetValue { value, count }),
+                ("dubbed", _) => facets.dubbed = count,
+                ("subbed", _) => facets.subbed = count,
+                ("multi_audio", _) => facets.multi_audio = count,
+                _ => {}
+            }
+        }
+
+        facets.quality.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+        facets.language.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+        facets.year.sort_by(|a, b| b.value.cmp(&a.value));
+
+        Ok(facets)
+    }
+
+    /// A page of movie titles (versions clustered) and the total title count
+    pub async fn get_titles(
+        &self,
+        hash: &str,
+        group: Option<&str>,
+        offset: usize,
+        limit: usize,
+        prefs: &VariantPreferences,
+    ) -> Result<(Vec<MovieTitle>, usize)> {
+        let playlist_id = self.get_playlist_id(hash)
+            .await?
+            .context("Playlist not found")?;
+
+        let (rows, total) = tokio::try_join!(
+            titles::get_title_variants(&self.pool, playlist_id, group, limit as i64, offset as i64),
+            titles::count_titles(&self.pool, playlist_id, group),
+        )?;
+
+        Ok((build_titles(&rows, prefs), total as usize))
+    }
+
+    /// A single movie title with all its variants
+    pub async fn get_title(
+        &self,
+        hash: &str,
+        title_id: &str,
+        prefs: &VariantPreferences,
+    ) -> Result<Option<MovieTitle>> {
+        let playlist_id = self.get_playlist_id(hash)
+            .await?
+            .context("Playlist not found")?;
+
+        let rows = titles::get_by_key(&self.pool, playlist_id, title_id).await?;
+
+        Ok(build_titles(&rows, prefs).into_iter().next())
+    }
+
+    /// Accent-insensitive ranked search over items, with highlighted matches
     pub async fn search_items(
         &self,
         hash: &str,
         query: &str,
+        filters: &SearchFilters<'_>,
         limit: usize,
-    ) -> Result<Vec<PlaylistItem>> {
+    ) -> Result<Vec<SearchHit<PlaylistItem>>> {
         let playlist_id = self.get_playlist_id(hash)
             .await?
             .context("Playlist not found")?;
 
-        let item_rows = items::search_items(&self.pool, playlist_id, query, limit as i64).await?;
-        let playlist_items: Vec<PlaylistItem> = item_rows.into_iter().map(Into::into).collect();
+        let Some(tsquery) = search::prefix_tsquery(query) else {
+            return Ok(Vec::new());
+        };
+        let tokens = search::query_tokens(query);
+        let normalized = search::normalize(query);
+
+        let rows = items::search_items(
+            &self.pool,
+            playlist_id,
+            &tsquery,
+            &normalized,
+            filters,
+            limit as i64,
+        ).await?;
 
-        Ok(playlist_items)
+        Ok(rows.into_iter().map(|row| item_hit(row, &tokens)).collect())
+    }
+
+    /// Unified accent-insensitive search, grouped into series/movies/live/groups
+    ///
+    /// Episodes are represented by their series rather than listed one by one.
+    /// A media_kind filter restricts results to the matching section.
+    pub async fn search(
+        &self,
+        hash: &str,
+        query: &str,
+        filters: &SearchFilters<'_>,
+        limits: SearchLimits,
+    ) -> Result<SearchResults> {
+        let playlist_id = self.get_playlist_id(hash)
+            .await?
+            .context("Playlist not found")?;
+
+        let Some(tsquery) = search::prefix_tsquery(query) else {
+            return Ok(SearchResults::default());
+        };
+        let tokens = search::query_tokens(query);
+        let normalized = search::normalize(query);
+        let wants = |kind: &str| filters.media_kind.map(|k| k == kind).unwrap_or(true);
+
+        let items_in = |kind: &'static str, limit: usize| {
+            let kind_filters = SearchFilters { media_kind: Some(kind), ..filters.clone() };
+            let (tsquery, normalized) = (&tsquery, &normalized);
+            async move {
+                if !wants(kind) || limit == 0 {
+                    return Ok(Vec::new());
+                }
+                items::search_items(&self.pool, playlist_id, tsquery, normalized, &kind_filters, limit as i64).await
+            }
+        };
+
+        let series_search = async {
+            if !wants("series") || limits.series == 0 {
+                return Ok(Vec::new());
+            }
+            series::search_series(
+                &self.pool,
+                playlist_id,
+                &tsquery,
+                &normalized,
+                filters,
+                limits.series as i64,
+            ).await
+        };
+
+        let groups_search = async {
+            // Group/year filters narrow down items; they don't apply to group names
+            if filters.group.is_some() || filters.year.is_some() || limits.groups == 0 {
+                return Ok(Vec::new());
+            }
+            groups::search_groups(
+                &self.pool,
+                playlist_id,
+                &tsquery,
+                &normalized,
+                filters.media_kind,
+                filters.restrictions,
+                limits.groups as i64,
+            ).await
+        };
+
+        let (series_rows, movie_rows, live_rows, group_rows) = tokio::try_join!(
+            series_search,
+            items_in("movie", limits.movies),
+            items_in("live", limits.live),
+            groups_search,
+        )?;
+
+        let item_section = |rows: Vec<items::SearchHitRow>| SearchSection {
+            total: rows.first().map(|r| r.total as usize).unwrap_or(0),
+            items: rows.into_iter().map(|row| item_hit(row, &tokens)).collect(),
+        };
+
+        let series = SearchSection {
+            total: series_rows.first().map(|r| r.total as usize).unwrap_or(0),
+            items: series_rows
+                .into_iter()
+                .map(|row| SearchHit {
+                    highlights: search::highlight_ranges("name", &row.series.name, &tokens),
+                    item: row.series.into(),
+                    score: row.rank,
+                })
+                .collect(),
+        };
+
+        let groups = SearchSection {
+            total: group_rows.first().map(|r| r.total as usize).unwrap_or(0),
+            items: group_rows
+                .into_iter()
+                .map(|row| SearchHit {
+                    highlights: search::highlight_ranges("name", &row.group.name, &tokens),
+                    item: row.group.into(),
+                    score: row.rank,
+                })
+                .collect(),
+        };
+
+        Ok(SearchResults {
+            series,
+            movies: item_section(movie_rows),
+            live: item_section(live_rows),
+            groups,
+        })
     }
 
     /// Get groups for a playlist
@@ -427,3 +622,12 @@ impl DbCacheService {
         Ok(result)
     }
 }
+
+/// Item search hit with name/title highlight spans
+fn item_hit(row: items::SearchHitRow, tokens: &[String]) -> SearchHit<PlaylistItem> {
+    let mut highlights = search::highlight_ranges("name", &row.item.name, tokens);
+    if let Some(title) = &row.item.parsed_title {
+        highlights.extend(search::highlight_ranges("title", title, tokens));
+    }
+    SearchHit { item: row.item.into(), score: row.rank, highlights }
+}
@@ -0,0 +1,373 @@
+//! Push events for TV clients
+//!
+//! Session URL arrival, parse progress, playlist completions, account
+//! warnings and remote control commands are published to Redis channels, so the instance holding a
+//! client's stream receives them wherever they were produced. Each instance
+//! keeps a single pattern subscription and fans messages out to its local
+//! streams.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::stream::SelectAll;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::models::PlaylistStats;
+use crate::services::redis::{ParseProgress, RedisService};
+use crate::services::remote::RemoteCommand;
+
+const CHANNEL_PATTERN: &str = "events:*";
+/// Events buffered per channel before slow streams start skipping
+const CHANNEL_CAPACITY: usize = 64;
+
+pub fn device_channel(device_id: &str) -> String {
+    format!("events:device:{}", device_id)
+}
+
+pub fn session_channel(session_id: &str) -> String {
+    format!("events:session:{}", session_id)
+}
+
+pub fn playlist_channel(hash: &str) -> String {
+    format!("events:playlist:{}", hash)
+}
+
+/// Typed event delivered to clients (the SSE event name is `type`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PushEvent {
+    /// The phone sent a playlist to a pairing session
+    ///
+    /// Only the session id: playlist URLs can embed Xtream credentials, so
+    /// the TV takes the URL from `/session/:id/poll` instead.
+    #[serde(rename_all = "camelCase")]
+    SessionUrl { session_id: String },
+    #[serde(rename_all = "camelCase")]
+    ParseProgress { hash: String, progress: ParseProgress },
+    /// A playlist finished parsing and is ready to browse
+    #[serde(rename_all = "camelCase")]
+    PlaylistReady { hash: String, stats: PlaylistStats },
+    AccountWarning(AccountWarning),
+    /// A phone paired as a remote control
+    #[serde(rename_all = "camelCase")]
+    RemotePaired { remote_id: String, name: String },
+    /// Command sent from a paired phone
+    #[serde(rename_all = "camelCase")]
+    RemoteCommand { remote_id: String, command: RemoteCommand },
+}
+
+impl PushEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PushEvent::SessionUrl { .. } => "sessionUrl",
+            PushEvent::ParseProgress { .. } => "parseProgress",
+            PushEvent::PlaylistReady { .. } => "playlistReady",
+            PushEvent::AccountWarning(_) => "accountWarning",
+            PushEvent::RemotePaired { .. } => "remotePaired",
+            PushEvent::RemoteCommand { .. } => "remoteCommand",
+        }
+    }
+}
+
+/// Problem with the IPTV account behind a playlist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountWarning {
+    pub hash: String,
+    /// "expired" | "expiring" | "trial"
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+/// Warnings for an Xtream account (expired, expiring within `warn_days`, trial)
+pub fn account_warnings(
+    hash: &str,
+    expires_at: Option<DateTime<Utc>>,
+    is_trial: bool,
+    now: DateTime<Utc>,
+    warn_days: i64,
+) -> Vec<AccountWarning> {
+    let mut warnings = Vec::new();
+
+    if let Some(expires_at) = expires_at {
+        let days_left = (expires_at - now).num_days();
+        let status = if expires_at <= now {
+            Some(("expired", "Sua assinatura expirou".to_string()))
+        } else if days_left < warn_days {
+            let message = match days_left {
+                0 => "Sua assinatura expira hoje".to_string(),
+                1 => "Sua assinatura expira amanhã".to_string(),
+                days => format!("Sua assinatura expira em {} dias", days),
+            };
+            Some(("expiring", message))
+        } else {
+            None
+        };
+
+        if let Some((code, message)) = status {
+            warnings.push(AccountWarning {
+                hash: hash.to_string(),
+                code: code.to_string(),
+                message,
+                expires_at: Some(expires_at.timestamp_millis()),
+            });
+        }
+    }
+
+    if is_trial {
+        warnings.push(AccountWarning {
+            hash: hash.to_string(),
+            code: "trial".to_string(),
+            message: "Conta de teste".to_string(),
+            expires_at: expires_at.map(|t| t.timestamp_millis()),
+        });
+    }
+
+    warnings
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Arc<PushEvent>> + Send>>;
+
+/// Live events of one stream; its channels are released when it's dropped
+pub struct Subscription {
+    streams: SelectAll<EventStream>,
+    channels: Vec<String>,
+    subscribers: Subscribers,
+}
+
+impl Stream for Subscription {
+    type Item = Arc<PushEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.streams.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        // Receivers go first so the channels they held count as unused
+        self.streams = SelectAll::new();
+        self.subscribers.release(&self.channels);
+    }
+}
+
+/// Local streams by channel
+#[derive(Clone, Default)]
+pub struct Subscribers {
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<Arc<PushEvent>>>>>,
+}
+
+impl Subscribers {
+    /// Events on any of `channels`, until the stream is dropped
+    pub fn subscribe(&self, channels: &[String]) -> Subscription {
+        let receivers: Vec<_> = {
+            let mut map = self.channels.lock().unwrap();
+            channels
+                .iter()
+                .map(|channel| {
+                    map.entry(channel.clone())
+                        .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+                        .subscribe()
+                })
+                .collect()
+        };
+
+        let streams = futures::stream::select_all(receivers.into_iter().map(|mut rx| {
+            Box::pin(async_stream::stream! {
+                loop {
+                    match rx.recv().await {
+                        Ok(event) => yield event,
+                        // A slow client misses intermediate events (progress is cumulative)
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }) as EventStream
+        }));
+
+        Subscription {
+            streams,
+            channels: channels.to_vec(),
+            subscribers: self.clone(),
+        }
+    }
+
+    /// Forget `channels` that no stream listens on anymore
+    fn release(&self, channels: &[String]) {
+        let mut map = self.channels.lock().unwrap();
+        for channel in channels {
+            if map.get(channel).is_some_and(|sender| sender.receiver_count() == 0) {
+                map.remove(channel);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn channel_count(&self) -> usize {
+        self.channels.lock().unwrap().len()
+    }
+
+    /// Deliver a raw channel message to the streams listening on it
+    pub fn dispatch(&self, channel: &str, payload: &str) {
+        let map = self.channels.lock().unwrap();
+        let Some(sender) = map.get(channel) else {
+            return;
+        };
+
+        match serde_json::from_str::<PushEvent>(payload) {
+            Ok(event) => {
+                let _ = sender.send(Arc::new(event));
+            }
+            Err(e) => tracing::warn!("Invalid event on {}: {}", channel, e),
+        }
+    }
+}
+
+/// Publishes events and delivers them to this instance's streams
+#[derive(Clone)]
+pub struct EventHub {
+    redis: RedisService,
+    subscribers: Subscribers,
+}
+
+impl EventHub {
+    pub fn new(redis: RedisService) -> Self {
+        Self {
+            redis,
+            subscribers: Subscribers::default(),
+        }
+    }
+
+    /// Publish an event to every instance
+    pub async fn publish(&self, channel: &str, event: &PushEvent) -> Result<()> {
+        self.redis
+            .publish(channel, &serde_json::to_string(event)?)
+            .await
+    }
+
+    /// Publish, logging instead of failing (events are best effort)
+    pub async fn notify(&self, channel: &str, event: PushEvent) {
+        if let Err(e) = self.publish(channel, &event).await {
+            tracing::warn!("Failed to publish {} event to {}: {}", event.name(), channel, e);
+        }
+    }
+
+    /// Store a playlist's parse progress and push it to the streams following it
+    pub async fn parse_progress(&self, hash: &str, progress: &ParseProgress) -> Result<()> {
+        self.redis.set_parse_progress(hash, progress).await?;
+        let event = PushEvent::ParseProgress {
+            hash: hash.to_string(),
+            progress: progress.clone(),
+        };
+        self.publish(&playlist_channel(hash), &event).await
+    }
+
+    /// Events on any of `channels`, until the stream is dropped
+    pub fn subscribe(&self, channels: &[String]) -> Subscription {
+        self.subscribers.subscribe(channels)
+    }
+
+    /// Receive events from Redis and fan them out (reconnects on failure)
+    pub async fn run(self) {
+        loop {
+            match self.redis.pubsub().await {
+                Ok(mut pubsub) => match pubsub.psubscribe(CHANNEL_PATTERN).await {
+                    Ok(()) => {
+                        tracing::info!("Event hub subscribed to {}", CHANNEL_PATTERN);
+                        let mut messages = pubsub.into_on_message();
+                        while let Some(msg) = messages.next().await {
+                            match msg.get_payload::<String>() {
+                                Ok(payload) => {
+                                    self.subscribers.dispatch(msg.get_channel_name(), &payload)
+                                }
+                                Err(e) => tracing::warn!("Invalid event payload: {}", e),
+                            }
+                        }
+                        tracing::warn!("Event hub subscription closed, reconnecting");
+                    }
+                    Err(e) => tracing::error!("Failed to subscribe to events: {}", e),
+                },
+                Err(e) => tracing::error!("Failed to open event subscription: {}", e),
+            }
+
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_account_warnings() {
+        let now = Utc::now();
+        let codes = |expires: Option<DateTime<Utc>>, trial: bool| -> Vec<String> {
+            account_warnings("h", expires, trial, now, 7)
+                .into_iter()
+                .map(|w| w.code)
+                .collect()
+        };
+
+        assert_eq!(codes(Some(now - Duration::hours(1)), false), vec!["expired"]);
+        assert_eq!(codes(Some(now + Duration::days(3)), false), vec!["expiring"]);
+        assert!(codes(Some(now + Duration::days(30)), false).is_empty());
+        assert!(codes(None, false).is_empty());
+        assert_eq!(codes(None, true), vec!["trial"]);
+
+        let warning = &account_warnings("h", Some(now + Duration::hours(30)), false, now, 7)[0];
+        assert_eq!(warning.message, "Sua assinatura expira amanhã");
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = PushEvent::SessionUrl {
+            session_id: "abc".to_string(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "sessionUrl");
+        assert_eq!(json["sessionId"], "abc");
+        assert!(json.get("url").is_none());
+
+        let back: PushEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(back.name(), "sessionUrl");
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_reaches_subscribers() {
+        let subscribers = Subscribers::default();
+        let mut events = Box::pin(subscribers.subscribe(&[device_channel("tv")]));
+
+        let event = PushEvent::SessionUrl { session_id: "s".to_string() };
+        let payload = serde_json::to_string(&event).unwrap();
+        subscribers.dispatch(&device_channel("other"), &payload);
+        subscribers.dispatch(&device_channel("tv"), "not json");
+        subscribers.dispatch(&device_channel("tv"), &payload);
+
+        let received = events.next().await.unwrap();
+        assert_eq!(received.name(), "sessionUrl");
+    }
+
+    #[tokio::test]
+    async fn test_dropped_streams_release_channels() {
+        let subscribers = Subscribers::default();
+        let first = subscribers.subscribe(&[device_channel("tv"), session_channel("s")]);
+        let second = subscribers.subscribe(&[device_channel("tv")]);
+        assert_eq!(subscribers.channel_count(), 2);
+
+        drop(first);
+        assert_eq!(subscribers.channel_count(), 1);
+        drop(second);
+        assert_eq!(subscribers.channel_count(), 0);
+    }
+}
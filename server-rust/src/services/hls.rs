@@ -0,0 +1,59 @@
+//! HLS playlist helpers shared by the proxy, recorder and probe
+
+/// Split an HLS attribute list (`KEY=value,KEY="quoted, value"`) into
+/// upper-cased keys and unquoted values, respecting commas inside quotes
+pub fn parse_attribute_list(list: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in list.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ',' if !in_quotes => {
+                if let Some((k, v)) = current.split_once('=') {
+                    attrs.push((k.trim().to_uppercase(), v.trim().trim_matches('"').to_string()));
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    if let Some((k, v)) = current.split_once('=') {
+        attrs.push((k.trim().to_uppercase(), v.trim().trim_matches('"').to_string()));
+    }
+
+    attrs
+}
+
+/// Attribute list of a tag line (`#EXT-X-STREAM-INF:...`)
+pub fn tag_attributes(tag: &str) -> Vec<(String, String)> {
+    parse_attribute_list(tag.split_once(':').map(|(_, rest)| rest).unwrap_or(""))
+}
+
+/// Parse a `RESOLUTION=WxH` value
+pub fn parse_resolution(value: &str) -> Option<(u32, u32)> {
+    let (w, h) = value.split_once(['x', 'X'])?;
+    Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_attribute_list_quoted_commas() {
+        let attrs = parse_attribute_list("BANDWIDTH=1,CODECS=\"avc1.64001f,mp4a.40.2\",RESOLUTION=1280x720");
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(attrs[1], ("CODECS".to_string(), "avc1.64001f,mp4a.40.2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_resolution() {
+        assert_eq!(parse_resolution("1920x1080"), Some((1920, 1080)));
+        assert_eq!(parse_resolution("bogus"), None);
+    }
+}
@@ -0,0 +1,344 @@
+//! Logo/image processing
+//!
+//! Resizes and re-encodes remote logos for TV channel grids, and draws
+//! initials-based placeholder logos for items with no (or a broken) logo.
+//! Placeholders use a built-in 5x7 bitmap font so no font files are needed.
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{ColorType, ImageEncoder, Rgba, RgbaImage};
+use std::time::Duration;
+
+use crate::services::search::fold_char;
+use crate::services::upstream::{UpstreamOutcome, UpstreamPool};
+
+/// Timeout for fetching a source image
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest source width/height accepted for decoding (logos are a few hundred px)
+const MAX_SOURCE_DIMENSION: u32 = 8192;
+/// Cap on decoder allocations, so a small crafted file can't claim gigabytes
+const MAX_DECODE_ALLOC: u64 = 128 * 1024 * 1024;
+
+/// Source image refused for good (e.g. too large), as opposed to a transient failure
+#[derive(Debug)]
+pub struct ImageRejected(&'static str);
+
+impl std::fmt::Display for ImageRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for ImageRejected {}
+
+/// Whether a logo failure will repeat on retry: 4xx responses (except timeouts and
+/// rate limits), undecodable or oversized images. Timeouts, 5xx, connection errors
+/// and open circuits are transient and must not be remembered.
+pub fn is_definitive_failure(e: &anyhow::Error) -> bool {
+    if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+        return e.status().is_some_and(|status| {
+            status.is_client_error()
+                && status != reqwest::StatusCode::REQUEST_TIMEOUT
+                && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        });
+    }
+    e.is::<image::ImageError>() || e.is::<ImageRejected>()
+}
+
+/// Output encoding for processed images
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    WebP,
+    Png,
+}
+
+impl ImageFormat {
+    /// Pick the format from an explicit `format=` value or the Accept header
+    pub fn negotiate(requested: Option<&str>, accept: Option<&str>) -> Self {
+        match requested.map(|f| f.to_lowercase()) {
+            Some(f) if f == "png" => ImageFormat::Png,
+            Some(f) if f == "webp" => ImageFormat::WebP,
+            _ => {
+                if accept.map(|a| a.contains("image/webp")).unwrap_or(false) {
+                    ImageFormat::WebP
+                } else {
+                    ImageFormat::Png
+                }
+            }
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::WebP => "image/webp",
+            ImageFormat::Png => "image/png",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::WebP => "webp",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// Fetch a source image through the shared upstream pool, rejecting bodies over max_bytes
+pub async fn fetch_image(upstream: &UpstreamPool, url: &str, max_bytes: usize) -> Result<Vec<u8>> {
+    let handle = upstream.acquire(url)?;
+    let response = match tokio::time::timeout(FETCH_TIMEOUT, handle.client.get(url).send()).await {
+        Err(_) => {
+            handle.finish(UpstreamOutcome::Timeout);
+            return Err(anyhow!("Timeout fetching {}", url));
+        }
+        Ok(Err(e)) => {
+            handle.finish(UpstreamOutcome::from_error(&e));
+            return Err(e.into());
+        }
+        Ok(Ok(response)) => {
+            handle.finish(UpstreamOutcome::from_status(response.status()));
+            response.error_for_status()?
+        }
+    };
+
+    if response.content_length().map(|len| len as usize > max_bytes).unwrap_or(false) {
+        return Err(anyhow::Error::from(ImageRejected("Image too large")));
+    }
+
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    tokio::time::timeout(FETCH_TIMEOUT, async {
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+            if bytes.len() > max_bytes {
+                return Err(anyhow::Error::from(ImageRejected("Image too large")));
+            }
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|_| anyhow!("Timeout reading {}", url))??;
+
+    Ok(bytes)
+}
+
+/// Decode, downscale to fit within (max_w, max_h) keeping aspect ratio, and encode
+pub fn resize_image(bytes: &[u8], max_w: u32, max_h: u32, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = image::io::Reader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(image::ImageError::IoError)?;
+    reader.limits(limits);
+    let img = reader.decode()?;
+
+    // Only downscale: small logos are returned at their own size
+    let img = if img.width() > max_w || img.height() > max_h {
+        img.resize(max_w, max_h, FilterType::Triangle)
+    } else {
+        img
+    };
+
+    encode(&img.to_rgba8(), format)
+}
+
+/// Draw a placeholder logo with the initials of `name`
+pub fn placeholder(name: &str, width: u32, height: u32, format: ImageFormat) -> Result<Vec<u8>> {
+    let text = initials(name);
+    let background = palette_color(name);
+    let mut img = RgbaImage::from_pixel(width, height, background);
+
+    let glyphs: Vec<[u8; 7]> = text.chars().map(glyph).collect();
+    let text_w = (glyphs.len() as u32 * 6).saturating_sub(1).max(1);
+    let scale = ((width * 6 / 10) / text_w).min((height / 2) / 7).max(1);
+
+    let origin_x = width.saturating_sub(text_w * scale) / 2;
+    let origin_y = height.saturating_sub(7 * scale) / 2;
+    let white = Rgba([255, 255, 255, 255]);
+
+    for (i, rows) in glyphs.iter().enumerate() {
+        let glyph_x = origin_x + i as u32 * 6 * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..5u32 {
+                if bits & (0x10 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = glyph_x + col * scale + dx;
+                        let y = origin_y + row as u32 * scale + dy;
+                        if x < width && y < height {
+                            img.put_pixel(x, y, white);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    encode(&img, format)
+}
+
+fn encode(img: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        ImageFormat::Png => {
+            PngEncoder::new(&mut out).write_image(img.as_raw(), img.width(), img.height(), ColorType::Rgba8)?
+        }
+        ImageFormat::WebP => {
+            WebPEncoder::new_lossless(&mut out).encode(img.as_raw(), img.width(), img.height(), ColorType::Rgba8)?
+        }
+    }
+    Ok(out)
+}
+
+/// Up to two initials: first letters of the first two words, or the first two
+/// characters of a single word. Quality tags (HD, FHD, 4K...) are ignored.
+pub fn initials(name: &str) -> String {
+    const IGNORED: &[&str] = &["HD", "FHD", "UHD", "SD", "4K", "8K", "H265", "HEVC"];
+
+    let folded: String = name.chars().map(fold_char).collect();
+    let words: Vec<String> = folded
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_uppercase())
+        .filter(|w| !IGNORED.contains(&w.as_str()))
+        .collect();
+
+    let result: String = match words.as_slice() {
+        [] => "?".to_string(),
+        [single] => single.chars().take(2).collect(),
+        [first, second, ..] => first.chars().take(1).chain(second.chars().take(1)).collect(),
+    };
+
+    result
+}
+
+/// Stable background color per name
+fn palette_color(name: &str) -> Rgba<u8> {
+    const PALETTE: [[u8; 3]; 8] = [
+        [0x1e, 0x88, 0xe5],
+        [0x43, 0xa0, 0x47],
+        [0xe5, 0x39, 0x35],
+        [0x8e, 0x24, 0xaa],
+        [0xfb, 0x8c, 0x00],
+        [0x00, 0x89, 0x7b],
+        [0x3f, 0x51, 0xb5],
+        [0x6d, 0x4c, 0x41],
+    ];
+    let sum = name.bytes().fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
+    let [r, g, b] = PALETTE[(sum % PALETTE.len() as u32) as usize];
+    Rgba([r, g, b, 255])
+}
+
+/// 5x7 bitmap glyphs (bit 4 = leftmost column)
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1e],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        _ => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // '?'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initials() {
+        assert_eq!(initials("Globo HD"), "GL");
+        assert_eq!(initials("ESPN Brasil FHD"), "EB");
+        assert_eq!(initials("Ação Filmes"), "AF");
+        assert_eq!(initials("  "), "?");
+    }
+
+    #[test]
+    fn test_placeholder_decodes_with_requested_size() {
+        let png = placeholder("Canal Teste", 120, 80, ImageFormat::Png).unwrap();
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (120, 80));
+
+        let webp = placeholder("Canal Teste", 64, 64, ImageFormat::WebP).unwrap();
+        assert_eq!(&webp[..4], b"RIFF");
+    }
+
+    #[test]
+    fn test_resize_image_only_downscales() {
+        let big = placeholder("X", 800, 400, ImageFormat::Png).unwrap();
+        let out = resize_image(&big, 200, 200, ImageFormat::Png).unwrap();
+        let img = image::load_from_memory(&out).unwrap();
+        assert_eq!((img.width(), img.height()), (200, 100));
+
+        let small = placeholder("X", 50, 50, ImageFormat::Png).unwrap();
+        let out = resize_image(&small, 200, 200, ImageFormat::Png).unwrap();
+        let img = image::load_from_memory(&out).unwrap();
+        assert_eq!((img.width(), img.height()), (50, 50));
+    }
+
+    #[test]
+    fn test_resize_rejects_oversized_source_definitively() {
+        let wide = placeholder("X", MAX_SOURCE_DIMENSION + 1, 1, ImageFormat::Png).unwrap();
+        let err = resize_image(&wide, 200, 200, ImageFormat::Png).unwrap_err();
+        assert!(is_definitive_failure(&err));
+
+        let garbage = resize_image(b"not an image", 200, 200, ImageFormat::Png).unwrap_err();
+        assert!(is_definitive_failure(&garbage));
+    }
+
+    #[test]
+    fn test_transient_failures_are_not_definitive() {
+        let open = anyhow::Error::from(crate::services::upstream::UpstreamError::CircuitOpen(
+            "cdn.example".to_string(),
+        ));
+        assert!(!is_definitive_failure(&open));
+        assert!(!is_definitive_failure(&anyhow!("Timeout fetching x")));
+        assert!(is_definitive_failure(&ImageRejected("Image too large").into()));
+    }
+
+    #[test]
+    fn test_negotiate_format() {
+        assert_eq!(ImageFormat::negotiate(Some("png"), Some("image/webp")), ImageFormat::Png);
+        assert_eq!(ImageFormat::negotiate(None, Some("image/webp,*/*")), ImageFormat::WebP);
+        assert_eq!(ImageFormat::negotiate(None, None), ImageFormat::Png);
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use crate::services::cache::{plan_evictions, EvictionCandidate};
+
+/// Index entry for a cached image
+#[derive(Debug, Clone)]
+struct LogoCacheEntry {
+    extension: String,
+    size: u64,
+    created_at: i64,
+}
+
+/// Disk cache for processed logos
+/// Files are stored as `{key}.{ext}`; eviction uses the same policy as CacheService
+/// (oldest first, by entry count and total size)
+pub struct LogoCacheService {
+    cache_dir: PathBuf,
+    /// In-memory index (rebuilt from the directory on startup)
+    index: Arc<RwLock<HashMap<String, LogoCacheEntry>>>,
+    /// Optional cap on number of cached images (oldest evicted)
+    max_entries: Option<usize>,
+    /// Optional cap on total cache size in bytes (oldest evicted)
+    max_bytes: Option<u64>,
+}
+
+impl LogoCacheService {
+    /// Create a new logo cache and index existing files
+    pub async fn new(cache_dir: &str, max_entries: Option<usize>, max_bytes: Option<u64>) -> Result<Self> {
+        let cache_dir = PathBuf::from(cache_dir);
+        fs::create_dir_all(&cache_dir).await?;
+
+        let service = Self {
+            cache_dir,
+            index: Arc::new(RwLock::new(HashMap::new())),
+            max_entries,
+            max_bytes,
+        };
+
+        service.load_index().await?;
+        service.enforce_limits().await?;
+
+        Ok(service)
+    }
+
+    /// Index image files already on disk (leftover .tmp files are removed)
+    async fn load_index(&self) -> Result<()> {
+        let mut entries = fs::read_dir(&self.cache_dir).await?;
+        let mut index = self.index.write().await;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let ext = path.extension().map(|e| e.to_string_lossy().to_string());
+
+            match ext.as_deref() {
+                Some("webp") | Some("png") => {}
+                Some("tmp") => {
+                    let _ = fs::remove_file(&path).await;
+                    continue;
+                }
+                _ => continue,
+            }
+
+            let (Some(key), Ok(metadata)) = (path.file_stem(), entry.metadata().await) else {
+                continue;
+            };
+            let created_at = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+
+            index.insert(
+                key.to_string_lossy().to_string(),
+                LogoCacheEntry {
+                    extension: ext.unwrap_or_default(),
+                    size: metadata.len(),
+                    created_at,
+                },
+            );
+        }
+
+        tracing::info!("Loaded {} cached logos", index.len());
+        Ok(())
+    }
+
+    /// Read a cached image
+    pub async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let entry = self.index.read().await.get(key)?.clone();
+        match fs::read(self.file_path(key, &entry.extension)).await {
+            Ok(bytes) => Some(bytes),
+            Err(_) => {
+                // File vanished; drop the stale index entry
+                self.index.write().await.remove(key);
+                None
+            }
+        }
+    }
+
+    /// Store an image (atomic write) and enforce limits
+    pub async fn put(&self, key: &str, extension: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.file_path(key, extension);
+        let tmp_path = self.cache_dir.join(format!("{}.{}.tmp", key, extension));
+
+        let mut file = File::create(&tmp_path).await?;
+        file.write_all(bytes).await?;
+        file.sync_all().await?;
+        drop(file);
+
+        // Atomic replace to avoid readers seeing partial writes
+        let _ = fs::remove_file(&path).await;
+        fs::rename(&tmp_path, &path).await?;
+
+        let mut index = self.index.write().await;
+        index.insert(
+            key.to_string(),
+            LogoCacheEntry {
+                extension: extension.to_string(),
+                size: bytes.len() as u64,
+                created_at: chrono::Utc::now().timestamp_millis(),
+            },
+        );
+        drop(index);
+
+        self.enforce_limits().await
+    }
+
+    async fn delete(&self, key: &str) {
+        if let Some(entry) = self.index.write().await.remove(key) {
+            let _ = fs::remove_file(self.file_path(key, &entry.extension)).await;
+        }
+    }
+
+    async fn enforce_limits(&self) -> Result<()> {
+        let candidates: Vec<EvictionCandidate> = {
+            let index = self.index.read().await;
+            index
+                .iter()
+                .map(|(k, e)| EvictionCandidate {
+                    key: k.clone(),
+                    created_at: e.created_at,
+                    size: e.size,
+                })
+                .collect()
+        };
+        let total_size = candidates.iter().map(|c| c.size).sum();
+
+        let (by_count, by_size) = plan_evictions(candidates, total_size, self.max_entries, self.max_bytes);
+        for key in by_count.iter().chain(by_size.iter()) {
+            self.delete(key).await;
+        }
+
+        if !by_count.is_empty() {
+            tracing::info!(logo_cache_evicted = by_count.len(), max_entries = self.max_entries, msg = "logo cache entries evicted by LRU");
+        }
+        if !by_size.is_empty() {
+            tracing::info!(logo_cache_evicted = by_size.len(), max_bytes = self.max_bytes, msg = "logo cache entries evicted by size");
+        }
+
+        Ok(())
+    }
+
+    /// Total size of cached images in bytes (from the index)
+    pub async fn get_cache_size(&self) -> u64 {
+        self.index.read().await.values().map(|e| e.size).sum()
+    }
+
+    fn file_path(&self, key: &str, extension: &str) -> PathBuf {
+        self.cache_dir.join(format!("{}.{}", key, extension))
+    }
+}
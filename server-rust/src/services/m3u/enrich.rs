@@ -0,0 +1,554 @@
+//! Enrichment of raw M3U entries into playlist items
+//!
+//! Deduplicates by URL, normalizes names and groups, classifies content and
+//! accumulates the groups, series and stats that are saved with the items.
+
+use sha1::{Digest, Sha1};
+use std::collections::{HashMap, HashSet};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::models::{
+    MediaKind, ParsedTitle, PlaylistGroup, PlaylistItem, PlaylistStats, SeasonData,
+    SeriesEpisode, SeriesInfo,
+};
+use crate::services::classifier::ContentClassifier;
+
+use super::reader::M3UEntry;
+
+lazy_static! {
+    /// Regex to normalize multiple whitespaces into single space
+    static ref MULTI_SPACE_REGEX: Regex = Regex::new(r"\s{2,}").unwrap();
+}
+
+/// Series Run for RLE (Run-Length Encoding) optimization
+/// Accumulates consecutive episodes of the same series
+#[derive(Debug)]
+struct SeriesRun {
+    series_key: String,
+    series_name: String,
+    group: String,
+    logo: Option<String>,
+    year: Option<u16>,
+    quality: Option<String>,
+    episodes: Vec<SeriesRunEpisode>,
+}
+
+#[derive(Debug, Clone)]
+struct SeriesRunEpisode {
+    item_id: String,
+    name: String,
+    season: u8,
+    episode: u16,
+    episode_end: Option<u16>,
+    air_date: Option<chrono::NaiveDate>,
+    url: String,
+}
+
+/// Accumulated series data during parsing
+#[derive(Debug)]
+struct SeriesAccumulator {
+    id: String,
+    name: String,
+    group: String,
+    logo: Option<String>,
+    year: Option<u16>,
+    quality: Option<String>,
+    episodes: Vec<SeriesRunEpisode>,
+}
+
+/// Generate SHA1 hash of URL for cache key
+pub fn hash_url(url: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(url.as_bytes());
+    let result = hasher.finalize();
+    format!("{:x}", result)
+}
+
+/// Generate a unique item ID based on URL and index
+fn generate_item_id(url: &str, index: usize) -> String {
+    let hash: i32 = url.chars().fold(0, |acc, c| {
+        ((acc << 5).wrapping_sub(acc)).wrapping_add(c as i32)
+    });
+    format!("item_{}_{}", hash.unsigned_abs(), index)
+}
+
+/// Stable content identity (survives reordering, refreshes and URL token rotation)
+///
+/// Episodes are identified by show + season + episode, live channels by tvg-id
+/// (or cleaned name), everything else by cleaned title + year.
+pub fn content_id(
+    media_kind: MediaKind,
+    name: &str,
+    parsed_title: &ParsedTitle,
+    tvg_id: Option<&str>,
+    episode: Option<(&str, u8, u16)>,
+) -> String {
+    let tvg_id = tvg_id.map(str::trim).filter(|id| !id.is_empty());
+    let key = match (media_kind, episode, tvg_id) {
+        (_, Some((series_id, season, episode)), _) => {
+            format!("episode|{}|{}|{}", series_id, season, episode)
+        }
+        (MediaKind::Live, None, Some(tvg_id)) => format!("live|tvg|{}", tvg_id.to_lowercase()),
+        (MediaKind::Live, None, None) => format!("live|{}", ContentClassifier::series_key(name)),
+        (kind, None, _) => format!(
+            "{}|{}|{}",
+            kind,
+            ContentClassifier::series_key(&parsed_title.title),
+            parsed_title.year.map(|y| y.to_string()).unwrap_or_default()
+        ),
+    };
+    format!("c_{}", hash_url(&key))
+}
+
+/// Normalize text: trim and collapse multiple spaces into single space
+/// Like the JS parser's normalize option
+fn normalize_text(text: &str) -> String {
+    let trimmed = text.trim();
+    MULTI_SPACE_REGEX.replace_all(trimmed, " ").to_string()
+}
+
+/// Generate URL hash for deduplication (shorter than full SHA1)
+fn url_dedup_hash(url: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    url.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Flush a series run to the accumulator
+/// This merges episodes from consecutive runs of the same series
+fn flush_run_to_accumulator(
+    accum: &mut HashMap<String, SeriesAccumulator>,
+    run: SeriesRun,
+) {
+    if run.episodes.is_empty() {
+        return;
+    }
+
+    let series_id = format!("series_{}", hash_url(&run.series_key));
+
+    let entry = accum.entry(run.series_key.clone()).or_insert_with(|| SeriesAccumulator {
+        id: series_id,
+        name: run.series_name.clone(),
+        group: run.group.clone(),
+        logo: run.logo.clone(),
+        year: run.year,
+        quality: run.quality.clone(),
+        episodes: Vec::new(),
+    });
+
+    // Merge episodes from this run
+    entry.episodes.extend(run.episodes);
+}
+
+/// Build SeriesInfo from accumulator with episodes sorted by season/episode
+fn build_series_info(accum: SeriesAccumulator) -> SeriesInfo {
+    let mut episodes = accum.episodes;
+
+    // Sort episodes by season, then by episode
+    episodes.sort_by(|a, b| {
+        match a.season.cmp(&b.season) {
+            std::cmp::Ordering::Equal => a.episode.cmp(&b.episode),
+            other => other,
+        }
+    });
+
+    // The same episode listed in several groups: keep the first (sort is stable)
+    episodes.dedup_by_key(|e| (e.season, e.episode));
+
+    // Group episodes by season
+    let mut seasons_map: HashMap<u8, Vec<SeriesEpisode>> = HashMap::new();
+    for ep in &episodes {
+        seasons_map
+            .entry(ep.season)
+            .or_default()
+            .push(SeriesEpisode {
+                item_id: ep.item_id.clone(),
+                season: ep.season,
+                episode: ep.episode,
+                episode_end: ep.episode_end,
+                air_date: ep.air_date,
+                name: ep.name.clone(),
+                url: ep.url.clone(),
+            });
+    }
+
+    // Convert to sorted SeasonData
+    let mut seasons_data: Vec<SeasonData> = seasons_map
+        .into_iter()
+        .map(|(season_num, mut eps)| {
+            // Sort episodes within season
+            eps.sort_by_key(|e| e.episode);
+            SeasonData {
+                season_number: season_num,
+                episodes: eps,
+            }
+        })
+        .collect();
+
+    // Sort seasons
+    seasons_data.sort_by_key(|s| s.season_number);
+
+    // Calculate stats
+    let total_episodes = episodes.len();
+    let total_seasons = seasons_data.len();
+    let first_season = seasons_data.first().map(|s| s.season_number as u16).unwrap_or(0);
+    let last_season = seasons_data.last().map(|s| s.season_number as u16).unwrap_or(0);
+
+    SeriesInfo {
+        id: accum.id,
+        name: accum.name,
+        logo: accum.logo,
+        group: accum.group,
+        total_episodes,
+        total_seasons,
+        first_season,
+        last_season,
+        year: accum.year,
+        quality: accum.quality,
+        seasons_data: Some(seasons_data),
+    }
+}
+
+/// Everything saved alongside the items of a parsed playlist
+#[derive(Debug, Clone, Default)]
+pub struct ParsedPlaylist {
+    pub stats: PlaylistStats,
+    pub groups: Vec<PlaylistGroup>,
+    pub series: Vec<SeriesInfo>,
+}
+
+/// Turns entries into classified playlist items, in playlist order
+#[derive(Default)]
+pub struct Enricher {
+    item_index: usize,
+    // ✅ DEDUPLICATION: hashes of URLs already seen
+    seen_urls: HashSet<u64>,
+    duplicates_skipped: usize,
+    stats: PlaylistStats,
+    groups: HashMap<String, (MediaKind, usize, Option<String>)>,
+    // Series accumulator for RLE grouping, plus the current run
+    series_accum: HashMap<String, SeriesAccumulator>,
+    current_run: Option<SeriesRun>,
+}
+
+impl Enricher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Items produced so far
+    pub fn items(&self) -> usize {
+        self.item_index
+    }
+
+    pub fn duplicates_skipped(&self) -> usize {
+        self.duplicates_skipped
+    }
+
+    pub fn groups_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Stats of the items so far (`group_count` is set by `build_groups`)
+    pub fn stats(&self) -> &PlaylistStats {
+        &self.stats
+    }
+
+    /// Classify an entry; None for duplicate and non-HTTP URLs
+    pub fn enrich(&mut self, entry: M3UEntry) -> Option<PlaylistItem> {
+        if !entry.url.starts_with("http") {
+            return None;
+        }
+        let stream_url = entry.url.clone();
+
+        // ✅ DEDUPLICATION: Skip duplicate URLs
+        if !self.seen_urls.insert(url_dedup_hash(&stream_url)) {
+            self.duplicates_skipped += 1;
+            return None;
+        }
+
+        // ✅ NORMALIZATION: Normalize title and group
+        let name = normalize_text(&entry.title);
+        let group_title = normalize_text(
+            entry
+                .attribute("group-title")
+                .filter(|g| !g.trim().is_empty())
+                .unwrap_or("Sem Grupo"),
+        );
+
+        // Extract metadata from attributes
+        let tvg_id = entry.attribute("tvg-id").map(str::to_string);
+        let tvg_logo = entry.attribute("tvg-logo").map(str::to_string);
+
+        // Classify content
+        let media_kind = ContentClassifier::classify(&name, &group_title);
+        let parsed_title = ContentClassifier::parse_title(&name);
+        let item_id = generate_item_id(&stream_url, self.item_index);
+
+        // Extract series info for series items
+        let series_info = if media_kind == MediaKind::Series {
+            ContentClassifier::extract_series_info(&name, &group_title)
+        } else {
+            None
+        };
+
+        // Generate series ID and track episodes
+        let (series_id, season_number, episode_number) = if let Some(info) = series_info {
+            // Keyed by show and audio variant: the same show merges across
+            // groups, while dubbed and subtitled copies stay separate series
+            let series_key = match ContentClassifier::audio_variant(&name, &group_title) {
+                Some(variant) => format!("{}|{}", ContentClassifier::series_key(&info.series_name), variant),
+                None => ContentClassifier::series_key(&info.series_name),
+            };
+            let series_db_id = format!("series_{}", hash_url(&series_key));
+
+            // RLE: Check if this episode belongs to current run
+            let is_same_run = self
+                .current_run
+                .as_ref()
+                .is_some_and(|run| run.series_key == series_key);
+
+            if !is_same_run {
+                // Flush current run to accumulator
+                if let Some(run) = self.current_run.take() {
+                    flush_run_to_accumulator(&mut self.series_accum, run);
+                }
+
+                // Start new run
+                self.current_run = Some(SeriesRun {
+                    series_key,
+                    series_name: info.series_name.clone(),
+                    group: group_title.clone(),
+                    logo: tvg_logo.clone(),
+                    year: parsed_title.year,
+                    quality: parsed_title.quality.clone(),
+                    episodes: Vec::new(),
+                });
+            }
+
+            // Add episode to current run
+            if let Some(run) = &mut self.current_run {
+                run.episodes.push(SeriesRunEpisode {
+                    item_id: item_id.clone(),
+                    name: name.clone(),
+                    season: info.season,
+                    episode: info.episode,
+                    episode_end: info.episode_end,
+                    air_date: info.air_date,
+                    url: stream_url.clone(),
+                });
+            }
+
+            (Some(series_db_id), Some(info.season), Some(info.episode))
+        } else {
+            // Not a series - flush current run if any
+            if let Some(run) = self.current_run.take() {
+                flush_run_to_accumulator(&mut self.series_accum, run);
+            }
+            (None, None, None)
+        };
+
+        // Update stats
+        self.stats.total_items += 1;
+        match media_kind {
+            MediaKind::Live => self.stats.live_count += 1,
+            MediaKind::Movie => self.stats.movie_count += 1,
+            MediaKind::Series => self.stats.series_count += 1,
+            MediaKind::Unknown => self.stats.unknown_count += 1,
+        }
+
+        // Update groups
+        let group_entry = self
+            .groups
+            .entry(group_title.clone())
+            .or_insert((media_kind, 0, tvg_logo.clone()));
+        group_entry.1 += 1;
+
+        // Create item with season/episode numbers
+        let episode = series_id
+            .as_deref()
+            .zip(season_number)
+            .zip(episode_number)
+            .map(|((series, season), episode)| (series, season, episode));
+        let content_id = content_id(media_kind, &name, &parsed_title, tvg_id.as_deref(), episode);
+        self.item_index += 1;
+
+        Some(PlaylistItem {
+            id: item_id,
+            content_id,
+            name,
+            url: stream_url,
+            logo: tvg_logo,
+            group: group_title,
+            media_kind,
+            parsed_title: Some(parsed_title),
+            epg_id: tvg_id,
+            series_id,
+            season_number,
+            episode_number,
+        })
+    }
+
+    /// Groups of all items so far (call once, after the last entry)
+    pub fn build_groups(&mut self) -> Vec<PlaylistGroup> {
+        let groups: Vec<PlaylistGroup> = std::mem::take(&mut self.groups)
+            .into_iter()
+            .map(|(name, (media_kind, count, logo))| PlaylistGroup {
+                id: format!("group_{}", hash_url(&name)),
+                name,
+                media_kind,
+                item_count: count,
+                logo,
+            })
+            .collect();
+
+        self.stats.group_count = groups.len();
+        groups
+    }
+
+    /// Series with sorted episodes (call once, after the last entry)
+    pub fn build_series(&mut self) -> Vec<SeriesInfo> {
+        // Flush final run if any
+        if let Some(run) = self.current_run.take() {
+            flush_run_to_accumulator(&mut self.series_accum, run);
+        }
+
+        std::mem::take(&mut self.series_accum)
+            .into_values()
+            .map(build_series_info)
+            .collect()
+    }
+
+    /// Groups, series and stats of everything enriched
+    pub fn finish(mut self) -> ParsedPlaylist {
+        let groups = self.build_groups();
+        let series = self.build_series();
+        ParsedPlaylist {
+            stats: self.stats,
+            groups,
+            series,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, group: &str, url: &str) -> M3UEntry {
+        M3UEntry {
+            duration: -1,
+            title: title.to_string(),
+            attributes: HashMap::from([("group-title".to_string(), group.to_string())]),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hash_url() {
+        let hash = hash_url("http://example.com/playlist.m3u");
+        assert!(!hash.is_empty());
+        assert_eq!(hash.len(), 40); // SHA1 produces 40 hex chars
+    }
+
+    #[test]
+    fn test_generate_item_id() {
+        let id1 = generate_item_id("http://stream1.com", 0);
+        let id2 = generate_item_id("http://stream2.com", 0);
+        assert_ne!(id1, id2);
+        assert!(id1.starts_with("item_"));
+    }
+
+    #[test]
+    fn test_content_id_is_stable() {
+        let title = |name: &str| ContentClassifier::parse_title(name);
+
+        // Versions of the same movie share an identity; other movies don't
+        let movie = content_id(MediaKind::Movie, "Flow (2024) 4K", &title("Flow (2024) 4K"), None, None);
+        let dubbed = content_id(MediaKind::Movie, "Flow (2024) Dublado", &title("Flow (2024) Dublado"), None, None);
+        let remake = content_id(MediaKind::Movie, "Flow (1999)", &title("Flow (1999)"), None, None);
+        assert_eq!(movie, dubbed);
+        assert_ne!(movie, remake);
+        assert!(movie.starts_with("c_"));
+
+        // Episodes by show/season/episode, live channels by tvg-id
+        let ep = |e| content_id(MediaKind::Series, "x", &title("x"), None, Some(("series_a", 1, e)));
+        assert_ne!(ep(1), ep(2));
+        assert_eq!(ep(1), ep(1));
+        let globo = content_id(MediaKind::Live, "Globo HD", &title("Globo HD"), Some("globo.br"), None);
+        let globo_fhd = content_id(MediaKind::Live, "Globo FHD", &title("Globo FHD"), Some("GLOBO.BR"), None);
+        assert_eq!(globo, globo_fhd);
+    }
+
+    #[test]
+    fn test_enricher_dedup_and_series() {
+        let mut enricher = Enricher::new();
+        let items: Vec<PlaylistItem> = [
+            entry("Dark  S01E02", "Séries | Netflix", "http://a/dark-102.mp4"),
+            entry("Dark S01E01", "Séries | Netflix", "http://a/dark-101.mp4"),
+            entry("Dark S01E01 (copy)", "Séries | Netflix", "http://a/dark-101.mp4"),
+            entry("Canal", "TV", "rtmp://a/canal"),
+            entry("Dark S02E01", "Séries | Netflix", "http://a/dark-201.mp4"),
+        ]
+        .into_iter()
+        .filter_map(|e| enricher.enrich(e))
+        .collect();
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(enricher.duplicates_skipped(), 1);
+        assert_eq!(items[0].name, "Dark S01E02");
+        assert!(items.iter().all(|i| i.series_id == items[0].series_id));
+
+        let playlist = enricher.finish();
+        assert_eq!(playlist.stats.total_items, 3);
+        assert_eq!(playlist.stats.group_count, 1);
+        assert_eq!(playlist.series.len(), 1);
+        let series = &playlist.series[0];
+        assert_eq!((series.total_episodes, series.total_seasons), (3, 2));
+        let first_season = &series.seasons_data.as_ref().unwrap()[0];
+        assert_eq!(first_season.episodes[0].episode, 1);
+    }
+
+    #[test]
+    fn test_enricher_keeps_audio_variants_apart() {
+        let mut enricher = Enricher::new();
+        let items: Vec<PlaylistItem> = [
+            entry("Dark S01E01", "Séries Dubladas", "http://a/dub-101.mp4"),
+            entry("Dark S01E01", "Séries Legendadas", "http://a/leg-101.mp4"),
+            entry("Dark S01E01", "Netflix Dubladas | Séries", "http://b/dub-101.mp4"),
+        ]
+        .into_iter()
+        .filter_map(|e| enricher.enrich(e))
+        .collect();
+
+        assert_ne!(items[0].series_id, items[1].series_id);
+        assert_eq!(items[0].series_id, items[2].series_id);
+
+        let playlist = enricher.finish();
+        assert_eq!(playlist.series.len(), 2);
+        // The dubbed episode listed in two groups is kept once
+        assert!(playlist.series.iter().all(|s| s.total_episodes == 1));
+    }
+
+    #[test]
+    fn test_enricher_group_default() {
+        let mut enricher = Enricher::new();
+        let mut extgrp = entry("Globo", "", "http://a/1.ts");
+        extgrp.directives.push("#EXTGRP:Abertos".to_string());
+        let blank = entry("SBT", "   ", "http://a/2.ts");
+        let plain = M3UEntry {
+            title: "Record".to_string(),
+            url: "http://a/3.ts".to_string(),
+            ..Default::default()
+        };
+
+        // An empty or blank group-title counts as missing; #EXTGRP isn't a group source
+        assert_eq!(enricher.enrich(extgrp).unwrap().group, "Sem Grupo");
+        assert_eq!(enricher.enrich(blank).unwrap().group, "Sem Grupo");
+        assert_eq!(enricher.enrich(plain).unwrap().group, "Sem Grupo");
+    }
+}
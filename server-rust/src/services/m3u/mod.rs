@@ -0,0 +1,131 @@
+//! Streaming M3U parsing, independent of HTTP and storage
+//!
+//! Parsing runs in three stages:
+//!
+//! - **Reading**: `M3UReader` turns any `AsyncBufRead` into `M3UEntry`s
+//!   (title, `#EXTINF` attributes, directives, URL), also as a `Stream`
+//! - **Enrichment**: `Enricher` deduplicates, normalizes and classifies
+//!   entries into `PlaylistItem`s and builds groups, series and stats
+//! - **Sinks**: a `PlaylistSink` stores the result (`PostgresSink`,
+//!   `NdjsonSink`; tests collect into `sink::MemorySink`)
+//!
+//! `M3UParser` wires these to HTTP downloads and progress reporting;
+//! `parse_into` runs them directly, e.g. on a file
+//! (`ativeplay-server parse playlist.m3u` prints the items as NDJSON).
+//!
+//! # Usage
+//!
+//! ```rust,ignore
+//! use crate::services::m3u::{parse_into, NdjsonSink};
+//!
+//! let file = tokio::fs::File::open("playlist.m3u").await?;
+//! let mut sink = NdjsonSink::new(tokio::io::stdout());
+//! let playlist = parse_into(tokio::io::BufReader::new(file), &mut sink).await?;
+//! eprintln!("{} items in {} groups", playlist.stats.total_items, playlist.groups.len());
+//! ```
+
+pub mod enrich;
+pub mod reader;
+pub mod sink;
+
+pub use enrich::{hash_url, Enricher, ParsedPlaylist};
+pub use reader::{M3UReader, ReadStep};
+pub use sink::{NdjsonSink, PlaylistSink, PostgresSink};
+
+use anyhow::{Context, Result};
+use futures::TryStreamExt;
+use tokio::io::{AsyncBufRead, BufReader};
+
+/// Read, enrich and store a whole playlist
+pub async fn parse_into<R, S>(input: R, sink: &mut S) -> Result<ParsedPlaylist>
+where
+    R: AsyncBufRead + Unpin,
+    S: PlaylistSink + ?Sized,
+{
+    let entries = M3UReader::new(input).into_stream();
+    futures::pin_mut!(entries);
+    let mut enricher = Enricher::new();
+
+    while let Some(entry) = entries.try_next().await? {
+        if let Some(item) = enricher.enrich(entry) {
+            sink.write_item(&item).await?;
+        }
+    }
+
+    let playlist = enricher.finish();
+    sink.finish(&playlist).await?;
+    Ok(playlist)
+}
+
+/// Parse a local playlist file, printing items as NDJSON on stdout
+pub async fn print_ndjson(path: &str) -> Result<()> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("Failed to open {}", path))?;
+
+    let mut sink = NdjsonSink::new(tokio::io::stdout());
+    let playlist = parse_into(BufReader::new(file), &mut sink).await?;
+
+    let stats = &playlist.stats;
+    eprintln!(
+        "{} items ({} live, {} movies, {} episodes, {} unknown), {} groups, {} series",
+        stats.total_items,
+        stats.live_count,
+        stats.movie_count,
+        stats.series_count,
+        stats.unknown_count,
+        stats.group_count,
+        playlist.series.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::sink::MemorySink;
+    use crate::models::PlaylistItem;
+
+    const PLAYLIST: &str = r#"#EXTM3U
+#EXTINF:-1 tvg-id="globo.br" tvg-logo="http://logo/globo.png" group-title="Canais | Abertos",Globo HD
+http://example.com/live/1.ts
+#EXTINF:-1 group-title="Filmes | Ação",Duna (2021) 4K
+http://example.com/movie/2.mp4
+#EXTINF:-1 group-title="Filmes | Ação",Duna (2021) 4K
+http://example.com/movie/2.mp4
+"#;
+
+    #[tokio::test]
+    async fn test_parse_into_memory() {
+        let mut sink = MemorySink::default();
+        let playlist = parse_into(PLAYLIST.as_bytes(), &mut sink).await.unwrap();
+
+        assert_eq!(sink.items.len(), 2);
+        assert_eq!(sink.items[0].epg_id.as_deref(), Some("globo.br"));
+        assert_eq!(playlist.stats.total_items, 2);
+        assert_eq!(playlist.groups.len(), 2);
+        assert_eq!(sink.playlist.unwrap().stats.total_items, 2);
+    }
+
+    #[tokio::test]
+    async fn test_parse_into_ndjson() {
+        let mut output = Vec::new();
+        parse_into(PLAYLIST.as_bytes(), &mut NdjsonSink::new(&mut output)).await.unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let items: Vec<PlaylistItem> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].url, "http://example.com/movie/2.mp4");
+    }
+
+    #[tokio::test]
+    async fn test_parse_into_rejects_missing_header() {
+        let mut sink = MemorySink::default();
+        let input = PLAYLIST.trim_start_matches("#EXTM3U\n");
+        assert!(parse_into(input.as_bytes(), &mut sink).await.is_err());
+        assert!(sink.playlist.is_none());
+    }
+}
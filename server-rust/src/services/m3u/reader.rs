@@ -0,0 +1,314 @@
+//! Streaming M3U reader
+//!
+//! Turns any buffered async source into parsed entries, one `#EXTINF` +
+//! URL pair at a time, without classifying or storing anything.
+
+use anyhow::{anyhow, bail, Result};
+use async_stream::try_stream;
+use futures::Stream;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+// Defensive limits for streamed parsing
+const MAX_LINE_BYTES: usize = 32 * 1024; // protect against maliciously long lines
+const READ_LINE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Directive lines kept per entry (the rest are dropped)
+const MAX_DIRECTIVES: usize = 32;
+
+lazy_static! {
+    /// Regex to parse EXTINF attributes (tvg-id="...", group-title="...", etc)
+    static ref ATTR_REGEX: Regex = Regex::new(r#"(\w+(?:-\w+)*)="([^"]*)""#).unwrap();
+
+    /// Regex to extract duration from EXTINF line
+    static ref DURATION_REGEX: Regex = Regex::new(r"^-?\d+").unwrap();
+}
+
+/// Attributes of the `#EXTM3U` line (url-tvg, x-tvg-url, ...)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct M3UHeader {
+    pub attributes: HashMap<String, String>,
+}
+
+/// One playlist entry as written in the file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct M3UEntry {
+    /// `#EXTINF` duration (-1 for live streams)
+    pub duration: i32,
+    /// Title after the first comma (trimmed, inner spaces kept)
+    pub title: String,
+    /// `key="value"` attributes of the `#EXTINF` line
+    pub attributes: HashMap<String, String>,
+    /// Other `#` lines since the previous entry (`#EXTGRP:...`, `#EXTVLCOPT:...`)
+    pub directives: Vec<String>,
+    pub url: String,
+    /// 1-based line number of the URL
+    pub line: usize,
+}
+
+impl M3UEntry {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Value of a directive, e.g. `directive("EXTGRP")` for `#EXTGRP:News`
+    #[cfg(test)]
+    pub fn directive(&self, name: &str) -> Option<&str> {
+        self.directives.iter().find_map(|d| {
+            d.strip_prefix('#')?
+                .strip_prefix(name)?
+                .strip_prefix(':')
+                .map(str::trim)
+        })
+    }
+}
+
+/// Parsed `#EXTINF` line
+#[derive(Debug, Default)]
+struct Extinf {
+    duration: i32,
+    attributes: HashMap<String, String>,
+    title: String,
+}
+
+/// Parse the `key="value"` attributes of a header
+fn parse_attributes(header: &str) -> HashMap<String, String> {
+    ATTR_REGEX
+        .captures_iter(header)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+/// Parse an EXTINF line
+/// Format: #EXTINF:duration tvg-id="..." tvg-name="..." tvg-logo="..." group-title="...",Title
+fn parse_extinf(line: &str) -> Option<Extinf> {
+    let content = line.strip_prefix("#EXTINF:")?;
+
+    // Find first comma separating header from title
+    let first_comma = content.find(',')?;
+
+    let header = &content[..first_comma];
+    let title = content[first_comma + 1..].trim().to_string();
+
+    // Parse duration
+    let duration = DURATION_REGEX
+        .find(header)
+        .and_then(|m| m.as_str().parse().ok())
+        .unwrap_or(-1);
+
+    Some(Extinf {
+        duration,
+        attributes: parse_attributes(header),
+        title,
+    })
+}
+
+/// Result of reading one line
+#[derive(Debug)]
+pub enum ReadStep {
+    /// The line completed an entry
+    Entry(M3UEntry),
+    /// The line was consumed without completing an entry
+    Line,
+    /// End of the playlist
+    End,
+}
+
+/// Reads entries from an M3U/M3U8 playlist
+///
+/// URL lines without a preceding (valid) `#EXTINF` are skipped. The
+/// `#EXTM3U` header may appear anywhere before the end, but a playlist
+/// without one is rejected once the input is exhausted.
+pub struct M3UReader<R> {
+    input: R,
+    line: String,
+    line_number: usize,
+    bytes_read: u64,
+    header: Option<M3UHeader>,
+    extinf: Option<Extinf>,
+    directives: Vec<String>,
+}
+
+impl<R: AsyncBufRead + Unpin> M3UReader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            line: String::new(),
+            line_number: 0,
+            bytes_read: 0,
+            header: None,
+            extinf: None,
+            directives: Vec::new(),
+        }
+    }
+
+    /// Header attributes, once the `#EXTM3U` line has been read
+    #[cfg(test)]
+    pub fn header(&self) -> Option<&M3UHeader> {
+        self.header.as_ref()
+    }
+
+    /// Bytes consumed from the input so far
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Next entry, or None at the end of the playlist
+    pub async fn next_entry(&mut self) -> Result<Option<M3UEntry>> {
+        loop {
+            match self.read_line().await? {
+                ReadStep::Entry(entry) => return Ok(Some(entry)),
+                ReadStep::Line => continue,
+                ReadStep::End => return Ok(None),
+            }
+        }
+    }
+
+    /// Read one line, returning the entry it completes (if any)
+    ///
+    /// Lets callers do periodic work between lines, which `next_entry` hides.
+    pub async fn read_line(&mut self) -> Result<ReadStep> {
+        self.line.clear();
+        let read = tokio::time::timeout(READ_LINE_TIMEOUT, self.input.read_line(&mut self.line))
+            .await
+            .map_err(|_| anyhow!("Timed out while reading playlist line"))??;
+
+        if read == 0 {
+            if self.header.is_none() {
+                bail!("Invalid playlist format (missing #EXTM3U header)");
+            }
+            return Ok(ReadStep::End);
+        }
+        self.bytes_read += read as u64;
+        self.line_number += 1;
+
+        if self.line.len() > MAX_LINE_BYTES {
+            bail!("Playlist line exceeds max length of {} bytes", MAX_LINE_BYTES);
+        }
+
+        let trimmed = self.line.trim().trim_start_matches('\u{feff}');
+
+        if trimmed.is_empty() {
+            return Ok(ReadStep::Line);
+        }
+
+        // Check M3U header
+        if let Some(attributes) = trimmed.strip_prefix("#EXTM3U") {
+            self.header = Some(M3UHeader {
+                attributes: parse_attributes(attributes),
+            });
+            return Ok(ReadStep::Line);
+        }
+
+        // Parse EXTINF (a malformed one drops the entry)
+        if trimmed.starts_with("#EXTINF:") {
+            self.extinf = parse_extinf(trimmed);
+            return Ok(ReadStep::Line);
+        }
+
+        // Keep other directives for the next entry
+        if trimmed.starts_with('#') {
+            if self.directives.len() < MAX_DIRECTIVES {
+                self.directives.push(trimmed.to_string());
+            }
+            return Ok(ReadStep::Line);
+        }
+
+        // Stream URL line
+        let directives = std::mem::take(&mut self.directives);
+        match self.extinf.take() {
+            Some(extinf) => Ok(ReadStep::Entry(M3UEntry {
+                duration: extinf.duration,
+                title: extinf.title,
+                attributes: extinf.attributes,
+                directives,
+                url: trimmed.to_string(),
+                line: self.line_number,
+            })),
+            None => Ok(ReadStep::Line),
+        }
+    }
+
+    /// All remaining entries as a stream (ends after the first error)
+    pub fn into_stream(mut self) -> impl Stream<Item = Result<M3UEntry>> {
+        try_stream! {
+            while let Some(entry) = self.next_entry().await? {
+                yield entry;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+
+    #[test]
+    fn test_parse_extinf() {
+        let line = r#"#EXTINF:-1 tvg-id="globo" tvg-name="Globo HD" tvg-logo="http://logo.com/globo.png" group-title="TV",Globo HD"#;
+        let extinf = parse_extinf(line).unwrap();
+
+        assert_eq!(extinf.title, "Globo HD");
+        assert_eq!(extinf.duration, -1);
+        assert_eq!(extinf.attributes.get("tvg-id"), Some(&"globo".to_string()));
+        assert_eq!(extinf.attributes.get("group-title"), Some(&"TV".to_string()));
+    }
+
+    #[test]
+    fn test_parse_extinf_minimal() {
+        let line = "#EXTINF:-1,Canal Teste";
+        let extinf = parse_extinf(line).unwrap();
+
+        assert_eq!(extinf.title, "Canal Teste");
+        assert_eq!(extinf.duration, -1);
+        assert!(extinf.attributes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reader_entries() {
+        let playlist = "\u{feff}#EXTM3U url-tvg=\"http://epg.example/guide.xml\"\n\
+            #EXTINF:-1 tvg-id=\"globo\" group-title=\"TV\",Globo HD\n\
+            #EXTVLCOPT:http-user-agent=VLC\n\
+            \n\
+            http://example.com/globo.ts\n\
+            http://example.com/orphan.ts\n\
+            #EXTINF:broken\n\
+            http://example.com/broken.ts\n\
+            #EXTINF:120,Filme\r\n\
+            #EXTGRP:Filmes\r\n\
+            http://example.com/filme.mp4\r\n";
+
+        let mut reader = M3UReader::new(playlist.as_bytes());
+        let first = reader.next_entry().await.unwrap().unwrap();
+        assert_eq!(
+            reader.header().unwrap().attributes.get("url-tvg").map(String::as_str),
+            Some("http://epg.example/guide.xml")
+        );
+        assert_eq!(first.title, "Globo HD");
+        assert_eq!(first.attribute("group-title"), Some("TV"));
+        assert_eq!(first.directive("EXTVLCOPT"), Some("http-user-agent=VLC"));
+        assert_eq!(first.url, "http://example.com/globo.ts");
+        assert_eq!(first.line, 5);
+
+        let entries: Vec<M3UEntry> = reader.into_stream().try_collect().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].duration, 120);
+        assert_eq!(entries[0].directive("EXTGRP"), Some("Filmes"));
+        assert_eq!(entries[0].url, "http://example.com/filme.mp4");
+    }
+
+    #[tokio::test]
+    async fn test_reader_rejects_invalid_input() {
+        let mut reader = M3UReader::new("#EXTINF:-1,Canal\nhttp://a/1.ts\n".as_bytes());
+        // Entries are read, but the end of a headerless playlist is an error
+        assert!(reader.next_entry().await.unwrap().is_some());
+        assert!(reader.next_entry().await.is_err());
+
+        let long = format!("#EXTM3U\n#EXTINF:-1,{}\n", "x".repeat(MAX_LINE_BYTES));
+        let mut reader = M3UReader::new(long.as_bytes());
+        assert!(reader.next_entry().await.is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Destinations for enriched playlist items
+//!
+//! Items are written one at a time as they are parsed; the groups, series
+//! and stats follow once at the end.
+
+use anyhow::{anyhow, Context, Result};
+use futures::future::BoxFuture;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use uuid::Uuid;
+
+use crate::db::repository::StreamingDbWriter;
+use crate::models::PlaylistItem;
+use crate::services::db_cache::DbCacheService;
+
+use super::enrich::ParsedPlaylist;
+
+/// Where parsed items go
+pub trait PlaylistSink: Send {
+    fn write_item<'a>(&'a mut self, item: &'a PlaylistItem) -> BoxFuture<'a, Result<()>>;
+
+    /// Store groups, series and stats after the last item; returns items written
+    fn finish<'a>(&'a mut self, playlist: &'a ParsedPlaylist) -> BoxFuture<'a, Result<usize>>;
+}
+
+/// Streams items into Postgres (COPY in one transaction) for a playlist record
+///
+/// Nothing is visible until `finish`; dropping the sink rolls the items back.
+pub struct PostgresSink {
+    db_cache: DbCacheService,
+    hash: String,
+    playlist_id: Uuid,
+    writer: Option<StreamingDbWriter<'static>>,
+}
+
+impl PostgresSink {
+    pub async fn new(db_cache: DbCacheService, hash: &str, playlist_id: Uuid) -> Result<Self> {
+        let writer = db_cache
+            .create_streaming_writer(playlist_id)
+            .await
+            .context("Failed to create streaming writer")?;
+
+        Ok(Self {
+            db_cache,
+            hash: hash.to_string(),
+            playlist_id,
+            writer: Some(writer),
+        })
+    }
+
+    fn writer(&mut self) -> Result<&mut StreamingDbWriter<'static>> {
+        self.writer.as_mut().ok_or_else(|| anyhow!("Playlist sink already finished"))
+    }
+}
+
+impl PlaylistSink for PostgresSink {
+    fn write_item<'a>(&'a mut self, item: &'a PlaylistItem) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { Ok(self.writer()?.write_item(item).await?) })
+    }
+
+    fn finish<'a>(&'a mut self, playlist: &'a ParsedPlaylist) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            let writer = self.writer.take().ok_or_else(|| anyhow!("Playlist sink already finished"))?;
+            let items_written = writer.finish().await
+                .context("Failed to finish writing items")?;
+
+            self.db_cache.save_groups(self.playlist_id, &playlist.groups).await
+                .context("Failed to save groups")?;
+
+            self.db_cache.save_series(self.playlist_id, &playlist.series).await
+                .context("Failed to save series")?;
+
+            self.db_cache.update_stats(&self.hash, &playlist.stats).await
+                .context("Failed to update stats")?;
+
+            Ok(items_written)
+        })
+    }
+}
+
+/// Keeps everything in memory (tests)
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    pub items: Vec<PlaylistItem>,
+    pub playlist: Option<ParsedPlaylist>,
+}
+
+#[cfg(test)]
+impl PlaylistSink for MemorySink {
+    fn write_item<'a>(&'a mut self, item: &'a PlaylistItem) -> BoxFuture<'a, Result<()>> {
+        self.items.push(item.clone());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn finish<'a>(&'a mut self, playlist: &'a ParsedPlaylist) -> BoxFuture<'a, Result<usize>> {
+        self.playlist = Some(playlist.clone());
+        let items = self.items.len();
+        Box::pin(async move { Ok(items) })
+    }
+}
+
+/// Writes items as newline-delimited JSON (same format as the disk cache)
+pub struct NdjsonSink<W> {
+    writer: BufWriter<W>,
+    items_written: usize,
+}
+
+impl<W: AsyncWrite + Unpin + Send> NdjsonSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: BufWriter::with_capacity(64 * 1024, writer),
+            items_written: 0,
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send> PlaylistSink for NdjsonSink<W> {
+    fn write_item<'a>(&'a mut self, item: &'a PlaylistItem) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(item)?;
+            line.push(b'\n');
+            self.writer.write_all(&line).await?;
+            self.items_written += 1;
+            Ok(())
+        })
+    }
+
+    fn finish<'a>(&'a mut self, _playlist: &'a ParsedPlaylist) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            self.writer.flush().await?;
+            Ok(self.items_written)
+        })
+    }
+}
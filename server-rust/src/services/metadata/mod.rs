@@ -0,0 +1,164 @@
+//! Metadata enrichment for M3U movies and series
+//!
+//! M3U lines only carry a name, logo and group. A `MetadataProvider` looks up
+//! plot, poster, genres, cast and rating by parsed title + year; results
+//! (including misses) are cached in Postgres by `MetadataService`.
+
+mod tmdb;
+
+pub use tmdb::{TmdbConfig, TmdbProvider};
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::db::repository::metadata;
+use crate::services::search::normalize;
+
+/// What is being looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataKind {
+    Movie,
+    Series,
+}
+
+impl MetadataKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetadataKind::Movie => "movie",
+            MetadataKind::Series => "series",
+        }
+    }
+}
+
+/// Enriched fields returned by a provider
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaMetadata {
+    pub provider: String,
+    pub external_id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overview: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poster_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backdrop_url: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub cast: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_minutes: Option<u32>,
+}
+
+/// A source of movie/series metadata
+pub trait MetadataProvider: Send + Sync {
+    /// Stable provider name (part of the cache key)
+    fn name(&self) -> &'static str;
+
+    /// Best match for a title (+ optional year), or None if nothing matches
+    fn lookup<'a>(
+        &'a self,
+        kind: MetadataKind,
+        title: &'a str,
+        year: Option<u16>,
+    ) -> BoxFuture<'a, Result<Option<MediaMetadata>>>;
+}
+
+/// Cache key for a lookup: normalized title plus year
+pub fn lookup_key(title: &str, year: Option<u16>) -> String {
+    let title: String = normalize(title).chars().take(1024).collect();
+    match year {
+        Some(year) => format!("{}|{}", title, year),
+        None => title,
+    }
+}
+
+/// Provider lookups cached in Postgres
+#[derive(Clone)]
+pub struct MetadataService {
+    pool: PgPool,
+    provider: Option<Arc<dyn MetadataProvider>>,
+    /// How long a match is reused
+    hit_ttl: Duration,
+    /// How long a miss is remembered before asking the provider again
+    miss_ttl: Duration,
+}
+
+impl MetadataService {
+    pub fn new(
+        pool: PgPool,
+        provider: Option<Arc<dyn MetadataProvider>>,
+        hit_ttl: Duration,
+        miss_ttl: Duration,
+    ) -> Self {
+        Self { pool, provider, hit_ttl, miss_ttl }
+    }
+
+    /// Whether a provider is configured
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    /// Metadata for a title, from cache or the provider
+    pub async fn lookup(
+        &self,
+        kind: MetadataKind,
+        title: &str,
+        year: Option<u16>,
+    ) -> Result<Option<MediaMetadata>> {
+        let Some(provider) = &self.provider else {
+            return Ok(None);
+        };
+
+        let key = lookup_key(title, year);
+        if key.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(cached) = metadata::find(&self.pool, provider.name(), kind.as_str(), &key).await? {
+            let ttl = if cached.data.is_some() { self.hit_ttl } else { self.miss_ttl };
+            if Utc::now() - cached.fetched_at < ttl {
+                return Ok(cached
+                    .data
+                    .and_then(|data| serde_json::from_value(data.0).ok()));
+            }
+        }
+
+        let found = provider.lookup(kind, title, year).await?;
+
+        let data = found.as_ref().map(serde_json::to_value).transpose()?;
+        metadata::upsert(
+            &self.pool,
+            provider.name(),
+            kind.as_str(),
+            &key,
+            found.as_ref().map(|m| m.external_id.as_str()),
+            data.as_ref(),
+        )
+        .await?;
+
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_key() {
+        assert_eq!(lookup_key("Ação Mortal", Some(2020)), "acao mortal|2020");
+        assert_eq!(lookup_key("  The   Office ", None), "the office");
+    }
+}
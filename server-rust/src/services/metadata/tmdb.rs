@@ -0,0 +1,310 @@
+//! TMDB-compatible metadata provider
+//!
+//! Uses `/search/{movie,tv}` to find a match and `/{movie,tv}/{id}` with
+//! `append_to_response=credits` for details. The base URL is configurable so
+//! tests (and TMDB-compatible mirrors) can point it elsewhere.
+
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::{MediaMetadata, MetadataKind, MetadataProvider};
+use crate::services::search::normalize;
+
+/// Cast members kept per title
+const MAX_CAST: usize = 10;
+
+/// TMDB provider settings
+#[derive(Debug, Clone)]
+pub struct TmdbConfig {
+    /// v3 API key, or a v4 read access token (sent as Bearer)
+    pub api_key: String,
+    pub base_url: String,
+    pub image_base_url: String,
+    pub language: String,
+}
+
+pub struct TmdbProvider {
+    client: Client,
+    config: TmdbConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SearchResult {
+    id: u64,
+    #[serde(default, alias = "name")]
+    title: String,
+    #[serde(default, alias = "original_name")]
+    original_title: Option<String>,
+    #[serde(default, alias = "first_air_date")]
+    release_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Details {
+    id: u64,
+    #[serde(default, alias = "name")]
+    title: String,
+    #[serde(default, alias = "original_name")]
+    original_title: Option<String>,
+    #[serde(default)]
+    overview: Option<String>,
+    #[serde(default)]
+    poster_path: Option<String>,
+    #[serde(default)]
+    backdrop_path: Option<String>,
+    #[serde(default)]
+    genres: Vec<Named>,
+    #[serde(default)]
+    vote_average: Option<f32>,
+    #[serde(default, alias = "first_air_date")]
+    release_date: Option<String>,
+    #[serde(default)]
+    runtime: Option<u32>,
+    #[serde(default)]
+    episode_run_time: Vec<u32>,
+    #[serde(default)]
+    credits: Option<Credits>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Credits {
+    #[serde(default)]
+    cast: Vec<Named>,
+}
+
+impl TmdbProvider {
+    pub fn new(config: TmdbConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Self { client, config }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str, params: &[(&str, String)]) -> Result<T> {
+        let url = format!("{}{}", self.config.base_url.trim_end_matches('/'), path);
+        let mut request = self
+            .client
+            .get(&url)
+            .query(&[("language", self.config.language.as_str())])
+            .query(params);
+
+        // v4 tokens are JWTs; v3 keys go in the query string
+        request = if self.config.api_key.starts_with("eyJ") {
+            request.bearer_auth(&self.config.api_key)
+        } else {
+            request.query(&[("api_key", self.config.api_key.as_str())])
+        };
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("TMDB request {} failed: {}", path, response.status()));
+        }
+        let body = response.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    fn image_url(&self, path: Option<String>) -> Option<String> {
+        path.filter(|p| !p.is_empty())
+            .map(|p| format!("{}{}", self.config.image_base_url.trim_end_matches('/'), p))
+    }
+
+    async fn lookup_inner(
+        &self,
+        kind: MetadataKind,
+        title: &str,
+        year: Option<u16>,
+    ) -> Result<Option<MediaMetadata>> {
+        let (search_path, year_param, detail_path) = match kind {
+            MetadataKind::Movie => ("/search/movie", "year", "/movie"),
+            MetadataKind::Series => ("/search/tv", "first_air_date_year", "/tv"),
+        };
+
+        let mut params = vec![("query", title.to_string())];
+        if let Some(year) = year {
+            params.push((year_param, year.to_string()));
+        }
+        let mut results = self.get::<SearchResponse>(search_path, &params).await?.results;
+
+        // A wrong year in the playlist is common; retry without it
+        if results.is_empty() && year.is_some() {
+            params.truncate(1);
+            results = self.get::<SearchResponse>(search_path, &params).await?.results;
+        }
+
+        let Some(best) = pick_best(&results, title, year) else {
+            return Ok(None);
+        };
+
+        let details: Details = self
+            .get(
+                &format!("{}/{}", detail_path, best.id),
+                &[("append_to_response", "credits".to_string())],
+            )
+            .await?;
+
+        Ok(Some(MediaMetadata {
+            provider: self.name().to_string(),
+            external_id: details.id.to_string(),
+            title: details.title,
+            original_title: details.original_title,
+            overview: details.overview.filter(|o| !o.is_empty()),
+            poster_url: self.image_url(details.poster_path),
+            backdrop_url: self.image_url(details.backdrop_path),
+            genres: details.genres.into_iter().map(|g| g.name).collect(),
+            cast: details
+                .credits
+                .map(|c| c.cast.into_iter().take(MAX_CAST).map(|p| p.name).collect())
+                .unwrap_or_default(),
+            rating: details.vote_average.filter(|r| *r > 0.0),
+            year: release_year(details.release_date.as_deref()),
+            runtime_minutes: details
+                .runtime
+                .or_else(|| details.episode_run_time.first().copied())
+                .filter(|r| *r > 0),
+        }))
+    }
+}
+
+impl MetadataProvider for TmdbProvider {
+    fn name(&self) -> &'static str {
+        "tmdb"
+    }
+
+    fn lookup<'a>(
+        &'a self,
+        kind: MetadataKind,
+        title: &'a str,
+        year: Option<u16>,
+    ) -> BoxFuture<'a, Result<Option<MediaMetadata>>> {
+        Box::pin(self.lookup_inner(kind, title, year))
+    }
+}
+
+fn release_year(date: Option<&str>) -> Option<u16> {
+    date.and_then(|d| d.get(..4)).and_then(|y| y.parse().ok())
+}
+
+/// Best search result: exact (normalized) title and year matches score highest;
+/// ties keep the provider's popularity order
+fn pick_best<'a>(results: &'a [SearchResult], title: &str, year: Option<u16>) -> Option<&'a SearchResult> {
+    let wanted = normalize(title);
+
+    let score = |r: &SearchResult| {
+        let mut score = 0;
+        if normalize(&r.title) == wanted
+            || r.original_title.as_deref().map(normalize).as_deref() == Some(wanted.as_str())
+        {
+            score += 2;
+        }
+        if let (Some(year), Some(found)) = (year, release_year(r.release_date.as_deref())) {
+            match year.abs_diff(found) {
+                0 => score += 2,
+                1 => score += 1,
+                _ => {}
+            }
+        }
+        score
+    };
+
+    results
+        .iter()
+        .enumerate()
+        .max_by_key(|(idx, r)| (score(r), std::cmp::Reverse(*idx)))
+        .map(|(_, r)| r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, routing::get, Json, Router};
+
+    fn result(id: u64, title: &str, date: &str) -> SearchResult {
+        SearchResult {
+            id,
+            title: title.to_string(),
+            original_title: None,
+            release_date: Some(date.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_pick_best_prefers_title_and_year() {
+        let results = vec![
+            result(1, "Flow Motion", "2024-01-01"),
+            result(2, "Flow", "2019-05-01"),
+            result(3, "Flow", "2024-10-30"),
+        ];
+        assert_eq!(pick_best(&results, "Flow", Some(2024)).map(|r| r.id), Some(3));
+        assert_eq!(pick_best(&results, "flow", None).map(|r| r.id), Some(2));
+        assert!(pick_best(&[], "Flow", None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_lookup_against_stub_server() {
+        let app = Router::new()
+            .route(
+                "/search/movie",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "results": [{ "id": 42, "title": "Flow", "release_date": "2024-10-30" }]
+                    }))
+                }),
+            )
+            .route(
+                "/movie/:id",
+                get(|Path(id): Path<u64>| async move {
+                    Json(serde_json::json!({
+                        "id": id,
+                        "title": "Flow",
+                        "overview": "Um gato sobrevive a uma enchente.",
+                        "poster_path": "/poster.jpg",
+                        "genres": [{ "id": 16, "name": "Animação" }],
+                        "vote_average": 8.2,
+                        "release_date": "2024-10-30",
+                        "runtime": 85,
+                        "credits": { "cast": [{ "name": "Gato" }] }
+                    }))
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let provider = TmdbProvider::new(TmdbConfig {
+            api_key: "test".to_string(),
+            base_url: format!("http://{}", addr),
+            image_base_url: "https://img.example/w500".to_string(),
+            language: "pt-BR".to_string(),
+        });
+
+        let found = provider
+            .lookup(MetadataKind::Movie, "Flow", Some(2024))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(found.external_id, "42");
+        assert_eq!(found.poster_url.as_deref(), Some("https://img.example/w500/poster.jpg"));
+        assert_eq!(found.genres, vec!["Animação"]);
+        assert_eq!(found.cast, vec!["Gato"]);
+        assert_eq!(found.year, Some(2024));
+        assert_eq!(found.runtime_minutes, Some(85));
+    }
+}
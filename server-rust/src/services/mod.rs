@@ -1,7 +1,24 @@
 pub mod cache;
 pub mod classifier;
 pub mod cleanup;
+pub mod continue_watching;
 pub mod db_cache;
+pub mod events;
+pub mod hls;
+pub mod logo;
+pub mod logo_cache;
+pub mod m3u;
 pub mod m3u_parser;
+pub mod metadata;
+pub mod parental;
+pub mod parse_queue;
+pub mod probe;
+pub mod recorder;
 pub mod redis;
+pub mod remote;
+pub mod search;
+pub mod session_guard;
+pub mod suggest;
+pub mod titles;
+pub mod upstream;
 pub mod xtream;
@@ -0,0 +1,324 @@
+//! Parental controls
+//!
+//! Per-device restrictions (adult content, media kinds, groups, keywords)
+//! protected by a PIN. Devices without settings still get adult content
+//! hidden. A correct PIN unlocks the device for a limited time (Redis), and
+//! wrong attempts are rate limited per device.
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::repository::parental::{self, ParentalControlsRow};
+use crate::db::repository::playlists;
+use crate::models::MovieTitle;
+use crate::services::classifier::ContentClassifier;
+use crate::services::redis::RedisService;
+use crate::services::search::fold_char;
+use crate::services::suggest::Suggestion;
+use crate::services::titles::{default_variant, VariantPreferences};
+
+/// What a device may see
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentRestrictions {
+    pub hide_adult: bool,
+    /// Media kinds (live, movie, series, unknown)
+    pub blocked_kinds: Vec<String>,
+    /// Lowercased group names
+    pub blocked_groups: Vec<String>,
+    /// Folded (lowercase, unaccented) keywords matched against names and groups
+    pub blocked_keywords: Vec<String>,
+}
+
+impl Default for ContentRestrictions {
+    /// Adult content is hidden unless a device explicitly allows it
+    fn default() -> Self {
+        Self {
+            hide_adult: true,
+            blocked_kinds: Vec::new(),
+            blocked_groups: Vec::new(),
+            blocked_keywords: Vec::new(),
+        }
+    }
+}
+
+fn fold(text: &str) -> String {
+    text.chars().map(fold_char).collect()
+}
+
+impl ContentRestrictions {
+    /// No restrictions at all (device unlocked with its PIN)
+    pub fn unrestricted() -> Self {
+        Self { hide_adult: false, ..Default::default() }
+    }
+
+    pub fn from_row(row: &ParentalControlsRow) -> Self {
+        Self {
+            hide_adult: row.hide_adult,
+            blocked_kinds: row.blocked_kinds.iter().map(|k| k.to_lowercase()).collect(),
+            blocked_groups: row.blocked_groups.iter().map(|g| g.to_lowercase()).collect(),
+            blocked_keywords: row
+                .blocked_keywords
+                .iter()
+                .map(|k| fold(k.trim()))
+                .filter(|k| !k.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        !self.hide_adult
+            && self.blocked_kinds.is_empty()
+            && self.blocked_groups.is_empty()
+            && self.blocked_keywords.is_empty()
+    }
+
+    /// Whether blocked groups or keywords are set (adult filtering aside)
+    pub fn has_group_rules(&self) -> bool {
+        !self.blocked_groups.is_empty() || !self.blocked_keywords.is_empty()
+    }
+
+    pub fn allows_kind(&self, kind: &str) -> bool {
+        !self.blocked_kinds.iter().any(|k| k.eq_ignore_ascii_case(kind))
+    }
+
+    fn has_keyword(&self, text: &str) -> bool {
+        if self.blocked_keywords.is_empty() {
+            return false;
+        }
+        let folded = fold(text);
+        self.blocked_keywords.iter().any(|k| folded.contains(k.as_str()))
+    }
+
+    /// Whether a group (and everything in it) is visible
+    pub fn allows_group(&self, group: &str) -> bool {
+        (!self.hide_adult || !ContentClassifier::is_adult("", group))
+            && !self.blocked_groups.contains(&group.to_lowercase())
+            && !self.has_keyword(group)
+    }
+
+    /// Whether an item/series is visible
+    pub fn allows(&self, kind: &str, group: &str, name: &str) -> bool {
+        self.allows_kind(kind)
+            && (!self.hide_adult || !ContentClassifier::is_adult(name, group))
+            && !self.blocked_groups.contains(&group.to_lowercase())
+            && !self.has_keyword(name)
+            && !self.has_keyword(group)
+    }
+
+    /// LIKE patterns for the keywords (`%` / `_` / `\` escaped)
+    pub fn keyword_patterns(&self) -> Vec<String> {
+        self.blocked_keywords
+            .iter()
+            .map(|k| {
+                let escaped = k.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+                format!("%{}%", escaped)
+            })
+            .collect()
+    }
+
+    /// Drop hidden variants (re-picking the default), and titles left without any
+    pub fn apply_to_titles(&self, titles: &mut Vec<MovieTitle>, prefs: &VariantPreferences) {
+        if self.is_unrestricted() {
+            return;
+        }
+        for title in titles.iter_mut() {
+            title.variants.retain(|v| self.allows("movie", &v.group, &v.name));
+            let variants = &title.variants;
+            title.groups.retain(|g| variants.iter().any(|v| &v.group == g));
+            if !title.variants.is_empty() {
+                let default = default_variant(&title.variants, prefs);
+                title.default_variant_id = title.variants[default].id.clone();
+            }
+        }
+        titles.retain(|t| !t.variants.is_empty());
+    }
+
+    /// Drop hidden suggestions (only the text and kind are known here)
+    pub fn apply_to_suggestions(&self, suggestions: &mut Vec<Suggestion>) {
+        if self.is_unrestricted() {
+            return;
+        }
+        suggestions.retain(|s| match s.kind.as_str() {
+            "group" => self.allows_group(&s.text),
+            kind => self.allows(kind, "", &s.text),
+        });
+    }
+}
+
+/// Whether a PIN is acceptable (4 to 8 digits)
+pub fn is_valid_pin(pin: &str) -> bool {
+    (4..=8).contains(&pin.len()) && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Hash a PIN (argon2, random salt) into a PHC string
+pub fn hash_pin(pin: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes()).map_err(|e| anyhow!("{}", e))?;
+    let hash = Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map_err(|e| anyhow!("{}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Check a PIN against a stored hash
+pub fn verify_pin(pin: &str, pin_hash: &str) -> bool {
+    PasswordHash::new(pin_hash)
+        .map(|hash| Argon2::default().verify_password(pin.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+/// Outcome of a PIN check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinCheck {
+    Correct,
+    Wrong { attempts_left: u64 },
+    /// Too many wrong attempts; try again after the lockout window
+    LockedOut,
+}
+
+/// Parental settings lookup, PIN checks and unlock windows
+#[derive(Clone)]
+pub struct ParentalService {
+    pool: PgPool,
+    redis: RedisService,
+    unlock_seconds: u64,
+    max_attempts: u64,
+    lockout_seconds: u64,
+}
+
+impl ParentalService {
+    pub fn new(
+        pool: PgPool,
+        redis: RedisService,
+        unlock_seconds: u64,
+        max_attempts: u64,
+        lockout_seconds: u64,
+    ) -> Self {
+        Self { pool, redis, unlock_seconds, max_attempts: max_attempts.max(1), lockout_seconds }
+    }
+
+    /// Restrictions for a device; defaults when unknown or without settings
+    pub async fn restrictions(&self, device_id: Option<&str>) -> Result<ContentRestrictions> {
+        let Some(device_id) = device_id.filter(|d| !d.is_empty()) else {
+            return Ok(ContentRestrictions::default());
+        };
+
+        match self.unlock_ttl(device_id).await {
+            Ok(Some(_)) => return Ok(ContentRestrictions::unrestricted()),
+            Ok(None) => {}
+            // Fail closed: keep restrictions if Redis is unavailable
+            Err(e) => tracing::warn!("Parental unlock check failed for {}: {}", device_id, e),
+        }
+
+        Ok(parental::get(&self.pool, device_id)
+            .await?
+            .map(|row| ContentRestrictions::from_row(&row))
+            .unwrap_or_default())
+    }
+
+    /// Restrictions for a playlist's device, falling back to the requesting
+    /// device when the playlist isn't bound to one
+    pub async fn restrictions_for_playlist(
+        &self,
+        hash: &str,
+        request_device: Option<&str>,
+    ) -> Result<ContentRestrictions> {
+        let playlist_device = playlists::find_by_hash_any(&self.pool, hash)
+            .await?
+            .and_then(|p| p.device_id);
+        self.restrictions(playlist_device.as_deref().or(request_device)).await
+    }
+
+    /// Check a PIN, counting wrong attempts against the device
+    ///
+    /// The attempt is counted (one atomic INCR) before verifying, so concurrent
+    /// guesses can't all slip past the lockout check; a correct PIN resets it.
+    pub async fn check_pin(&self, device_id: &str, pin: &str, pin_hash: &str) -> Result<PinCheck> {
+        let attempts = self.redis.record_pin_attempt(device_id, self.lockout_seconds).await?;
+        if attempts > self.max_attempts {
+            return Ok(PinCheck::LockedOut);
+        }
+
+        let pin = pin.to_string();
+        let pin_hash = pin_hash.to_string();
+        // argon2 is deliberately slow; keep it off the async workers
+        let correct = tokio::task::spawn_blocking(move || verify_pin(&pin, &pin_hash)).await?;
+
+        if correct {
+            self.redis.clear_pin_failures(device_id).await?;
+            return Ok(PinCheck::Correct);
+        }
+
+        Ok(PinCheck::Wrong { attempts_left: self.max_attempts.saturating_sub(attempts) })
+    }
+
+    /// Lift restrictions for the unlock window; returns its length in seconds
+    pub async fn unlock(&self, device_id: &str) -> Result<u64> {
+        self.redis.set_parental_unlock(device_id, self.unlock_seconds).await?;
+        Ok(self.unlock_seconds)
+    }
+
+    pub async fn lock(&self, device_id: &str) -> Result<()> {
+        self.redis.clear_parental_unlock(device_id).await
+    }
+
+    /// Seconds left in the device's unlock window
+    pub async fn unlock_ttl(&self, device_id: &str) -> Result<Option<u64>> {
+        self.redis.parental_unlock_ttl(device_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(kinds: &[&str], groups: &[&str], keywords: &[&str]) -> ParentalControlsRow {
+        let owned = |v: &[&str]| v.iter().map(|s| s.to_string()).collect();
+        ParentalControlsRow {
+            device_id: "tv".to_string(),
+            pin_hash: String::new(),
+            hide_adult: true,
+            blocked_kinds: owned(kinds),
+            blocked_groups: owned(groups),
+            blocked_keywords: owned(keywords),
+        }
+    }
+
+    #[test]
+    fn test_default_hides_adult_only() {
+        let r = ContentRestrictions::default();
+        assert!(!r.allows("live", "XXX Adultos", "Canal"));
+        assert!(!r.allows("movie", "Filmes", "OnlyFans Special"));
+        assert!(r.allows("movie", "Filmes", "Um Filme Adulto"));
+        assert!(ContentRestrictions::unrestricted().allows("live", "XXX", "Canal"));
+        assert!(!r.has_group_rules());
+    }
+
+    #[test]
+    fn test_blocked_kinds_groups_keywords() {
+        let r = ContentRestrictions::from_row(&row(&["Series"], &["Terror"], &["Violência"]));
+        assert!(!r.allows("series", "Netflix", "Dark"));
+        assert!(!r.allows("movie", "TERROR", "It"));
+        assert!(!r.allows("movie", "Filmes", "Violencia Urbana"));
+        assert!(r.allows("movie", "Comédia", "Shrek"));
+        assert!(!r.allows_group("terror"));
+        assert_eq!(r.keyword_patterns(), vec!["%violencia%"]);
+        assert!(r.has_group_rules());
+        assert!(!ContentRestrictions::from_row(&row(&["Live"], &[], &[])).has_group_rules());
+    }
+
+    #[test]
+    fn test_pin_hash_roundtrip() {
+        assert!(is_valid_pin("1234"));
+        assert!(!is_valid_pin("12a4"));
+        assert!(!is_valid_pin("123"));
+
+        let hash = hash_pin("1234").unwrap();
+        assert_ne!(hash, "1234");
+        assert!(verify_pin("1234", &hash));
+        assert!(!verify_pin("4321", &hash));
+        assert!(!verify_pin("1234", "not a hash"));
+    }
+}
@@ -0,0 +1,544 @@
+//! Background playlist parse queue
+//!
+//! Imports are queued in the `parse_jobs` table and run by a fixed number of
+//! workers per instance, so a burst of large imports can't exhaust memory or
+//! the database pool. Interactive imports run before background refreshes.
+//! Running jobs are heartbeated; jobs of a process that died are queued again
+//! by whichever instance notices first.
+
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::db::repository::parse_jobs::{self, ParseJobRow};
+use crate::db::repository::playlists;
+use crate::models::CacheMetadata;
+use crate::services::db_cache::DbCacheService;
+use crate::services::events::{device_channel, playlist_channel, EventHub, PushEvent};
+use crate::services::m3u_parser::M3UParser;
+use crate::services::redis::{ParseProgress, RedisService};
+use crate::services::suggest::SuggestService;
+
+/// How often idle workers look for jobs queued by other instances
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How often running jobs are heartbeated (and stale jobs recovered)
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Order in which queued parses run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobPriority {
+    /// Refreshes and other parses nobody is waiting on
+    Background,
+    /// A user importing a playlist on the TV
+    Interactive,
+}
+
+impl JobPriority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "interactive" => Some(JobPriority::Interactive),
+            "background" | "refresh" => Some(JobPriority::Background),
+            _ => None,
+        }
+    }
+
+    fn value(self) -> i16 {
+        match self {
+            JobPriority::Background => 0,
+            JobPriority::Interactive => 10,
+        }
+    }
+}
+
+/// What queueing a parse did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnqueueOutcome {
+    /// A new job was queued
+    Queued,
+    /// Joined the active job as is
+    Joined,
+    /// Joined a queued job, raising its priority
+    Raised,
+}
+
+fn enqueue_outcome(inserted: bool, previous_priority: Option<i16>, priority: i16) -> EnqueueOutcome {
+    match previous_priority {
+        _ if inserted => EnqueueOutcome::Queued,
+        Some(previous) if previous < priority => EnqueueOutcome::Raised,
+        _ => EnqueueOutcome::Joined,
+    }
+}
+
+/// What happens to a running job whose worker stopped heartbeating
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recovery {
+    Requeue,
+    Fail,
+}
+
+fn recovery(attempts: i32, max_attempts: i32) -> Recovery {
+    if attempts >= max_attempts {
+        Recovery::Fail
+    } else {
+        Recovery::Requeue
+    }
+}
+
+/// Result of a cancel request
+#[derive(Debug)]
+pub enum CancelResult {
+    /// Cancelled the job with this id
+    Cancelled(Uuid),
+    /// Nothing queued or running for the playlist
+    NotFound,
+    /// The active job was queued by another device (or by no device)
+    NotOwner,
+}
+
+/// Who reports a cancelled job as stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CancelAction {
+    /// Never started: the request itself reports it
+    Report,
+    /// Running: its worker stops and reports it
+    Stop,
+}
+
+fn cancel_action(job: &ParseJobRow) -> CancelAction {
+    if job.worker_id.is_none() {
+        CancelAction::Report
+    } else {
+        CancelAction::Stop
+    }
+}
+
+fn owned_by(job: &ParseJobRow, device_id: &str) -> bool {
+    job.device_id.as_deref() == Some(device_id)
+}
+
+/// Parse queue configuration
+#[derive(Debug, Clone)]
+pub struct ParseQueueConfig {
+    /// Parses running at once on this instance
+    pub workers: usize,
+    /// Seconds without a heartbeat before a running job is considered dead
+    pub stale_seconds: u64,
+    /// Runs of a job (including recoveries) before it's marked failed
+    pub max_attempts: i32,
+}
+
+/// Queue and worker pool for background playlist parses
+#[derive(Clone)]
+pub struct ParseQueue {
+    pool: PgPool,
+    redis: RedisService,
+    db_cache: DbCacheService,
+    parser: Arc<M3UParser>,
+    events: EventHub,
+    suggest: SuggestService,
+    config: ParseQueueConfig,
+    /// Identifies this instance's claims in `parse_jobs.worker_id`
+    worker_id: String,
+    wake: Arc<Notify>,
+    active: Arc<Mutex<HashMap<Uuid, CancellationToken>>>,
+}
+
+impl ParseQueue {
+    pub fn new(
+        pool: PgPool,
+        redis: RedisService,
+        db_cache: DbCacheService,
+        parser: Arc<M3UParser>,
+        events: EventHub,
+        suggest: SuggestService,
+        config: ParseQueueConfig,
+    ) -> Self {
+        Self {
+            pool,
+            redis,
+            db_cache,
+            parser,
+            events,
+            suggest,
+            config: ParseQueueConfig {
+                workers: config.workers.max(1),
+                max_attempts: config.max_attempts.max(1),
+                ..config
+            },
+            worker_id: Uuid::new_v4().simple().to_string(),
+            wake: Arc::new(Notify::new()),
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Queue a parse, or join the active job for the same playlist
+    ///
+    /// Returns the job and whether it was newly queued.
+    pub async fn enqueue(
+        &self,
+        hash: &str,
+        url: &str,
+        device_id: Option<&str>,
+        priority: JobPriority,
+    ) -> Result<(ParseJobRow, bool)> {
+        let enqueued = parse_jobs::enqueue(&self.pool, hash, url, device_id, priority.value()).await?;
+        let job = enqueued.job;
+
+        match enqueue_outcome(enqueued.inserted, enqueued.previous_priority, priority.value()) {
+            EnqueueOutcome::Queued => {
+                if let Err(e) = self.events.parse_progress(hash, &ParseProgress::new_queued()).await {
+                    tracing::warn!("Failed to set initial progress: {}", e);
+                }
+                self.wake.notify_one();
+                tracing::info!("Parse job {} queued for {} ({:?})", job.id, hash, priority);
+            }
+            EnqueueOutcome::Raised => {
+                tracing::info!("Parse job {} for {} raised to {:?}", job.id, hash, priority);
+            }
+            EnqueueOutcome::Joined => {}
+        }
+
+        Ok((job, enqueued.inserted))
+    }
+
+    /// Jobs queued ahead of a job
+    pub async fn position(&self, job: &ParseJobRow) -> Result<i64> {
+        Ok(parse_jobs::queue_position(&self.pool, job).await?)
+    }
+
+    /// Cancel the active parse of a playlist, if `device_id` queued it
+    ///
+    /// A job running on another instance stops at its next heartbeat.
+    pub async fn cancel(&self, hash: &str, device_id: &str) -> Result<CancelResult> {
+        let Some(active) = parse_jobs::find_active(&self.pool, hash).await? else {
+            return Ok(CancelResult::NotFound);
+        };
+        if !owned_by(&active, device_id) {
+            return Ok(CancelResult::NotOwner);
+        }
+        let Some(job) = parse_jobs::cancel(&self.pool, active.id).await? else {
+            return Ok(CancelResult::NotFound);
+        };
+
+        match cancel_action(&job) {
+            CancelAction::Report => {
+                let progress = ParseProgress::new_parsing().cancelled();
+                self.events.parse_progress(hash, &progress).await?;
+            }
+            CancelAction::Stop => {
+                if let Some(token) = self.active.lock().unwrap().get(&job.id) {
+                    token.cancel();
+                }
+            }
+        }
+
+        tracing::info!("Parse job {} cancelled for {}", job.id, hash);
+        Ok(CancelResult::Cancelled(job.id))
+    }
+
+    /// Worker loop: claim and run jobs one at a time
+    async fn work(self) {
+        loop {
+            match parse_jobs::claim_next(&self.pool, &self.worker_id).await {
+                Ok(Some(job)) => self.process(job).await,
+                Ok(None) => {
+                    tokio::select! {
+                        _ = self.wake.notified() => {}
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to claim parse job: {}", e);
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    async fn process(&self, job: ParseJobRow) {
+        let token = CancellationToken::new();
+        self.active.lock().unwrap().insert(job.id, token.clone());
+        tracing::info!(
+            "Parse job {} started for {} (attempt {})",
+            job.id,
+            job.hash,
+            job.attempts
+        );
+
+        let result = tokio::select! {
+            _ = token.cancelled() => None,
+            r = self.parser.parse_and_cache_with_progress(&job.url, &self.events) => Some(r),
+        };
+        self.active.lock().unwrap().remove(&job.id);
+
+        match result {
+            Some(Ok(metadata)) => {
+                self.complete(&job, &metadata).await;
+                self.finish(&job, parse_jobs::STATUS_COMPLETE, None).await;
+            }
+            Some(Err(e)) => {
+                let progress = ParseProgress::new_parsing().failed(&e.to_string());
+                let _ = self.events.parse_progress(&job.hash, &progress).await;
+                self.finish(&job, parse_jobs::STATUS_FAILED, Some(&e.to_string())).await;
+                tracing::error!("Background parse failed for {}: {}", job.hash, e);
+            }
+            None => self.stopped(&job).await,
+        }
+    }
+
+    /// Post-processing of a successful parse
+    async fn complete(&self, job: &ParseJobRow, metadata: &CacheMetadata) {
+        let hash = &job.hash;
+
+        // Update playlist with device_id and 1-day TTL
+        let expires_at = Utc::now() + ChronoDuration::days(1);
+        if let Ok(Some(playlist)) = playlists::find_by_hash_any(&self.pool, hash).await {
+            if let Some(did) = &job.device_id {
+                if let Err(e) = playlists::update_device_and_ttl(&self.pool, playlist.id, did, expires_at).await {
+                    tracing::warn!("Failed to set device_id and TTL for {}: {}", hash, e);
+                } else {
+                    tracing::info!("Set device_id {} and 1-day TTL for playlist {}", did, hash);
+                }
+            } else {
+                // No device_id, but still set 1-day TTL
+                let _ = sqlx::query("UPDATE playlists SET expires_at = $2, updated_at = NOW() WHERE id = $1")
+                    .bind(playlist.id)
+                    .bind(expires_at)
+                    .execute(&self.pool)
+                    .await;
+                tracing::info!("Set 1-day TTL for playlist {} (no device)", hash);
+            }
+        }
+
+        // Mark progress as complete, keeping byte counts and phase timings
+        let mut progress = match self.redis.get_parse_progress(hash).await {
+            Ok(Some(progress)) => progress,
+            _ => ParseProgress::new_parsing(),
+        };
+        progress.items_parsed = metadata.stats.total_items as u64;
+        progress.items_total = Some(metadata.stats.total_items as u64);
+        let progress = progress.complete(metadata.stats.group_count as u64, metadata.stats.series_count as u64);
+        let _ = self.events.parse_progress(hash, &progress).await;
+
+        let ready = PushEvent::PlaylistReady {
+            hash: hash.clone(),
+            stats: metadata.stats.clone(),
+        };
+        self.events.notify(&playlist_channel(hash), ready.clone()).await;
+        if let Some(did) = &job.device_id {
+            self.events.notify(&device_channel(did), ready).await;
+        }
+
+        // Warm the suggestion index so the first keystrokes are fast
+        if let Err(e) = self.suggest.rebuild(hash).await {
+            tracing::warn!("Failed to build suggest index for {}: {}", hash, e);
+        }
+
+        tracing::info!(
+            "Background parse complete for {}: {} items, {} groups",
+            hash,
+            metadata.stats.total_items,
+            metadata.stats.group_count
+        );
+    }
+
+    /// A job stopped before finishing: cancelled, or taken over after a missed heartbeat
+    async fn stopped(&self, job: &ParseJobRow) {
+        match parse_jobs::find_active(&self.pool, &job.hash).await {
+            Ok(Some(active)) => {
+                tracing::warn!("Parse job {} was requeued as {}, stopped here", job.id, active.id);
+                return;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to check parse job {}: {}", job.id, e),
+        }
+
+        self.drop_partial(&job.hash).await;
+
+        let progress = ParseProgress::new_parsing().cancelled();
+        let _ = self.events.parse_progress(&job.hash, &progress).await;
+        tracing::info!("Parse job {} stopped for {}", job.id, job.hash);
+    }
+
+    /// Drop the partial playlist of a stopped job (a complete one has items and is kept)
+    async fn drop_partial(&self, hash: &str) {
+        if let Ok(Some(playlist)) = playlists::find_by_hash_any(&self.pool, hash).await {
+            if playlist.total_items == 0 {
+                let _ = self.db_cache.delete_playlist(hash).await;
+            }
+        }
+    }
+
+    async fn finish(&self, job: &ParseJobRow, status: &str, error: Option<&str>) {
+        if let Err(e) = parse_jobs::finish(&self.pool, job.id, &self.worker_id, status, error).await {
+            tracing::error!("Failed to finish parse job {}: {}", job.id, e);
+        }
+    }
+
+    /// Heartbeat this instance's jobs, stopping those cancelled elsewhere
+    async fn heartbeat(&self) {
+        let running: Vec<(Uuid, CancellationToken)> = self
+            .active
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, token)| (*id, token.clone()))
+            .collect();
+
+        for (id, token) in running {
+            match parse_jobs::heartbeat(&self.pool, id, &self.worker_id).await {
+                Ok(true) => {}
+                Ok(false) => token.cancel(),
+                Err(e) => tracing::warn!("Failed to heartbeat parse job {}: {}", id, e),
+            }
+        }
+    }
+
+    /// Requeue jobs whose process died
+    async fn recover(&self) {
+        let stale_seconds = self.config.stale_seconds;
+        let jobs = match parse_jobs::find_stale(&self.pool, stale_seconds).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                tracing::error!("Failed to recover parse jobs: {}", e);
+                return;
+            }
+        };
+
+        for job in jobs {
+            let action = recovery(job.attempts, self.config.max_attempts);
+            let released = match action {
+                Recovery::Requeue => {
+                    parse_jobs::release_stale(&self.pool, job.id, stale_seconds, parse_jobs::STATUS_QUEUED, None).await
+                }
+                Recovery::Fail => {
+                    parse_jobs::release_stale(
+                        &self.pool,
+                        job.id,
+                        stale_seconds,
+                        parse_jobs::STATUS_FAILED,
+                        Some("Importação interrompida"),
+                    )
+                    .await
+                }
+            };
+            match released {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::error!("Failed to recover parse job {}: {}", job.id, e);
+                    continue;
+                }
+            }
+
+            let progress = match action {
+                Recovery::Requeue => {
+                    tracing::warn!("Parse job {} for {} was interrupted, requeued", job.id, job.hash);
+                    self.wake.notify_one();
+                    ParseProgress::new_queued()
+                }
+                Recovery::Fail => {
+                    tracing::error!("Parse job {} for {} was interrupted too many times", job.id, job.hash);
+                    self.drop_partial(&job.hash).await;
+                    ParseProgress::new_parsing().failed("Importação interrompida")
+                }
+            };
+            let _ = self.events.parse_progress(&job.hash, &progress).await;
+        }
+    }
+}
+
+/// Start the parse workers and the heartbeat/recovery loop (runs forever)
+pub async fn start_parse_workers(queue: ParseQueue) {
+    tracing::info!(
+        "Starting parse queue ({} workers, worker id {})",
+        queue.config.workers,
+        queue.worker_id
+    );
+
+    for _ in 0..queue.config.workers {
+        tokio::spawn(queue.clone().work());
+    }
+
+    // The first tick runs immediately, recovering jobs from before a restart
+    let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        interval.tick().await;
+        queue.heartbeat().await;
+        queue.recover().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_priority() {
+        assert_eq!(JobPriority::parse("interactive"), Some(JobPriority::Interactive));
+        assert_eq!(JobPriority::parse("refresh"), Some(JobPriority::Background));
+        assert_eq!(JobPriority::parse("urgent"), None);
+        assert!(JobPriority::Interactive.value() > JobPriority::Background.value());
+    }
+
+    fn job(device_id: Option<&str>, worker_id: Option<&str>) -> ParseJobRow {
+        ParseJobRow {
+            id: Uuid::new_v4(),
+            hash: "abc".to_string(),
+            url: "http://example.com/list.m3u".to_string(),
+            device_id: device_id.map(str::to_string),
+            priority: 0,
+            status: parse_jobs::STATUS_QUEUED.to_string(),
+            attempts: 0,
+            worker_id: worker_id.map(str::to_string),
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    #[test]
+    fn test_enqueue_outcome() {
+        let interactive = JobPriority::Interactive.value();
+        let background = JobPriority::Background.value();
+
+        assert_eq!(enqueue_outcome(true, None, background), EnqueueOutcome::Queued);
+        // Lost the race with a concurrent enqueue: still a new job
+        assert_eq!(enqueue_outcome(true, Some(background), interactive), EnqueueOutcome::Queued);
+
+        assert_eq!(enqueue_outcome(false, Some(background), interactive), EnqueueOutcome::Raised);
+        assert_eq!(enqueue_outcome(false, Some(interactive), background), EnqueueOutcome::Joined);
+        assert_eq!(enqueue_outcome(false, Some(interactive), interactive), EnqueueOutcome::Joined);
+        // Conflicted with a job queued after the statement's snapshot
+        assert_eq!(enqueue_outcome(false, None, interactive), EnqueueOutcome::Joined);
+    }
+
+    #[test]
+    fn test_recovery() {
+        assert_eq!(recovery(1, 3), Recovery::Requeue);
+        assert_eq!(recovery(2, 3), Recovery::Requeue);
+        assert_eq!(recovery(3, 3), Recovery::Fail);
+        assert_eq!(recovery(4, 3), Recovery::Fail);
+        assert_eq!(recovery(1, 1), Recovery::Fail);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let queued = job(Some("tv-1"), None);
+        assert_eq!(cancel_action(&queued), CancelAction::Report);
+
+        let running = job(Some("tv-1"), Some("worker"));
+        assert_eq!(cancel_action(&running), CancelAction::Stop);
+
+        assert!(owned_by(&queued, "tv-1"));
+        assert!(!owned_by(&queued, "tv-2"));
+        assert!(!owned_by(&job(None, None), "tv-1"));
+    }
+}
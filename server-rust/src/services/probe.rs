@@ -0,0 +1,827 @@
+//! Stream probing
+//!
+//! Detects codecs, resolution, audio and subtitle tracks of a stream before the
+//! TV tries to play it. HLS masters are read from their attributes; the first
+//! MPEG-TS segment (or the first bytes of a raw TS stream) is parsed for its
+//! PAT/PMT stream types and, for H.264, the SPS resolution.
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use url::Url;
+
+use crate::services::hls::{parse_resolution, tag_attributes};
+use crate::services::upstream::{UpstreamOutcome, UpstreamPool};
+
+/// Bytes read from a segment or raw stream
+const MAX_PROBE_BYTES: usize = 1024 * 1024;
+/// Timeout for each upstream fetch while probing
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const TS_PACKET_SIZE: usize = 188;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    pub codecs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_rate: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoTrack {
+    pub codec: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec_string: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTrack {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrack {
+    pub format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u16>,
+}
+
+/// Probe result (cached per URL)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamProbe {
+    /// hls | ts | unknown
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub variants: Vec<VariantInfo>,
+    pub video: Vec<VideoTrack>,
+    pub audio: Vec<AudioTrack>,
+    pub subtitles: Vec<SubtitleTrack>,
+    pub probed_at: i64,
+}
+
+/// Streams found in a transport stream
+#[derive(Debug, Default, PartialEq)]
+struct TsInfo {
+    video: Vec<VideoTrack>,
+    audio: Vec<AudioTrack>,
+    subtitles: Vec<SubtitleTrack>,
+}
+
+/// Parsed HLS master playlist
+#[derive(Debug, Default)]
+struct MasterInfo {
+    variants: Vec<VariantInfo>,
+    audio: Vec<AudioTrack>,
+    subtitles: Vec<SubtitleTrack>,
+    first_variant_uri: Option<String>,
+}
+
+/// Probe a stream URL
+pub async fn probe_url(upstream: &UpstreamPool, url: &str) -> Result<StreamProbe> {
+    let (bytes, content_type, final_url) = fetch_limited(upstream, url).await?;
+
+    let mut probe = StreamProbe {
+        source: "unknown".to_string(),
+        content_type: content_type.clone(),
+        probed_at: chrono::Utc::now().timestamp_millis(),
+        ..Default::default()
+    };
+
+    let looks_like_playlist = content_type
+        .as_deref()
+        .map(|ct| ct.to_lowercase().contains("mpegurl"))
+        .unwrap_or(false)
+        || bytes.starts_with(b"#EXTM3U");
+
+    if looks_like_playlist {
+        probe.source = "hls".to_string();
+        let text = String::from_utf8_lossy(&bytes).to_string();
+        let base = Url::parse(&final_url)?;
+
+        let (media_text, media_base) = if text.contains("#EXT-X-STREAM-INF") {
+            let master = parse_master_playlist(&text);
+            probe.variants = master.variants;
+            probe.audio = master.audio;
+            probe.subtitles = master.subtitles;
+
+            let variant_uri = master
+                .first_variant_uri
+                .ok_or_else(|| anyhow!("Master playlist without variants"))?;
+            let variant_url = base.join(&variant_uri)?;
+            let (bytes, _, final_url) = fetch_limited(upstream, variant_url.as_str()).await?;
+            (String::from_utf8_lossy(&bytes).to_string(), Url::parse(&final_url)?)
+        } else {
+            (text, base)
+        };
+
+        // fMP4 segments (EXT-X-MAP) aren't parsed; attributes are all we report
+        if media_text.contains("#EXT-X-MAP") {
+            return Ok(probe);
+        }
+
+        if let Some(segment) = first_segment_uri(&media_text) {
+            let segment_url = media_base.join(&segment)?;
+            let (bytes, _, _) = fetch_limited(upstream, segment_url.as_str()).await?;
+            merge_ts_info(&mut probe, parse_ts(&bytes));
+        }
+    } else if find_ts_sync(&bytes).is_some() {
+        probe.source = "ts".to_string();
+        merge_ts_info(&mut probe, parse_ts(&bytes));
+    }
+
+    Ok(probe)
+}
+
+/// Fill in tracks from a TS segment where the playlist attributes were silent
+fn merge_ts_info(probe: &mut StreamProbe, ts: TsInfo) {
+    if probe.audio.is_empty() {
+        probe.audio = ts.audio;
+    } else if probe.audio.iter().all(|a| a.codec.is_none()) {
+        // EXT-X-MEDIA gives names/languages but not codecs
+        if let Some(codec) = ts.audio.first().and_then(|a| a.codec.clone()) {
+            for track in probe.audio.iter_mut() {
+                track.codec = Some(codec.clone());
+            }
+        }
+    }
+    if probe.subtitles.is_empty() {
+        probe.subtitles = ts.subtitles;
+    }
+    probe.video = ts.video;
+}
+
+/// GET a URL through the shared upstream pool, reading at most MAX_PROBE_BYTES
+async fn fetch_limited(
+    upstream: &UpstreamPool,
+    url: &str,
+) -> Result<(Vec<u8>, Option<String>, String)> {
+    let handle = upstream.acquire(url)?;
+    let result = tokio::time::timeout(FETCH_TIMEOUT, handle.client.get(url).send()).await;
+
+    let response = match result {
+        Err(_) => {
+            handle.finish(UpstreamOutcome::Timeout);
+            return Err(anyhow!("Timeout fetching {}", url));
+        }
+        Ok(Err(e)) => {
+            handle.finish(UpstreamOutcome::from_error(&e));
+            return Err(e.into());
+        }
+        Ok(Ok(response)) => {
+            handle.finish(UpstreamOutcome::from_status(response.status()));
+            response.error_for_status()?
+        }
+    };
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let final_url = response.url().to_string();
+
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    let read = tokio::time::timeout(FETCH_TIMEOUT, async {
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+            if bytes.len() >= MAX_PROBE_BYTES {
+                break;
+            }
+        }
+        Ok::<_, reqwest::Error>(())
+    })
+    .await;
+
+    // A live TS stream never ends: whatever arrived before the timeout is enough
+    match read {
+        Ok(Err(e)) if bytes.is_empty() => return Err(e.into()),
+        Err(_) if bytes.is_empty() => return Err(anyhow!("Timeout reading {}", url)),
+        _ => {}
+    }
+
+    Ok((bytes, content_type, final_url))
+}
+
+fn parse_master_playlist(text: &str) -> MasterInfo {
+    let mut info = MasterInfo::default();
+    let mut expect_uri = false;
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if line.starts_with("#EXT-X-STREAM-INF") {
+            let mut variant = VariantInfo::default();
+            for (key, value) in tag_attributes(line) {
+                match key.as_str() {
+                    "BANDWIDTH" => variant.bandwidth = value.parse().ok(),
+                    "RESOLUTION" => {
+                        if let Some((w, h)) = parse_resolution(&value) {
+                            variant.width = Some(w);
+                            variant.height = Some(h);
+                        }
+                    }
+                    "CODECS" => {
+                        variant.codecs = value
+                            .split(',')
+                            .map(|c| c.trim().to_string())
+                            .filter(|c| !c.is_empty())
+                            .collect();
+                    }
+                    "FRAME-RATE" => variant.frame_rate = value.parse().ok(),
+                    _ => {}
+                }
+            }
+            info.variants.push(variant);
+            expect_uri = true;
+        } else if line.starts_with("#EXT-X-MEDIA:") {
+            let attrs = tag_attributes(line);
+            let get = |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.clone())
+            };
+            match get("TYPE").as_deref() {
+                Some("AUDIO") => info.audio.push(AudioTrack {
+                    codec: None,
+                    language: get("LANGUAGE"),
+                    name: get("NAME"),
+                    channels: get("CHANNELS"),
+                    pid: None,
+                }),
+                Some("SUBTITLES") => info.subtitles.push(SubtitleTrack {
+                    format: "webvtt".to_string(),
+                    language: get("LANGUAGE"),
+                    name: get("NAME"),
+                    pid: None,
+                }),
+                Some("CLOSED-CAPTIONS") => info.subtitles.push(SubtitleTrack {
+                    format: "cea-608".to_string(),
+                    language: get("LANGUAGE"),
+                    name: get("NAME"),
+                    pid: None,
+                }),
+                _ => {}
+            }
+        } else if expect_uri && !line.is_empty() && !line.starts_with('#') {
+            if info.first_variant_uri.is_none() {
+                info.first_variant_uri = Some(line.to_string());
+            }
+            expect_uri = false;
+        }
+    }
+
+    info
+}
+
+fn first_segment_uri(media: &str) -> Option<String> {
+    media
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| l.to_string())
+}
+
+// ============ MPEG-TS ============
+
+fn find_ts_sync(data: &[u8]) -> Option<usize> {
+    (0..TS_PACKET_SIZE.min(data.len())).find(|&i| {
+        data.get(i) == Some(&0x47)
+            && data.get(i + TS_PACKET_SIZE) == Some(&0x47)
+            && data.get(i + 2 * TS_PACKET_SIZE).map(|b| *b == 0x47).unwrap_or(true)
+    })
+}
+
+/// Iterate (pid, payload_unit_start, payload) for each packet
+fn ts_packets(data: &[u8]) -> impl Iterator<Item = (u16, bool, &[u8])> {
+    let start = find_ts_sync(data).unwrap_or(data.len());
+    data[start..]
+        .chunks_exact(TS_PACKET_SIZE)
+        .filter(|p| p[0] == 0x47)
+        .filter_map(|p| {
+            let pid = (((p[1] & 0x1f) as u16) << 8) | p[2] as u16;
+            let pusi = p[1] & 0x40 != 0;
+            let afc = (p[3] >> 4) & 0x03;
+            if afc & 0x01 == 0 {
+                return None;
+            }
+            let offset = if afc & 0x02 != 0 { 5 + p[4] as usize } else { 4 };
+            if offset >= TS_PACKET_SIZE {
+                return None;
+            }
+            Some((pid, pusi, &p[offset..]))
+        })
+}
+
+/// Section payload after the pointer field, bounded by section_length
+fn psi_section(payload: &[u8]) -> Option<&[u8]> {
+    let pointer = *payload.first()? as usize;
+    let section = payload.get(1 + pointer..)?;
+    if section.len() < 3 {
+        return None;
+    }
+    let length = (((section[1] & 0x0f) as usize) << 8) | section[2] as usize;
+    section.get(..(3 + length).min(section.len()))
+}
+
+fn parse_pat(section: &[u8]) -> Vec<u16> {
+    let mut pmt_pids = Vec::new();
+    if section.first() != Some(&0x00) || section.len() < 12 {
+        return pmt_pids;
+    }
+    // Program loop ends before the 4-byte CRC
+    let mut i = 8;
+    while i + 4 <= section.len() - 4 {
+        let program = ((section[i] as u16) << 8) | section[i + 1] as u16;
+        let pid = (((section[i + 2] & 0x1f) as u16) << 8) | section[i + 3] as u16;
+        if program != 0 {
+            pmt_pids.push(pid);
+        }
+        i += 4;
+    }
+    pmt_pids
+}
+
+/// Elementary stream entry of a PMT
+struct EsEntry<'a> {
+    stream_type: u8,
+    pid: u16,
+    descriptors: &'a [u8],
+}
+
+fn parse_pmt(section: &[u8]) -> Vec<EsEntry<'_>> {
+    let mut entries = Vec::new();
+    if section.first() != Some(&0x02) || section.len() < 16 {
+        return entries;
+    }
+    let program_info_len = (((section[10] & 0x0f) as usize) << 8) | section[11] as usize;
+    let end = section.len() - 4;
+    let mut i = 12 + program_info_len;
+
+    while i + 5 <= end {
+        let stream_type = section[i];
+        let pid = (((section[i + 1] & 0x1f) as u16) << 8) | section[i + 2] as u16;
+        let info_len = (((section[i + 3] & 0x0f) as usize) << 8) | section[i + 4] as usize;
+        let desc_end = (i + 5 + info_len).min(end);
+        entries.push(EsEntry {
+            stream_type,
+            pid,
+            descriptors: &section[i + 5..desc_end],
+        });
+        i = desc_end;
+    }
+    entries
+}
+
+/// Iterate (tag, body) over a descriptor loop
+fn descriptors(data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        if i + 2 > data.len() {
+            return None;
+        }
+        let tag = data[i];
+        let len = data[i + 1] as usize;
+        let body = data.get(i + 2..i + 2 + len)?;
+        i += 2 + len;
+        Some((tag, body))
+    })
+}
+
+fn language_from(body: &[u8]) -> Option<String> {
+    let lang = std::str::from_utf8(body.get(..3)?).ok()?;
+    if lang.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(lang.to_lowercase())
+    } else {
+        None
+    }
+}
+
+fn parse_ts(data: &[u8]) -> TsInfo {
+    let mut info = TsInfo::default();
+
+    // Pass 1: PAT -> PMT PIDs
+    let pmt_pids: HashSet<u16> = ts_packets(data)
+        .filter(|(pid, pusi, _)| *pid == 0 && *pusi)
+        .find_map(|(_, _, payload)| psi_section(payload).map(parse_pat))
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
+    // Pass 2: first PMT
+    let Some(pmt_payload) = ts_packets(data)
+        .find(|(pid, pusi, _)| pmt_pids.contains(pid) && *pusi)
+        .map(|(_, _, payload)| payload)
+    else {
+        return info;
+    };
+    let Some(section) = psi_section(pmt_payload) else {
+        return info;
+    };
+
+    for es in parse_pmt(section) {
+        let mut language = None;
+        let mut registration = None;
+        let mut private_kind = None;
+
+        for (tag, body) in descriptors(es.descriptors) {
+            match tag {
+                0x0A => language = language_from(body),
+                0x05 => registration = body.get(..4).map(|b| String::from_utf8_lossy(b).to_string()),
+                0x6A => private_kind = Some("ac3"),
+                0x7A => private_kind = Some("eac3"),
+                0x7C => private_kind = Some("aac"),
+                0x59 => {
+                    private_kind = Some("dvb_subtitle");
+                    language = language.or_else(|| language_from(body));
+                }
+                0x56 => {
+                    private_kind = Some("teletext");
+                    language = language.or_else(|| language_from(body));
+                }
+                _ => {}
+            }
+        }
+
+        let video_codec = match es.stream_type {
+            0x01 => Some("mpeg1video"),
+            0x02 => Some("mpeg2video"),
+            0x10 => Some("mpeg4"),
+            0x1B => Some("h264"),
+            0x24 => Some("hevc"),
+            0x33 => Some("vvc"),
+            _ => None,
+        };
+        if let Some(codec) = video_codec {
+            info.video.push(VideoTrack {
+                codec: codec.to_string(),
+                pid: Some(es.pid),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let audio_codec = match (es.stream_type, private_kind, registration.as_deref()) {
+            (0x03 | 0x04, _, _) => Some("mp3"),
+            (0x0F, _, _) => Some("aac"),
+            (0x11, _, _) => Some("aac_latm"),
+            (0x81, _, _) => Some("ac3"),
+            (0x87, _, _) => Some("eac3"),
+            (0x82 | 0x85 | 0x86, _, _) => Some("dts"),
+            (0x06, Some(kind @ ("ac3" | "eac3" | "aac")), _) => Some(kind),
+            (0x06, _, Some("AC-3")) => Some("ac3"),
+            (0x06, _, Some("EAC3")) => Some("eac3"),
+            (0x06, _, Some("Opus")) => Some("opus"),
+            _ => None,
+        };
+        if let Some(codec) = audio_codec {
+            info.audio.push(AudioTrack {
+                codec: Some(codec.to_string()),
+                language,
+                pid: Some(es.pid),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        if let (0x06, Some(format @ ("dvb_subtitle" | "teletext"))) = (es.stream_type, private_kind) {
+            info.subtitles.push(SubtitleTrack {
+                format: format.to_string(),
+                language,
+                pid: Some(es.pid),
+                ..Default::default()
+            });
+        }
+    }
+
+    // Pass 3: H.264 resolution from the first SPS on the video PID
+    if let Some(track) = info.video.iter_mut().find(|v| v.codec == "h264") {
+        let pid = track.pid;
+        let es: Vec<u8> = ts_packets(data)
+            .filter(|(p, _, _)| Some(*p) == pid)
+            .flat_map(|(_, _, payload)| payload.iter().copied())
+            .collect();
+
+        if let Some(sps) = find_nal(&es, 7) {
+            if let Some(parsed) = parse_h264_sps(&sps) {
+                track.width = Some(parsed.width);
+                track.height = Some(parsed.height);
+                track.codec_string = Some(parsed.codec_string);
+            }
+        }
+    }
+
+    info
+}
+
+// ============ H.264 SPS ============
+
+/// Find the first NAL unit of a type in an Annex B byte stream (start code excluded)
+fn find_nal(data: &[u8], nal_type: u8) -> Option<Vec<u8>> {
+    let mut i = 0;
+    while i + 3 < data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            let start = i + 3;
+            if data[start] & 0x1f == nal_type {
+                let end = (start..data.len().saturating_sub(2))
+                    .find(|&j| data[j] == 0 && data[j + 1] == 0 && (data[j + 2] == 1 || data[j + 2] == 0))
+                    .unwrap_or(data.len());
+                return Some(data[start..end].to_vec());
+            }
+            i = start;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Remove emulation prevention bytes (00 00 03 -> 00 00)
+fn unescape_rbsp(nal: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &b in nal {
+        if zeros >= 2 && b == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+        out.push(b);
+    }
+    out
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Option<u32> {
+        let byte = *self.data.get(self.pos / 8)?;
+        let bit = (byte >> (7 - (self.pos % 8))) & 1;
+        self.pos += 1;
+        Some(bit as u32)
+    }
+
+    fn bits(&mut self, n: u32) -> Option<u32> {
+        (0..n).try_fold(0u32, |acc, _| Some((acc << 1) | self.bit()?))
+    }
+
+    fn ue(&mut self) -> Option<u32> {
+        let mut zeros = 0;
+        while self.bit()? == 0 {
+            zeros += 1;
+            if zeros > 31 {
+                return None;
+            }
+        }
+        Some((1u32 << zeros) - 1 + self.bits(zeros)?)
+    }
+
+    fn se(&mut self) -> Option<i32> {
+        let v = self.ue()?;
+        Some(if v % 2 == 1 { v.div_ceil(2) as i32 } else { -((v / 2) as i32) })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct SpsInfo {
+    width: u32,
+    height: u32,
+    codec_string: String,
+}
+
+fn parse_h264_sps(nal: &[u8]) -> Option<SpsInfo> {
+    let rbsp = unescape_rbsp(nal);
+    // Skip NAL header byte
+    let mut r = BitReader::new(rbsp.get(1..)?);
+
+    let profile_idc = r.bits(8)?;
+    let constraints = r.bits(8)?;
+    let level_idc = r.bits(8)?;
+    r.ue()?; // seq_parameter_set_id
+
+    let mut chroma_format_idc = 1;
+    if matches!(profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+        chroma_format_idc = r.ue()?;
+        if chroma_format_idc == 3 {
+            r.bit()?; // separate_colour_plane_flag
+        }
+        r.ue()?; // bit_depth_luma_minus8
+        r.ue()?; // bit_depth_chroma_minus8
+        r.bit()?; // qpprime_y_zero_transform_bypass_flag
+        if r.bit()? == 1 {
+            let lists = if chroma_format_idc != 3 { 8 } else { 12 };
+            for i in 0..lists {
+                if r.bit()? == 1 {
+                    let size = if i < 6 { 16 } else { 64 };
+                    let (mut last, mut next) = (8i32, 8i32);
+                    for _ in 0..size {
+                        if next != 0 {
+                            next = last.checked_add(r.se()?)?.rem_euclid(256);
+                        }
+                        last = if next == 0 { last } else { next };
+                    }
+                }
+            }
+        }
+    }
+
+    r.ue()?; // log2_max_frame_num_minus4
+    match r.ue()? {
+        0 => {
+            r.ue()?; // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            r.bit()?;
+            r.se()?;
+            r.se()?;
+            for _ in 0..r.ue()? {
+                r.se()?;
+            }
+        }
+        _ => {}
+    }
+    r.ue()?; // max_num_ref_frames
+    r.bit()?; // gaps_in_frame_num_value_allowed_flag
+
+    let width_mbs = r.ue()?.checked_add(1)?;
+    let height_map_units = r.ue()?.checked_add(1)?;
+    let frame_mbs_only = r.bit()?;
+    if frame_mbs_only == 0 {
+        r.bit()?; // mb_adaptive_frame_field_flag
+    }
+    r.bit()?; // direct_8x8_inference_flag
+
+    // Garbled streams decode to absurd sizes: overflow means "no info", never a panic
+    let mut width = width_mbs.checked_mul(16)?;
+    let mut height = (2 - frame_mbs_only).checked_mul(height_map_units)?.checked_mul(16)?;
+
+    if r.bit()? == 1 {
+        let (left, right, top, bottom) = (r.ue()?, r.ue()?, r.ue()?, r.ue()?);
+        let (crop_x, crop_y) = match chroma_format_idc {
+            1 => (2, 2 * (2 - frame_mbs_only)),
+            2 => (2, 2 - frame_mbs_only),
+            _ => (1, 2 - frame_mbs_only),
+        };
+        width = width.checked_sub(left.checked_add(right)?.checked_mul(crop_x)?)?;
+        height = height.checked_sub(top.checked_add(bottom)?.checked_mul(crop_y)?)?;
+    }
+
+    Some(SpsInfo {
+        width,
+        height,
+        codec_string: format!("avc1.{:02x}{:02x}{:02x}", profile_idc, constraints, level_idc),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// x264 1920x1080 High@4.0 SPS
+    const SPS_1080P: &[u8] = &[
+        0x67, 0x64, 0x00, 0x28, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00,
+        0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xf0, 0x3c, 0x60, 0xc6, 0x58,
+    ];
+
+    fn ts_packet(pid: u16, payload: &[u8]) -> Vec<u8> {
+        let mut p = vec![0x47, 0x40 | ((pid >> 8) as u8 & 0x1f), pid as u8, 0x10];
+        p.extend_from_slice(payload);
+        p.resize(TS_PACKET_SIZE, 0xff);
+        p
+    }
+
+    fn section(table_id: u8, body: &[u8]) -> Vec<u8> {
+        // pointer field, table_id, length (body + CRC), body, fake CRC
+        let len = body.len() + 4;
+        let mut s = vec![0x00, table_id, 0xb0 | ((len >> 8) as u8 & 0x0f), len as u8];
+        s.extend_from_slice(body);
+        s.extend_from_slice(&[0, 0, 0, 0]);
+        s
+    }
+
+    #[test]
+    fn test_parse_h264_sps_1080p() {
+        let sps = parse_h264_sps(SPS_1080P).unwrap();
+        assert_eq!((sps.width, sps.height), (1920, 1080));
+        assert_eq!(sps.codec_string, "avc1.640028");
+    }
+
+    /// SPS from a header and an RBSP bit string ('0'/'1', zero padded)
+    fn sps_from_bits(header: &[u8], bits: &str) -> Vec<u8> {
+        let mut out = header.to_vec();
+        let bits: Vec<u8> = bits.bytes().map(|b| b - b'0').collect();
+        for chunk in bits.chunks(8) {
+            out.push(chunk.iter().enumerate().fold(0, |acc, (i, b)| acc | (b << (7 - i))));
+        }
+        out
+    }
+
+    #[test]
+    fn test_parse_h264_sps_corrupt_returns_none() {
+        let huge_ue = format!("{}1{}", "0".repeat(31), "1".repeat(31));
+
+        // Baseline profile with a width that overflows once multiplied to pixels
+        let bits = format!("111110{}1110", huge_ue);
+        assert_eq!(parse_h264_sps(&sps_from_bits(&[0x67, 66, 0, 30], &bits)), None);
+
+        // High profile with a scaling list delta that overflows the running value
+        let huge_se = format!("{}1{}0", "0".repeat(31), "1".repeat(30));
+        let bits = format!("1010110 11{}", huge_se).replace(' ', "");
+        assert_eq!(parse_h264_sps(&sps_from_bits(&[0x67, 100, 0, 40], &bits)), None);
+
+        // Truncated
+        assert_eq!(parse_h264_sps(&SPS_1080P[..6]), None);
+    }
+
+    #[test]
+    fn test_parse_ts_pat_pmt() {
+        // PAT: program 1 -> PMT PID 0x1000
+        let pat = section(0x00, &[0x00, 0x01, 0xc1, 0x00, 0x00, 0x00, 0x01, 0xf0, 0x00]);
+        // PMT: H.264 on 0x100, AC-3 (private + 0x6A) on 0x101 with "por", DVB subs on 0x102
+        let pmt_body = [
+            0x00, 0x01, 0xc1, 0x00, 0x00, 0xe1, 0x00, 0xf0, 0x00, // header, PCR PID, no program info
+            0x1b, 0xe1, 0x00, 0xf0, 0x00, // H.264
+            0x06, 0xe1, 0x01, 0xf0, 0x09, 0x6a, 0x01, 0x00, 0x0a, 0x04, b'p', b'o', b'r', 0x00,
+            0x06, 0xe1, 0x02, 0xf0, 0x0a, 0x59, 0x08, b'e', b'n', b'g', 0x10, 0x00, 0x01, 0x00, 0x01,
+        ];
+        let pmt = section(0x02, &pmt_body);
+
+        let mut video_payload = vec![0x00, 0x00, 0x00, 0x01];
+        video_payload.extend_from_slice(SPS_1080P);
+        video_payload.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x68]);
+
+        let mut data = ts_packet(0, &pat);
+        data.extend(ts_packet(0x1000, &pmt));
+        data.extend(ts_packet(0x100, &video_payload));
+
+        let info = parse_ts(&data);
+        assert_eq!(info.video.len(), 1);
+        assert_eq!(info.video[0].codec, "h264");
+        assert_eq!(info.video[0].height, Some(1080));
+        assert_eq!(info.audio.len(), 1);
+        assert_eq!(info.audio[0].codec.as_deref(), Some("ac3"));
+        assert_eq!(info.audio[0].language.as_deref(), Some("por"));
+        assert_eq!(info.subtitles.len(), 1);
+        assert_eq!(info.subtitles[0].format, "dvb_subtitle");
+        assert_eq!(info.subtitles[0].language.as_deref(), Some("eng"));
+    }
+
+    #[test]
+    fn test_parse_master_playlist() {
+        let master = "#EXTM3U\n\
+#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",LANGUAGE=\"pt\",NAME=\"Português\",CHANNELS=\"6\"\n\
+#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"sub\",LANGUAGE=\"en\",NAME=\"English\",URI=\"subs.m3u8\"\n\
+#EXT-X-STREAM-INF:BANDWIDTH=6000000,RESOLUTION=3840x2160,CODECS=\"hvc1.2.4.L153,ec-3\",FRAME-RATE=50.000\n\
+uhd.m3u8\n\
+#EXT-X-STREAM-INF:BANDWIDTH=2000000,RESOLUTION=1280x720,CODECS=\"avc1.64001f,mp4a.40.2\"\n\
+hd.m3u8\n";
+        let info = parse_master_playlist(master);
+        assert_eq!(info.variants.len(), 2);
+        assert_eq!(info.variants[0].height, Some(2160));
+        assert_eq!(info.variants[0].codecs, vec!["hvc1.2.4.L153", "ec-3"]);
+        assert_eq!(info.variants[0].frame_rate, Some(50.0));
+        assert_eq!(info.audio[0].channels.as_deref(), Some("6"));
+        assert_eq!(info.subtitles[0].language.as_deref(), Some("en"));
+        assert_eq!(info.first_variant_uri.as_deref(), Some("uhd.m3u8"));
+    }
+}
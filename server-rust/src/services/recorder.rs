@@ -0,0 +1,526 @@
+//! DVR recorder
+//!
+//! Captures live channels to local disk. Raw MPEG-TS streams are appended as they
+//! arrive; HLS streams are followed by polling the media playlist and appending new
+//! segments. State lives in the `recordings` table: each due row is claimed by one
+//! instance, which heartbeats while capturing. A restart (or another instance,
+//! once the heartbeat goes stale) resumes any recording whose time window is
+//! still open, appending to the same file.
+//!
+//! Storage quota is reserved in blocks through the database before bytes are
+//! written, so concurrent recordings of one device share a single quota.
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use reqwest::Client;
+use sqlx::PgPool;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+use uuid::Uuid;
+
+use crate::db::repository::recordings::{self, RecordingRow};
+use crate::services::hls::tag_attributes;
+
+/// How often the scheduler looks for due recordings
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(5);
+/// How often captured size is persisted while recording
+const SIZE_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+/// How often an active capture proves it's alive
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// Heartbeat age after which another instance takes the recording over
+const STALE_AFTER_SECONDS: i64 = 60;
+/// Quota reserved per database round trip
+const RESERVE_BLOCK: u64 = 16 * 1024 * 1024;
+/// Pause before reconnecting after an upstream error or end of stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+/// Number of HLS segment URIs remembered to avoid duplicates
+const SEEN_SEGMENTS: usize = 512;
+
+/// Why a capture stopped before its end time
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopReason {
+    Quota,
+}
+
+/// Media playlist contents relevant for recording
+#[derive(Debug, Default, PartialEq)]
+struct MediaPlaylist {
+    target_duration: u64,
+    segments: Vec<String>,
+    ended: bool,
+    encrypted: bool,
+}
+
+/// Output file with quota accounting
+struct RecordingSink {
+    pool: PgPool,
+    id: Uuid,
+    device_id: String,
+    file: File,
+    written: u64,
+    /// Quota held in the database for this recording (always >= written)
+    reserved: u64,
+    quota: u64,
+    last_sync: Instant,
+}
+
+impl RecordingSink {
+    /// Append a chunk. Returns false (without writing) when it would exceed the quota.
+    async fn write(&mut self, chunk: &[u8]) -> Result<bool> {
+        let needed = self.written + chunk.len() as u64;
+        if needed > self.reserved {
+            let wanted = (needed - self.reserved).max(RESERVE_BLOCK);
+            let reserved = recordings::reserve_bytes(
+                &self.pool,
+                self.id,
+                &self.device_id,
+                self.reserved as i64,
+                wanted as i64,
+                self.quota as i64,
+            )
+            .await?;
+            self.reserved = reserved.max(0) as u64;
+            if needed > self.reserved {
+                return Ok(false);
+            }
+        }
+
+        self.file.write_all(chunk).await?;
+        self.written += chunk.len() as u64;
+
+        if self.last_sync.elapsed() >= SIZE_SYNC_INTERVAL {
+            self.last_sync = Instant::now();
+            self.file.flush().await?;
+            if let Err(e) = recordings::update_size(&self.pool, self.id, self.written as i64).await {
+                tracing::warn!("Failed to update recording size for {}: {}", self.id, e);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// DVR recording service
+#[derive(Clone)]
+pub struct Recorder {
+    pool: PgPool,
+    client: Client,
+    dir: PathBuf,
+    quota_bytes: u64,
+    active: Arc<Mutex<HashMap<Uuid, CancellationToken>>>,
+}
+
+impl Recorder {
+    pub async fn new(pool: PgPool, dir: &str, quota_bytes: u64, user_agent: &str) -> Result<Self> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir).await?;
+
+        // No total timeout: captures run for the whole programme
+        let client = Client::builder()
+            .user_agent(user_agent)
+            .redirect(reqwest::redirect::Policy::limited(10))
+            .tcp_keepalive(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(10))
+            .build()?;
+
+        Ok(Self {
+            pool,
+            client,
+            dir,
+            quota_bytes,
+            active: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Storage quota per device in bytes
+    pub fn quota_bytes(&self) -> u64 {
+        self.quota_bytes
+    }
+
+    /// Path of the file holding a recording
+    pub fn file_path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.ts", id))
+    }
+
+    /// Stop an active capture. Returns false if it wasn't running.
+    pub fn cancel(&self, id: Uuid) -> bool {
+        match self.active.lock().unwrap().get(&id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// One scheduler pass: close stale rows and start due captures
+    pub async fn tick(&self) -> Result<()> {
+        let finalized = recordings::finalize_stale(&self.pool).await?;
+        if finalized > 0 {
+            tracing::info!("Finalized {} stale recordings", finalized);
+        }
+
+        for rec in recordings::claim_due(&self.pool, STALE_AFTER_SECONDS).await? {
+            let token = {
+                let mut active = self.active.lock().unwrap();
+                if active.contains_key(&rec.id) {
+                    continue;
+                }
+                let token = CancellationToken::new();
+                active.insert(rec.id, token.clone());
+                token
+            };
+
+            let recorder = self.clone();
+            tokio::spawn(async move {
+                let id = rec.id;
+                recorder.run(rec, token).await;
+                recorder.active.lock().unwrap().remove(&id);
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn run(&self, rec: RecordingRow, cancel: CancellationToken) {
+        let path = self.file_path(rec.id);
+        let mut sink = match self.open_sink(&rec, &path).await {
+            Ok(sink) => sink,
+            Err(e) => {
+                tracing::error!("Failed to start recording {}: {}", rec.id, e);
+                let _ = recordings::finish(
+                    &self.pool,
+                    rec.id,
+                    recordings::STATUS_FAILED,
+                    rec.size_bytes,
+                    Some(&e.to_string()),
+                )
+                .await;
+                return;
+            }
+        };
+
+        tracing::info!(
+            "Recording {} started: {} until {} ({} bytes already captured)",
+            rec.id,
+            rec.channel_name,
+            rec.end_at,
+            sink.written
+        );
+
+        let heartbeat = tokio::spawn(heartbeat(self.pool.clone(), rec.id, cancel.clone()));
+
+        let mut stop_reason = None;
+        let mut last_error: Option<String> = None;
+        // Kept across reconnects so a re-fetched live playlist doesn't repeat segments
+        let mut seen: VecDeque<String> = VecDeque::with_capacity(SEEN_SEGMENTS);
+
+        loop {
+            let remaining = (rec.end_at - chrono::Utc::now()).to_std().unwrap_or_default();
+            if remaining.is_zero() || cancel.is_cancelled() {
+                break;
+            }
+
+            let result = tokio::select! {
+                _ = cancel.cancelled() => break,
+                r = tokio::time::timeout(remaining, self.capture_once(&rec.stream_url, &mut sink, &mut seen)) => r,
+            };
+
+            match result {
+                // Reached end time
+                Err(_) => break,
+                Ok(Ok(Some(reason))) => {
+                    stop_reason = Some(reason);
+                    break;
+                }
+                Ok(Ok(None)) => {
+                    tracing::debug!("Recording {} upstream ended, reconnecting", rec.id);
+                }
+                Ok(Err(e)) => {
+                    tracing::warn!("Recording {} capture error: {}", rec.id, e);
+                    last_error = Some(e.to_string());
+                }
+            }
+
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+            }
+        }
+
+        heartbeat.abort();
+        let _ = sink.file.flush().await;
+        let size = sink.written as i64;
+
+        let (status, error) = match (stop_reason, size > 0) {
+            (Some(StopReason::Quota), true) => (
+                recordings::STATUS_COMPLETED,
+                Some("Cota de armazenamento excedida".to_string()),
+            ),
+            (Some(StopReason::Quota), false) => (
+                recordings::STATUS_FAILED,
+                Some("Cota de armazenamento excedida".to_string()),
+            ),
+            (None, true) => (recordings::STATUS_COMPLETED, None),
+            (None, false) => (
+                recordings::STATUS_FAILED,
+                Some(last_error.unwrap_or_else(|| "Nenhum dado gravado".to_string())),
+            ),
+        };
+
+        if let Err(e) = recordings::finish(&self.pool, rec.id, status, size, error.as_deref()).await {
+            tracing::error!("Failed to finish recording {}: {}", rec.id, e);
+        }
+
+        tracing::info!("Recording {} finished: {} ({} bytes)", rec.id, status, size);
+    }
+
+    async fn open_sink(&self, rec: &RecordingRow, path: &PathBuf) -> Result<RecordingSink> {
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+        let existing = file.metadata().await?.len();
+
+        recordings::mark_recording(&self.pool, rec.id, &path.to_string_lossy()).await?;
+
+        // What is already on disk counts as held; more is reserved on the first write
+        Ok(RecordingSink {
+            pool: self.pool.clone(),
+            id: rec.id,
+            device_id: rec.device_id.clone(),
+            file,
+            written: existing,
+            reserved: existing,
+            quota: self.quota_bytes,
+            last_sync: Instant::now(),
+        })
+    }
+
+    /// Capture until the upstream ends (Ok(None)), the quota is hit, or an error occurs
+    async fn capture_once(
+        &self,
+        url: &str,
+        sink: &mut RecordingSink,
+        seen: &mut VecDeque<String>,
+    ) -> Result<Option<StopReason>> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+        let final_url = response.url().to_string();
+
+        if content_type.contains("mpegurl") || final_url.to_lowercase().contains(".m3u8") {
+            let body = response.text().await?;
+            return self.capture_hls(&final_url, body, sink, seen).await;
+        }
+
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            if !sink.write(&chunk?).await? {
+                return Ok(Some(StopReason::Quota));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn capture_hls(
+        &self,
+        playlist_url: &str,
+        body: String,
+        sink: &mut RecordingSink,
+        seen: &mut VecDeque<String>,
+    ) -> Result<Option<StopReason>> {
+        let mut media_url = Url::parse(playlist_url)?;
+        let mut body = body;
+
+        if body.contains("#EXT-X-STREAM-INF") {
+            media_url = select_variant(&body, &media_url)
+                .ok_or_else(|| anyhow!("Master playlist sem variantes"))?;
+            body = self.fetch_text(media_url.as_str()).await?;
+        }
+
+        loop {
+            let playlist = parse_media_playlist(&body);
+            if playlist.encrypted {
+                return Err(anyhow!("HLS criptografado não suportado para gravação"));
+            }
+
+            for segment in &playlist.segments {
+                let segment_url = media_url.join(segment)?.to_string();
+                if seen.contains(&segment_url) {
+                    continue;
+                }
+
+                let response = self.client.get(&segment_url).send().await?.error_for_status()?;
+                let mut stream = response.bytes_stream();
+                while let Some(chunk) = stream.next().await {
+                    if !sink.write(&chunk?).await? {
+                        return Ok(Some(StopReason::Quota));
+                    }
+                }
+
+                if seen.len() == SEEN_SEGMENTS {
+                    seen.pop_front();
+                }
+                seen.push_back(segment_url);
+            }
+
+            if playlist.ended {
+                return Ok(None);
+            }
+
+            // Poll at half the target duration, as recommended for live playlists
+            let wait = (playlist.target_duration.max(2) / 2).min(10);
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+            body = self.fetch_text(media_url.as_str()).await?;
+        }
+    }
+
+    async fn fetch_text(&self, url: &str) -> Result<String> {
+        let response = self
+            .client
+            .get(url)
+            .timeout(Duration::from_secs(20))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.text().await?)
+    }
+}
+
+/// Keep the claim on a recording alive and cancel the capture once its end time
+/// moves into the past, or once it was deleted or is no longer recording
+/// (stopped or deleted through an API call served by another instance)
+async fn heartbeat(pool: PgPool, id: Uuid, cancel: CancellationToken) {
+    let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        match recordings::touch(&pool, id).await {
+            Ok(Some(end_at)) if end_at > chrono::Utc::now() => {}
+            Ok(_) => {
+                cancel.cancel();
+                return;
+            }
+            Err(e) => tracing::warn!("Recording {} heartbeat failed: {}", id, e),
+        }
+    }
+}
+
+/// Pick the highest-bandwidth variant of a master playlist
+fn select_variant(master: &str, base: &Url) -> Option<Url> {
+    let mut best: Option<(u64, &str)> = None;
+    let mut pending_bandwidth: Option<u64> = None;
+
+    for line in master.lines() {
+        let line = line.trim();
+        if line.starts_with("#EXT-X-STREAM-INF") {
+            let bandwidth = tag_attributes(line)
+                .into_iter()
+                .find(|(k, _)| k == "BANDWIDTH")
+                .and_then(|(_, b)| b.parse().ok())
+                .unwrap_or(0);
+            pending_bandwidth = Some(bandwidth);
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if let Some(bandwidth) = pending_bandwidth.take() {
+                if best.map(|(b, _)| bandwidth > b).unwrap_or(true) {
+                    best = Some((bandwidth, line));
+                }
+            }
+        }
+    }
+
+    best.and_then(|(_, uri)| base.join(uri).ok())
+}
+
+fn parse_media_playlist(text: &str) -> MediaPlaylist {
+    let mut playlist = MediaPlaylist {
+        target_duration: 6,
+        ..Default::default()
+    };
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            playlist.target_duration = value.trim().parse().unwrap_or(6);
+        } else if line.starts_with("#EXT-X-ENDLIST") {
+            playlist.ended = true;
+        } else if line.starts_with("#EXT-X-KEY") && !line.contains("METHOD=NONE") {
+            playlist.encrypted = true;
+        } else if !line.is_empty() && !line.starts_with('#') {
+            playlist.segments.push(line.to_string());
+        }
+    }
+
+    playlist
+}
+
+/// Start the recording scheduler (runs forever)
+pub async fn start_recorder_task(recorder: Recorder) {
+    let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = recorder.tick().await {
+            tracing::error!("Recorder scheduler error: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_variant_picks_highest_bandwidth() {
+        let master = "#EXTM3U\n\
+#EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360\n\
+low/index.m3u8\n\
+#EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080\n\
+high/index.m3u8\n";
+        let base = Url::parse("http://cdn.example/live/master.m3u8").unwrap();
+        let url = select_variant(master, &base).unwrap();
+        assert_eq!(url.as_str(), "http://cdn.example/live/high/index.m3u8");
+    }
+
+    #[test]
+    fn test_parse_media_playlist() {
+        let media = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:10\n\
+#EXT-X-MEDIA-SEQUENCE:42\n\
+#EXTINF:10.0,\n\
+seg42.ts\n\
+#EXTINF:10.0,\n\
+seg43.ts\n";
+        let playlist = parse_media_playlist(media);
+        assert_eq!(playlist.target_duration, 10);
+        assert_eq!(playlist.segments, vec!["seg42.ts", "seg43.ts"]);
+        assert!(!playlist.ended);
+        assert!(!playlist.encrypted);
+    }
+
+    #[test]
+    fn test_parse_media_playlist_endlist_and_key() {
+        let media = "#EXTM3U\n\
+#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n\
+#EXTINF:6.0,\n\
+a.ts\n\
+#EXT-X-ENDLIST\n";
+        let playlist = parse_media_playlist(media);
+        assert!(playlist.ended);
+        assert!(playlist.encrypted);
+    }
+}
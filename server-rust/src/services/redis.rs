@@ -2,20 +2,43 @@ use anyhow::Result;
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 /// Parse progress for real-time status tracking
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ParseProgress {
-    pub status: String,           // "parsing" | "building_groups" | "complete" | "failed"
+    pub status: String,           // "parsing" | "building_groups" | "complete" | "failed" | "cancelled"
     pub items_parsed: u64,
     pub items_total: Option<u64>, // Estimated based on content-length
     pub groups_count: u64,
     pub series_count: u64,
-    pub current_phase: String,    // "downloading" | "parsing" | "groups" | "series" | "done"
+    pub current_phase: String,    // "queued" | "downloading" | "parsing" | "building_groups" | "building_series" | "committing" | "done"
     pub error: Option<String>,
     pub started_at: i64,
     pub updated_at: i64,
+    /// Bytes received so far (compressed bytes for gzip responses)
+    #[serde(default)]
+    pub bytes_read: u64,
+    /// Response Content-Length, when the server sends one
+    #[serde(default)]
+    pub bytes_total: Option<u64>,
+    /// Playlist size after decompression (estimated while a gzip body downloads)
+    #[serde(default)]
+    pub decoded_total: Option<u64>,
+    /// Download/parse completion, 0-100 (None when the size is unknown)
+    #[serde(default)]
+    pub percent: Option<f64>,
+    #[serde(default)]
+    pub bytes_per_second: Option<u64>,
+    /// Estimated time until the body is fully parsed
+    #[serde(default)]
+    pub eta_ms: Option<i64>,
+    /// Duration of finished phases: connect (until response headers), parse
+    /// (streams the body, so it includes the download), groups, series, commit
+    #[serde(default)]
+    pub phase_ms: BTreeMap<String, u64>,
 }
 
 impl ParseProgress {
@@ -31,24 +54,100 @@ impl ParseProgress {
             error: None,
             started_at: now,
             updated_at: now,
+            bytes_read: 0,
+            bytes_total: None,
+            decoded_total: None,
+            percent: None,
+            bytes_per_second: None,
+            eta_ms: None,
+            phase_ms: BTreeMap::new(),
         }
     }
 
+    /// Waiting for a parse worker (reported as parsing for older clients)
+    pub fn new_queued() -> Self {
+        let mut progress = Self::new_parsing();
+        progress.current_phase = "queued".to_string();
+        progress
+    }
+
     pub fn update(&mut self, items: u64, phase: &str) {
         self.items_parsed = items;
         self.current_phase = phase.to_string();
         self.updated_at = chrono::Utc::now().timestamp_millis();
     }
 
+    /// Record bytes consumed from the body (`raw` as transferred, `decoded`
+    /// after decompression) and update percent, throughput and ETA
+    ///
+    /// For gzip bodies the decompressed size and the item total are
+    /// extrapolated from the ratio seen so far.
+    pub fn record_bytes(&mut self, raw: u64, decoded: u64, elapsed: Duration) {
+        self.bytes_read = raw;
+
+        let secs = elapsed.as_secs_f64();
+        self.bytes_per_second = (secs > 0.0).then(|| (raw as f64 / secs) as u64);
+
+        let Some(total) = self.bytes_total.filter(|t| *t > 0) else {
+            self.percent = None;
+            self.eta_ms = None;
+            return;
+        };
+
+        let fraction = (raw as f64 / total as f64).min(1.0);
+        self.percent = Some((fraction * 1000.0).round() / 10.0);
+
+        if raw > 0 && decoded > 0 {
+            let decoded_total = (decoded as f64 / fraction) as u64;
+            self.decoded_total = Some(decoded_total);
+            if self.items_parsed > 0 {
+                let items_per_byte = self.items_parsed as f64 / decoded as f64;
+                self.items_total = Some((items_per_byte * decoded_total as f64) as u64);
+            }
+        }
+
+        self.eta_ms = self
+            .bytes_per_second
+            .filter(|rate| *rate > 0)
+            .map(|rate| (total.saturating_sub(raw) as f64 / rate as f64 * 1000.0) as i64);
+    }
+
+    /// Record how long a finished phase took
+    pub fn record_phase(&mut self, phase: &str, duration: Duration) {
+        self.phase_ms.insert(phase.to_string(), duration.as_millis() as u64);
+    }
+
+    /// Phase timings for logs ("connect 1.2s, parse 53.0s, ...")
+    pub fn phase_summary(&self) -> String {
+        ["connect", "parse", "groups", "series", "commit"]
+            .iter()
+            .filter_map(|phase| {
+                self.phase_ms
+                    .get(*phase)
+                    .map(|ms| format!("{} {:.1}s", phase, *ms as f64 / 1000.0))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
     pub fn complete(mut self, groups: u64, series: u64) -> Self {
         self.status = "complete".to_string();
         self.current_phase = "done".to_string();
+        self.percent = Some(100.0);
+        self.eta_ms = Some(0);
         self.groups_count = groups;
         self.series_count = series;
         self.updated_at = chrono::Utc::now().timestamp_millis();
         self
     }
 
+    pub fn cancelled(mut self) -> Self {
+        self.status = "cancelled".to_string();
+        self.current_phase = "done".to_string();
+        self.updated_at = chrono::Utc::now().timestamp_millis();
+        self
+    }
+
     pub fn failed(mut self, error: &str) -> Self {
         self.status = "failed".to_string();
         self.error = Some(error.to_string());
@@ -60,6 +159,7 @@ impl ParseProgress {
 /// Redis service for session management and caching
 #[derive(Clone)]
 pub struct RedisService {
+    client: redis::Client,
     conn: ConnectionManager,
 }
 
@@ -67,8 +167,8 @@ impl RedisService {
     /// Create a new Redis service with connection pooling
     pub async fn new(redis_url: &str) -> Result<Self> {
         let client = redis::Client::open(redis_url)?;
-        let conn = ConnectionManager::new(client).await?;
-        Ok(Self { conn })
+        let conn = ConnectionManager::new(client.clone()).await?;
+        Ok(Self { client, conn })
     }
 
     /// Set a key with expiration (seconds)
@@ -114,6 +214,26 @@ impl RedisService {
         Ok(result.is_some())
     }
 
+    /// Increment a counter, starting its expiration window on first increment.
+    /// Creation with TTL and increment run in one transaction, so a counter can
+    /// never be left without expiry.
+    pub async fn incr_ex(&self, key: &str, ttl_seconds: u64) -> Result<u64> {
+        let mut conn = self.conn.clone();
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(key)
+            .arg(0)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .ignore()
+            .incr(key, 1)
+            .query_async(&mut conn)
+            .await?;
+        Ok(count)
+    }
+
     /// Check if a key exists
     pub async fn exists(&self, key: &str) -> Result<bool> {
         let mut conn = self.conn.clone();
@@ -137,6 +257,18 @@ impl RedisService {
         Ok(ttl)
     }
 
+    /// Publish a message to a pub/sub channel
+    pub async fn publish(&self, channel: &str, message: &str) -> Result<()> {
+        let mut conn = self.conn.clone();
+        let _: i64 = conn.publish(channel, message).await?;
+        Ok(())
+    }
+
+    /// Open a dedicated pub/sub connection
+    pub async fn pubsub(&self) -> Result<redis::aio::PubSub> {
+        Ok(self.client.get_async_pubsub().await?)
+    }
+
     /// Ping Redis to check connection
     pub async fn ping(&self) -> Result<bool> {
         let mut conn = self.conn.clone();
@@ -157,12 +289,15 @@ impl RedisService {
     pub async fn create_session(
         &self,
         session_id: &str,
+        device_id: Option<&str>,
+        pairing_code: Option<&str>,
         ttl_seconds: u64,
     ) -> Result<()> {
         use crate::models::Session;
 
         let session = Session {
-            url: None,
+            device_id: device_id.map(str::to_string),
+            pairing_code: pairing_code.map(str::to_string),
             created_at: chrono::Utc::now().timestamp_millis(),
         };
 
@@ -175,49 +310,66 @@ impl RedisService {
         self.get(&format!("session:{}", session_id)).await
     }
 
-    /// Update session with URL
-    pub async fn set_session_url(
+    /// Reserve a pairing code for a session
+    /// Returns false if the code is taken by another live session
+    pub async fn reserve_pairing_code(
         &self,
+        code: &str,
         session_id: &str,
-        url: &str,
         ttl_seconds: u64,
     ) -> Result<bool> {
-        use crate::models::Session;
-
-        // Get existing session first
-        if let Some(mut session) = self.get_session(session_id).await? {
-            session.url = Some(url.to_string());
-            self.set_ex(&format!("session:{}", session_id), &session, ttl_seconds)
-                .await?;
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+        self.set_nx_ex(&format!("session:code:{}", code), session_id, ttl_seconds)
+            .await
     }
 
-    // ============ Processing Lock Operations ============
+    /// Session id behind a pairing code
+    pub async fn resolve_pairing_code(&self, code: &str) -> Result<Option<String>> {
+        let mut conn = self.conn.clone();
+        let value: Option<String> = conn.get(format!("session:code:{}", code)).await?;
+        Ok(value)
+    }
 
-    /// Acquire processing lock (prevents duplicate parsing)
-    pub async fn acquire_processing_lock(
+    /// Store the URL sent by the phone
+    /// Returns false if a URL was already sent to this session
+    pub async fn set_session_url(
         &self,
-        hash: &str,
-        job_id: &str,
+        session_id: &str,
+        url: &str,
         ttl_seconds: u64,
     ) -> Result<bool> {
-        self.set_nx_ex(&format!("processing:{}", hash), job_id, ttl_seconds)
+        self.set_nx_ex(&format!("session:url:{}", session_id), url, ttl_seconds)
             .await
     }
 
-    /// Get processing lock value (job_id)
-    pub async fn get_processing_lock(&self, hash: &str) -> Result<Option<String>> {
+    /// Check whether the phone already sent a URL
+    pub async fn has_session_url(&self, session_id: &str) -> Result<bool> {
+        self.exists(&format!("session:url:{}", session_id)).await
+    }
+
+    /// Take the URL sent by the phone, ending the session
+    /// Only one caller ever gets the URL
+    pub async fn take_session_url(&self, session_id: &str) -> Result<Option<String>> {
         let mut conn = self.conn.clone();
-        let value: Option<String> = conn.get(format!("processing:{}", hash)).await?;
-        Ok(value)
+        let url: Option<String> = redis::cmd("GETDEL")
+            .arg(format!("session:url:{}", session_id))
+            .query_async(&mut conn)
+            .await?;
+        if url.is_some() {
+            self.delete_session(session_id).await?;
+        }
+        Ok(url)
     }
 
-    /// Release processing lock
-    pub async fn release_processing_lock(&self, hash: &str) -> Result<()> {
-        self.del(&format!("processing:{}", hash)).await
+    /// Delete a session and its pairing code
+    pub async fn delete_session(&self, session_id: &str) -> Result<()> {
+        if let Some(code) = self
+            .get_session(session_id)
+            .await?
+            .and_then(|s| s.pairing_code)
+        {
+            self.del(&format!("session:code:{}", code)).await?;
+        }
+        self.del(&format!("session:{}", session_id)).await
     }
 
     // ============ Cache Meta Operations ============
@@ -244,6 +396,8 @@ impl RedisService {
     // ============ Parse Progress Operations ============
 
     /// Set parse progress for real-time status tracking
+    ///
+    /// Use `EventHub::parse_progress` to also push it to clients.
     pub async fn set_parse_progress(&self, hash: &str, progress: &ParseProgress) -> Result<()> {
         // 1 hour TTL for progress (cleanup after completion)
         self.set_ex(&format!("progress:{}", hash), progress, 3600).await
@@ -258,4 +412,100 @@ impl RedisService {
     pub async fn del_parse_progress(&self, hash: &str) -> Result<()> {
         self.del(&format!("progress:{}", hash)).await
     }
+
+    // ============ Logo Proxy Operations ============
+
+    /// Remember that a logo URL failed (skip refetching it for ttl_seconds)
+    pub async fn mark_logo_failed(&self, url_hash: &str, ttl_seconds: u64) -> Result<()> {
+        self.set_nx_ex(&format!("logo:failed:{}", url_hash), "1", ttl_seconds)
+            .await
+            .map(|_| ())
+    }
+
+    /// Check whether a logo URL recently failed
+    pub async fn is_logo_failed(&self, url_hash: &str) -> Result<bool> {
+        self.exists(&format!("logo:failed:{}", url_hash)).await
+    }
+
+    // ============ Parental Control Operations ============
+
+    /// Lift a device's parental restrictions for ttl_seconds (after a correct PIN)
+    pub async fn set_parental_unlock(&self, device_id: &str, ttl_seconds: u64) -> Result<()> {
+        self.set_ex(&format!("parental:unlocked:{}", device_id), &true, ttl_seconds)
+            .await
+    }
+
+    /// Seconds left in a device's unlock window (None if locked)
+    pub async fn parental_unlock_ttl(&self, device_id: &str) -> Result<Option<u64>> {
+        let ttl = self.ttl(&format!("parental:unlocked:{}", device_id)).await?;
+        Ok((ttl > 0).then_some(ttl as u64))
+    }
+
+    /// End a device's unlock window
+    pub async fn clear_parental_unlock(&self, device_id: &str) -> Result<()> {
+        self.del(&format!("parental:unlocked:{}", device_id)).await
+    }
+
+    /// Count a PIN attempt; returns attempts in the current window
+    pub async fn record_pin_attempt(&self, device_id: &str, window_seconds: u64) -> Result<u64> {
+        self.incr_ex(&format!("parental:failures:{}", device_id), window_seconds)
+            .await
+    }
+
+    /// Reset PIN attempts (after a correct PIN)
+    pub async fn clear_pin_failures(&self, device_id: &str) -> Result<()> {
+        self.del(&format!("parental:failures:{}", device_id)).await
+    }
+
+    // ============ Probe Cache Operations ============
+
+    /// Cache a stream probe result (keyed by URL hash)
+    pub async fn set_probe(
+        &self,
+        url_hash: &str,
+        probe: &crate::services::probe::StreamProbe,
+        ttl_seconds: u64,
+    ) -> Result<()> {
+        self.set_ex(&format!("probe:{}", url_hash), probe, ttl_seconds)
+            .await
+    }
+
+    /// Get a cached stream probe result
+    pub async fn get_probe(
+        &self,
+        url_hash: &str,
+    ) -> Result<Option<crate::services::probe::StreamProbe>> {
+        self.get(&format!("probe:{}", url_hash)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress_bytes_and_phases() {
+        let mut progress = ParseProgress::new_parsing();
+        progress.record_bytes(1000, 4000, Duration::from_secs(2));
+        assert_eq!(progress.bytes_per_second, Some(500));
+        assert_eq!(progress.percent, None);
+        assert_eq!(progress.eta_ms, None);
+
+        // Gzip: 1 MB compressed of 4 MB decoded, halfway through the download
+        progress.bytes_total = Some(2000);
+        progress.items_parsed = 20;
+        progress.record_bytes(1000, 4000, Duration::from_secs(2));
+        assert_eq!(progress.percent, Some(50.0));
+        assert_eq!(progress.eta_ms, Some(2000));
+        assert_eq!(progress.decoded_total, Some(8000));
+        assert_eq!(progress.items_total, Some(40));
+
+        progress.record_phase("parse", Duration::from_millis(2500));
+        progress.record_phase("connect", Duration::from_millis(300));
+        assert_eq!(progress.phase_summary(), "connect 0.3s, parse 2.5s");
+
+        let done = progress.complete(3, 1);
+        assert_eq!(done.percent, Some(100.0));
+        assert_eq!(done.eta_ms, Some(0));
+    }
 }
@@ -0,0 +1,126 @@
+//! Phone-as-remote control
+//!
+//! A phone pairs with a TV through the QR session and gets a bearer token.
+//! With it, the phone browses the TV's playlist through the regular API and
+//! sends commands that are pushed to the TV's event stream.
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use uuid::Uuid;
+
+const MAX_ID_LEN: usize = 128;
+const MAX_QUERY_LEN: usize = 200;
+
+/// New random bearer token for a paired phone
+pub fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Stored form of a token
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Command sent from the phone to the TV
+///
+/// Ids are the ones returned by the playlist (or Xtream) API the phone
+/// browsed; the TV resolves them in its own playlist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum RemoteCommand {
+    /// Start playing an item (optionally from a position)
+    #[serde(rename_all = "camelCase")]
+    Play {
+        item_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position_ms: Option<i64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    OpenSeries { series_id: String },
+    /// Add an item or series to the TV's favorites
+    #[serde(rename_all = "camelCase")]
+    AddFavorite {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        item_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        series_id: Option<String>,
+    },
+    /// Open the TV's search with text typed on the phone
+    Search { query: String },
+}
+
+impl RemoteCommand {
+    /// Check ids and text before forwarding to the TV
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let valid_id = |id: &str| !id.trim().is_empty() && id.len() <= MAX_ID_LEN;
+
+        match self {
+            RemoteCommand::Play { item_id, position_ms } => {
+                if !valid_id(item_id) {
+                    return Err("Invalid itemId");
+                }
+                if position_ms.is_some_and(|p| p < 0) {
+                    return Err("Invalid positionMs");
+                }
+            }
+            RemoteCommand::OpenSeries { series_id } => {
+                if !valid_id(series_id) {
+                    return Err("Invalid seriesId");
+                }
+            }
+            RemoteCommand::AddFavorite { item_id, series_id } => match (item_id, series_id) {
+                (Some(id), None) | (None, Some(id)) if valid_id(id) => {}
+                _ => return Err("Exactly one of itemId or seriesId is required"),
+            },
+            RemoteCommand::Search { query } => {
+                if query.trim().is_empty() || query.len() > MAX_QUERY_LEN {
+                    return Err("Invalid query");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_hash() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_eq!(hash_token(&token).len(), 40);
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), hash_token(&generate_token()));
+    }
+
+    #[test]
+    fn test_command_parsing_and_validation() {
+        let play: RemoteCommand =
+            serde_json::from_str(r#"{"action":"play","itemId":"abc","positionMs":1000}"#).unwrap();
+        assert_eq!(
+            play,
+            RemoteCommand::Play { item_id: "abc".to_string(), position_ms: Some(1000) }
+        );
+        assert!(play.validate().is_ok());
+
+        let favorite: RemoteCommand =
+            serde_json::from_str(r#"{"action":"addFavorite","seriesId":"s1"}"#).unwrap();
+        assert!(favorite.validate().is_ok());
+
+        let both = RemoteCommand::AddFavorite {
+            item_id: Some("a".to_string()),
+            series_id: Some("b".to_string()),
+        };
+        assert!(both.validate().is_err());
+
+        let search = RemoteCommand::Search { query: "  ".to_string() };
+        assert!(search.validate().is_err());
+
+        assert!(serde_json::from_str::<RemoteCommand>(r#"{"action":"reboot"}"#).is_err());
+    }
+}
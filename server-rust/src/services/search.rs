@@ -0,0 +1,130 @@
+//! Search text helpers
+//!
+//! Query normalization mirrors the database side (`f_unaccent(lower(..))`), so
+//! "acao" matches "Ação" both in SQL and when computing highlight ranges.
+
+use serde::Serialize;
+
+/// Lowercase and strip common Latin diacritics (one char in, one char out)
+pub fn fold_char(c: char) -> char {
+    match c {
+        'á' | 'à' | 'â' | 'ã' | 'ä' | 'å' | 'Á' | 'À' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'a',
+        'é' | 'è' | 'ê' | 'ë' | 'É' | 'È' | 'Ê' | 'Ë' => 'e',
+        'í' | 'ì' | 'î' | 'ï' | 'Í' | 'Ì' | 'Î' | 'Ï' => 'i',
+        'ó' | 'ò' | 'ô' | 'õ' | 'ö' | 'Ó' | 'Ò' | 'Ô' | 'Õ' | 'Ö' => 'o',
+        'ú' | 'ù' | 'û' | 'ü' | 'Ú' | 'Ù' | 'Û' | 'Ü' => 'u',
+        'ç' | 'Ç' => 'c',
+        'ñ' | 'Ñ' => 'n',
+        _ => c.to_lowercase().next().unwrap_or(c),
+    }
+}
+
+/// Lowercase, unaccent and collapse whitespace
+pub fn normalize(text: &str) -> String {
+    query_tokens(text).join(" ")
+}
+
+/// Split a query into normalized alphanumeric tokens
+pub fn query_tokens(text: &str) -> Vec<String> {
+    text.chars()
+        .map(fold_char)
+        .collect::<String>()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Build a prefix tsquery ("aca:* & fil:*") so partial words typed on a
+/// remote already match. Returns None when the query has no usable tokens.
+pub fn prefix_tsquery(text: &str) -> Option<String> {
+    let tokens = query_tokens(text);
+    if tokens.is_empty() {
+        return None;
+    }
+
+    Some(
+        tokens
+            .iter()
+            .map(|t| format!("{}:*", t))
+            .collect::<Vec<_>>()
+            .join(" & "),
+    )
+}
+
+/// A matched span in a result field, in character (not byte) offsets
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Highlight {
+    pub field: &'static str,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Find spans of `text` where a word starts with one of the query tokens
+/// (accent-insensitive). Overlapping spans are merged.
+pub fn highlight_ranges(field: &'static str, text: &str, tokens: &[String]) -> Vec<Highlight> {
+    let folded: Vec<char> = text.chars().map(fold_char).collect();
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+
+    for token in tokens {
+        let token: Vec<char> = token.chars().collect();
+        if token.is_empty() || token.len() > folded.len() {
+            continue;
+        }
+
+        for start in 0..=folded.len() - token.len() {
+            let word_start = start == 0 || !folded[start - 1].is_alphanumeric();
+            if word_start && folded[start..start + token.len()] == token[..] {
+                spans.push((start, start + token.len()));
+            }
+        }
+    }
+
+    spans.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(start, end)| Highlight { field, start, end })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_strips_accents() {
+        assert_eq!(normalize("  Ação   e Reação! "), "acao e reacao");
+        assert_eq!(query_tokens("Pokémon: O Filme"), vec!["pokemon", "o", "filme"]);
+    }
+
+    #[test]
+    fn test_prefix_tsquery() {
+        assert_eq!(prefix_tsquery("acao fil").as_deref(), Some("acao:* & fil:*"));
+        assert_eq!(prefix_tsquery("'&|!"), None);
+    }
+
+    #[test]
+    fn test_highlight_ranges_accent_insensitive() {
+        let tokens = query_tokens("acao");
+        let ranges = highlight_ranges("name", "Filmes de Ação HD", &tokens);
+        assert_eq!(ranges, vec![Highlight { field: "name", start: 10, end: 14 }]);
+
+        // Only word starts match
+        assert!(highlight_ranges("name", "Reação", &tokens).is_empty());
+    }
+
+    #[test]
+    fn test_highlight_ranges_merge_overlaps() {
+        let tokens = query_tokens("the them");
+        let ranges = highlight_ranges("title", "Them", &tokens);
+        assert_eq!(ranges, vec![Highlight { field: "title", start: 0, end: 4 }]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Abuse protection for QR pairing sessions
+//!
+//! Session ids are unguessable, but typed pairing codes are only 6 digits,
+//! so creating, polling and sending are rate limited per client IP, and an
+//! IP that keeps looking up unknown sessions or codes is locked out for a
+//! while.
+
+use std::net::{IpAddr, SocketAddr};
+
+use anyhow::Result;
+use axum::http::HeaderMap;
+use uuid::Uuid;
+
+use crate::services::redis::RedisService;
+
+const RATE_WINDOW_SECONDS: u64 = 60;
+
+/// New session id (128 random bits, hex)
+pub fn generate_session_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// New 6-digit code for typing the session on the phone
+pub fn generate_pairing_code() -> String {
+    let n = u128::from_be_bytes(*Uuid::new_v4().as_bytes());
+    format!("{:06}", n % 1_000_000)
+}
+
+/// Digits of a typed code ("123 456", "123-456"); None unless exactly 6
+pub fn normalize_pairing_code(input: &str) -> Option<String> {
+    let code: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    (code.len() == 6 && code.chars().all(|c| c.is_ascii_digit())).then_some(code)
+}
+
+/// Client IP of a request
+///
+/// Behind a proxy the peer is the proxy itself; with `trust_proxy` the last
+/// `X-Forwarded-For` entry (the one our proxy appended) is used instead.
+/// Earlier entries are client-supplied and never trusted. Without a proxy
+/// the whole header is client-supplied, which is why `trust_proxy` is off
+/// unless configured.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_proxy: bool) -> String {
+    let forwarded = trust_proxy
+        .then(|| headers.get("x-forwarded-for")?.to_str().ok())
+        .flatten()
+        .and_then(|v| v.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+
+    forwarded.unwrap_or(peer.ip()).to_string()
+}
+
+/// Rate-limited session operations
+#[derive(Debug, Clone, Copy)]
+pub enum SessionAction {
+    Create,
+    Poll,
+    Send,
+}
+
+impl SessionAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            SessionAction::Create => "create",
+            SessionAction::Poll => "poll",
+            SessionAction::Send => "send",
+        }
+    }
+}
+
+/// Per-IP limits for session creation, polling, sending and lookups
+#[derive(Clone)]
+pub struct SessionGuard {
+    redis: RedisService,
+    create_limit: u64,
+    poll_limit: u64,
+    send_limit: u64,
+    max_failed_lookups: u64,
+    lockout_seconds: u64,
+}
+
+impl SessionGuard {
+    pub fn new(
+        redis: RedisService,
+        create_limit: u64,
+        send_limit: u64,
+        poll_limit: u64,
+        max_failed_lookups: u64,
+        lockout_seconds: u64,
+    ) -> Self {
+        Self {
+            redis,
+            create_limit: create_limit.max(1),
+            poll_limit: poll_limit.max(1),
+            send_limit: send_limit.max(1),
+            max_failed_lookups: max_failed_lookups.max(1),
+            lockout_seconds,
+        }
+    }
+
+    /// Count an attempt; returns seconds to wait if over the per-minute limit
+    pub async fn check_rate(&self, action: SessionAction, ip: &str) -> Result<Option<u64>> {
+        let limit = match action {
+            SessionAction::Create => self.create_limit,
+            SessionAction::Poll => self.poll_limit,
+            SessionAction::Send => self.send_limit,
+        };
+        let key = format!("ratelimit:session:{}:{}", action.as_str(), ip);
+
+        if self.redis.incr_ex(&key, RATE_WINDOW_SECONDS).await? <= limit {
+            return Ok(None);
+        }
+        Ok(Some(self.retry_after(&key).await?))
+    }
+
+    /// Seconds left in the IP's lockout, if too many lookups failed
+    pub async fn lockout(&self, ip: &str) -> Result<Option<u64>> {
+        let key = failures_key(ip);
+        let failures = self.redis.get::<u64>(&key).await?.unwrap_or(0);
+        if failures < self.max_failed_lookups {
+            return Ok(None);
+        }
+        Ok(Some(self.retry_after(&key).await?))
+    }
+
+    /// Count a lookup of an unknown or expired session or code
+    pub async fn record_failed_lookup(&self, ip: &str) -> Result<()> {
+        let failures = self
+            .redis
+            .incr_ex(&failures_key(ip), self.lockout_seconds)
+            .await?;
+        if failures == self.max_failed_lookups {
+            tracing::warn!("Session lookups locked for {} after {} failures", ip, failures);
+        }
+        Ok(())
+    }
+
+    async fn retry_after(&self, key: &str) -> Result<u64> {
+        Ok(self.redis.ttl(key).await?.max(1) as u64)
+    }
+}
+
+fn failures_key(ip: &str) -> String {
+    format!("session:failures:{}", ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_ids_and_codes() {
+        let id = generate_session_id();
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, generate_session_id());
+
+        let code = generate_pairing_code();
+        assert_eq!(normalize_pairing_code(&code), Some(code));
+        assert_eq!(normalize_pairing_code(" 123-456 "), Some("123456".to_string()));
+        assert_eq!(normalize_pairing_code("12345"), None);
+        assert_eq!(normalize_pairing_code("12a456"), None);
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let peer: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_ip(&headers, peer, true), "10.0.0.1");
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("1.1.1.1, 203.0.113.7"));
+        assert_eq!(client_ip(&headers, peer, true), "203.0.113.7");
+        assert_eq!(client_ip(&headers, peer, false), "10.0.0.1");
+
+        headers.insert("x-forwarded-for", HeaderValue::from_static("garbage"));
+        assert_eq!(client_ip(&headers, peer, true), "10.0.0.1");
+    }
+}
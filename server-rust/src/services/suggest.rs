@@ -0,0 +1,380 @@
+//! Search-as-you-type suggestions
+//!
+//! Each playlist gets an in-memory prefix index (sorted word-start keys) built
+//! from item titles, series names and groups. Lookups are a binary search plus
+//! a scan of the matching range, so they stay in the low milliseconds even on
+//! very large playlists. Indexes are keyed by playlist hash and remember the
+//! playlist id they were built from; re-parsing rebuilds them.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::db::repository::items;
+use crate::services::db_cache::DbCacheService;
+use crate::services::search::normalize;
+
+/// Only the first N words of a title start a key ("the walking dead" → 3 keys)
+const MAX_WORD_STARTS: usize = 4;
+
+/// Prefixes up to this many chars have their top results precomputed, since
+/// their key ranges cover a large part of the index
+const SHORT_PREFIX_CHARS: usize = 2;
+
+/// Results kept per precomputed short prefix
+const SHORT_PREFIX_TOP: usize = 20;
+
+/// Indexes are revalidated against the playlist's current id after this long,
+/// so a playlist re-parsed or removed by another instance stops serving stale
+/// suggestions
+const INDEX_MAX_AGE: Duration = Duration::from_secs(15 * 60);
+
+/// A single completion
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub text: String,
+    /// live | movie | series | group
+    pub kind: String,
+}
+
+struct Term {
+    text: String,
+    kind: String,
+    normalized: String,
+    weight: i64,
+}
+
+/// Prefix index over normalized terms
+pub struct SuggestIndex {
+    terms: Vec<Term>,
+    /// (term index, byte offset of a word start in `normalized`), sorted by suffix
+    keys: Vec<(u32, u16)>,
+    /// Ranked term indexes for every 1-2 char prefix
+    short: HashMap<String, Vec<u32>>,
+}
+
+impl SuggestIndex {
+    /// Build from (text, kind, weight) triples; duplicates (same normalized text
+    /// and kind) are merged and their weights summed
+    pub fn build(entries: impl IntoIterator<Item = (String, String, i64)>) -> Self {
+        let mut merged: HashMap<(String, String), usize> = HashMap::new();
+        let mut terms: Vec<Term> = Vec::new();
+
+        for (text, kind, weight) in entries {
+            let normalized = normalize(&text);
+            if normalized.is_empty() {
+                continue;
+            }
+            match merged.get(&(normalized.clone(), kind.clone())) {
+                Some(&idx) => terms[idx].weight += weight,
+                None => {
+                    merged.insert((normalized.clone(), kind.clone()), terms.len());
+                    terms.push(Term { text, kind, normalized, weight });
+                }
+            }
+        }
+
+        let mut keys = Vec::new();
+        for (idx, term) in terms.iter().enumerate() {
+            let starts = std::iter::once(0).chain(
+                term.normalized
+                    .match_indices(' ')
+                    .map(|(pos, _)| pos + 1),
+            );
+            for start in starts.take(MAX_WORD_STARTS) {
+                if start <= u16::MAX as usize {
+                    keys.push((idx as u32, start as u16));
+                }
+            }
+        }
+
+        let mut index = Self { terms, keys: Vec::new(), short: HashMap::new() };
+        keys.sort_unstable_by(|a, b| index.key(*a).cmp(index.key(*b)));
+        index.keys = keys;
+
+        let mut prefixes: HashSet<String> = HashSet::new();
+        for k in &index.keys {
+            let key = index.key(*k);
+            let ends = key.char_indices().map(|(i, c)| i + c.len_utf8());
+            for end in ends.take(SHORT_PREFIX_CHARS) {
+                if !prefixes.contains(&key[..end]) {
+                    prefixes.insert(key[..end].to_string());
+                }
+            }
+        }
+        let short = prefixes
+            .into_iter()
+            .map(|prefix| {
+                let top = index.ranked(index.range(&prefix), SHORT_PREFIX_TOP);
+                (prefix, top)
+            })
+            .collect();
+        index.short = short;
+
+        index
+    }
+
+    fn key(&self, (term, offset): (u32, u16)) -> &str {
+        &self.terms[term as usize].normalized[offset as usize..]
+    }
+
+    /// Number of distinct terms
+    pub fn term_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Top completions for a prefix: title-start matches first, then by weight,
+    /// then shorter texts
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let query = normalize(prefix);
+        if query.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let ranked = match self.short.get(&query) {
+            Some(top) if limit <= SHORT_PREFIX_TOP => top.iter().take(limit).copied().collect(),
+            _ => self.ranked(self.range(&query), limit),
+        };
+
+        ranked
+            .into_iter()
+            .map(|idx| {
+                let term = &self.terms[idx as usize];
+                Suggestion { text: term.text.clone(), kind: term.kind.clone() }
+            })
+            .collect()
+    }
+
+    /// Keys starting with `prefix` (contiguous, since keys are sorted)
+    fn range(&self, prefix: &str) -> &[(u32, u16)] {
+        let start = self.keys.partition_point(|k| self.key(*k) < prefix);
+        let len = self.keys[start..].partition_point(|k| self.key(*k).starts_with(prefix));
+        &self.keys[start..start + len]
+    }
+
+    /// Distinct terms of a key range, best first
+    fn ranked(&self, range: &[(u32, u16)], limit: usize) -> Vec<u32> {
+        let mut best: HashMap<u32, bool> = HashMap::new();
+        for k in range {
+            let at_start = best.entry(k.0).or_insert(false);
+            *at_start |= k.1 == 0;
+        }
+
+        let mut candidates: Vec<(u32, bool)> = best.into_iter().collect();
+        candidates.sort_unstable_by(|(a, a_start), (b, b_start)| {
+            let (ta, tb) = (&self.terms[*a as usize], &self.terms[*b as usize]);
+            b_start
+                .cmp(a_start)
+                .then(tb.weight.cmp(&ta.weight))
+                .then(ta.normalized.len().cmp(&tb.normalized.len()))
+                .then(ta.normalized.cmp(&tb.normalized))
+        });
+
+        candidates.into_iter().take(limit).map(|(idx, _)| idx).collect()
+    }
+}
+
+/// Index of one playlist, with the playlist id it was built from
+struct BuiltIndex {
+    playlist_id: Uuid,
+    index: Arc<SuggestIndex>,
+    built_at: Instant,
+}
+
+/// Index slot of a playlist hash; concurrent first requests share one build.
+/// Holds None when the playlist didn't exist.
+type IndexSlot = Arc<OnceCell<Option<Arc<BuiltIndex>>>>;
+
+/// Whether a slot can still be served (a build in progress counts as fresh)
+fn is_fresh(slot: &IndexSlot) -> bool {
+    match slot.get() {
+        None => true,
+        Some(Some(built)) => built.built_at.elapsed() < INDEX_MAX_AGE,
+        Some(None) => false,
+    }
+}
+
+/// Per-playlist suggestion indexes (bounded, oldest built evicted first)
+#[derive(Clone)]
+pub struct SuggestService {
+    db_cache: DbCacheService,
+    indexes: Arc<RwLock<HashMap<String, IndexSlot>>>,
+    max_playlists: usize,
+}
+
+impl SuggestService {
+    pub fn new(db_cache: DbCacheService, max_playlists: usize) -> Self {
+        Self {
+            db_cache,
+            indexes: Arc::new(RwLock::new(HashMap::new())),
+            max_playlists: max_playlists.max(1),
+        }
+    }
+
+    /// Suggestions for a playlist; builds its index on first use.
+    /// Returns None when the playlist doesn't exist.
+    pub async fn suggest(&self, hash: &str, prefix: &str, limit: usize) -> Result<Option<Vec<Suggestion>>> {
+        let (slot, previous) = self.slot(hash);
+        let built = slot.get_or_try_init(|| self.build(hash, previous)).await?.clone();
+
+        let Some(built) = built else {
+            // Don't remember missing playlists; they may be parsed later
+            let mut indexes = self.indexes.write().unwrap();
+            if indexes.get(hash).is_some_and(|current| Arc::ptr_eq(current, &slot)) {
+                indexes.remove(hash);
+            }
+            return Ok(None);
+        };
+
+        Ok(Some(built.index.suggest(prefix, limit)))
+    }
+
+    /// (Re)build the index for a playlist, e.g. right after parsing
+    pub async fn rebuild(&self, hash: &str) -> Result<()> {
+        if let Some(built) = self.build(hash, None).await? {
+            let slot = Arc::new(OnceCell::new_with(Some(Some(built))));
+            let mut indexes = self.indexes.write().unwrap();
+            self.make_room(&mut indexes, hash);
+            indexes.insert(hash.to_string(), slot);
+        }
+        Ok(())
+    }
+
+    /// Current slot of a hash, or a new empty one replacing a stale slot
+    /// (returned along with the stale index, which may still be reused)
+    fn slot(&self, hash: &str) -> (IndexSlot, Option<Arc<BuiltIndex>>) {
+        if let Some(slot) = self.indexes.read().unwrap().get(hash) {
+            if is_fresh(slot) {
+                return (slot.clone(), None);
+            }
+        }
+
+        let mut indexes = self.indexes.write().unwrap();
+        let previous = match indexes.get(hash) {
+            Some(slot) if is_fresh(slot) => return (slot.clone(), None),
+            Some(slot) => slot.get().cloned().flatten(),
+            None => None,
+        };
+        self.make_room(&mut indexes, hash);
+
+        let slot: IndexSlot = Arc::new(OnceCell::new());
+        indexes.insert(hash.to_string(), slot.clone());
+        (slot, previous)
+    }
+
+    /// Evict the oldest built index when adding `hash` would exceed the bound
+    fn make_room(&self, indexes: &mut HashMap<String, IndexSlot>, hash: &str) {
+        if indexes.len() < self.max_playlists || indexes.contains_key(hash) {
+            return;
+        }
+        let oldest = indexes
+            .iter()
+            .filter_map(|(hash, slot)| match slot.get() {
+                Some(Some(built)) => Some((hash, built.built_at)),
+                Some(None) => Some((hash, Instant::now())),
+                None => None,
+            })
+            .min_by_key(|(_, built_at)| *built_at)
+            .map(|(hash, _)| hash.clone());
+        if let Some(oldest) = oldest {
+            indexes.remove(&oldest);
+        }
+    }
+
+    /// Build the index of a playlist. An expired index built from the same
+    /// playlist id is still current and only gets its age reset.
+    async fn build(&self, hash: &str, previous: Option<Arc<BuiltIndex>>) -> Result<Option<Arc<BuiltIndex>>> {
+        let Some(playlist_id) = self.db_cache.get_playlist_id(hash).await? else {
+            return Ok(None);
+        };
+
+        if let Some(previous) = previous.filter(|p| p.playlist_id == playlist_id) {
+            return Ok(Some(Arc::new(BuiltIndex {
+                playlist_id,
+                index: previous.index.clone(),
+                built_at: Instant::now(),
+            })));
+        }
+
+        let started = Instant::now();
+        let terms = items::suggestion_terms(self.db_cache.pool(), playlist_id).await?;
+
+        let index = tokio::task::spawn_blocking(move || Arc::new(SuggestIndex::build(terms))).await?;
+        tracing::info!(
+            "Suggest index built for {}: {} terms in {}ms",
+            playlist_id,
+            index.term_count(),
+            started.elapsed().as_millis()
+        );
+
+        Ok(Some(Arc::new(BuiltIndex { playlist_id, index, built_at: Instant::now() })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> SuggestIndex {
+        SuggestIndex::build(vec![
+            ("Breaking Bad".to_string(), "series".to_string(), 62),
+            ("Bad Boys".to_string(), "movie".to_string(), 1),
+            ("Ação e Aventura".to_string(), "group".to_string(), 300),
+            ("Bad Boys".to_string(), "movie".to_string(), 1),
+            ("Globo SP".to_string(), "live".to_string(), 1),
+        ])
+    }
+
+    #[test]
+    fn test_prefix_prefers_title_start() {
+        let results = index().suggest("bad", 10);
+        let texts: Vec<&str> = results.iter().map(|s| s.text.as_str()).collect();
+        // "Bad Boys" starts with the prefix; "Breaking Bad" only matches a later word
+        assert_eq!(texts, vec!["Bad Boys", "Breaking Bad"]);
+    }
+
+    #[test]
+    fn test_accent_insensitive_and_dedup() {
+        let idx = index();
+        assert_eq!(idx.term_count(), 4);
+        assert_eq!(idx.suggest("ac", 5)[0].text, "Ação e Aventura");
+        assert_eq!(idx.suggest("AVE", 5)[0].kind, "group");
+    }
+
+    #[test]
+    fn test_empty_and_missing() {
+        let idx = index();
+        assert!(idx.suggest("", 5).is_empty());
+        assert!(idx.suggest("zzz", 5).is_empty());
+        assert_eq!(idx.suggest("b", 1).len(), 1);
+    }
+
+    #[test]
+    fn test_slot_freshness() {
+        let building: IndexSlot = Arc::new(OnceCell::new());
+        assert!(is_fresh(&building));
+
+        let missing: IndexSlot = Arc::new(OnceCell::new_with(Some(None)));
+        assert!(!is_fresh(&missing));
+
+        let built = |built_at| {
+            let index = Arc::new(SuggestIndex::build(Vec::new()));
+            let slot: IndexSlot = Arc::new(OnceCell::new_with(Some(Some(Arc::new(BuiltIndex {
+                playlist_id: Uuid::nil(),
+                index,
+                built_at,
+            })))));
+            slot
+        };
+        assert!(is_fresh(&built(Instant::now())));
+        let expired = Instant::now().checked_sub(INDEX_MAX_AGE + Duration::from_secs(1));
+        if let Some(expired) = expired {
+            assert!(!is_fresh(&built(expired)));
+        }
+    }
+}
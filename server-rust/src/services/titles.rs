@@ -0,0 +1,208 @@
+//! Movie titles with variants
+//!
+//! Movie items sharing a normalized parsed title + year are presented as one
+//! title whose variants differ in quality, audio (dubbed/subbed) and language.
+//! Each title carries a default variant chosen from the device's preferences.
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::repository::preferences::DevicePreferencesRow;
+use crate::db::repository::titles::TitleVariantRow;
+use crate::models::{MovieTitle, TitleVariant};
+
+/// Preferred audio for the default variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioPreference {
+    #[default]
+    Any,
+    Dubbed,
+    Subbed,
+}
+
+impl AudioPreference {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "any" | "" => Some(AudioPreference::Any),
+            "dubbed" => Some(AudioPreference::Dubbed),
+            "subbed" => Some(AudioPreference::Subbed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioPreference::Any => "any",
+            AudioPreference::Dubbed => "dubbed",
+            AudioPreference::Subbed => "subbed",
+        }
+    }
+}
+
+/// How a device picks the default variant of a title
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantPreferences {
+    /// Highest quality to pick by default (e.g. "FHD" on devices without 4K)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_quality: Option<String>,
+    #[serde(default)]
+    pub audio: AudioPreference,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl From<DevicePreferencesRow> for VariantPreferences {
+    fn from(row: DevicePreferencesRow) -> Self {
+        Self {
+            max_quality: row.max_quality,
+            audio: AudioPreference::parse(&row.audio).unwrap_or_default(),
+            language: row.language,
+        }
+    }
+}
+
+/// Rank of a quality label, higher is better. Also written to
+/// `playlist_items.quality_rank` for the quality sort.
+pub fn quality_rank(label: Option<&str>) -> i16 {
+    match label.map(str::to_uppercase).as_deref() {
+        Some("8K" | "4320P") => 6,
+        Some("4K" | "UHD" | "2160P") => 5,
+        Some("FHD" | "1080P") => 4,
+        Some("HD" | "720P") => 3,
+        Some("SD" | "480P" | "360P") => 2,
+        Some(_) => 1,
+        None => 0,
+    }
+}
+
+/// Index of the variant to play by default
+///
+/// Matching audio beats matching language, which beats quality. Within the
+/// device's max quality the best quality wins; above it, the closest one.
+/// Ties keep playlist order.
+pub fn default_variant(variants: &[TitleVariant], prefs: &VariantPreferences) -> usize {
+    let max_rank = prefs.max_quality.as_deref().map(|q| quality_rank(Some(q)));
+
+    let score = |v: &TitleVariant| {
+        let audio = match prefs.audio {
+            AudioPreference::Any => true,
+            AudioPreference::Dubbed => v.is_dubbed || v.is_multi_audio,
+            AudioPreference::Subbed => v.is_subbed,
+        };
+        let language = match (&prefs.language, &v.language) {
+            (None, _) => true,
+            (Some(wanted), Some(found)) => wanted.eq_ignore_ascii_case(found),
+            (Some(_), None) => false,
+        };
+        let within = max_rank.is_none_or(|max| v.quality_rank <= max);
+        let quality = if within { v.quality_rank } else { -v.quality_rank };
+        (audio, language, within, quality)
+    };
+
+    variants
+        .iter()
+        .enumerate()
+        .max_by_key(|(idx, v)| (score(v), std::cmp::Reverse(*idx)))
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+fn to_variant(row: &TitleVariantRow) -> TitleVariant {
+    let item = &row.item;
+    TitleVariant {
+        id: item.item_hash.clone(),
+        name: item.name.clone(),
+        url: item.url.clone(),
+        group: item.group_name.clone(),
+        logo: item.logo.clone(),
+        quality: item.parsed_quality.clone(),
+        language: item.parsed_language.clone(),
+        is_dubbed: item.is_dubbed,
+        is_subbed: item.is_subbed,
+        is_multi_audio: item.is_multi_audio,
+        quality_rank: row.quality_rank,
+    }
+}
+
+/// Cluster variant rows (ordered so each title's rows are contiguous) into titles
+pub fn build_titles(rows: &[TitleVariantRow], prefs: &VariantPreferences) -> Vec<MovieTitle> {
+    rows.chunk_by(|a, b| a.title_key == b.title_key)
+        .map(|chunk| {
+            let first = &chunk[0].item;
+            let variants: Vec<TitleVariant> = chunk.iter().map(to_variant).collect();
+
+            let mut groups: Vec<String> = Vec::new();
+            for v in &variants {
+                if !groups.contains(&v.group) {
+                    groups.push(v.group.clone());
+                }
+            }
+
+            MovieTitle {
+                id: chunk[0].title_key.clone(),
+                title: first.parsed_title.clone().unwrap_or_else(|| first.name.clone()),
+                year: first.parsed_year.map(|y| y as u16),
+                logo: variants.iter().find_map(|v| v.logo.clone()),
+                groups,
+                default_variant_id: variants[default_variant(&variants, prefs)].id.clone(),
+                variants,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(id: &str, quality: Option<&str>, dubbed: bool, subbed: bool) -> TitleVariant {
+        TitleVariant {
+            id: id.to_string(),
+            name: id.to_string(),
+            url: format!("http://x/{}", id),
+            group: "Filmes".to_string(),
+            logo: None,
+            quality: quality.map(str::to_string),
+            language: None,
+            is_dubbed: dubbed,
+            is_subbed: subbed,
+            is_multi_audio: false,
+            quality_rank: quality_rank(quality),
+        }
+    }
+
+    #[test]
+    fn test_quality_rank() {
+        assert_eq!(quality_rank(Some("4k")), 5);
+        assert_eq!(quality_rank(Some("1080P")), 4);
+        assert_eq!(quality_rank(Some("CAM")), 1);
+        assert_eq!(quality_rank(None), 0);
+    }
+
+    #[test]
+    fn test_default_variant_preferences() {
+        let variants = vec![
+            variant("uhd", Some("4K"), false, true),
+            variant("fhd-dub", Some("FHD"), true, false),
+            variant("leg", None, false, true),
+        ];
+
+        // No preference: best quality
+        assert_eq!(default_variant(&variants, &VariantPreferences::default()), 0);
+
+        // Device without 4K
+        let prefs = VariantPreferences { max_quality: Some("FHD".to_string()), ..Default::default() };
+        assert_eq!(default_variant(&variants, &prefs), 1);
+
+        // Audio beats quality
+        let prefs = VariantPreferences { audio: AudioPreference::Subbed, ..Default::default() };
+        assert_eq!(default_variant(&variants, &prefs), 0);
+        let prefs = VariantPreferences { audio: AudioPreference::Dubbed, ..Default::default() };
+        assert_eq!(default_variant(&variants, &prefs), 1);
+
+        // Only variants above the max: closest one
+        let prefs = VariantPreferences { max_quality: Some("SD".to_string()), ..Default::default() };
+        assert_eq!(default_variant(&variants[..2], &prefs), 1);
+    }
+}
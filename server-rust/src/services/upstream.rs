@@ -0,0 +1,507 @@
+//! Shared upstream HTTP clients for the proxy
+//!
+//! Keeps one keep-alive `reqwest::Client` per upstream host so segment fetches
+//! reuse warm connections, and guards every host with a circuit breaker so a
+//! dead IPTV server fails fast instead of tying up TV players for the full timeout.
+//!
+//! Metrics are labeled by host, but only the first `MAX_METRIC_HOSTS` hosts get
+//! their own label; the rest share `other` so playlists with thousands of CDN
+//! hosts don't blow up Prometheus cardinality.
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, HistogramVec,
+    IntCounterVec, IntGaugeVec,
+};
+use reqwest::Client;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use url::Url;
+
+lazy_static! {
+    static ref UPSTREAM_LATENCY: HistogramVec = register_histogram_vec!(
+        "upstream_request_duration_seconds",
+        "Time until upstream response headers are received",
+        &["host"],
+        vec![0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .unwrap();
+    static ref UPSTREAM_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "upstream_requests_total",
+        "Upstream requests by host and outcome",
+        &["host", "outcome"]
+    )
+    .unwrap();
+    static ref UPSTREAM_CIRCUIT_OPEN: IntGaugeVec = register_int_gauge_vec!(
+        "upstream_circuit_open",
+        "1 when the circuit breaker for the host is open",
+        &["host"]
+    )
+    .unwrap();
+}
+
+/// Hosts with their own metric label; later hosts are reported as `other`
+const MAX_METRIC_HOSTS: usize = 64;
+const OTHER_HOSTS_LABEL: &str = "other";
+
+/// Circuit breaker tuning
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerConfig {
+    /// Consecutive failures before the circuit opens
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a probe request is allowed
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    /// A probe request is in flight; another is allowed if it never reports back
+    HalfOpen { probe_started: Instant },
+}
+
+/// Per-host circuit breaker (closed -> open -> half-open -> closed)
+///
+/// Every transition bumps a generation. Requests are admitted with the current
+/// generation and their result only counts if the breaker is still in it, so a
+/// slow request admitted while closed can't close a circuit that opened since.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    state: BreakerState,
+    generation: u64,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: BreakerState::Closed { failures: 0 },
+            generation: 0,
+        }
+    }
+
+    fn transition(&mut self, state: BreakerState) {
+        self.state = state;
+        self.generation += 1;
+    }
+
+    /// Admit a request now, returning the generation to report its result with.
+    /// In half-open state only one probe is let through.
+    pub fn try_acquire(&mut self, now: Instant) -> Option<u64> {
+        match self.state {
+            BreakerState::Closed { .. } => {}
+            BreakerState::Open { until } if now >= until => {
+                self.transition(BreakerState::HalfOpen { probe_started: now });
+            }
+            BreakerState::Open { .. } => return None,
+            BreakerState::HalfOpen { probe_started } if now >= probe_started + self.config.cooldown => {
+                // The previous probe never reported back; its late result is ignored
+                self.transition(BreakerState::HalfOpen { probe_started: now });
+            }
+            BreakerState::HalfOpen { .. } => return None,
+        }
+        Some(self.generation)
+    }
+
+    pub fn record_success(&mut self, generation: u64) {
+        if generation != self.generation {
+            return;
+        }
+        match self.state {
+            BreakerState::Closed { .. } => self.state = BreakerState::Closed { failures: 0 },
+            _ => self.transition(BreakerState::Closed { failures: 0 }),
+        }
+    }
+
+    pub fn record_failure(&mut self, generation: u64, now: Instant) {
+        if generation != self.generation {
+            return;
+        }
+        match self.state {
+            BreakerState::Closed { failures } if failures + 1 < self.config.failure_threshold => {
+                self.state = BreakerState::Closed {
+                    failures: failures + 1,
+                };
+            }
+            _ => self.transition(BreakerState::Open {
+                until: now + self.config.cooldown,
+            }),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(self.state, BreakerState::Closed { .. })
+    }
+}
+
+struct HostEntry {
+    client: Client,
+    /// Metric label: the host itself, or `other` past `MAX_METRIC_HOSTS`
+    label: String,
+    breaker: Mutex<CircuitBreaker>,
+    last_used: Mutex<Instant>,
+}
+
+/// Error returned when a request can't be sent upstream
+#[derive(Debug)]
+pub enum UpstreamError {
+    /// URL has no usable host
+    InvalidUrl,
+    /// Host is failing, request rejected without contacting it
+    CircuitOpen(String),
+    /// HTTP client could not be built
+    Client(String),
+}
+
+impl std::fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpstreamError::InvalidUrl => write!(f, "Invalid upstream URL"),
+            UpstreamError::CircuitOpen(host) => write!(f, "Circuit open for host {}", host),
+            UpstreamError::Client(e) => write!(f, "Failed to create HTTP client: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+/// Outcome of an upstream request, used for metrics and the breaker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpstreamOutcome {
+    Ok,
+    /// Non-5xx error status: the host is alive, the breaker is not affected
+    ClientError,
+    ServerError,
+    Timeout,
+    ConnectError,
+}
+
+impl UpstreamOutcome {
+    fn label(&self) -> &'static str {
+        match self {
+            UpstreamOutcome::Ok => "ok",
+            UpstreamOutcome::ClientError => "client_error",
+            UpstreamOutcome::ServerError => "server_error",
+            UpstreamOutcome::Timeout => "timeout",
+            UpstreamOutcome::ConnectError => "connect_error",
+        }
+    }
+
+    pub fn from_status(status: reqwest::StatusCode) -> Self {
+        if status.is_server_error() {
+            UpstreamOutcome::ServerError
+        } else if status.is_client_error() {
+            UpstreamOutcome::ClientError
+        } else {
+            UpstreamOutcome::Ok
+        }
+    }
+
+    pub fn from_error(e: &reqwest::Error) -> Self {
+        if e.is_timeout() {
+            UpstreamOutcome::Timeout
+        } else {
+            UpstreamOutcome::ConnectError
+        }
+    }
+
+    fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            UpstreamOutcome::ServerError | UpstreamOutcome::Timeout | UpstreamOutcome::ConnectError
+        )
+    }
+}
+
+/// A request slot for one host. Report the result with `finish` so latency
+/// and breaker state are recorded.
+pub struct UpstreamHandle {
+    pub client: Client,
+    host: String,
+    entry: Arc<HostEntry>,
+    generation: u64,
+    started: Instant,
+}
+
+impl UpstreamHandle {
+    pub fn finish(self, outcome: UpstreamOutcome) {
+        let label = self.entry.label.as_str();
+        UPSTREAM_LATENCY
+            .with_label_values(&[label])
+            .observe(self.started.elapsed().as_secs_f64());
+        UPSTREAM_REQUESTS
+            .with_label_values(&[label, outcome.label()])
+            .inc();
+
+        let mut breaker = self.entry.breaker.lock().unwrap();
+        let was_open = breaker.is_open();
+        if outcome.is_failure() {
+            breaker.record_failure(self.generation, Instant::now());
+        } else {
+            breaker.record_success(self.generation);
+        }
+
+        let open = breaker.is_open();
+        if open != was_open {
+            // A shared `other` gauge would flap between unrelated hosts
+            if label != OTHER_HOSTS_LABEL {
+                UPSTREAM_CIRCUIT_OPEN.with_label_values(&[label]).set(open as i64);
+            }
+            if open {
+                tracing::warn!("Upstream circuit opened for {}", self.host);
+            } else {
+                tracing::info!("Upstream circuit closed for {}", self.host);
+            }
+        }
+    }
+}
+
+/// Per-host client pool shared by the proxy handlers
+pub struct UpstreamPool {
+    user_agent: String,
+    breaker_config: BreakerConfig,
+    max_hosts: usize,
+    hosts: RwLock<HashMap<String, Arc<HostEntry>>>,
+    /// Hosts that were given their own metric label (never shrinks, labels persist)
+    labeled_hosts: Mutex<HashSet<String>>,
+}
+
+impl UpstreamPool {
+    pub fn new(user_agent: &str, breaker_config: BreakerConfig, max_hosts: usize) -> Self {
+        Self {
+            user_agent: user_agent.to_string(),
+            breaker_config,
+            max_hosts: max_hosts.max(1),
+            hosts: RwLock::new(HashMap::new()),
+            labeled_hosts: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Get a client for the URL's host, failing fast if its circuit is open
+    pub fn acquire(&self, url: &str) -> Result<UpstreamHandle, UpstreamError> {
+        let host = host_key(url).ok_or(UpstreamError::InvalidUrl)?;
+        let entry = self.entry_for(&host)?;
+        let now = Instant::now();
+
+        let Some(generation) = entry.breaker.lock().unwrap().try_acquire(now) else {
+            UPSTREAM_REQUESTS
+                .with_label_values(&[&entry.label, "circuit_open"])
+                .inc();
+            return Err(UpstreamError::CircuitOpen(host));
+        };
+        *entry.last_used.lock().unwrap() = now;
+
+        Ok(UpstreamHandle {
+            client: entry.client.clone(),
+            host,
+            entry,
+            generation,
+            started: now,
+        })
+    }
+
+    /// Metric label for a host: its own name while under the cap, `other` after
+    fn metric_label(&self, host: &str) -> String {
+        let mut labeled = self.labeled_hosts.lock().unwrap();
+        if labeled.contains(host) {
+            return host.to_string();
+        }
+        if labeled.len() < MAX_METRIC_HOSTS {
+            labeled.insert(host.to_string());
+            return host.to_string();
+        }
+        OTHER_HOSTS_LABEL.to_string()
+    }
+
+    /// Number of hosts with a pooled client
+    pub fn host_count(&self) -> usize {
+        self.hosts.read().unwrap().len()
+    }
+
+    fn entry_for(&self, host: &str) -> Result<Arc<HostEntry>, UpstreamError> {
+        if let Some(entry) = self.hosts.read().unwrap().get(host) {
+            return Ok(Arc::clone(entry));
+        }
+
+        let mut hosts = self.hosts.write().unwrap();
+        if let Some(entry) = hosts.get(host) {
+            return Ok(Arc::clone(entry));
+        }
+
+        if hosts.len() >= self.max_hosts {
+            // Drop the least recently used host to keep the pool bounded
+            let oldest = hosts
+                .iter()
+                .min_by_key(|(_, e)| *e.last_used.lock().unwrap())
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                if let Some(evicted) = hosts.remove(&oldest) {
+                    if evicted.label != OTHER_HOSTS_LABEL {
+                        UPSTREAM_CIRCUIT_OPEN.remove_label_values(&[&evicted.label]).ok();
+                    }
+                }
+            }
+        }
+
+        // Client optimized for streaming:
+        // - TCP keepalive prevents NAT/firewall from closing idle connections
+        // - Connect timeout ensures we don't hang on unreachable servers
+        // - Pool idle timeout keeps connections alive for reuse
+        // - No read timeout allows indefinite streaming for live content
+        let client = Client::builder()
+            .user_agent(&self.user_agent)
+            .redirect(reqwest::redirect::Policy::limited(10))
+            .tcp_keepalive(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(10))
+            .pool_idle_timeout(Duration::from_secs(90))
+            .pool_max_idle_per_host(16)
+            .build()
+            .map_err(|e| UpstreamError::Client(e.to_string()))?;
+
+        let entry = Arc::new(HostEntry {
+            client,
+            label: self.metric_label(host),
+            breaker: Mutex::new(CircuitBreaker::new(self.breaker_config)),
+            last_used: Mutex::new(Instant::now()),
+        });
+        hosts.insert(host.to_string(), Arc::clone(&entry));
+
+        Ok(entry)
+    }
+}
+
+/// Pool key for a URL: host plus explicit port
+fn host_key(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    Some(match parsed.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(10),
+        })
+    }
+
+    #[test]
+    fn test_breaker_opens_after_threshold() {
+        let mut b = breaker();
+        let now = Instant::now();
+        let g = b.try_acquire(now).unwrap();
+        b.record_failure(g, now);
+        b.record_failure(g, now);
+        assert!(b.try_acquire(now).is_some());
+        b.record_failure(g, now);
+        assert!(b.is_open());
+        assert!(b.try_acquire(now + Duration::from_secs(5)).is_none());
+    }
+
+    #[test]
+    fn test_breaker_half_open_single_probe() {
+        let mut b = breaker();
+        let now = Instant::now();
+        let g = b.try_acquire(now).unwrap();
+        for _ in 0..3 {
+            b.record_failure(g, now);
+        }
+        let later = now + Duration::from_secs(11);
+        let probe = b.try_acquire(later).unwrap();
+        assert!(b.try_acquire(later).is_none());
+
+        // Failed probe re-opens, successful probe closes
+        b.record_failure(probe, later);
+        assert!(b.try_acquire(later + Duration::from_secs(1)).is_none());
+        let much_later = later + Duration::from_secs(11);
+        let probe = b.try_acquire(much_later).unwrap();
+        b.record_success(probe);
+        assert!(!b.is_open());
+        assert!(b.try_acquire(much_later).is_some());
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let mut b = breaker();
+        let now = Instant::now();
+        let g = b.try_acquire(now).unwrap();
+        b.record_failure(g, now);
+        b.record_failure(g, now);
+        b.record_success(g);
+        b.record_failure(g, now);
+        b.record_failure(g, now);
+        assert!(!b.is_open());
+    }
+
+    #[test]
+    fn test_late_result_from_previous_state_is_ignored() {
+        let mut b = breaker();
+        let now = Instant::now();
+        let slow = b.try_acquire(now).unwrap();
+        let g = b.try_acquire(now).unwrap();
+        for _ in 0..3 {
+            b.record_failure(g, now);
+        }
+        assert!(b.is_open());
+
+        // Admitted while closed, finishes after the circuit opened
+        b.record_success(slow);
+        assert!(b.is_open());
+
+        // Same for a stale probe superseded by a newer one
+        let later = now + Duration::from_secs(11);
+        let stale_probe = b.try_acquire(later).unwrap();
+        let probe = b.try_acquire(later + Duration::from_secs(11)).unwrap();
+        b.record_success(stale_probe);
+        assert!(b.is_open());
+        b.record_success(probe);
+        assert!(!b.is_open());
+    }
+
+    #[test]
+    fn test_metric_labels_are_capped() {
+        let pool = UpstreamPool::new("test", BreakerConfig::default(), MAX_METRIC_HOSTS + 8);
+        for i in 0..MAX_METRIC_HOSTS {
+            assert_eq!(pool.metric_label(&format!("h{}.example", i)), format!("h{}.example", i));
+        }
+        assert_eq!(pool.metric_label("late.example"), OTHER_HOSTS_LABEL);
+        assert_eq!(pool.metric_label("h0.example"), "h0.example");
+    }
+
+    #[test]
+    fn test_host_key() {
+        assert_eq!(host_key("http://CDN.example.com/a.ts").as_deref(), Some("cdn.example.com"));
+        assert_eq!(host_key("http://1.2.3.4:8080/live/a.m3u8").as_deref(), Some("1.2.3.4:8080"));
+        assert_eq!(host_key("not a url"), None);
+    }
+
+    #[test]
+    fn test_pool_reuses_host_entry() {
+        let pool = UpstreamPool::new("test", BreakerConfig::default(), 2);
+        let a = pool.acquire("http://a.example/1.ts").unwrap();
+        a.finish(UpstreamOutcome::Ok);
+        pool.acquire("http://a.example/2.ts").unwrap().finish(UpstreamOutcome::Ok);
+        assert_eq!(pool.host_count(), 1);
+        pool.acquire("http://b.example/1.ts").unwrap().finish(UpstreamOutcome::Ok);
+        pool.acquire("http://c.example/1.ts").unwrap().finish(UpstreamOutcome::Ok);
+        assert_eq!(pool.host_count(), 2);
+    }
+}
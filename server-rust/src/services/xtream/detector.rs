@@ -71,6 +71,92 @@ pub fn extract_credentials(m3u_url: &str) -> Option<XtreamCredentials> {
     })
 }
 
+/// Why credential validation failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum CredentialError {
+    /// HTTP client couldn't be built
+    Client(String),
+    Timeout,
+    Unreachable,
+    Request(String),
+    /// Non-2xx status from player_api.php
+    Http(u16),
+    Read(String),
+    /// HTML page instead of JSON (usually wrong username or password)
+    Html,
+    InvalidJson(String),
+    /// Account exists but isn't active (status as reported by the server)
+    Inactive(String),
+}
+
+impl CredentialError {
+    /// Message for end users (Portuguese, shown on the phone or TV)
+    pub fn user_message(&self) -> String {
+        match self {
+            CredentialError::Timeout => "O servidor não respondeu. Verifique o endereço e tente novamente.".to_string(),
+            CredentialError::Unreachable => "Não foi possível conectar ao servidor. Verifique o endereço e a porta.".to_string(),
+            CredentialError::Http(401) | CredentialError::Http(403) | CredentialError::Html => {
+                "Usuário ou senha inválidos.".to_string()
+            }
+            CredentialError::Http(code) => format!("O servidor recusou o acesso (HTTP {}).", code),
+            CredentialError::InvalidJson(_) => "O endereço informado não parece ser um servidor Xtream.".to_string(),
+            CredentialError::Inactive(status) => format!("Sua conta não está ativa (status: {}).", status),
+            CredentialError::Client(_) | CredentialError::Request(_) | CredentialError::Read(_) => {
+                "Não foi possível validar a conta. Tente novamente.".to_string()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for CredentialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialError::Client(e) => write!(f, "Failed to create HTTP client: {}", e),
+            CredentialError::Timeout => write!(f, "Connection timeout - server did not respond"),
+            CredentialError::Unreachable => write!(f, "Connection failed - server unreachable"),
+            CredentialError::Request(e) => write!(f, "Request failed: {}", e),
+            CredentialError::Http(code) => write!(f, "HTTP error: {}", code),
+            CredentialError::Read(e) => write!(f, "Failed to read response: {}", e),
+            CredentialError::Html => {
+                write!(f, "Server returned HTML instead of JSON - likely invalid credentials")
+            }
+            CredentialError::InvalidJson(e) => write!(f, "Invalid JSON response: {}", e),
+            CredentialError::Inactive(status) => write!(f, "Account not active. Status: {}", status),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {}
+
+/// Normalize a server address typed by a user into a base URL
+///
+/// Accepts a bare host (`example.com:8080`), a base URL or a full URL with
+/// a path (`http://example.com:8080/get.php?...`); the scheme defaults to http.
+pub fn normalize_server(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    let with_scheme = if input.contains("://") {
+        input.to_string()
+    } else {
+        format!("http://{}", input)
+    };
+
+    let parsed = Url::parse(&with_scheme).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = parsed.host_str()?;
+    let port_suffix = parsed
+        .port()
+        .map(|p| format!(":{}", p))
+        .unwrap_or_default();
+
+    Some(format!("{}://{}{}", parsed.scheme(), host, port_suffix))
+}
+
 /// Validate Xtream credentials by calling the player_api.php endpoint
 ///
 /// Makes a request to `{server}/player_api.php?username=X&password=Y`
@@ -78,8 +164,8 @@ pub fn extract_credentials(m3u_url: &str) -> Option<XtreamCredentials> {
 ///
 /// # Returns
 /// - `Ok(XtreamAuthResponse)` if credentials are valid and account is active
-/// - `Err(String)` with error message if validation fails
-pub async fn validate_credentials(creds: &XtreamCredentials) -> Result<XtreamAuthResponse, String> {
+/// - `Err(CredentialError)` describing why validation failed
+pub async fn validate_credentials(creds: &XtreamCredentials) -> Result<XtreamAuthResponse, CredentialError> {
     let url = creds.api_url();
 
     debug!("Validating Xtream credentials at: {}", url);
@@ -88,7 +174,7 @@ pub async fn validate_credentials(creds: &XtreamCredentials) -> Result<XtreamAut
         .timeout(Duration::from_secs(XTREAM_TIMEOUT_SECS))
         .danger_accept_invalid_certs(true) // Many Xtream servers have self-signed certs
         .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        .map_err(|e| CredentialError::Client(e.to_string()))?;
 
     let response = client
         .get(&url)
@@ -97,39 +183,36 @@ pub async fn validate_credentials(creds: &XtreamCredentials) -> Result<XtreamAut
         .await
         .map_err(|e| {
             if e.is_timeout() {
-                "Connection timeout - server did not respond".to_string()
+                CredentialError::Timeout
             } else if e.is_connect() {
-                "Connection failed - server unreachable".to_string()
+                CredentialError::Unreachable
             } else {
-                format!("Request failed: {}", e)
+                CredentialError::Request(e.to_string())
             }
         })?;
 
     let status = response.status();
     if !status.is_success() {
-        return Err(format!("HTTP error: {}", status));
+        return Err(CredentialError::Http(status.as_u16()));
     }
 
     // Try to parse as JSON
-    let text = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?;
+    let text = response.text().await.map_err(|e| CredentialError::Read(e.to_string()))?;
 
     // Some servers return HTML error pages instead of JSON
     if text.trim().starts_with('<') {
-        return Err("Server returned HTML instead of JSON - likely invalid credentials".to_string());
+        return Err(CredentialError::Html);
     }
 
     let auth: XtreamAuthResponse = serde_json::from_str(&text).map_err(|e| {
         debug!("Failed to parse response as XtreamAuthResponse: {}", e);
         debug!("Response text: {}", &text[..text.len().min(500)]);
-        format!("Invalid JSON response: {}", e)
+        CredentialError::InvalidJson(e.to_string())
     })?;
 
     // Check account status
     if !auth.user_info.is_active() {
-        return Err(format!(
-            "Account not active. Status: {}",
-            auth.user_info.status
-        ));
+        return Err(CredentialError::Inactive(auth.user_info.status.clone()));
     }
 
     info!(
@@ -298,6 +381,43 @@ mod tests {
         assert!(extract_credentials(url).is_none());
     }
 
+    #[test]
+    fn test_normalize_server() {
+        assert_eq!(normalize_server("example.com:8080").as_deref(), Some("http://example.com:8080"));
+        assert_eq!(normalize_server(" https://example.com/ ").as_deref(), Some("https://example.com"));
+        assert_eq!(
+            normalize_server("http://example.com:25461/get.php?username=a&password=b").as_deref(),
+            Some("http://example.com:25461")
+        );
+        assert!(normalize_server("").is_none());
+        assert!(normalize_server("ftp://example.com").is_none());
+    }
+
+    #[test]
+    fn test_m3u_url_roundtrip() {
+        let creds = XtreamCredentials {
+            server: "http://example.com:8080".to_string(),
+            username: "user".to_string(),
+            password: "p&ss word".to_string(),
+            preferred_live_format: "ts".to_string(),
+        };
+
+        let extracted = extract_credentials(&creds.m3u_url()).expect("Should extract credentials");
+        assert_eq!(extracted.server, creds.server);
+        assert_eq!(extracted.username, "user");
+        assert_eq!(extracted.password, "p&ss word");
+    }
+
+    #[test]
+    fn test_credential_error_messages() {
+        assert_eq!(CredentialError::Html.user_message(), "Usuário ou senha inválidos.");
+        assert_eq!(CredentialError::Http(403).user_message(), "Usuário ou senha inválidos.");
+        assert_eq!(
+            CredentialError::Inactive("Expired".to_string()).to_string(),
+            "Account not active. Status: Expired"
+        );
+    }
+
     #[test]
     fn test_credentials_url_builders() {
         let creds = XtreamCredentials {
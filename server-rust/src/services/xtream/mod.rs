@@ -25,7 +25,7 @@
 //! # Usage
 //!
 //! ```rust,ignore
-//! use crate::services::xtream::{detect_xtream, XtreamClient};
+//! use crate::services::xtream::{detector::detect_xtream, XtreamClient};
 //!
 //! // Try to detect if URL is Xtream
 //! if let Some((creds, auth)) = detect_xtream(&url).await {
@@ -43,7 +43,7 @@ pub mod types;
 
 // Re-exports for convenience
 pub use client::{XtreamClient, XtreamError};
-pub use detector::{detect_xtream, extract_credentials, validate_credentials};
+pub use detector::{extract_credentials, normalize_server, validate_credentials};
 pub use types::{
     // Normalization helpers (inspired by @iptv/xtream-api)
     decode_base64_if_needed, generate_seasons_from_episodes, parse_duration_to_secs, parse_rating,
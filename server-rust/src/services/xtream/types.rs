@@ -500,6 +500,21 @@ impl XtreamCredentials {
         )
     }
 
+    /// Build the get.php M3U URL (what users usually paste), with encoded credentials
+    pub fn m3u_url(&self) -> String {
+        let mut url = format!("{}/get.php", self.server);
+        if let Ok(mut parsed) = url::Url::parse(&url) {
+            parsed
+                .query_pairs_mut()
+                .append_pair("username", &self.username)
+                .append_pair("password", &self.password)
+                .append_pair("type", "m3u_plus")
+                .append_pair("output", "ts");
+            url = parsed.to_string();
+        }
+        url
+    }
+
     /// Build playback URL for live streams (respects preferred_live_format)
     pub fn live_url(&self, stream_id: i64) -> String {
         self.live_url_with_format(stream_id, None)